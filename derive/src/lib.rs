@@ -0,0 +1,145 @@
+//! Custom derives for [mysql](https://crates.io/crates/mysql) crate.
+//!
+//! Please do not depend on this crate directly, use `derive` feature of *mysql* crate:
+//!
+//! ```toml
+//! [dependencies.mysql]
+//! mysql = "*"
+//! features = ["derive"]
+//! ```
+//!
+//! * `#[derive(FromRow)]` implements `mysql::FromRow` for a struct with named fields. Each
+//!   field will be taken from a column with the same name.
+//! * `#[derive(IntoParams)]` implements `From<T> for mysql::Params`, so struct could be passed
+//!   as a named parameters to statement with `:field_name` placeholders.
+//! * `#[derive(IntoValue)]` implements `From<T> for mysql::Value` for a newtype (tuple struct
+//!   with a single field), so it could be passed as a parameter directly.
+
+extern crate proc_macro;
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+use proc_macro::TokenStream;
+
+fn struct_fields<'a>(ast: &'a syn::DeriveInput, derive: &str) -> &'a [syn::Field] {
+    match ast.body {
+        syn::Body::Struct(syn::VariantData::Struct(ref fields)) => fields,
+        _ => panic!("#[derive({})] is only defined for structs with named fields", derive),
+    }
+}
+
+#[proc_macro_derive(FromRow)]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_derive_input(&input.to_string()).unwrap();
+    let gen = impl_from_row(&ast);
+    gen.parse().unwrap()
+}
+
+fn impl_from_row(ast: &syn::DeriveInput) -> quote::Tokens {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let fields = struct_fields(ast, "FromRow");
+    let target = name.to_string();
+
+    let mut takes = Vec::with_capacity(fields.len());
+    let mut commits = Vec::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let column = ident.to_string();
+        let ir = syn::Ident::new(format!("__ir_{}", i));
+        // Values taken for previous fields should be placed back on error.
+        let rollbacks = fields[..i].iter().enumerate().map(|(j, prev)| {
+            let prev_ty = &prev.ty;
+            let prev_column = prev.ident.as_ref().unwrap().to_string();
+            let prev_ir = syn::Ident::new(format!("__ir_{}", j));
+            quote! {
+                ::mysql::value::place_by_name::<#prev_ty, _>(&mut row, #prev_column, #prev_ir);
+            }
+        }).collect::<Vec<_>>();
+        takes.push(quote! {
+            let #ir = match ::mysql::value::take_by_name::<#ty>(&mut row, #column) {
+                Some(ir) => ir,
+                None => {
+                    #(#rollbacks)*
+                    return Err(::mysql::Error::FromRowError(row));
+                },
+            };
+        });
+        commits.push(quote! {
+            #ident: <_ as ::mysql::ConvIr<#ty>>::commit(#ir)
+        });
+    }
+
+    quote! {
+        impl #impl_generics ::mysql::FromRow for #name #ty_generics #where_clause {
+            fn from_row(row: ::mysql::Row) -> Self {
+                ::mysql::value::unwrap_row(::mysql::FromRow::from_row_opt(row), #target)
+            }
+            fn from_row_opt(mut row: ::mysql::Row) -> ::mysql::Result<Self> {
+                #(#takes)*
+                Ok(#name {
+                    #(#commits),*
+                })
+            }
+        }
+    }
+}
+
+#[proc_macro_derive(IntoValue)]
+pub fn derive_into_value(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_derive_input(&input.to_string()).unwrap();
+    let gen = impl_into_value(&ast);
+    gen.parse().unwrap()
+}
+
+fn impl_into_value(ast: &syn::DeriveInput) -> quote::Tokens {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    match ast.body {
+        syn::Body::Struct(syn::VariantData::Tuple(ref fields)) if fields.len() == 1 => (),
+        _ => panic!("#[derive(IntoValue)] is only defined for tuple structs with a single field"),
+    }
+
+    quote! {
+        impl #impl_generics ::std::convert::From<#name #ty_generics> for ::mysql::Value
+        #where_clause {
+            fn from(x: #name #ty_generics) -> ::mysql::Value {
+                ::mysql::Value::from(x.0)
+            }
+        }
+    }
+}
+
+#[proc_macro_derive(IntoParams)]
+pub fn derive_into_params(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_derive_input(&input.to_string()).unwrap();
+    let gen = impl_into_params(&ast);
+    gen.parse().unwrap()
+}
+
+fn impl_into_params(ast: &syn::DeriveInput) -> quote::Tokens {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let fields = struct_fields(ast, "IntoParams");
+
+    let inserts = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let param = ident.to_string();
+        quote! {
+            params.insert(#param.to_string(), ::mysql::Value::from(x.#ident));
+        }
+    }).collect::<Vec<_>>();
+
+    quote! {
+        impl #impl_generics ::std::convert::From<#name #ty_generics> for ::mysql::Params
+        #where_clause {
+            fn from(x: #name #ty_generics) -> ::mysql::Params {
+                let mut params = ::std::collections::HashMap::default();
+                #(#inserts)*
+                ::mysql::Params::Named(params)
+            }
+        }
+    }
+}
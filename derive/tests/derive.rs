@@ -0,0 +1,76 @@
+#[macro_use]
+extern crate mysql;
+
+use mysql::{Conn, Opts};
+
+#[derive(FromRow, IntoParams, Clone, Debug, PartialEq)]
+struct User {
+    id: u64,
+    name: String,
+    email: Option<String>,
+}
+
+#[derive(FromRow, Debug)]
+struct Id {
+    id: u64,
+}
+
+#[derive(IntoValue, Clone, Copy, Debug)]
+struct UserId(u64);
+
+fn get_opts() -> Opts {
+    let pwd: String = ::std::env::var("MYSQL_SERVER_PASS").unwrap_or("password".to_string());
+    let port: u16 = ::std::env::var("MYSQL_SERVER_PORT").ok()
+                               .map(|my_port| my_port.parse().ok().unwrap_or(3307))
+                               .unwrap_or(3307);
+    Opts {
+        user: Some("root".to_string()),
+        pass: Some(pwd),
+        ip_or_hostname: Some("127.0.0.1".to_string()),
+        tcp_port: port,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn should_derive_from_row_and_into_params() {
+    let mut conn = Conn::new(get_opts()).unwrap();
+    conn.query("CREATE TEMPORARY TABLE tmp.users (id INT UNSIGNED, name TEXT, email TEXT)")
+        .unwrap();
+    let users = vec![
+        User { id: 1, name: "foo".into(), email: None },
+        User { id: 2, name: "bar".into(), email: Some("bar@baz".into()) },
+    ];
+    for user in users.iter() {
+        conn.prep_exec("INSERT INTO tmp.users (id, name, email) VALUES (:id, :name, :email)",
+                       user).unwrap();
+    }
+    let selected: Vec<User> = conn.prep_exec("SELECT email, name, id FROM tmp.users", ())
+        .and_then(|mut result| result.collect_rows())
+        .unwrap();
+    assert_eq!(selected, users);
+
+    let ids: Vec<Id> = conn.query("SELECT id, name FROM tmp.users")
+        .and_then(|mut result| result.collect_rows())
+        .unwrap();
+    assert_eq!(ids.iter().map(|x| x.id).collect::<Vec<_>>(), vec![1, 2]);
+
+    let names: Vec<String> = conn.exec("SELECT name FROM tmp.users WHERE id = ? OR id = ?",
+                                       (UserId(2), Some(UserId(3))))
+        .unwrap();
+    assert_eq!(names, vec!["bar".to_string()]);
+    assert_eq!(mysql::Value::from(None::<UserId>), mysql::Value::NULL);
+    let params: &[&mysql::ToValue] = &[&UserId(1)];
+    assert_eq!(mysql::Params::from(params),
+               mysql::Params::Positional(vec![mysql::Value::UInt(1)]));
+
+    // Missing column
+    let mut result = conn.query("SELECT id FROM tmp.users").unwrap();
+    let row = result.next().unwrap().unwrap();
+    match mysql::from_row_opt::<User>(row) {
+        Err(mysql::Error::FromRowError(row)) => {
+            assert_eq!(row.unwrap(), vec![mysql::Value::Bytes(b"1".to_vec())]);
+        },
+        _ => panic!("expected FromRowError"),
+    }
+}
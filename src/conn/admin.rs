@@ -0,0 +1,129 @@
+//! Typed results of server administration commands.
+
+use super::{Conn, Row, take_nullable};
+use super::super::error::Result as MyResult;
+
+/// Server statistics returned by
+/// [`COM_STATISTICS`](http://dev.mysql.com/doc/internals/en/com-statistics.html).
+///
+/// Counters which server did not report are zero.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ServerStatistics {
+    /// Server uptime in seconds.
+    pub uptime: u64,
+    /// Number of currently open connections.
+    pub threads: u64,
+    /// Number of statements executed since server start.
+    pub questions: u64,
+    pub slow_queries: u64,
+    /// Number of tables server has opened.
+    pub opens: u64,
+    pub flush_tables: u64,
+    /// Number of currently open tables.
+    pub open_tables: u64,
+    pub queries_per_second_avg: f64,
+    /// Statistics string as sent by server.
+    pub raw: String,
+}
+
+impl ServerStatistics {
+    /// Parses human readable statistics string, e.g.
+    /// `Uptime: 42  Threads: 1  Questions: 7  Slow queries: 0 ...`.
+    pub fn parse(raw: &str) -> ServerStatistics {
+        let mut stats = ServerStatistics {
+            uptime: 0,
+            threads: 0,
+            questions: 0,
+            slow_queries: 0,
+            opens: 0,
+            flush_tables: 0,
+            open_tables: 0,
+            queries_per_second_avg: 0.0,
+            raw: raw.into(),
+        };
+        for item in raw.split("  ") {
+            let mut parts = item.splitn(2, ':');
+            let (name, value) = match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => (name.trim(), value.trim()),
+                _ => continue,
+            };
+            let count = value.parse().unwrap_or(0);
+            match name {
+                "Uptime" => stats.uptime = count,
+                "Threads" => stats.threads = count,
+                "Questions" => stats.questions = count,
+                "Slow queries" => stats.slow_queries = count,
+                "Opens" => stats.opens = count,
+                "Flush tables" => stats.flush_tables = count,
+                "Open tables" => stats.open_tables = count,
+                "Queries per second avg" => {
+                    stats.queries_per_second_avg = value.parse().unwrap_or(0.0);
+                },
+                _ => (),
+            }
+        }
+        stats
+    }
+}
+
+/// Row of `SHOW FULL PROCESSLIST`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ProcessInfo {
+    /// Connection identifier (see [`Conn::id`](struct.Conn.html#method.id)).
+    pub id: u64,
+    pub user: String,
+    /// Client host and port.
+    pub host: String,
+    /// Default database of a connection.
+    pub db: Option<String>,
+    /// Command being executed, e.g. `Query` or `Sleep`.
+    pub command: String,
+    /// Seconds spent in the current state.
+    pub time: i64,
+    pub state: Option<String>,
+    /// Statement being executed.
+    pub info: Option<String>,
+}
+
+pub fn process_info(conn: &mut Conn) -> MyResult<Vec<ProcessInfo>> {
+    let rows: Vec<Row> = try!(try!(conn.query("SHOW FULL PROCESSLIST")).collect());
+    let mut output = Vec::with_capacity(rows.len());
+    for mut row in rows {
+        let row = &mut row;
+        output.push(ProcessInfo {
+            id: try!(take_nullable(row, "Id")).unwrap_or(0),
+            user: try!(take_nullable(row, "User")).unwrap_or_default(),
+            host: try!(take_nullable(row, "Host")).unwrap_or_default(),
+            db: try!(take_nullable(row, "db")),
+            command: try!(take_nullable(row, "Command")).unwrap_or_default(),
+            time: try!(take_nullable(row, "Time")).unwrap_or(0),
+            state: try!(take_nullable(row, "State")),
+            info: try!(take_nullable(row, "Info")),
+        });
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ServerStatistics;
+
+    #[test]
+    fn should_parse_server_statistics() {
+        let raw = "Uptime: 3600  Threads: 2  Questions: 120  Slow queries: 1  Opens: 33  \
+                   Flush tables: 3  Open tables: 26  Queries per second avg: 0.033";
+        assert_eq!(ServerStatistics::parse(raw), ServerStatistics {
+            uptime: 3600,
+            threads: 2,
+            questions: 120,
+            slow_queries: 1,
+            opens: 33,
+            flush_tables: 3,
+            open_tables: 26,
+            queries_per_second_avg: 0.033,
+            raw: raw.into(),
+        });
+        let stats = ServerStatistics::parse("Uptime: 5  Unknown: x  Threads: y");
+        assert_eq!((stats.uptime, stats.threads, stats.questions), (5, 0, 0));
+    }
+}
@@ -0,0 +1,768 @@
+//! #### Only available if `async` feature enabled.
+//!
+//! Experimental non-blocking counterpart of [`Conn`](../struct.Conn.html) and
+//! [`Pool`](../pool/struct.Pool.html).
+//!
+//! [`AsyncConn`](struct.AsyncConn.html) never blocks on socket reads or writes. Every `poll_*`
+//! method returns `Ok(Async::NotReady)` if it could not make progress without blocking, so it
+//! should be called again when the socket becomes readable or writable (use `AsRawFd` to
+//! register the connection in your event loop). Packet parsing and `Value` layers are shared
+//! with the blocking `Conn`.
+//!
+//! Only text protocol (`COM_QUERY`) over TCP without SSL is supported for now, so
+//! `DriverError::UnsupportedOption` is returned if SSL, unix socket, named pipe or proxy is
+//! configured. `Opts::init` commands are executed during the connection phase, but
+//! `Opts::init_fn` is ignored.
+//!
+//! Server could switch auth plugin during the connection phase. `mysql_native_password`,
+//! `mysql_old_password`, `client_ed25519` and fast authentication of `caching_sha2_password`
+//! are supported. Plugins which need to send a password over a secure transport or to request
+//! RSA public key (`sha256_password` and full authentication of `caching_sha2_password`) will
+//! fail with `DriverError::UnsupportedAuthPlugin` unless password is empty.
+//!
+//! ```ignore
+//! let mut conn = AsyncConn::new(opts).unwrap();
+//! // Drive connection phase.
+//! while let Async::NotReady = conn.poll_connect().unwrap() { wait_for_socket(&conn); }
+//! conn.start_query("SELECT 1, 2").unwrap();
+//! loop {
+//!     match conn.poll_row().unwrap() {
+//!         Async::Ready(Some(row)) => println!("{:?}", row),
+//!         Async::Ready(None) => break,
+//!         Async::NotReady => wait_for_socket(&conn),
+//!     }
+//! }
+//! ```
+
+use std::io;
+use std::io::Read as StdRead;
+use std::io::Write as StdWrite;
+use std::net;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use byteorder::LittleEndian as LE;
+use byteorder::ByteOrder;
+
+use super::{handshake_response, initial_auth_plugin, parse_auth_switch_request};
+use super::{Column, Opts, Row};
+use super::{CACHING_SHA2_PASSWORD_PLUGIN, ED25519_PLUGIN, NATIVE_PASSWORD_PLUGIN};
+use super::OLD_PASSWORD_PLUGIN;
+use super::super::consts;
+use super::super::consts::Command;
+use super::super::error::Error;
+use super::super::error::Error::{DriverError, MySqlError};
+use super::super::error::DriverError::{
+    CouldNotConnect,
+    PacketOutOfSync,
+    Protocol41NotSet,
+    UnexpectedPacket,
+    UnsupportedAuthPlugin,
+    UnsupportedOption,
+    UnsupportedProtocol,
+};
+use super::super::error::Result as MyResult;
+use super::super::io::Read as MyRead;
+use super::super::io::Write as MyWrite;
+use super::super::packet::{EOFPacket, ErrPacket, HandshakePacket, OkPacket};
+use super::super::scramble::{scramble, scramble_323, scramble_ed25519, scramble_sha256};
+use super::super::value::Value;
+
+/// Result of a non-blocking operation.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Async<T> {
+    /// Operation is complete.
+    Ready(T),
+    /// Operation would block. Call it again when the socket is ready.
+    NotReady,
+}
+
+/// Result of a `poll_*` call.
+pub type Poll<T> = MyResult<Async<T>>;
+
+macro_rules! try_ready {
+    ($e:expr) => (match try!($e) {
+        Async::Ready(x) => x,
+        Async::NotReady => return Ok(Async::NotReady),
+    });
+}
+
+/// Non-blocking packet stream.
+#[derive(Debug)]
+struct PacketStream {
+    stream: net::TcpStream,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    written: usize,
+    seq_id: u8,
+}
+
+impl PacketStream {
+    fn new(stream: net::TcpStream) -> PacketStream {
+        PacketStream {
+            stream: stream,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            written: 0,
+            seq_id: 0,
+        }
+    }
+
+    /// Takes complete (possibly multi-chunk) packet off the read buffer.
+    fn take_packet(&mut self) -> MyResult<Option<Vec<u8>>> {
+        let mut offset = 0;
+        let mut total = 0;
+        loop {
+            if self.read_buf.len() < offset + 4 {
+                return Ok(None);
+            }
+            let len = LE::read_uint(&self.read_buf[offset..], 3) as usize;
+            if self.read_buf.len() < offset + 4 + len {
+                return Ok(None);
+            }
+            offset += 4 + len;
+            total += len;
+            if len != consts::MAX_PAYLOAD_LEN {
+                break;
+            }
+        }
+        let mut pld = Vec::with_capacity(total);
+        let mut pos = 0;
+        while pos < offset {
+            let len = LE::read_uint(&self.read_buf[pos..], 3) as usize;
+            if self.read_buf[pos + 3] != self.seq_id {
+                return Err(DriverError(PacketOutOfSync));
+            }
+            self.seq_id = self.seq_id.wrapping_add(1);
+            pld.extend_from_slice(&self.read_buf[pos + 4..pos + 4 + len]);
+            pos += 4 + len;
+        }
+        self.read_buf.drain(..offset);
+        Ok(Some(pld))
+    }
+
+    fn poll_read_packet(&mut self) -> Poll<Vec<u8>> {
+        let mut chunk = [0u8; 8192];
+        loop {
+            if let Some(pld) = try!(self.take_packet()) {
+                return Ok(Async::Ready(pld));
+            }
+            match self.stream.read(&mut chunk[..]) {
+                Ok(0) => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                              "Unexpected EOF while reading packet").into());
+                },
+                Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    return Ok(Async::NotReady);
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn queue_chunk(&mut self, chunk: &[u8]) {
+        let _ = self.write_buf.write_le_uint_n(chunk.len() as u64, 3);
+        self.write_buf.push(self.seq_id);
+        self.seq_id = self.seq_id.wrapping_add(1);
+        self.write_buf.extend_from_slice(chunk);
+    }
+
+    /// Queues packet for writing. Call `poll_flush` to actually write it.
+    fn queue_packet(&mut self, data: &[u8]) {
+        for chunk in data.chunks(consts::MAX_PAYLOAD_LEN) {
+            self.queue_chunk(chunk);
+        }
+        // Empty packet or the packet which length is a multiple of `MAX_PAYLOAD_LEN`
+        // should be terminated by an empty chunk.
+        if data.len() % consts::MAX_PAYLOAD_LEN == 0 {
+            self.queue_chunk(&[]);
+        }
+    }
+
+    fn poll_flush(&mut self) -> Poll<()> {
+        while self.written < self.write_buf.len() {
+            match self.stream.write(&self.write_buf[self.written..]) {
+                Ok(0) => {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero,
+                                              "Could not write packet").into());
+                },
+                Ok(n) => self.written += n,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    return Ok(Async::NotReady);
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.write_buf.clear();
+        self.written = 0;
+        Ok(Async::Ready(()))
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum State {
+    /// Waiting for the initial handshake packet.
+    Handshake,
+    /// Handshake response was queued. Waiting for authentication result.
+    Auth,
+    /// Connection is ready for a new command.
+    Idle,
+    /// Command was queued. Waiting for a result set header.
+    ResultHeader,
+    /// Reading column definitions. Holds number of columns left.
+    Columns(usize),
+    /// Waiting for EOF packet which terminates column definitions.
+    ColumnsEof,
+    /// Reading rows.
+    Rows,
+    /// Result set is over but there are more result sets.
+    ResultSetEnd,
+}
+
+/// Non-blocking mysql connection.
+///
+/// See [module level documentation](index.html).
+#[derive(Debug)]
+pub struct AsyncConn {
+    opts: Opts,
+    stream: PacketStream,
+    state: State,
+    capability_flags: consts::CapabilityFlags,
+    status_flags: consts::StatusFlags,
+    affected_rows: u64,
+    last_insert_id: u64,
+    columns: Vec<Column>,
+    pending_init: Vec<String>,
+    /// Auth plugin of the connection phase and its scramble.
+    auth_plugin: Vec<u8>,
+    nonce: Vec<u8>,
+}
+
+/// Rejects transports which `AsyncConn` does not implement, rather than falling back to
+/// plain TCP.
+fn check_transport(opts: &Opts) -> MyResult<()> {
+    #[cfg(feature = "ssl")]
+    {
+        if opts.ssl_enabled() {
+            return Err(DriverError(UnsupportedOption("ssl_opts")));
+        }
+    }
+    #[cfg(feature = "socket")]
+    {
+        if opts.unix_addr.is_some() {
+            return Err(DriverError(UnsupportedOption("unix_addr")));
+        }
+    }
+    #[cfg(feature = "pipe")]
+    {
+        if opts.pipe_name.is_some() {
+            return Err(DriverError(UnsupportedOption("pipe_name")));
+        }
+    }
+    if opts.proxy.is_some() {
+        return Err(DriverError(UnsupportedOption("proxy")));
+    }
+    Ok(())
+}
+
+impl AsyncConn {
+    /// Connects to the server and switches the socket to non-blocking mode.
+    ///
+    /// TCP connection is established synchronously. Connection phase should then be driven
+    /// by [`poll_connect`](#method.poll_connect).
+    pub fn new<T: Into<Opts>>(opts: T) -> MyResult<AsyncConn> {
+        let opts = opts.into();
+        try!(check_transport(&opts));
+        let stream = {
+            let addr = match opts.ip_or_hostname {
+                Some(ref addr) => addr,
+                None => return Err(DriverError(CouldNotConnect(None))),
+            };
+            match net::TcpStream::connect(&(&**addr, opts.tcp_port)) {
+                Ok(stream) => stream,
+                Err(_) => return Err(DriverError(CouldNotConnect(opts.ip_or_hostname.clone()))),
+            }
+        };
+        try!(stream.set_nonblocking(true));
+        let mut pending_init = opts.init.clone();
+        pending_init.reverse();
+        Ok(AsyncConn {
+            opts: opts,
+            stream: PacketStream::new(stream),
+            state: State::Handshake,
+            capability_flags: consts::CapabilityFlags::empty(),
+            status_flags: consts::StatusFlags::empty(),
+            affected_rows: 0,
+            last_insert_id: 0,
+            columns: Vec::new(),
+            pending_init: pending_init,
+            auth_plugin: Vec::new(),
+            nonce: Vec::new(),
+        })
+    }
+
+    fn get_client_flags(&self) -> consts::CapabilityFlags {
+        let mut client_flags = consts::CLIENT_PROTOCOL_41 |
+                               consts::CLIENT_SECURE_CONNECTION |
+                               consts::CLIENT_LONG_PASSWORD |
+                               consts::CLIENT_TRANSACTIONS |
+                               consts::CLIENT_MULTI_STATEMENTS |
+                               consts::CLIENT_MULTI_RESULTS |
+                               consts::CLIENT_PS_MULTI_RESULTS |
+                               (self.capability_flags & (consts::CLIENT_LONG_FLAG |
+                                                         consts::CLIENT_PLUGIN_AUTH));
+        if let Some(ref db_name) = self.opts.db_name {
+            if db_name.len() > 0 {
+                client_flags.insert(consts::CLIENT_CONNECT_WITH_DB);
+            }
+        }
+        client_flags
+    }
+
+    fn handle_ok(&mut self, ok: &OkPacket) {
+        self.affected_rows = ok.affected_rows();
+        self.last_insert_id = ok.last_insert_id();
+        self.status_flags = ok.status_flags();
+    }
+
+    /// Returns state which follows the end of a result set.
+    fn end_of_result_set(&self) -> State {
+        if self.status_flags.contains(consts::SERVER_MORE_RESULTS_EXISTS) {
+            State::ResultSetEnd
+        } else {
+            State::Idle
+        }
+    }
+
+    /// Computes auth response for a plugin requested by server. Only plugins which do not
+    /// exchange additional packets are supported.
+    fn auth_plugin_response(&self) -> MyResult<Vec<u8>> {
+        let pass = self.opts.pass.as_ref().map(|x| x.as_bytes()).unwrap_or(&b""[..]);
+        let plugin = &*self.auth_plugin;
+        if plugin == NATIVE_PASSWORD_PLUGIN {
+            Ok(scramble(&*self.nonce, pass).unwrap_or(Vec::new()))
+        } else if plugin == OLD_PASSWORD_PLUGIN {
+            let mut response = scramble_323(&*self.nonce, pass).unwrap_or(Vec::new());
+            response.push(0);
+            Ok(response)
+        } else if plugin == CACHING_SHA2_PASSWORD_PLUGIN {
+            Ok(scramble_sha256(&*self.nonce, pass).unwrap_or(Vec::new()))
+        } else if plugin == ED25519_PLUGIN {
+            Ok(scramble_ed25519(&*self.nonce, pass))
+        } else {
+            Err(self.unsupported_auth_plugin())
+        }
+    }
+
+    fn unsupported_auth_plugin(&self) -> Error {
+        let name = String::from_utf8_lossy(&*self.auth_plugin).into_owned();
+        DriverError(UnsupportedAuthPlugin(name))
+    }
+
+    /// Handles [auth switch request](http://dev.mysql.com/doc/internals/en/connection-phase-packets.html#packet-Protocol::AuthSwitchRequest)
+    /// and additional auth data of `caching_sha2_password` plugin.
+    fn handle_auth_data(&mut self, pld: &[u8]) -> MyResult<()> {
+        match pld.first() {
+            Some(&0xfe) => {
+                if pld.len() > 1 {
+                    let (name, data) = parse_auth_switch_request(&pld[1..]);
+                    self.auth_plugin = name.to_vec();
+                    self.nonce = data.to_vec();
+                } else {
+                    // Old auth switch request (asks for `mysql_old_password` with
+                    // initial scramble).
+                    self.auth_plugin = OLD_PASSWORD_PLUGIN.to_vec();
+                }
+                let response = try!(self.auth_plugin_response());
+                self.stream.queue_packet(&*response);
+                Ok(())
+            },
+            Some(&0x01) if &*self.auth_plugin == CACHING_SHA2_PASSWORD_PLUGIN => {
+                match pld.get(1) {
+                    // Fast auth succeeded, OK packet follows.
+                    Some(&0x03) => Ok(()),
+                    // Full auth needs a secure transport or RSA public key of the server,
+                    // but empty password could be sent as is.
+                    Some(&0x04) if self.opts.pass.as_ref().map_or(true, |x| x.is_empty()) => {
+                        self.stream.queue_packet(&[0]);
+                        Ok(())
+                    },
+                    Some(&0x04) => Err(self.unsupported_auth_plugin()),
+                    _ => Err(DriverError(UnexpectedPacket)),
+                }
+            },
+            _ => Err(DriverError(UnexpectedPacket)),
+        }
+    }
+
+    fn handle_err(&mut self, pld: &[u8]) -> MyResult<()> {
+        self.state = State::Idle;
+        let err = try!(ErrPacket::from_payload(pld, self.capability_flags));
+        Err(MySqlError(err.into()))
+    }
+
+    /// Drives connection phase and executes `Opts::init` commands.
+    pub fn poll_connect(&mut self) -> Poll<()> {
+        loop {
+            match self.state {
+                State::Handshake => {
+                    let pld = try_ready!(self.stream.poll_read_packet());
+                    if pld[0] == 0xff {
+                        try!(self.handle_err(&*pld));
+                    }
+                    let hp = try!(HandshakePacket::from_payload(&*pld));
+                    if hp.protocol_version != 10u8 {
+                        return Err(DriverError(UnsupportedProtocol(hp.protocol_version)));
+                    }
+                    if !hp.capability_flags.contains(consts::CLIENT_PROTOCOL_41) {
+                        return Err(DriverError(Protocol41NotSet));
+                    }
+                    self.capability_flags = hp.capability_flags;
+                    self.status_flags = hp.status_flags;
+                    let client_flags = self.get_client_flags();
+                    let plugin = initial_auth_plugin(&hp);
+                    self.auth_plugin = plugin.to_vec();
+                    self.nonce = hp.auth_plugin_data.clone();
+                    let payload = try!(handshake_response(&self.opts, client_flags, &hp, plugin));
+                    self.stream.queue_packet(&*payload);
+                    self.state = State::Auth;
+                },
+                State::Auth => {
+                    try_ready!(self.stream.poll_flush());
+                    let pld = try_ready!(self.stream.poll_read_packet());
+                    match pld[0] {
+                        0x00 => {
+                            let ok = try!(OkPacket::from_payload(&*pld, self.get_client_flags()));
+                            self.handle_ok(&ok);
+                            self.state = State::Idle;
+                        },
+                        0xff => try!(self.handle_err(&*pld)),
+                        _ => try!(self.handle_auth_data(&*pld)),
+                    }
+                },
+                State::Idle => match self.pending_init.pop() {
+                    Some(cmd) => try!(self.start_query(cmd)),
+                    None => return Ok(Async::Ready(())),
+                },
+                State::ResultSetEnd => self.state = State::ResultHeader,
+                _ => {
+                    // Drain the result of an init command.
+                    while let Some(_) = try_ready!(self.poll_row()) {}
+                },
+            }
+        }
+    }
+
+    /// Queues `COM_QUERY` command. Use [`poll_columns`](#method.poll_columns) and
+    /// [`poll_row`](#method.poll_row) to drive it and to read its results.
+    ///
+    /// Will return `DriverError::UnexpectedPacket` if connection is not ready for a new command
+    /// (i.e. previous results was not consumed).
+    pub fn start_query<T: AsRef<str>>(&mut self, query: T) -> MyResult<()> {
+        if self.state != State::Idle {
+            return Err(DriverError(UnexpectedPacket));
+        }
+        let query = query.as_ref().as_bytes();
+        let mut data = Vec::with_capacity(query.len() + 1);
+        data.push(Command::COM_QUERY as u8);
+        data.extend_from_slice(query);
+        self.stream.seq_id = 0;
+        self.stream.queue_packet(&*data);
+        self.affected_rows = 0;
+        self.last_insert_id = 0;
+        self.columns.clear();
+        self.state = State::ResultHeader;
+        Ok(())
+    }
+
+    /// Reads result set header and column definitions of the next result set.
+    ///
+    /// Returns empty slice if the result set has no columns (i.e. it is an OK packet).
+    pub fn poll_columns(&mut self) -> Poll<&[Column]> {
+        loop {
+            match self.state {
+                State::ResultSetEnd => self.state = State::ResultHeader,
+                State::ResultHeader => {
+                    try_ready!(self.stream.poll_flush());
+                    let pld = try_ready!(self.stream.poll_read_packet());
+                    match pld[0] {
+                        0x00 => {
+                            let ok = try!(OkPacket::from_payload(&*pld, self.get_client_flags()));
+                            self.handle_ok(&ok);
+                            self.columns.clear();
+                            self.state = self.end_of_result_set();
+                            break;
+                        },
+                        0xff => try!(self.handle_err(&*pld)),
+                        0xfb => {
+                            // CLIENT_LOCAL_FILES is not set so server should not request a file.
+                            return Err(DriverError(UnexpectedPacket));
+                        },
+                        _ => {
+                            let column_count = try!((&pld[..]).read_lenenc_int()) as usize;
+                            self.columns = Vec::with_capacity(column_count);
+                            self.state = State::Columns(column_count);
+                        },
+                    }
+                },
+                State::Columns(0) => self.state = State::ColumnsEof,
+                State::Columns(n) => {
+                    let pld = try_ready!(self.stream.poll_read_packet());
+                    let column = try!(Column::from_payload(Command::COM_QUERY as u8, &*pld));
+                    self.columns.push(column);
+                    self.state = State::Columns(n - 1);
+                },
+                State::ColumnsEof => {
+                    try_ready!(self.stream.poll_read_packet());
+                    self.state = State::Rows;
+                },
+                _ => break,
+            }
+        }
+        Ok(Async::Ready(&self.columns[..]))
+    }
+
+    /// Reads next row of the current result set.
+    ///
+    /// Returns `Ready(None)` at the end of every result set. Use
+    /// [`more_results_exists`](#method.more_results_exists) to check for the following one.
+    pub fn poll_row(&mut self) -> Poll<Option<Row>> {
+        match self.state {
+            State::Idle | State::ResultSetEnd => return Ok(Async::Ready(None)),
+            State::Rows => (),
+            _ => {
+                try_ready!(self.poll_columns());
+                if self.state != State::Rows {
+                    return Ok(Async::Ready(None));
+                }
+            },
+        }
+        let pld = try_ready!(self.stream.poll_read_packet());
+        let x = pld[0];
+        if x == 0xfe && pld.len() < 0xfe {
+            let eof = try!(EOFPacket::from_payload(&*pld));
+            self.status_flags = eof.status_flags;
+            self.state = self.end_of_result_set();
+            return Ok(Async::Ready(None));
+        }
+        if x == 0xff && pld.len() < 0xfe {
+            try!(self.handle_err(&*pld));
+        }
+        let values = try!(Value::from_payload(&*pld, self.columns.len()));
+        Ok(Async::Ready(Some(Row::new(values))))
+    }
+
+    /// Writes queued packets.
+    pub fn poll_flush(&mut self) -> Poll<()> {
+        self.stream.poll_flush()
+    }
+
+    /// Returns `true` if there are queued packets, so caller should wait for the socket to
+    /// become writable.
+    pub fn wants_write(&self) -> bool {
+        !self.stream.write_buf.is_empty()
+    }
+
+    /// Returns `true` if connection is ready for a new command.
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Idle
+    }
+
+    /// Returns `true` if current result set is over and there is another one.
+    pub fn more_results_exists(&self) -> bool {
+        self.state == State::ResultSetEnd
+    }
+
+    /// Returns columns of the current result set.
+    pub fn columns_ref(&self) -> &[Column] {
+        self.columns.as_ref()
+    }
+
+    /// Returns
+    /// [`OkPacket`'s](http://dev.mysql.com/doc/internals/en/packet-OK_Packet.html)
+    /// affected rows.
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    /// Returns
+    /// [`OkPacket`'s](http://dev.mysql.com/doc/internals/en/packet-OK_Packet.html)
+    /// last insert id.
+    pub fn last_insert_id(&self) -> u64 {
+        self.last_insert_id
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for AsyncConn {
+    fn as_raw_fd(&self) -> RawFd {
+        self.stream.stream.as_raw_fd()
+    }
+}
+
+/// Single-threaded pool of [`AsyncConn`s](struct.AsyncConn.html).
+///
+/// Intended to be owned by an event loop, so it performs no locking.
+#[derive(Debug)]
+pub struct AsyncPool {
+    opts: Opts,
+    idle: Vec<AsyncConn>,
+    max: usize,
+    count: usize,
+}
+
+impl AsyncPool {
+    /// Creates empty pool which will hold at most `max` connections.
+    pub fn new<T: Into<Opts>>(max: usize, opts: T) -> AsyncPool {
+        AsyncPool {
+            opts: opts.into(),
+            idle: Vec::new(),
+            max: max,
+            count: 0,
+        }
+    }
+
+    /// Returns idle connection or creates a new one if pool is not full. Returns `None` if
+    /// every connection is in use.
+    ///
+    /// New connection should be driven via `AsyncConn::poll_connect` before use.
+    pub fn get_conn(&mut self) -> MyResult<Option<AsyncConn>> {
+        if let Some(conn) = self.idle.pop() {
+            return Ok(Some(conn));
+        }
+        if self.count < self.max {
+            let conn = try!(AsyncConn::new(self.opts.clone()));
+            self.count += 1;
+            return Ok(Some(conn));
+        }
+        Ok(None)
+    }
+
+    /// Returns connection to the pool. Connections which are not idle will be dropped.
+    pub fn put(&mut self, conn: AsyncConn) {
+        if conn.is_idle() && !conn.wants_write() {
+            self.idle.push(conn);
+        } else {
+            // Connection could be created outside of this pool.
+            self.count = self.count.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::default::Default;
+    use std::net;
+    use super::{Async, AsyncConn, AsyncPool, PacketStream, State};
+    use super::super::{Opts, ProxyOpts, NATIVE_PASSWORD_PLUGIN};
+    use super::super::super::error::{DriverError, Error};
+    use super::super::super::consts;
+    use super::super::super::scramble::scramble;
+
+    fn stream_pair() -> (PacketStream, net::TcpStream) {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        client.set_nonblocking(true).unwrap();
+        (PacketStream::new(client), server)
+    }
+
+    #[test]
+    fn should_not_block_on_incomplete_packet() {
+        use std::io::Write;
+        let (mut stream, mut server) = stream_pair();
+        assert_eq!(stream.poll_read_packet().unwrap(), Async::NotReady);
+        server.write_all(&[3, 0, 0, 0, b'f']).unwrap();
+        assert_eq!(stream.poll_read_packet().unwrap(), Async::NotReady);
+        server.write_all(b"oo").unwrap();
+        let mut result = stream.poll_read_packet().unwrap();
+        while result == Async::NotReady {
+            result = stream.poll_read_packet().unwrap();
+        }
+        assert_eq!(result, Async::Ready(b"foo".to_vec()));
+        assert_eq!(stream.seq_id, 1);
+    }
+
+    #[test]
+    fn should_queue_packets() {
+        let (mut stream, _server) = stream_pair();
+        stream.queue_packet(b"foo");
+        stream.queue_packet(b"");
+        assert_eq!(stream.write_buf, vec![3, 0, 0, 0, b'f', b'o', b'o', 0, 0, 0, 1]);
+        let data = vec![0u8; consts::MAX_PAYLOAD_LEN];
+        stream.write_buf.clear();
+        stream.queue_packet(&*data);
+        assert_eq!(stream.write_buf.len(), 4 + consts::MAX_PAYLOAD_LEN + 4);
+        assert_eq!(&stream.write_buf[stream.write_buf.len() - 4..], &[0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn should_reject_unsupported_transport() {
+        let opts = Opts {
+            proxy: Some(ProxyOpts::Http {
+                host: "127.0.0.1".to_string(),
+                port: 3128,
+            }),
+            ..Default::default()
+        };
+        match AsyncConn::new(opts) {
+            Err(Error::DriverError(DriverError::UnsupportedOption("proxy"))) => (),
+            _ => panic!("Expected UnsupportedOption error"),
+        }
+    }
+
+    #[test]
+    fn should_not_underflow_count_of_foreign_conn() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let opts = Opts {
+            ip_or_hostname: Some("127.0.0.1".to_string()),
+            tcp_port: listener.local_addr().unwrap().port(),
+            ..Default::default()
+        };
+        // Connection is not idle until its connection phase is driven.
+        let conn = AsyncConn::new(opts.clone()).unwrap();
+        let mut pool = AsyncPool::new(1, opts);
+        pool.put(conn);
+        assert_eq!(pool.count, 0);
+    }
+
+    #[test]
+    fn should_answer_auth_switch_request() {
+        use std::io::{Read, Write};
+
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let opts = Opts {
+            ip_or_hostname: Some("127.0.0.1".to_string()),
+            tcp_port: listener.local_addr().unwrap().port(),
+            pass: Some("password".to_string()),
+            ..Default::default()
+        };
+        let mut conn = AsyncConn::new(opts).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        // Handshake response was sent with seq_id 1.
+        conn.state = State::Auth;
+        conn.stream.seq_id = 2;
+        conn.auth_plugin = b"caching_sha2_password".to_vec();
+
+        let nonce = b"01234567890123456789";
+        let mut pld = vec![0xfe];
+        pld.extend_from_slice(NATIVE_PASSWORD_PLUGIN);
+        pld.push(0);
+        pld.extend_from_slice(nonce);
+        pld.push(0);
+        server.write_all(&[pld.len() as u8, 0, 0, 2]).unwrap();
+        server.write_all(&*pld).unwrap();
+
+        while conn.nonce != nonce || conn.wants_write() {
+            assert_eq!(conn.poll_connect().unwrap(), Async::NotReady);
+        }
+        assert_eq!(conn.auth_plugin, NATIVE_PASSWORD_PLUGIN);
+        let mut response = [0u8; 24];
+        server.read_exact(&mut response).unwrap();
+        assert_eq!(&response[..4], &[20, 0, 0, 3]);
+        assert_eq!(&response[4..], &*scramble(nonce, b"password").unwrap());
+    }
+}
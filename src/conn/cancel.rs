@@ -0,0 +1,71 @@
+//! Cancellation of running queries from another thread.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{Conn, Opts};
+use super::super::error::Result as MyResult;
+
+/// `ER_QUERY_INTERRUPTED` is returned by a server for a statement killed via `KILL QUERY`.
+pub const ER_QUERY_INTERRUPTED: u16 = 1317;
+
+/// Handle which could be sent to another thread to cancel a query running on a connection.
+///
+/// Obtained via [`Conn::cancellation_token`](struct.Conn.html#method.cancellation_token).
+/// `cancel` opens a helper connection with the same `Opts` and sends `KILL QUERY`, so the
+/// call blocked on the original connection returns
+/// `Error::DriverError(DriverError::QueryCancelled)` and the connection remains usable.
+///
+/// ```ignore
+/// let token = conn.cancellation_token();
+/// thread::spawn(move || {
+///     wait_for_cancel_button();
+///     let _ = token.cancel();
+/// });
+/// match conn.query("SELECT * FROM log ORDER BY message") {
+///     Err(Error::DriverError(DriverError::QueryCancelled)) => println!("cancelled"),
+///     result => { try!(result); },
+/// }
+/// ```
+///
+/// Token is bound to the server side connection id, so it should be obtained again after
+/// a reconnect (e.g. `Conn::reset`).
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    opts: Opts,
+    connection_id: u32,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    #[doc(hidden)]
+    pub fn new(opts: Opts, connection_id: u32, cancelled: Arc<AtomicBool>) -> CancellationToken {
+        CancellationToken {
+            opts: opts,
+            connection_id: connection_id,
+            cancelled: cancelled,
+        }
+    }
+
+    /// Returns id of a connection which will be affected by `cancel`.
+    pub fn connection_id(&self) -> u32 {
+        self.connection_id
+    }
+
+    /// Cancels statement which is executed by the connection at the moment.
+    ///
+    /// `KILL QUERY` is not tied to a particular statement. If the statement completes before
+    /// server receives `KILL QUERY`, the next statement sent over the connection could be
+    /// interrupted instead, and it will fail with `MySqlError` 1317 (`ER_QUERY_INTERRUPTED`)
+    /// rather than `DriverError::QueryCancelled`, because every new command clears the
+    /// cancellation flag. Call `cancel` only while a statement is known to be running.
+    ///
+    /// Fails if helper connection could not be established, e.g. if original connection
+    /// was created via `Conn::new_with_stream`.
+    pub fn cancel(&self) -> MyResult<()> {
+        self.cancelled.store(true, Ordering::SeqCst);
+        let mut conn = try!(Conn::new(self.opts.clone()));
+        try!(conn.query(format!("KILL QUERY {}", self.connection_id)));
+        Ok(())
+    }
+}
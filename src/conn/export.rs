@@ -0,0 +1,196 @@
+//! Export of result sets as CSV or TSV.
+
+use std::io::{self, Write};
+
+use super::QueryResult;
+use super::super::error::Result as MyResult;
+use super::super::value::ValueRef;
+
+/// Options of [`QueryResult::write_csv`](struct.QueryResult.html#method.write_csv).
+///
+/// `Default` is RFC 4180 CSV with a header, empty unquoted field for `NULL` and quoted empty
+/// field for an empty string.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CsvOptions {
+    /// Field delimiter (defaults to `,`).
+    pub delimiter: u8,
+    /// Quote character (defaults to `Some(b'"')`).
+    ///
+    /// Fields containing delimiter, quote or line break are quoted and quotes are doubled.
+    /// If `None`, backslash, tab, line breaks and delimiter are escaped with backslash instead
+    /// (e.g. `\t` and `\n`), as in output of `SELECT ... INTO OUTFILE`.
+    pub quote: Option<u8>,
+    /// Representation of `NULL` (defaults to empty field).
+    pub null: String,
+    /// Whether to write column names as the first line (defaults to `true`).
+    pub header: bool,
+    /// Line terminator (defaults to `\r\n`).
+    pub line_terminator: String,
+}
+
+impl CsvOptions {
+    /// Same as `CsvOptions::default()`.
+    pub fn csv() -> CsvOptions {
+        CsvOptions::default()
+    }
+
+    /// Tab separated values with backslash escapes, `\N` for `NULL` and `\n` line terminator.
+    pub fn tsv() -> CsvOptions {
+        CsvOptions {
+            delimiter: b'\t',
+            quote: None,
+            null: "\\N".into(),
+            header: true,
+            line_terminator: "\n".into(),
+        }
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: b',',
+            quote: Some(b'"'),
+            null: String::new(),
+            header: true,
+            line_terminator: "\r\n".into(),
+        }
+    }
+}
+
+fn write_field<W: Write>(writer: &mut W, bytes: &[u8], options: &CsvOptions) -> io::Result<()> {
+    match options.quote {
+        Some(quote) => {
+            let needs_quotes = bytes.is_empty() ||
+                               bytes == options.null.as_bytes() ||
+                               bytes.iter().any(|&x| {
+                                   x == options.delimiter || x == quote || x == b'\r' || x == b'\n'
+                               });
+            if !needs_quotes {
+                return writer.write_all(bytes);
+            }
+            try!(writer.write_all(&[quote]));
+            for (i, part) in bytes.split(|&x| x == quote).enumerate() {
+                if i > 0 {
+                    try!(writer.write_all(&[quote, quote]));
+                }
+                try!(writer.write_all(part));
+            }
+            writer.write_all(&[quote])
+        },
+        None => {
+            let mut start = 0;
+            for (i, &x) in bytes.iter().enumerate() {
+                let escaped: &[u8] = match x {
+                    b'\\' => b"\\\\",
+                    b'\t' => b"\\t",
+                    b'\n' => b"\\n",
+                    b'\r' => b"\\r",
+                    b'\x00' => b"\\0",
+                    x if x == options.delimiter => &bytes[i..i + 1],
+                    _ => continue,
+                };
+                try!(writer.write_all(&bytes[start..i]));
+                if escaped.len() == 1 {
+                    try!(writer.write_all(b"\\"));
+                }
+                try!(writer.write_all(escaped));
+                start = i + 1;
+            }
+            writer.write_all(&bytes[start..])
+        },
+    }
+}
+
+fn write_value<W: Write>(writer: &mut W, value: &ValueRef, options: &CsvOptions) -> io::Result<()> {
+    match *value {
+        ValueRef::NULL => writer.write_all(options.null.as_bytes()),
+        ValueRef::Bytes(bytes) => write_field(writer, bytes, options),
+        ValueRef::Int(x) => write!(writer, "{}", x),
+        ValueRef::UInt(x) => write!(writer, "{}", x),
+        ValueRef::Float(x) => write!(writer, "{}", x),
+        ValueRef::Date(y, m, d, 0, 0, 0, 0) => write!(writer, "{:04}-{:02}-{:02}", y, m, d),
+        ValueRef::Date(y, m, d, h, i, s, 0) => {
+            write!(writer, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}", y, m, d, h, i, s)
+        },
+        ValueRef::Date(y, m, d, h, i, s, u) => {
+            write!(writer, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}", y, m, d, h, i, s, u)
+        },
+        ValueRef::Time(neg, d, h, i, s, u) => {
+            let sign = if neg { "-" } else { "" };
+            try!(write!(writer, "{}{:02}:{:02}:{:02}", sign, d * 24 + h as u32, i, s));
+            if u > 0 {
+                try!(write!(writer, ".{:06}", u));
+            }
+            Ok(())
+        },
+    }
+}
+
+pub fn write_csv<'a, W: Write>(result: &mut QueryResult<'a>,
+                               mut writer: W,
+                               options: &CsvOptions) -> MyResult<u64> {
+    if options.header && !result.columns_ref().is_empty() {
+        for (i, column) in result.columns_ref().iter().enumerate() {
+            if i > 0 {
+                try!(writer.write_all(&[options.delimiter]));
+            }
+            try!(write_field(&mut writer, &*column.name, options));
+        }
+        try!(writer.write_all(options.line_terminator.as_bytes()));
+    }
+    let mut count = 0;
+    let mut rows = result.by_ref_rows();
+    while let Some(row) = rows.next() {
+        let row = try!(row);
+        for (i, value) in row.values().iter().enumerate() {
+            if i > 0 {
+                try!(writer.write_all(&[options.delimiter]));
+            }
+            try!(write_value(&mut writer, value, options));
+        }
+        try!(writer.write_all(options.line_terminator.as_bytes()));
+        count += 1;
+    }
+    try!(writer.flush());
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CsvOptions, write_value};
+    use super::super::super::value::ValueRef;
+
+    fn format(values: &[ValueRef], options: &CsvOptions) -> String {
+        let mut output = Vec::new();
+        for value in values {
+            write_value(&mut output, value, options).unwrap();
+            output.push(b'|');
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn should_format_csv_fields() {
+        let values = [ValueRef::NULL,
+                      ValueRef::Bytes(b""),
+                      ValueRef::Bytes(b"plain"),
+                      ValueRef::Bytes(b"a,b"),
+                      ValueRef::Bytes(b"say \"hi\"\r\n"),
+                      ValueRef::Int(-1),
+                      ValueRef::Float(1.5),
+                      ValueRef::Date(2016, 2, 29, 0, 0, 0, 0),
+                      ValueRef::Date(2016, 2, 29, 1, 2, 3, 4),
+                      ValueRef::Time(true, 1, 2, 3, 4, 0)];
+        assert_eq!(format(&values, &CsvOptions::csv()),
+                   "|\"\"|plain|\"a,b\"|\"say \"\"hi\"\"\r\n\"|-1|1.5|2016-02-29|\
+                    2016-02-29 01:02:03.000004|-26:03:04|");
+        assert_eq!(format(&values, &CsvOptions::tsv()),
+                   "\\N||plain|a,b|say \"hi\"\\r\\n|-1|1.5|2016-02-29|\
+                    2016-02-29 01:02:03.000004|-26:03:04|");
+        assert_eq!(format(&[ValueRef::Bytes(b"a\tb\\c\x00")], &CsvOptions::tsv()),
+                   "a\\tb\\\\c\\0|");
+        let options = CsvOptions { delimiter: b';', ..CsvOptions::tsv() };
+        assert_eq!(format(&[ValueRef::Bytes(b"a;b")], &options), "a\\;b|");
+    }
+}
@@ -0,0 +1,91 @@
+//! Helpers for log records emitted via `mysql_debug!`.
+//!
+//! Every helper is formatted lazily, so nothing is computed unless a record is actually written.
+
+use std::fmt;
+
+#[cfg(feature = "log")]
+use time::SteadyTime;
+
+use super::super::error::Result as MyResult;
+
+/// Displays time elapsed since its creation, e.g. `1.250ms`.
+///
+/// Clock is not read at all if `log` feature is disabled.
+#[cfg(feature = "log")]
+pub struct Elapsed(SteadyTime);
+
+/// Displays time elapsed since its creation, e.g. `1.250ms`.
+///
+/// Clock is not read at all if `log` feature is disabled.
+#[cfg(not(feature = "log"))]
+pub struct Elapsed;
+
+impl Elapsed {
+    #[cfg(feature = "log")]
+    pub fn start() -> Elapsed {
+        Elapsed(SteadyTime::now())
+    }
+
+    #[cfg(not(feature = "log"))]
+    pub fn start() -> Elapsed {
+        Elapsed
+    }
+}
+
+impl fmt::Display for Elapsed {
+    #[cfg(feature = "log")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let micros = (SteadyTime::now() - self.0).num_microseconds().unwrap_or(0);
+        write!(f, "{}.{:03}ms", micros / 1000, micros % 1000)
+    }
+
+    #[cfg(not(feature = "log"))]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "-")
+    }
+}
+
+/// Displays `ok` or an error.
+pub struct Outcome<'a, T: 'a>(pub &'a MyResult<T>);
+
+impl<'a, T: 'a> fmt::Display for Outcome<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Ok(_) => write!(f, "ok"),
+            Err(ref err) => write!(f, "error: {}", err),
+        }
+    }
+}
+
+/// Displays SQL text truncated to `Opts::log_sql_max_len` bytes.
+pub struct Sql<'a>(pub &'a str, pub Option<usize>);
+
+impl<'a> fmt::Display for Sql<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1 {
+            Some(max_len) if self.0.len() > max_len => {
+                let mut end = max_len;
+                while !self.0.is_char_boundary(end) {
+                    end -= 1;
+                }
+                write!(f, "{}... ({} bytes)", &self.0[..end], self.0.len())
+            },
+            _ => f.write_str(self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sql;
+
+    #[test]
+    fn should_truncate_sql() {
+        assert_eq!(format!("{}", Sql("SELECT 1", None)), "SELECT 1");
+        assert_eq!(format!("{}", Sql("SELECT 1", Some(8))), "SELECT 1");
+        assert_eq!(format!("{}", Sql("SELECT 1", Some(6))), "SELECT... (8 bytes)");
+        assert_eq!(format!("{}", Sql("SELECT 'ü'", Some(9))), "SELECT '... (11 bytes)");
+        assert_eq!(format!("{}", Sql("SELECT 1", Some(0))), "... (8 bytes)");
+    }
+}
@@ -0,0 +1,89 @@
+//! Hooks invoked around every query, statement preparation and statement execution.
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::super::error::Error;
+use super::super::error::Result as MyResult;
+
+/// Kind of an intercepted command.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum QueryKind {
+    /// Text query (`Conn::query`).
+    Query,
+    /// Statement preparation (`Conn::prepare`, `Conn::prep_exec` and friends). Invoked even if
+    /// statement will be taken from the statement cache.
+    Prepare,
+    /// Execution of a prepared statement. SQL text is the one which was prepared.
+    Execute,
+}
+
+/// Query middleware registered via [`Opts::interceptors`](struct.Opts.html#structfield.interceptors).
+///
+/// Could be used to rewrite SQL (e.g. to inject comments with request context), to veto
+/// queries or to observe results (e.g. for audit logging).
+///
+/// ```ignore
+/// struct Commenter;
+///
+/// impl QueryInterceptor for Commenter {
+///     fn before(&self, kind: QueryKind, query: &str) -> MyResult<Option<String>> {
+///         Ok(Some(format!("{} /* request_id={} */", query, current_request_id())))
+///     }
+/// }
+///
+/// let opts = Opts {
+///     interceptors: vec![Interceptor::new(Commenter)],
+///     ..get_opts()
+/// };
+/// ```
+pub trait QueryInterceptor: Send + Sync {
+    /// Called before a command is sent to the server.
+    ///
+    /// Returning `Ok(Some(sql))` replaces SQL text and returning an error vetoes the command.
+    /// Replacement is ignored for `QueryKind::Execute` since statement is already prepared.
+    #[allow(unused_variables)]
+    fn before(&self, kind: QueryKind, query: &str) -> MyResult<Option<String>> {
+        Ok(None)
+    }
+
+    /// Called after server response to a command (which was not vetoed) is handled.
+    ///
+    /// For commands returning rows it is called before rows are read.
+    #[allow(unused_variables)]
+    fn after(&self, kind: QueryKind, query: &str, error: Option<&Error>) {}
+}
+
+/// Shareable [`QueryInterceptor`](trait.QueryInterceptor.html) which could be stored in `Opts`.
+#[derive(Clone)]
+pub struct Interceptor(Arc<QueryInterceptor>);
+
+impl Interceptor {
+    pub fn new<T: QueryInterceptor + 'static>(interceptor: T) -> Interceptor {
+        Interceptor(Arc::new(interceptor))
+    }
+
+    #[doc(hidden)]
+    pub fn before(&self, kind: QueryKind, query: &str) -> MyResult<Option<String>> {
+        self.0.before(kind, query)
+    }
+
+    #[doc(hidden)]
+    pub fn after(&self, kind: QueryKind, query: &str, error: Option<&Error>) {
+        self.0.after(kind, query, error)
+    }
+}
+
+impl PartialEq for Interceptor {
+    fn eq(&self, other: &Interceptor) -> bool {
+        &*self.0 as *const _ as *const () == &*other.0 as *const _ as *const ()
+    }
+}
+
+impl Eq for Interceptor {}
+
+impl fmt::Debug for Interceptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Interceptor")
+    }
+}
@@ -0,0 +1,184 @@
+//! Export of driver metrics (e.g. to Prometheus) via a user-provided recorder.
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use super::pool::PoolEventHandler;
+
+/// Counter of connections established by pools.
+pub const POOL_CONNECTIONS_CREATED: &'static str = "mysql_pool_connections_created_total";
+/// Counter of connections closed by pools.
+pub const POOL_CONNECTIONS_DISCARDED: &'static str = "mysql_pool_connections_discarded_total";
+/// Counter of failed attempts of pools to establish a connection.
+pub const POOL_CONNECTION_ERRORS: &'static str = "mysql_pool_connection_errors_total";
+/// Counter of connections taken from pools.
+pub const POOL_CHECKOUTS: &'static str = "mysql_pool_checkouts_total";
+/// Histogram of time (in seconds) callers waited for a connection of a pool.
+pub const POOL_CHECKOUT_WAIT: &'static str = "mysql_pool_checkout_wait_seconds";
+/// Counter of callers who gave up waiting for a connection of a pool.
+pub const POOL_CHECKOUT_TIMEOUTS: &'static str = "mysql_pool_checkout_timeouts_total";
+/// Histogram of latency (in seconds) of queries, prepares and executions.
+pub const QUERY_DURATION: &'static str = "mysql_query_duration_seconds";
+/// Counter of queries, prepares and executions which failed.
+pub const QUERY_ERRORS: &'static str = "mysql_query_errors_total";
+
+/// Receiver of driver metrics registered via
+/// [`set_metrics_recorder`](fn.set_metrics_recorder.html).
+///
+/// Metrics are identified by names (see constants of this module) in Prometheus style, so an
+/// implementation usually just forwards them to a counter or a histogram of a registry.
+/// Recorder is called on hot paths (and by pools while they are locked), so it should be fast.
+///
+/// ```ignore
+/// struct Prometheus(prometheus::Registry);
+///
+/// impl MetricsRecorder for Prometheus {
+///     fn increment_counter(&self, name: &'static str, value: u64) { ... }
+///     fn record_histogram(&self, name: &'static str, value: f64) { ... }
+/// }
+///
+/// mysql::set_metrics_recorder(Prometheus(registry));
+/// ```
+pub trait MetricsRecorder: Send + Sync {
+    /// Adds `value` to a counter `name`.
+    fn increment_counter(&self, name: &'static str, value: u64);
+
+    /// Records an observation `value` of a histogram `name`.
+    fn record_histogram(&self, name: &'static str, value: f64);
+}
+
+lazy_static! {
+    static ref RECORDER: RwLock<Option<Arc<MetricsRecorder>>> = RwLock::new(None);
+}
+
+/// Sets process-wide recorder of metrics of every pool and connection. Replaces previously
+/// set recorder.
+pub fn set_metrics_recorder<R: MetricsRecorder + 'static>(recorder: R) {
+    let mut guard = RECORDER.write().unwrap_or_else(|err| err.into_inner());
+    *guard = Some(Arc::new(recorder));
+}
+
+/// Removes recorder set via [`set_metrics_recorder`](fn.set_metrics_recorder.html).
+pub fn clear_metrics_recorder() {
+    let mut guard = RECORDER.write().unwrap_or_else(|err| err.into_inner());
+    *guard = None;
+}
+
+fn with_recorder<F: FnOnce(&MetricsRecorder)>(f: F) {
+    let recorder = match RECORDER.read() {
+        Ok(guard) => guard.clone(),
+        Err(err) => err.into_inner().clone(),
+    };
+    if let Some(recorder) = recorder {
+        f(&*recorder);
+    }
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1_000_000_000.0
+}
+
+#[doc(hidden)]
+pub fn increment_counter(name: &'static str) {
+    with_recorder(|recorder| recorder.increment_counter(name, 1));
+}
+
+#[doc(hidden)]
+pub fn record_duration(name: &'static str, duration: Duration) {
+    with_recorder(|recorder| recorder.record_histogram(name, seconds(duration)));
+}
+
+/// Records latency of a command and whether it failed.
+#[doc(hidden)]
+pub fn record_query(duration: Duration, failed: bool) {
+    with_recorder(|recorder| {
+        recorder.record_histogram(QUERY_DURATION, seconds(duration));
+        if failed {
+            recorder.increment_counter(QUERY_ERRORS, 1);
+        }
+    });
+}
+
+/// Event handler which is registered in every pool to forward its events to the recorder.
+#[doc(hidden)]
+pub struct PoolMetrics;
+
+impl PoolEventHandler for PoolMetrics {
+    fn connection_created(&self, _: u32) {
+        increment_counter(POOL_CONNECTIONS_CREATED);
+    }
+
+    fn checked_out(&self, _: u32, wait: Duration) {
+        increment_counter(POOL_CHECKOUTS);
+        record_duration(POOL_CHECKOUT_WAIT, wait);
+    }
+
+    fn discarded(&self, _: u32) {
+        increment_counter(POOL_CONNECTIONS_DISCARDED);
+    }
+
+    fn checkout_timed_out(&self, wait: Duration) {
+        increment_counter(POOL_CHECKOUT_TIMEOUTS);
+        record_duration(POOL_CHECKOUT_WAIT, wait);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use conn::Opts;
+    use conn::pool::Pool;
+    use std::default::Default;
+
+    #[derive(Clone, Default)]
+    struct TestRecorder(Arc<Mutex<(HashMap<&'static str, u64>, Vec<(&'static str, f64)>)>>);
+
+    impl MetricsRecorder for TestRecorder {
+        fn increment_counter(&self, name: &'static str, value: u64) {
+            *self.0.lock().unwrap().0.entry(name).or_insert(0) += value;
+        }
+
+        fn record_histogram(&self, name: &'static str, value: f64) {
+            self.0.lock().unwrap().1.push((name, value));
+        }
+    }
+
+    fn get_opts() -> Opts {
+        let pwd: String = ::std::env::var("MYSQL_SERVER_PASS").unwrap_or("password".to_string());
+        let port: u16 = ::std::env::var("MYSQL_SERVER_PORT").ok()
+                                   .map(|my_port| my_port.parse().ok().unwrap_or(3307))
+                                   .unwrap_or(3307);
+        Opts {
+            user: Some("root".to_string()),
+            pass: Some(pwd),
+            ip_or_hostname: Some("127.0.0.1".to_string()),
+            tcp_port: port,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_record_pool_and_query_metrics() {
+        let recorder = TestRecorder::default();
+        set_metrics_recorder(recorder.clone());
+
+        let pool = Pool::new_manual(1, 1, get_opts()).unwrap();
+        pool.get_conn().unwrap().query("SELECT 1").unwrap();
+        assert!(pool.get_conn().unwrap().query("SELECT * FROM no_such_table").is_err());
+        assert!(Pool::new_manual(1, 1, Opts { tcp_port: 1, ..get_opts() }).is_err());
+        clear_metrics_recorder();
+
+        // Other tests may use the driver meanwhile, so only lower bounds are checked.
+        let data = recorder.0.lock().unwrap();
+        let (ref counters, ref histograms) = *data;
+        assert!(counters[POOL_CONNECTIONS_CREATED] >= 1);
+        assert!(counters[POOL_CHECKOUTS] >= 2);
+        assert!(counters[POOL_CONNECTION_ERRORS] >= 1);
+        assert!(counters[QUERY_ERRORS] >= 1);
+        assert!(histograms.iter().filter(|x| x.0 == POOL_CHECKOUT_WAIT).count() >= 2);
+        assert!(histograms.iter().filter(|x| x.0 == QUERY_DURATION).count() >= 2);
+    }
+}
@@ -1,11 +1,15 @@
 use std::borrow::Borrow;
+use std::borrow::Cow;
+use std::cmp;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::fs;
 use std::fmt;
 use std::hash::BuildHasherDefault as BldHshrDflt;
 use std::io;
 use std::io::Read;
 use std::io::Write as NewWrite;
+use std::mem;
 use std::net;
 use std::ops::{
     Deref,
@@ -14,13 +18,18 @@ use std::ops::{
 };
 use std::path;
 use std::str::from_utf8;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration as StdDuration;
+use std::time::Instant;
 
 use super::consts;
 use super::consts::Command;
 use super::consts::ColumnType;
 use super::io::Read as MyRead;
 use super::io::Write;
-use super::io::Stream;
+use super::io::{CustomStream, Stream as IoStream};
 use super::io::TcpStream::Insecure;
 use super::error::Error::{
     IoError,
@@ -33,18 +42,44 @@ use super::error::DriverError::{
     Protocol41NotSet,
     UnexpectedPacket,
     MismatchedStmtParams,
+    MissingNamedParameter,
+    UnsupportedAuthPlugin,
+    CleartextPasswordNotAllowed,
+    NamedParamsForPositionalQuery,
+    ResultNotConsumed,
+    MultiStatementsDisabled,
+    ResultBudgetExceeded,
+    RowLimitExceeded,
+    PacketTooLarge,
+    QueryCancelled,
+    InvalidStmtParam,
     SetupError,
     ReadOnlyTransNotSupported,
+    AuthFailed,
+    AuthPluginMismatch,
+    SecureTransportRequired,
+    HandshakeRejected,
 };
+#[cfg(feature = "ssl")]
+use super::error::DriverError::SslOverCustomStream;
+use super::error::{Error, FromColumnError};
 use super::error::Result as MyResult;
 #[cfg(feature = "ssl")]
 use super::error::DriverError::SslNotSupported;
-use super::scramble::scramble;
+use super::escape::{escape_string, quote_identifier};
+use super::scramble::{scramble, scramble_323, scramble_ed25519, scramble_sha256};
 use super::packet::{OkPacket, EOFPacket, ErrPacket, HandshakePacket, ServerVersion};
+use self::instrument::{Elapsed, Outcome, Sql};
+use self::recorder::render_params;
 use super::value::{
+    FromRow,
+    FromValue,
     Params,
     Value,
-    from_value_opt
+    ValueRef,
+    from_row_opt,
+    from_value_opt,
+    rejected_value
 };
 #[cfg(any(feature = "pipe", feature = "socket"))]
 use super::value::from_value;
@@ -54,14 +89,51 @@ use bufstream::BufStream;
 use byteorder::LittleEndian as LE;
 use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
 use fnv::FnvHasher;
+use socket2;
 #[cfg(feature = "socket")]
 use unix_socket as us;
 #[cfg(feature = "pipe")]
 use named_pipe as np;
 
 pub mod pool;
+mod admin;
+mod cancel;
+mod export;
+mod instrument;
+mod interceptor;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod my_cnf;
+mod named_params;
 mod opts;
-pub use self::opts::Opts;
+mod proxy;
+mod recorder;
+pub mod replicated;
+mod replication;
+mod schema;
+pub mod sharded;
+mod sink;
+mod stream;
+mod trace;
+#[cfg(feature = "async")]
+pub mod async;
+pub use self::admin::{ProcessInfo, ServerStatistics};
+pub use self::cancel::CancellationToken;
+pub use self::export::CsvOptions;
+pub use self::interceptor::{Interceptor, QueryInterceptor, QueryKind};
+pub use self::named_params::in_list;
+#[doc(hidden)]
+pub use self::named_params::{count_placeholders, parse_named_params};
+pub use self::opts::{InitFn, OnBudgetExceeded, Opts, PoolOpts, ProxyOpts, ResolveStrategy};
+pub use self::opts::{ResultBudget, RetryOn, RetryPolicy, TextCoercion};
+pub use self::recorder::{Record, Recorder};
+pub use self::replication::{GtidSet, MasterStatus, ParseGtidSetError, ReplicaStatus};
+pub use self::schema::ColumnInfo;
+pub use self::sink::{SharedSink, Sink};
+pub use self::stream::Stream;
+pub use self::trace::{Packet, PacketDirection, PacketTracer};
+#[cfg(feature = "ssl")]
+pub use self::opts::{Certificate, Identity, SslOpts, TlsVersion};
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum IsolationLevel {
@@ -82,11 +154,111 @@ impl fmt::Display for IsolationLevel {
     }
 }
 
+/// Transaction access mode. Available since MySQL 5.6.5.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AccessMode {
+    ReadOnly,
+    ReadWrite,
+}
+
+impl fmt::Display for AccessMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AccessMode::ReadOnly => write!(f, "READ ONLY"),
+            AccessMode::ReadWrite => write!(f, "READ WRITE"),
+        }
+    }
+}
+
+/// Options of a transaction started via
+/// [`Conn::start_transaction`](struct.Conn.html#method.start_transaction).
+///
+/// Isolation level and access mode which are not set will be taken from
+/// `Opts::default_isolation_level` and `Opts::default_access_mode` respectively, or will be left
+/// at server defaults.
+///
+/// ```ignore
+/// let tx_opts = TxOpts::new()
+///     .with_consistent_snapshot(true)
+///     .with_isolation_level(IsolationLevel::RepeatableRead)
+///     .with_access_mode(AccessMode::ReadOnly);
+/// let mut t = try!(conn.start_transaction(tx_opts));
+/// ```
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct TxOpts {
+    consistent_snapshot: bool,
+    isolation_level: Option<IsolationLevel>,
+    access_mode: Option<AccessMode>,
+}
+
+impl TxOpts {
+    /// Creates default options, i.e. without consistent snapshot and with default isolation
+    /// level and access mode.
+    pub fn new() -> TxOpts {
+        TxOpts::default()
+    }
+
+    /// Starts transaction `WITH CONSISTENT SNAPSHOT` (defaults to `false`).
+    pub fn with_consistent_snapshot(mut self, consistent_snapshot: bool) -> TxOpts {
+        self.consistent_snapshot = consistent_snapshot;
+        self
+    }
+
+    /// Sets isolation level of a transaction.
+    pub fn with_isolation_level(mut self, isolation_level: IsolationLevel) -> TxOpts {
+        self.isolation_level = Some(isolation_level);
+        self
+    }
+
+    /// Sets access mode of a transaction.
+    pub fn with_access_mode(mut self, access_mode: AccessMode) -> TxOpts {
+        self.access_mode = Some(access_mode);
+        self
+    }
+
+    pub fn consistent_snapshot(&self) -> bool {
+        self.consistent_snapshot
+    }
+
+    pub fn isolation_level(&self) -> Option<IsolationLevel> {
+        self.isolation_level
+    }
+
+    pub fn access_mode(&self) -> Option<AccessMode> {
+        self.access_mode
+    }
+}
+
+/// What happens to a [`Transaction`](struct.Transaction.html) which is dropped without
+/// `commit` or `rollback`.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum DropBehavior {
+    /// Transaction is rolled back (the default).
+    Rollback,
+    /// Transaction is committed.
+    ///
+    /// `Drop` can not return an error, so failure of `COMMIT` (e.g. a deadlock or a lost
+    /// connection) is swallowed and only logged with `mysql` target if `log` feature is
+    /// enabled. Call [`Transaction::commit`](struct.Transaction.html#method.commit) if the
+    /// outcome matters.
+    Commit,
+    /// Drop panics in debug builds (unless thread is already panicking), so that a forgotten
+    /// `commit` is caught by tests. Transaction is rolled back in release builds.
+    Panic,
+}
+
+impl Default for DropBehavior {
+    fn default() -> DropBehavior {
+        DropBehavior::Rollback
+    }
+}
+
 #[derive(Debug)]
 pub struct Transaction<'a> {
     conn: ConnRef<'a>,
     committed: bool,
     rolled_back: bool,
+    drop_behavior: DropBehavior,
 }
 
 impl<'a> Transaction<'a> {
@@ -95,6 +267,7 @@ impl<'a> Transaction<'a> {
             conn: ConnRef::ViaConnRef(conn),
             committed: false,
             rolled_back: false,
+            drop_behavior: DropBehavior::default(),
         }
     }
 
@@ -103,14 +276,52 @@ impl<'a> Transaction<'a> {
             conn: ConnRef::ViaPooledConn(conn),
             committed: false,
             rolled_back: false,
+            drop_behavior: DropBehavior::default(),
         }
     }
 
+    /// Returns what happens if transaction is dropped without `commit` or `rollback`.
+    pub fn drop_behavior(&self) -> DropBehavior {
+        self.drop_behavior
+    }
+
+    /// Sets what happens if transaction is dropped without `commit` or `rollback`.
+    pub fn set_drop_behavior(&mut self, drop_behavior: DropBehavior) {
+        self.drop_behavior = drop_behavior;
+    }
+
+    /// Shortcut for `set_drop_behavior(DropBehavior::Rollback)` if `rollback` is `true` and
+    /// `set_drop_behavior(DropBehavior::Commit)` otherwise.
+    pub fn set_rollback_on_drop(&mut self, rollback: bool) {
+        self.drop_behavior = if rollback {
+            DropBehavior::Rollback
+        } else {
+            DropBehavior::Commit
+        };
+    }
+
+    /// See [`Conn#in_transaction`](struct.Conn.html#method.in_transaction).
+    pub fn in_transaction(&self) -> bool {
+        self.conn.in_transaction()
+    }
+
     /// See [`Conn#query`](struct.Conn.html#method.query).
     pub fn query<'c, T: AsRef<str> + 'c>(&'c mut self, query: T) -> MyResult<QueryResult<'c>> {
         self.conn.query(query)
     }
 
+    /// See [`Conn#query_multi`](struct.Conn.html#method.query_multi).
+    pub fn query_multi<'c, T: AsRef<str>>(&'c mut self, query: T) -> MyResult<MultiResult<'c>> {
+        self.conn.query_multi(query)
+    }
+
+    /// See [`Conn#call`](struct.Conn.html#method.call).
+    pub fn call<T, P>(&mut self, proc_name: T, params: P) -> MyResult<ProcResult>
+    where T: AsRef<str>,
+          P: Into<Params> {
+        self.conn.call(proc_name, params)
+    }
+
     /// See [`Conn#prepare`](struct.Conn.html#method.prepare).
     pub fn prepare<'c, T: AsRef<str> + 'c>(&'c mut self, query: T) -> MyResult<Stmt<'c>> {
         self.conn.prepare(query)
@@ -121,6 +332,87 @@ impl<'a> Transaction<'a> {
         self.conn.prep_exec(query, params)
     }
 
+    /// See [`Conn#prep_exec_buffered`](struct.Conn.html#method.prep_exec_buffered).
+    pub fn prep_exec_buffered<A, T>(&mut self, query: A, params: T) -> MyResult<Vec<Row>>
+    where A: AsRef<str>,
+          T: Into<Params> {
+        self.conn.prep_exec_buffered(query, params)
+    }
+
+    /// See [`Conn#exec`](struct.Conn.html#method.exec).
+    pub fn exec<T, A, P>(&mut self, query: A, params: P) -> MyResult<Vec<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        self.conn.exec(query, params)
+    }
+
+    /// See [`Conn#exec_first`](struct.Conn.html#method.exec_first).
+    pub fn exec_first<T, A, P>(&mut self, query: A, params: P) -> MyResult<Option<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        self.conn.exec_first(query, params)
+    }
+
+    /// See [`Conn#exec_drop`](struct.Conn.html#method.exec_drop).
+    pub fn exec_drop<A, P>(&mut self, query: A, params: P) -> MyResult<()>
+    where A: AsRef<str>,
+          P: Into<Params> {
+        self.conn.exec_drop(query, params)
+    }
+
+    /// See [`Conn#insert_many`](struct.Conn.html#method.insert_many).
+    pub fn insert_many<T, C, I, P>(&mut self,
+                                   table: T,
+                                   columns: &[C],
+                                   rows: I,
+                                   chunk_size: usize) -> MyResult<u64>
+    where T: AsRef<str>,
+          C: AsRef<str>,
+          I: IntoIterator<Item=P>,
+          P: Into<Params> {
+        self.conn.insert_many(table, columns, rows, chunk_size)
+    }
+
+    /// See [`Conn#query_map`](struct.Conn.html#method.query_map).
+    pub fn query_map<T, U, Q, F>(&mut self, query: Q, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          Q: AsRef<str>,
+          F: FnMut(T) -> U {
+        self.conn.query_map(query, f)
+    }
+
+    /// See [`Conn#query_fold`](struct.Conn.html#method.query_fold).
+    pub fn query_fold<T, U, Q, F>(&mut self, query: Q, init: U, f: F) -> MyResult<U>
+    where T: FromRow,
+          Q: AsRef<str>,
+          F: FnMut(U, T) -> U {
+        self.conn.query_fold(query, init, f)
+    }
+
+    /// See [`Conn#prep_exec_map`](struct.Conn.html#method.prep_exec_map).
+    pub fn prep_exec_map<T, U, A, P, F>(&mut self, query: A, params: P, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params>,
+          F: FnMut(T) -> U {
+        self.conn.prep_exec_map(query, params, f)
+    }
+
+    /// See [`Conn#prep_exec_fold`](struct.Conn.html#method.prep_exec_fold).
+    pub fn prep_exec_fold<T, U, A, P, F>(&mut self,
+                                         query: A,
+                                         params: P,
+                                         init: U,
+                                         f: F) -> MyResult<U>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params>,
+          F: FnMut(U, T) -> U {
+        self.conn.prep_exec_fold(query, params, init, f)
+    }
+
     /// Will consume and commit transaction.
     pub fn commit(mut self) -> MyResult<()> {
         try!(self.conn.query("COMMIT"));
@@ -128,6 +420,17 @@ impl<'a> Transaction<'a> {
         Ok(())
     }
 
+    /// Same as `commit` but returns GTID of the committed transaction (see
+    /// [`Conn::last_gtid`](struct.Conn.html#method.last_gtid)).
+    ///
+    /// Returns `Ok(None)` if GTIDs are not tracked or transaction did not change any data.
+    pub fn commit_with_gtid(mut self) -> MyResult<Option<GtidSet>> {
+        self.conn.last_gtid = None;
+        try!(self.conn.query("COMMIT"));
+        self.committed = true;
+        Ok(self.conn.last_gtid.clone())
+    }
+
     /// Will consume and rollback transaction. You also can rely on `Drop` implementation but it
     /// will swallow errors.
     pub fn rollback(mut self) -> MyResult<()> {
@@ -138,10 +441,24 @@ impl<'a> Transaction<'a> {
 }
 
 impl<'a> Drop for Transaction<'a> {
-    /// Will rollback transaction.
+    /// Will rollback or commit transaction according to its `DropBehavior`.
     fn drop(&mut self) {
-        if ! self.committed && ! self.rolled_back {
-            let _ = self.conn.query("ROLLBACK");
+        if self.committed || self.rolled_back {
+            return;
+        }
+        match self.drop_behavior {
+            DropBehavior::Commit => {
+                if let Err(err) = self.conn.query("COMMIT") {
+                    mysql_warn!("COMMIT of a dropped transaction failed: {}", err);
+                }
+            },
+            DropBehavior::Panic if cfg!(debug_assertions) && !thread::panicking() => {
+                let _ = self.conn.query("ROLLBACK");
+                panic!("Transaction was dropped without commit or rollback");
+            },
+            DropBehavior::Rollback | DropBehavior::Panic => {
+                let _ = self.conn.query("ROLLBACK");
+            },
         }
     }
 }
@@ -167,6 +484,14 @@ struct InnerStmt {
     num_columns: u16,
     num_params: u16,
     warning_count: u16,
+    /// Names of `:name` placeholders in order of appearance.
+    named_params: Option<Vec<String>>,
+    /// SQL text passed to `Conn::_prepare` (after interceptors).
+    query: Arc<String>,
+    /// SQL text passed to `Conn::_prepare` (before interceptors). Key of the statement cache.
+    cache_key: Arc<String>,
+    /// `Conn::stmt_epoch` at the time of preparation.
+    epoch: u64,
 }
 
 impl InnerStmt {
@@ -181,7 +506,11 @@ impl InnerStmt {
                      num_params: num_params,
                      warning_count: warning_count,
                      params: None,
-                     columns: None})
+                     columns: None,
+                     named_params: None,
+                     query: Arc::new(String::new()),
+                     cache_key: Arc::new(String::new()),
+                     epoch: 0})
     }
 }
 
@@ -214,6 +543,9 @@ impl<'a> DerefMut for ConnRef<'a> {
 
 /// Mysql
 /// [prepared statement](http://dev.mysql.com/doc/internals/en/prepared-statements.html).
+///
+/// Statement remains valid if its connection is reset or reconnected: it is prepared again
+/// on its next execution.
 #[derive(Debug)]
 pub struct Stmt<'a> {
     stmt: InnerStmt,
@@ -253,6 +585,39 @@ impl<'a> Stmt<'a> {
         }
     }
 
+    /// Returns parameters of a statement as declared by server (empty slice if there are no
+    /// parameters).
+    ///
+    /// Server does not infer much about parameters, so most of them will have
+    /// `MYSQL_TYPE_VAR_STRING` type, but the count is exact.
+    pub fn params(&self) -> &[Column] {
+        self.params_ref().unwrap_or(&[])
+    }
+
+    /// Returns columns of a statement's result set (empty slice if statement returns no rows).
+    pub fn columns(&self) -> &[Column] {
+        self.columns_ref().unwrap_or(&[])
+    }
+
+    /// Returns number of `?` placeholders of a statement.
+    ///
+    /// Every occurrence of a named parameter is a separate placeholder, use
+    /// [`Stmt::named_params`](#method.named_params) to get their names.
+    pub fn num_params(&self) -> u16 {
+        self.stmt.num_params
+    }
+
+    /// Returns number of columns of a statement's result set.
+    pub fn num_columns(&self) -> u16 {
+        self.stmt.num_columns
+    }
+
+    /// Returns names of `:name` placeholders in order of appearance or `None` if statement
+    /// has positional parameters. Name which is used more than once is repeated.
+    pub fn named_params(&self) -> Option<&[String]> {
+        self.stmt.named_params.as_ref().map(|names| &**names)
+    }
+
     /// Returns index of a `Stmt`'s column by name.
     pub fn column_index<T: AsRef<str>>(&self, name: T) -> Option<usize> {
         match self.stmt.columns {
@@ -271,6 +636,9 @@ impl<'a> Stmt<'a> {
 
     /// Executes prepared statement with parameters passed as a [`Into<Params>`] implementor.
     ///
+    /// Parameters are serialized into a buffer owned by the connection, which is reused by
+    /// subsequent executions, so executing a hot statement does not allocate a packet.
+    ///
     /// ```rust
     /// # use mysql::conn::pool;
     /// # use mysql::conn::Opts;
@@ -338,15 +706,34 @@ impl<'a> Stmt<'a> {
     /// }
     /// ```
     pub fn execute<'s, T: Into<Params>>(&'s mut self, params: T) -> MyResult<QueryResult<'s>> {
-        self.conn.execute(&self.stmt, params)
+        self.conn.execute(&mut self.stmt, params)
+    }
+
+    /// Resets server-side state of a statement (via `COM_STMT_RESET`).
+    ///
+    /// Discards long data accumulated for parameters and closes an open cursor. Driver resets
+    /// a statement by itself if its execution fails after long data was sent, so this is only
+    /// needed if server-side state was changed by other means.
+    pub fn reset(&mut self) -> MyResult<()> {
+        if self.stmt.epoch != self.conn.stmt_epoch {
+            // Statement is not prepared on the current connection yet.
+            return Ok(());
+        }
+        self.conn.reset_stmt(self.stmt.statement_id)
     }
 
     fn prep_exec<T: Into<Params>>(mut self, params: T) -> MyResult<QueryResult<'a>> {
-        let (columns, ok_packet) = try!(self.conn._execute(&self.stmt, params.into()));
+        let (columns, ok_packet) = try!(self.conn._execute(&mut self.stmt, params.into()));
         Ok(QueryResult::new(ResultConnRef::ViaStmt(self), columns, ok_packet, true))
     }
 }
 
+impl<'a> Drop for Stmt<'a> {
+    fn drop(&mut self) {
+        self.conn.release_stmt(&self.stmt);
+    }
+}
+
 /***
  *     .d8888b.           888
  *    d88P  Y88b          888
@@ -361,8 +748,36 @@ impl<'a> Stmt<'a> {
  *
  */
 
+/// Response to a command sent via [`Conn::write_command`](struct.Conn.html#method.write_command).
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum RawResponse {
+    Ok(OkPacket),
+    /// EOF packet, e.g. a response to `COM_SET_OPTION`.
+    Eof,
+    /// Any other packet (e.g. a column count of a result set or a response to
+    /// `COM_STATISTICS`). Following packets of a response should be read via
+    /// [`Conn::read_raw_packet`](struct.Conn.html#method.read_raw_packet).
+    Packet(Vec<u8>),
+}
+
+/// Row of a [`SHOW WARNINGS`](http://dev.mysql.com/doc/refman/5.7/en/show-warnings.html)
+/// output. See [`Conn::get_warnings`](struct.Conn.html#method.get_warnings).
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Warning {
+    /// `Note`, `Warning` or `Error`.
+    pub level: String,
+    /// Mysql error code.
+    pub code: u16,
+    /// Warning message.
+    pub message: String,
+}
+
 /// Mysql
 /// [`Column`](http://dev.mysql.com/doc/internals/en/com-query-response.html#packet-Protocol::ColumnDefinition).
+///
+/// Protocol does not tell whether a column is generated, invisible or has an expression
+/// default. Use [`Conn::columns`](struct.Conn.html#method.columns) (see
+/// [`ColumnInfo::is_generated`](struct.ColumnInfo.html#method.is_generated)) to get it.
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct Column {
     /// Schema name.
@@ -425,11 +840,26 @@ impl Column {
                   decimals: decimals,
                   default_values: default_values})
     }
+
+    /// Returns `true` if column is an `ENUM` column.
+    pub fn is_enum(&self) -> bool {
+        self.flags.contains(consts::ENUM_FLAG) ||
+        self.column_type == ColumnType::MYSQL_TYPE_ENUM
+    }
+
+    /// Returns `true` if column is a `SET` column.
+    ///
+    /// Use [`Set`](value/struct.Set.html) to convert values of such column.
+    pub fn is_set(&self) -> bool {
+        self.flags.contains(consts::SET_FLAG) ||
+        self.column_type == ColumnType::MYSQL_TYPE_SET
+    }
 }
 
 /// Mysql row representation.
 ///
-/// Only stores values of a cells. To get column names please refer to
+/// Stores values of a cells and shares columns metadata with other rows of a result set.
+/// To get column index by name please refer to [`Row::column_index`](#method.column_index),
 /// [`QueryResult::column_index`](struct.QueryResult.html#method.column_index) and
 /// [`QueryResult::column_indexes`](struct.QueryResult.html#method.column_indexes) methods.
 ///
@@ -473,20 +903,47 @@ impl Column {
 /// });
 /// ```
 ///
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Debug)]
 pub struct Row {
     values: Vec<Option<Value>>,
+    columns: Arc<Vec<Column>>,
+    conversion_error: Option<Box<FromColumnError>>,
+}
+
+impl PartialEq for Row {
+    fn eq(&self, other: &Row) -> bool {
+        self.values == other.values
+    }
 }
 
 impl Row {
     /// Creates instance of `Row` from raw row representation
     #[doc(hidden)]
     pub fn new(raw_row: Vec<Value>) -> Row {
+        Row::new_with_columns(raw_row, Arc::new(Vec::new()))
+    }
+
+    fn new_with_columns(raw_row: Vec<Value>, columns: Arc<Vec<Column>>) -> Row {
         Row {
             values: raw_row.into_iter().map(|value| Some(value)).collect(),
+            columns: columns,
+            conversion_error: None,
         }
     }
 
+    /// Returns columns of a result set this row belongs to.
+    ///
+    /// Slice will be empty for rows which was not produced by `QueryResult`.
+    pub fn columns_ref(&self) -> &[Column] {
+        &*self.columns
+    }
+
+    /// Returns index of a row's column by name.
+    pub fn column_index<T: AsRef<str>>(&self, name: T) -> Option<usize> {
+        let name = name.as_ref().as_bytes();
+        self.columns.iter().position(|c| c.name == name)
+    }
+
     /// Returns length of a row.
     pub fn len(&self) -> usize {
         self.values.len()
@@ -503,6 +960,97 @@ impl Row {
         self.values.get_mut(index).and_then(|x| x.take())
     }
 
+    /// Converts value of a column given by index or name into `T` without taking it.
+    ///
+    /// Returns `None` if there is no such column or if its value was taken.
+    ///
+    /// # Panics
+    ///
+    /// Panics if conversion fails. Panic message contains name and index of the column.
+    pub fn get<T: FromValue, I: ColumnIndex>(&self, index: I) -> Option<T> {
+        self.get_opt(index).map(|result| match result {
+            Ok(value) => value,
+            Err(err) => panic!("{}", err),
+        })
+    }
+
+    /// Same as [`Row::get`](#method.get) but returns `Error::FromColumnError` if conversion
+    /// fails.
+    pub fn get_opt<T: FromValue, I: ColumnIndex>(&self, index: I) -> Option<MyResult<T>> {
+        let index = match index.idx(&*self.columns) {
+            Some(index) => index,
+            None => return None,
+        };
+        self.as_ref(index).map(|value| {
+            T::from_value_opt(value.clone()).map_err(|err| self.column_error::<T>(index, err))
+        })
+    }
+
+    /// Takes value of a column given by index or name and converts it into `T`.
+    ///
+    /// Value is placed back into the row if conversion fails.
+    pub fn take_opt<T: FromValue, I: ColumnIndex>(&mut self, index: I) -> Option<MyResult<T>> {
+        let index = match index.idx(&*self.columns) {
+            Some(index) => index,
+            None => return None,
+        };
+        let value = match self.take(index) {
+            Some(value) => value,
+            None => return None,
+        };
+        match T::from_value_opt(value) {
+            Ok(value) => Some(Ok(value)),
+            Err(err) => {
+                let err = self.column_error::<T>(index, err);
+                match err {
+                    Error::FromColumnError(ref err) => self.place(index, err.value.clone()),
+                    Error::Utf8Error(ref err) => {
+                        self.place(index, Value::Bytes(err.as_bytes().to_vec()));
+                    },
+                    _ => (),
+                }
+                Some(Err(err))
+            },
+        }
+    }
+
+    /// Adds column context to an error of `FromValue` conversion.
+    fn column_error<T>(&self, index: usize, err: Error) -> Error {
+        match err {
+            Error::FromValueError(value) => {
+                Error::FromColumnError(self.from_column_error::<T>(index, value))
+            },
+            err => err,
+        }
+    }
+
+    fn from_column_error<T>(&self, index: usize, value: Value) -> FromColumnError {
+        FromColumnError {
+            index: index,
+            name: self.columns.get(index).map(|column| {
+                String::from_utf8_lossy(&*column.name).into_owned()
+            }),
+            value: value,
+            type_name: ::std::any::type_name::<T>(),
+        }
+    }
+
+    /// Returns column which could not be converted by
+    /// [`from_row_opt`](../value/fn.from_row_opt.html) if this row was returned within
+    /// `Error::FromRowError`.
+    pub fn conversion_error(&self) -> Option<&FromColumnError> {
+        self.conversion_error.as_ref().map(|err| &**err)
+    }
+
+    /// Places value rejected by conversion into `T` back into the row and remembers the column
+    /// for [`Row::conversion_error`](#method.conversion_error).
+    #[doc(hidden)]
+    pub fn place_rejected<T>(&mut self, index: usize, err: Error) {
+        let value = rejected_value(err);
+        self.conversion_error = Some(Box::new(self.from_column_error::<T>(index, value.clone())));
+        self.place(index, value);
+    }
+
     /// Unwraps values of a row.
     ///
     /// # Panics
@@ -520,6 +1068,26 @@ impl Row {
     }
 }
 
+/// Index of a column within a row given by position or by name.
+///
+/// Used by [`Row::get`](struct.Row.html#method.get) and friends.
+pub trait ColumnIndex {
+    fn idx(&self, columns: &[Column]) -> Option<usize>;
+}
+
+impl ColumnIndex for usize {
+    fn idx(&self, _: &[Column]) -> Option<usize> {
+        Some(*self)
+    }
+}
+
+impl<'a> ColumnIndex for &'a str {
+    fn idx(&self, columns: &[Column]) -> Option<usize> {
+        let name = self.as_bytes();
+        columns.iter().position(|c| c.name == name)
+    }
+}
+
 impl Index<usize> for Row {
     type Output = Value;
 
@@ -528,6 +1096,61 @@ impl Index<usize> for Row {
     }
 }
 
+/// Takes nullable value of a column, treating a missing column as `NULL`.
+///
+/// Used to read results of `SHOW` statements and of `information_schema` queries.
+fn take_nullable<T: FromValue, I: ColumnIndex>(row: &mut Row, index: I) -> MyResult<Option<T>> {
+    match row.take_opt::<Option<T>, _>(index) {
+        Some(result) => result,
+        None => Ok(None),
+    }
+}
+
+/// Row which borrows its values from the network buffer.
+///
+/// Returned by [`RowsRef::next`](struct.RowsRef.html#method.next).
+/// Use [`to_row`](#method.to_row) to get an owned [`Row`](struct.Row.html).
+#[derive(Clone, PartialEq, Debug)]
+pub struct RowRef<'a> {
+    values: Vec<ValueRef<'a>>,
+}
+
+impl<'a> RowRef<'a> {
+    fn new(values: Vec<ValueRef<'a>>) -> RowRef<'a> {
+        RowRef {
+            values: values,
+        }
+    }
+
+    /// Returns length of a row.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns value of a column with index `index` if it exists.
+    pub fn get(&self, index: usize) -> Option<ValueRef<'a>> {
+        self.values.get(index).map(|x| *x)
+    }
+
+    /// Returns a slice of row values.
+    pub fn values(&self) -> &[ValueRef<'a>] {
+        self.values.as_ref()
+    }
+
+    /// Converts this row into an owned [`Row`](struct.Row.html).
+    pub fn to_row(&self) -> Row {
+        Row::new(self.values.iter().map(ValueRef::to_value).collect())
+    }
+}
+
+impl<'a> Index<usize> for RowRef<'a> {
+    type Output = ValueRef<'a>;
+
+    fn index<'b>(&'b self, index: usize) -> &'b ValueRef<'a> {
+        &self.values[index]
+    }
+}
+
 /***
  *    888b     d888           .d8888b.
  *    8888b   d8888          d88P  Y88b
@@ -542,24 +1165,260 @@ impl Index<usize> for Row {
  *                   "Y88P"
  */
 
+const NATIVE_PASSWORD_PLUGIN: &'static [u8] = b"mysql_native_password";
+const CACHING_SHA2_PASSWORD_PLUGIN: &'static [u8] = b"caching_sha2_password";
+const CLEAR_PASSWORD_PLUGIN: &'static [u8] = b"mysql_clear_password";
+const OLD_PASSWORD_PLUGIN: &'static [u8] = b"mysql_old_password";
+const SHA256_PASSWORD_PLUGIN: &'static [u8] = b"sha256_password";
+const ED25519_PLUGIN: &'static [u8] = b"client_ed25519";
+
+/// Execute buffers which grew larger (e.g. because of a large blob) are not kept.
+const MAX_EXEC_BUF_CAPACITY: usize = 1024 * 1024;
+
+/// Password followed by `0` XORed with the scramble, as expected by `sha256_password` plugin
+/// before RSA encryption.
+#[cfg(feature = "openssl")]
+fn xor_password(pass: &[u8], scramble: &[u8]) -> Vec<u8> {
+    let mut output = pass.to_vec();
+    output.push(0);
+    if !scramble.is_empty() {
+        for (i, x) in output.iter_mut().enumerate() {
+            *x ^= scramble[i % scramble.len()];
+        }
+    }
+    output
+}
+
+/// Builds `INSERT` statement with placeholders for `rows` rows.
+///
+/// `table` could be qualified with database name.
+fn insert_query(table: &str, columns: &[&str], rows: usize) -> String {
+    let table: Vec<String> = table.split('.').map(quote_identifier).collect();
+    let columns: Vec<String> = columns.iter().map(|column| quote_identifier(column)).collect();
+    let row = format!("({})", vec!["?"; columns.len()].join(", "));
+    format!("INSERT INTO {} ({}) VALUES {}",
+            table.join("."),
+            columns.join(", "),
+            vec![&*row; rows].join(", "))
+}
+
+/// Appends values of a row of `Conn::insert_many` in order of `columns`.
+fn push_insert_row(values: &mut Vec<Value>, columns: &[&str], row: Params) -> MyResult<()> {
+    match row {
+        Params::Positional(row) => {
+            if row.len() != columns.len() {
+                return Err(DriverError(MismatchedStmtParams(columns.len() as u16, row.len())));
+            }
+            values.extend(row);
+        },
+        Params::Named(mut row) => {
+            for column in columns {
+                match row.remove(*column) {
+                    Some(value) => values.push(value),
+                    None => return Err(DriverError(MissingNamedParameter(column.to_string()))),
+                }
+            }
+        },
+        Params::Empty => {
+            if !columns.is_empty() {
+                return Err(DriverError(MismatchedStmtParams(columns.len() as u16, 0)));
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Splits payload of an auth switch request (without header) into plugin name and
+/// auth plugin data.
+fn parse_auth_switch_request(pld: &[u8]) -> (&[u8], &[u8]) {
+    let name_len = pld.iter().position(|x| *x == 0).unwrap_or(pld.len());
+    let mut data = &pld[cmp::min(name_len + 1, pld.len())..];
+    if data.last() == Some(&0) {
+        data = &data[..data.len() - 1];
+    }
+    (&pld[..name_len], data)
+}
+
+/// Returns auth plugin for the initial handshake response.
+///
+/// `caching_sha2_password` is used if it is the default plugin of a server (MySQL 8.0), so that
+/// server does not need to switch plugins for most users. Otherwise `mysql_native_password`
+/// is used and server will switch plugin if user is configured with another one.
+fn initial_auth_plugin(hp: &HandshakePacket) -> &'static [u8] {
+    if hp.capability_flags.contains(consts::CLIENT_PLUGIN_AUTH) &&
+       hp.auth_plugin_name == CACHING_SHA2_PASSWORD_PLUGIN {
+        CACHING_SHA2_PASSWORD_PLUGIN
+    } else {
+        NATIVE_PASSWORD_PLUGIN
+    }
+}
+
+/// Builds payload of a
+/// [`HandshakeResponse41`](http://dev.mysql.com/doc/internals/en/connection-phase-packets.html#packet-Protocol::HandshakeResponse41)
+/// with password scrambled for `plugin` (`mysql_native_password` or `caching_sha2_password`).
+fn handshake_response(opts: &Opts,
+                      client_flags: consts::CapabilityFlags,
+                      hp: &HandshakePacket,
+                      plugin: &[u8]) -> io::Result<Vec<u8>> {
+    let scramble_buf = match opts.pass {
+        Some(ref pass) if plugin == CACHING_SHA2_PASSWORD_PLUGIN => {
+            scramble_sha256(&*hp.auth_plugin_data, pass.as_bytes())
+        },
+        Some(ref pass) => scramble(&*hp.auth_plugin_data, pass.as_bytes()),
+        None => None,
+    };
+    let user_len = opts.user.as_ref().map(|x| x.as_bytes().len()).unwrap_or(0);
+    let db_name_len = opts.db_name.as_ref().map(|x| x.as_bytes().len()).unwrap_or(0);
+    let scramble_buf_len = scramble_buf.as_ref().map(|x| x.len()).unwrap_or(0);
+    let mut payload_len = 4 + 4 + 1 + 23 + user_len + 1 + 1 + scramble_buf_len;
+    if db_name_len > 0 {
+        payload_len += db_name_len + 1;
+    }
+    let mut writer = io::Cursor::new(Vec::with_capacity(payload_len));
+    try!(writer.write_u32::<LE>(client_flags.bits()));
+    try!(writer.write_all(&[0u8; 4]));
+    try!(writer.write_u8(opts.handshake_collation()));
+    try!(writer.write_all(&[0u8; 23]));
+    if let Some(ref user) = opts.user {
+        try!(writer.write_all(user.as_bytes()));
+    }
+    try!(writer.write_u8(0u8));
+    try!(writer.write_u8(scramble_buf_len as u8));
+    if let Some(scr) = scramble_buf {
+        try!(writer.write_all(scr.as_ref()));
+    }
+    if db_name_len > 0 {
+        let db_name = opts.db_name.as_ref().unwrap();
+        try!(writer.write_all(db_name.as_bytes()));
+        try!(writer.write_u8(0u8));
+    }
+    if client_flags.contains(consts::CLIENT_PLUGIN_AUTH) {
+        try!(writer.write_all(plugin));
+        try!(writer.write_u8(0u8));
+    }
+    Ok(writer.into_inner())
+}
+
+/// Connects a socket to `addr` applying socket related options of `opts`.
+fn tcp_connect_addr(addr: net::SocketAddr, opts: &Opts) -> io::Result<net::TcpStream> {
+    let domain = match addr {
+        net::SocketAddr::V4(_) => socket2::Domain::ipv4(),
+        net::SocketAddr::V6(_) => socket2::Domain::ipv6(),
+    };
+    let socket = try!(socket2::Socket::new(domain,
+                                           socket2::Type::stream(),
+                                           Some(socket2::Protocol::tcp())));
+    if let Some(bind_address) = opts.bind_address {
+        try!(socket.bind(&bind_address.into()));
+    }
+    match opts.tcp_connect_timeout {
+        Some(timeout) => try!(socket.connect_timeout(&addr.into(), timeout)),
+        None => try!(socket.connect(&addr.into())),
+    }
+    let keepalive = opts.tcp_keepalive_time.map(|ms| StdDuration::from_millis(ms as u64));
+    try!(socket.set_keepalive(keepalive));
+    try!(socket.set_nodelay(opts.tcp_nodelay));
+    Ok(socket.into_tcp_stream())
+}
+
+/// Connects to `host:port` applying socket related options of `opts`
+/// (connect timeout, keepalive, `TCP_NODELAY` and bind address).
+///
+/// Every resolved address is tried in turn.
+fn tcp_connect(host: &str, port: u16, opts: &Opts) -> io::Result<net::TcpStream> {
+    use std::net::ToSocketAddrs;
+
+    let addrs = try!((host, port).to_socket_addrs()).collect();
+    let mut last_err = None;
+    for addr in opts.resolve_strategy.apply(addrs) {
+        match tcp_connect_addr(addr, opts) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Could not resolve to any address")
+    }))
+}
+
+/// Number of columns and OK packet of a command result for `Opts::recorder`.
+fn result_summary(result: &MyResult<(Vec<Column>, Option<OkPacket>)>)
+                  -> Result<(usize, Option<&OkPacket>), &Error> {
+    match *result {
+        Ok((ref columns, ref ok_packet)) => Ok((columns.len(), ok_packet.as_ref())),
+        Err(ref err) => Err(err),
+    }
+}
+
+/// Converts parameters of an execution using parameter types of a statement
+/// (see `Opts::coerce_params`).
+fn coerce_params(stmt: &InnerStmt, params: Vec<Value>) -> MyResult<Vec<Value>> {
+    let columns = match stmt.params {
+        Some(ref columns) => columns,
+        None => return Ok(params),
+    };
+    let mut output = Vec::with_capacity(params.len());
+    for (i, (value, column)) in params.into_iter().zip(columns.iter()).enumerate() {
+        match value.coerce_param(column) {
+            Ok(value) => output.push(value),
+            Err(_) => return Err(DriverError(InvalidStmtParam(i, column.column_type))),
+        }
+    }
+    Ok(output)
+}
+
 /// Mysql connection.
 #[derive(Debug)]
 pub struct Conn {
     opts: Opts,
-    stream: Option<Stream>,
+    stream: Option<IoStream>,
     stmts: HashMap<String, InnerStmt, BldHshrDflt<FnvHasher>>,
+    /// Cached queries in order of preparation. Used to evict statements if
+    /// `Opts::stmt_cache_size` is set.
+    stmt_order: VecDeque<String>,
+    /// Scratch buffer for `COM_STMT_EXECUTE` payloads reused across executions.
+    exec_buf: Vec<u8>,
     server_version: ServerVersion,
+    server_version_str: String,
     affected_rows: u64,
     last_insert_id: u64,
+    /// Warnings count of the last OK or EOF packet.
+    warnings: u16,
+    /// Bytes of row packets read since the last `QueryResult` was created.
+    result_bytes_read: u64,
     max_allowed_packet: usize,
     capability_flags: consts::CapabilityFlags,
+    /// Capabilities sent in the handshake response.
+    client_flags: consts::CapabilityFlags,
+    mariadb_capability_flags: consts::MariadbCapabilityFlags,
     connection_id: u32,
+    /// Scramble sent by server in the initial handshake.
+    nonce: Vec<u8>,
+    /// Default auth plugin of the server announced in the initial handshake.
+    server_auth_plugin: Vec<u8>,
     status_flags: consts::StatusFlags,
     seq_id: u8,
     character_set: u8,
     last_command: u8,
     connected: bool,
     has_results: bool,
+    /// When connection was established. Used by the pool to enforce its max lifetime.
+    created_at: Instant,
+    /// When connection was returned to the pool. Used to enforce its idle timeout.
+    idle_since: Instant,
+    /// Set by `CancellationToken::cancel` and cleared by the next command.
+    cancelled: Arc<AtomicBool>,
+    /// GTID of the last committed transaction reported via session state tracking.
+    last_gtid: Option<GtidSet>,
+    /// Address of a server if connected over TCP without a proxy.
+    peer_addr: Option<net::SocketAddr>,
+    /// Incremented whenever server drops prepared statements of a connection (reconnect,
+    /// `COM_RESET_CONNECTION`, `COM_CHANGE_USER`). Statements prepared before that are
+    /// prepared again on their next execution.
+    stmt_epoch: u64,
+    /// Whether multiple statements are enabled for the session. Initially equals
+    /// `Opts::multi_statements` and could be changed via `Conn::set_multi_statements`.
+    multi_statements: bool,
 }
 
 impl Conn {
@@ -568,18 +1427,34 @@ impl Conn {
             opts: opts.into(),
             stream: None,
             stmts: HashMap::default(),
+            stmt_order: VecDeque::new(),
+            exec_buf: Vec::new(),
             seq_id: 0u8,
             capability_flags: consts::CapabilityFlags::empty(),
+            client_flags: consts::CapabilityFlags::empty(),
+            mariadb_capability_flags: consts::MariadbCapabilityFlags::empty(),
             status_flags: consts::StatusFlags::empty(),
             connection_id: 0u32,
+            nonce: Vec::new(),
+            server_auth_plugin: Vec::new(),
             character_set: 0u8,
             affected_rows: 0u64,
             last_insert_id: 0u64,
+            warnings: 0u16,
+            result_bytes_read: 0,
             last_command: 0u8,
             max_allowed_packet: consts::MAX_PAYLOAD_LEN,
             connected: false,
             has_results: false,
             server_version: (0, 0, 0),
+            server_version_str: String::new(),
+            created_at: Instant::now(),
+            idle_since: Instant::now(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            last_gtid: None,
+            peer_addr: None,
+            stmt_epoch: 0,
+            multi_statements: false,
         }
     }
 
@@ -604,9 +1479,7 @@ impl Conn {
                 }
             }
         }
-        for cmd in conn.opts.init.clone() {
-            try!(conn.query(cmd));
-        }
+        try!(conn.run_init());
         return Ok(conn);
     }
 
@@ -631,9 +1504,7 @@ impl Conn {
                 }
             }
         }
-        for cmd in conn.opts.init.clone() {
-            try!(conn.query(cmd));
-        }
+        try!(conn.run_init());
         return Ok(conn);
     }
 
@@ -643,7 +1514,7 @@ impl Conn {
         let mut conn = Conn::empty(opts);
         try!(conn.connect_stream());
         try!(conn.connect());
-        if let None = conn.opts.ssl_opts {
+        if !conn.opts.ssl_enabled() {
             if conn.opts.unix_addr.is_none() && conn.opts.prefer_socket {
                 if conn.opts.addr_is_loopback() {
                     match conn.get_system_var("socket") {
@@ -660,9 +1531,7 @@ impl Conn {
                 }
             }
         }
-        for cmd in conn.opts.init.clone() {
-            try!(conn.query(cmd));
-        }
+        try!(conn.run_init());
         return Ok(conn);
     }
 
@@ -672,7 +1541,7 @@ impl Conn {
         let mut conn = Conn::empty(opts);
         try!(conn.connect_stream());
         try!(conn.connect());
-        if let None = conn.opts.ssl_opts {
+        if !conn.opts.ssl_enabled() {
             if conn.opts.unix_addr.is_none() && conn.opts.prefer_socket {
                 if conn.opts.addr_is_loopback() {
                     match conn.get_system_var("socket") {
@@ -689,9 +1558,7 @@ impl Conn {
                 }
             }
         }
-        for cmd in conn.opts.init.clone() {
-            try!(conn.query(cmd));
-        }
+        try!(conn.run_init());
         return Ok(conn);
     }
 
@@ -701,9 +1568,7 @@ impl Conn {
         let mut conn = Conn::empty(opts);
         try!(conn.connect_stream());
         try!(conn.connect());
-        for cmd in conn.opts.init.clone() {
-            try!(conn.query(cmd));
-        }
+        try!(conn.run_init());
         return Ok(conn);
     }
 
@@ -713,21 +1578,65 @@ impl Conn {
         let mut conn = Conn::empty(opts);
         try!(conn.connect_stream());
         try!(conn.connect());
-        for cmd in conn.opts.init.clone() {
-            try!(conn.query(cmd));
-        }
+        try!(conn.run_init());
         return Ok(conn);
     }
 
-    fn soft_reset(&mut self) -> MyResult<()> {
-        try!(self.write_command(Command::COM_RESET_CONNECTION));
-        self.read_packet().and_then(|pld| {
-            match pld[0] {
-                0 => {
-                    let ok = try!(OkPacket::from_payload(&*pld));
+    /// Creates new `Conn` over the given transport (e.g. a channel of an SSH tunnel).
+    ///
+    /// Address, socket and proxy options are ignored. Connection could not be reestablished,
+    /// so [`reset`](#method.reset) fails if server does not support `COM_RESET_CONNECTION`.
+    ///
+    /// SSL could not be negotiated over a custom stream, so `DriverError::SslOverCustomStream`
+    /// is returned if `Opts::ssl_opts` is set (wrap the transport into a TLS stream instead).
+    ///
+    /// ```ignore
+    /// let stream = TcpStream::connect("127.0.0.1:3306").unwrap();
+    /// let mut conn = Conn::new_with_stream(stream, opts).unwrap();
+    /// ```
+    pub fn new_with_stream<S, T>(stream: S, opts: T) -> MyResult<Conn>
+    where S: Stream + 'static,
+          T: Into<Opts> {
+        let mut conn = Conn::empty(opts);
+        #[cfg(feature = "ssl")]
+        {
+            if conn.opts.ssl_enabled() {
+                return Err(DriverError(SslOverCustomStream));
+            }
+        }
+        let stream = CustomStream(Box::new(stream));
+        conn.stream = Some(IoStream::Custom(BufStream::new(stream)));
+        try!(conn.connect());
+        try!(conn.run_init());
+        Ok(conn)
+    }
+
+    /// Enforces character set if needed, executes `Opts::init` commands and calls `Opts::init_fn`.
+    fn run_init(&mut self) -> MyResult<()> {
+        if let Some(query) = try!(self.opts.set_names_query()) {
+            try!(self.query(query));
+        }
+        if self.client_flags.contains(consts::CLIENT_SESSION_TRACK) {
+            try!(self.query("SET SESSION session_track_gtids = OWN_GTID"));
+        }
+        for cmd in self.opts.init.clone() {
+            try!(self.query(cmd));
+        }
+        if let Some(init_fn) = self.opts.init_fn.clone() {
+            try!(init_fn.call(self));
+        }
+        Ok(())
+    }
+
+    fn soft_reset(&mut self) -> MyResult<()> {
+        try!(self.write_simple_command(Command::COM_RESET_CONNECTION));
+        self.read_packet().and_then(|pld| {
+            match pld[0] {
+                0 => {
+                    let ok = try!(OkPacket::from_payload(&*pld, self.client_flags));
                     self.handle_ok(&ok);
                     self.last_command = 0;
-                    self.stmts.clear();
+                    self.forget_stmts();
                     Ok(())
                 },
                 _ => {
@@ -739,19 +1648,27 @@ impl Conn {
     }
 
     fn hard_reset(&mut self) -> MyResult<()> {
+        if self.stream.as_ref().map(IoStream::is_custom).unwrap_or(false) {
+            // custom stream could not be reestablished
+            return Err(DriverError(CouldNotConnect(None)));
+        }
         self.stream = None;
-        self.stmts.clear();
+        self.peer_addr = None;
+        self.forget_stmts();
         self.seq_id = 0;
         self.capability_flags = consts::CapabilityFlags::empty();
+        self.client_flags = consts::CapabilityFlags::empty();
         self.status_flags = consts::StatusFlags::empty();
         self.connection_id = 0;
         self.character_set = 0;
         self.affected_rows = 0;
         self.last_insert_id = 0;
+        self.last_gtid = None;
         self.last_command = 0;
         self.max_allowed_packet = consts::MAX_PAYLOAD_LEN;
         self.connected = false;
         self.has_results = false;
+        self.created_at = Instant::now();
         try!(self.connect_stream());
         self.connect()
     }
@@ -768,7 +1685,454 @@ impl Conn {
         }
     }
 
-    fn get_mut_stream<'a>(&'a mut self) -> &'a mut Stream {
+    /// Returns server version parsed from the initial handshake as `(major, minor, patch)`.
+    ///
+    /// ```ignore
+    /// if conn.server_version() >= (5, 7, 8) {
+    ///     // JSON type is available
+    /// }
+    /// ```
+    pub fn server_version(&self) -> (u16, u16, u16) {
+        self.server_version
+    }
+
+    /// Returns server version string as sent by server (e.g. `"5.7.12-log"`).
+    pub fn server_version_str(&self) -> &str {
+        &*self.server_version_str
+    }
+
+    /// Returns `true` if connected to MariaDB.
+    ///
+    /// Note that `server_version` of MariaDB is not comparable with MySQL versions
+    /// (e.g. `(10, 4, 12)`).
+    pub fn is_mariadb(&self) -> bool {
+        self.server_version_str.contains("MariaDB")
+    }
+
+    /// Returns MariaDB extended capabilities announced by server (empty for MySQL).
+    pub fn mariadb_capabilities(&self) -> consts::MariadbCapabilityFlags {
+        self.mariadb_capability_flags
+    }
+
+    /// Returns capabilities announced by server in the initial handshake.
+    pub fn capabilities(&self) -> consts::CapabilityFlags {
+        self.capability_flags
+    }
+
+    /// Returns server default character set (collation id) announced in the initial handshake.
+    pub fn character_set(&self) -> u8 {
+        self.character_set
+    }
+
+    /// Returns name of the character set of a connection (`utf8` unless `Opts::charset` or
+    /// `Opts::collation` is given).
+    pub fn charset(&self) -> &str {
+        self.opts.charset_name()
+    }
+
+    /// Returns `true` if `NO_BACKSLASH_ESCAPES` SQL mode is enabled as reported by the last
+    /// server response.
+    ///
+    /// Server reports the mode in status flags of every response, so it is up to date even if
+    /// SQL mode was changed by a plain `SET` statement.
+    pub fn no_backslash_escapes(&self) -> bool {
+        self.status_flags.contains(consts::SERVER_STATUS_NO_BACKSLASH_ESCAPES)
+    }
+
+    /// Returns SQL mode of a session (`@@SESSION.sql_mode`).
+    ///
+    /// Modes unknown to this crate are omitted.
+    pub fn sql_mode(&mut self) -> MyResult<consts::SqlMode> {
+        let names = try!(self.sql_mode_names());
+        Ok(consts::SqlMode::from_names(&*names))
+    }
+
+    fn sql_mode_names(&mut self) -> MyResult<String> {
+        let names: Vec<String> = try!(self.query_map("SELECT @@SESSION.sql_mode", |x: String| x));
+        Ok(names.into_iter().next().unwrap_or_default())
+    }
+
+    /// Sets SQL mode of a session.
+    ///
+    /// Replaces the whole mode, so modes unknown to this crate are disabled. Use
+    /// [`Conn::update_sql_mode`](#method.update_sql_mode) to keep them.
+    ///
+    /// [`Conn::escape_string`](#method.escape_string) follows the new mode, e.g.
+    /// `consts::MODE_NO_BACKSLASH_ESCAPES`.
+    pub fn set_sql_mode(&mut self, mode: consts::SqlMode) -> MyResult<()> {
+        try!(self.query(format!("SET SESSION sql_mode = '{}'", mode.names())));
+        Ok(())
+    }
+
+    /// Updates SQL mode of a session with `f` applied to the current mode.
+    ///
+    /// Modes unknown to this crate (e.g. modes of newer server versions), which are enabled
+    /// for the session, are kept as is.
+    ///
+    /// ```ignore
+    /// try!(conn.update_sql_mode(|mode| mode | consts::MODE_STRICT_ALL_TABLES));
+    /// ```
+    pub fn update_sql_mode<F>(&mut self, f: F) -> MyResult<()>
+    where F: FnOnce(consts::SqlMode) -> consts::SqlMode {
+        let current = try!(self.sql_mode_names());
+        let mut names = f(consts::SqlMode::from_names(&*current)).names();
+        for unknown in consts::SqlMode::unknown_names(&*current) {
+            if !names.is_empty() {
+                names.push(',');
+            }
+            names.push_str(unknown);
+        }
+        try!(self.query(format!("SET SESSION sql_mode = '{}'", names)));
+        Ok(())
+    }
+
+    /// Escapes `value` to be placed between single quotes of a string literal according to
+    /// the current SQL mode of a connection.
+    ///
+    /// See [`escape_string`](fn.escape_string.html). Prefer statement parameters if possible.
+    pub fn escape_string<T: AsRef<str>>(&self, value: T) -> String {
+        escape_string(value.as_ref(), self.no_backslash_escapes())
+    }
+
+    /// Returns connection identifier (aka thread id) assigned by server.
+    ///
+    /// It is the same value as returned by `SELECT CONNECTION_ID()`.
+    pub fn id(&self) -> u32 {
+        self.connection_id
+    }
+
+    /// Returns token which could be used to cancel a statement executed by this connection
+    /// from another thread (see [`CancellationToken`](struct.CancellationToken.html)).
+    pub fn cancellation_token(&self) -> CancellationToken {
+        CancellationToken::new(self.opts.clone(), self.connection_id, self.cancelled.clone())
+    }
+
+    /// Kills connection with given `id` via `KILL CONNECTION` statement.
+    ///
+    /// Use [`Conn::id`](#method.id) to get an identifier of a connection.
+    pub fn kill(&mut self, id: u32) -> MyResult<()> {
+        try!(self.query(format!("KILL CONNECTION {}", id)));
+        Ok(())
+    }
+
+    /// Clears session state of a connection without reconnecting.
+    ///
+    /// Uses [`COM_RESET_CONNECTION`](http://dev.mysql.com/doc/internals/en/com-reset-connection.html)
+    /// if server supports it (MySQL >= 5.7.3) and falls back to
+    /// [`COM_CHANGE_USER`](http://dev.mysql.com/doc/internals/en/com-change-user.html)
+    /// with credentials from `Opts` otherwise. Temporary tables, user variables and
+    /// prepared statements will be dropped, so `Opts::init` commands and `Opts::init_fn`
+    /// are executed again.
+    pub fn reset_connection(&mut self) -> MyResult<()> {
+        if self.server_version > (5, 7, 2) {
+            try!(self.soft_reset().or_else(|_| self.do_change_user()));
+        } else {
+            try!(self.do_change_user());
+        }
+        self.run_init()
+    }
+
+    /// Changes user and default database of a connection using
+    /// [`COM_CHANGE_USER`](http://dev.mysql.com/doc/internals/en/com-change-user.html).
+    ///
+    /// New credentials will be stored in connection's `Opts`, so they will be used on reconnect.
+    /// Previous credentials are kept if server rejects the new ones.
+    /// As with [`Conn::reset_connection`](#method.reset_connection) session state and prepared
+    /// statements will be dropped and `Opts::init` commands and `Opts::init_fn` are executed
+    /// again.
+    ///
+    /// ```ignore
+    /// conn.change_user(Some("tenant_42"), Some("secret"), Some("tenant_42_db")).unwrap();
+    /// ```
+    pub fn change_user(&mut self,
+                       user: Option<&str>,
+                       pass: Option<&str>,
+                       db_name: Option<&str>) -> MyResult<()> {
+        // Authentication reads credentials from `Opts`, so previous ones are restored on error.
+        let old_user = mem::replace(&mut self.opts.user, user.map(Into::into));
+        let old_pass = mem::replace(&mut self.opts.pass, pass.map(Into::into));
+        let old_db_name = mem::replace(&mut self.opts.db_name, db_name.map(Into::into));
+        if let Err(err) = self.do_change_user() {
+            self.opts.user = old_user;
+            self.opts.pass = old_pass;
+            self.opts.db_name = old_db_name;
+            return Err(err);
+        }
+        self.run_init()
+    }
+
+    /// Changes default database of a connection using
+    /// [`COM_INIT_DB`](http://dev.mysql.com/doc/internals/en/com-init-db.html).
+    ///
+    /// New database will be stored in connection's `Opts`. Cached prepared statements will be
+    /// closed because unqualified table names in them are resolved against the database that was
+    /// default at the time of preparation.
+    pub fn select_db<T: AsRef<str>>(&mut self, db_name: T) -> MyResult<()> {
+        let db_name = db_name.as_ref();
+        try!(self.write_command_data(Command::COM_INIT_DB, db_name.as_bytes()));
+        let pld = try!(self.read_packet());
+        match pld[0] {
+            0x00 => {
+                let ok = try!(OkPacket::from_payload(&*pld, self.client_flags));
+                self.handle_ok(&ok);
+            },
+            _ => {
+                let err = try!(ErrPacket::from_payload(&*pld, self.capability_flags));
+                return Err(MySqlError(err.into()));
+            },
+        }
+        self.opts.db_name = Some(db_name.into());
+        self.close_all_stmts()
+    }
+
+    /// Closes every cached prepared statement.
+    fn close_all_stmts(&mut self) -> MyResult<()> {
+        let stmts: Vec<InnerStmt> = self.stmts.drain().map(|(_, stmt)| stmt).collect();
+        self.stmt_order.clear();
+        self.stmt_epoch = self.stmt_epoch.wrapping_add(1);
+        for stmt in stmts {
+            try!(self.close_stmt(stmt.statement_id));
+        }
+        Ok(())
+    }
+
+    fn do_change_user(&mut self) -> MyResult<()> {
+        let scramble_buf = match self.opts.pass {
+            Some(ref pass) => scramble(&*self.nonce, pass.as_bytes()),
+            None => None,
+        };
+        let mut writer = io::Cursor::new(Vec::new());
+        if let Some(ref user) = self.opts.user {
+            try!(writer.write_all(user.as_bytes()));
+        }
+        try!(writer.write_u8(0u8));
+        match scramble_buf {
+            Some(scr) => {
+                try!(writer.write_u8(scr.len() as u8));
+                try!(writer.write_all(&*scr));
+            },
+            None => try!(writer.write_u8(0u8)),
+        }
+        if let Some(ref db_name) = self.opts.db_name {
+            try!(writer.write_all(db_name.as_bytes()));
+        }
+        try!(writer.write_u8(0u8));
+        try!(writer.write_u16::<LE>(self.opts.handshake_collation() as u16));
+        if self.capability_flags.contains(consts::CLIENT_PLUGIN_AUTH) {
+            try!(writer.write_all(NATIVE_PASSWORD_PLUGIN));
+            try!(writer.write_u8(0u8));
+        }
+        try!(self.write_command_data(Command::COM_CHANGE_USER, &*writer.into_inner()));
+        try!(self.handle_auth_result(NATIVE_PASSWORD_PLUGIN));
+        self.last_command = 0;
+        self.forget_stmts();
+        Ok(())
+    }
+
+    /// Clears statement cache after server dropped prepared statements of a connection.
+    ///
+    /// `Stmt` handles (which hold SQL text of a statement) remain usable, because a statement
+    /// of a previous epoch is transparently prepared again on its next execution.
+    fn forget_stmts(&mut self) {
+        self.stmts.clear();
+        self.stmt_order.clear();
+        self.stmt_epoch = self.stmt_epoch.wrapping_add(1);
+    }
+
+    /// Returns `true` if connection is established via SSL, unix socket or named pipe.
+    fn is_secure_transport(&self) -> bool {
+        match self.stream {
+            Some(IoStream::TcpStream(Some(Insecure(_)))) |
+            Some(IoStream::TcpStream(None)) |
+            None => false,
+            Some(IoStream::Custom(ref stream)) => stream.get_ref().0.is_secure(),
+            _ => true,
+        }
+    }
+
+    /// Reads server response to authentication data computed for `plugin` and handles
+    /// [auth switch requests](http://dev.mysql.com/doc/internals/en/connection-phase-packets.html#packet-Protocol::AuthSwitchRequest)
+    /// and additional auth data of `caching_sha2_password` plugin.
+    ///
+    /// Password is re-derived from the scramble sent with every auth switch request.
+    fn handle_auth_result(&mut self, plugin: &[u8]) -> MyResult<()> {
+        let mut plugin = plugin.to_vec();
+        let mut nonce = self.nonce.clone();
+        loop {
+            let pld = try!(self.read_packet());
+            match pld[0] {
+                0x00 => {
+                    let ok = try!(OkPacket::from_payload(&*pld, self.client_flags));
+                    self.handle_ok(&ok);
+                    return Ok(());
+                },
+                0xff => {
+                    let err = try!(ErrPacket::from_payload(&*pld, self.capability_flags));
+                    return Err(self.auth_error(err.into(), &*plugin));
+                },
+                0xfe => {
+                    if pld.len() > 1 {
+                        let (name, data) = parse_auth_switch_request(&pld[1..]);
+                        plugin = name.to_vec();
+                        nonce = data.to_vec();
+                    } else {
+                        // Old auth switch request (asks for `mysql_old_password` with
+                        // initial scramble).
+                        plugin = OLD_PASSWORD_PLUGIN.to_vec();
+                        nonce = self.nonce.clone();
+                    }
+                    let response = try!(self.auth_plugin_response(&*plugin, &*nonce));
+                    try!(self.write_packet(&*response));
+                },
+                0x01 if &*plugin == CACHING_SHA2_PASSWORD_PLUGIN => {
+                    match pld.get(1) {
+                        // Fast auth succeeded, OK packet follows.
+                        Some(&0x03) => (),
+                        // Server has no cached hash of a password and asks for the password.
+                        Some(&0x04) => {
+                            let response = try!(self.caching_sha2_password_full_auth(&*nonce));
+                            try!(self.write_packet(&*response));
+                        },
+                        _ => return Err(DriverError(UnexpectedPacket)),
+                    }
+                },
+                _ => return Err(DriverError(UnexpectedPacket)),
+            }
+        }
+    }
+
+    /// Maps server error returned in response to authentication data to a `DriverError`
+    /// if it is specific to the handshake.
+    fn auth_error(&self, err: super::error::MySqlError, plugin: &[u8]) -> Error {
+        let plugin = String::from_utf8_lossy(plugin).into_owned();
+        match err.code {
+            1044 | // ER_DBACCESS_DENIED_ERROR
+            1045 | // ER_ACCESS_DENIED_ERROR
+            1698 => DriverError(AuthFailed(err, plugin)), // ER_ACCESS_DENIED_NO_PASSWORD_ERROR
+            1251 => { // ER_NOT_SUPPORTED_AUTH_MODE
+                let server_plugin = String::from_utf8_lossy(&*self.server_auth_plugin);
+                DriverError(AuthPluginMismatch(err, plugin, server_plugin.into_owned()))
+            },
+            3159 => DriverError(SecureTransportRequired(err)), // ER_SECURE_TRANSPORT_REQUIRED
+            1043 => DriverError(HandshakeRejected(err, self.client_flags)), // ER_HANDSHAKE_ERROR
+            _ => MySqlError(err),
+        }
+    }
+
+    /// Computes auth response for a plugin requested by server.
+    ///
+    /// May exchange additional packets with the server (`sha256_password` asks for RSA
+    /// public key).
+    fn auth_plugin_response(&mut self, plugin: &[u8], data: &[u8]) -> MyResult<Vec<u8>> {
+        if plugin == NATIVE_PASSWORD_PLUGIN {
+            let pass = self.opts.pass.as_ref().map(|x| x.as_bytes()).unwrap_or(&b""[..]);
+            Ok(scramble(data, pass).unwrap_or(Vec::new()))
+        } else if plugin == OLD_PASSWORD_PLUGIN {
+            let pass = self.opts.pass.as_ref().map(|x| x.as_bytes()).unwrap_or(&b""[..]);
+            let mut response = scramble_323(data, pass).unwrap_or(Vec::new());
+            response.push(0);
+            Ok(response)
+        } else if plugin == CACHING_SHA2_PASSWORD_PLUGIN {
+            let pass = self.opts.pass.as_ref().map(|x| x.as_bytes()).unwrap_or(&b""[..]);
+            Ok(scramble_sha256(data, pass).unwrap_or(Vec::new()))
+        } else if plugin == SHA256_PASSWORD_PLUGIN {
+            self.sha256_password_response(data)
+        } else if plugin == ED25519_PLUGIN {
+            let pass = self.opts.pass.as_ref().map(|x| x.as_bytes()).unwrap_or(&b""[..]);
+            Ok(scramble_ed25519(data, pass))
+        } else if plugin == CLEAR_PASSWORD_PLUGIN {
+            // Password will be sent as is, so it is only allowed over a secure transport.
+            if !self.is_secure_transport() {
+                return Err(DriverError(CleartextPasswordNotAllowed));
+            }
+            let mut response = Vec::new();
+            if let Some(ref pass) = self.opts.pass {
+                response.extend_from_slice(pass.as_bytes());
+            }
+            response.push(0);
+            Ok(response)
+        } else {
+            let name = String::from_utf8_lossy(plugin).into_owned();
+            Err(DriverError(UnsupportedAuthPlugin(name)))
+        }
+    }
+
+    /// Computes auth response for `sha256_password` plugin.
+    ///
+    /// Password is sent as is over a secure transport, otherwise it is encrypted with RSA
+    /// public key requested from the server.
+    fn sha256_password_response(&mut self, data: &[u8]) -> MyResult<Vec<u8>> {
+        let pass = self.opts.pass.clone().unwrap_or(String::new());
+        if pass.is_empty() {
+            Ok(vec![0])
+        } else if self.is_secure_transport() {
+            let mut response = pass.into_bytes();
+            response.push(0);
+            Ok(response)
+        } else {
+            self.rsa_encrypt_password(SHA256_PASSWORD_PLUGIN, pass.as_bytes(), data)
+        }
+    }
+
+    /// Computes response to a full authentication request of `caching_sha2_password` plugin.
+    ///
+    /// Same as for `sha256_password`, password is sent as is over a secure transport,
+    /// otherwise it is encrypted with RSA public key requested from the server.
+    fn caching_sha2_password_full_auth(&mut self, nonce: &[u8]) -> MyResult<Vec<u8>> {
+        let pass = self.opts.pass.clone().unwrap_or(String::new());
+        if pass.is_empty() || self.is_secure_transport() {
+            let mut response = pass.into_bytes();
+            response.push(0);
+            Ok(response)
+        } else {
+            self.rsa_encrypt_password(CACHING_SHA2_PASSWORD_PLUGIN, pass.as_bytes(), nonce)
+        }
+    }
+
+    /// Encrypts password for `sha256_password` or `caching_sha2_password` plugin with RSA
+    /// public key requested from the server.
+    #[cfg(feature = "openssl")]
+    fn rsa_encrypt_password(&mut self,
+                            plugin: &[u8],
+                            pass: &[u8],
+                            data: &[u8]) -> MyResult<Vec<u8>> {
+        use openssl::rsa;
+
+        // Request public key.
+        let request = if plugin == CACHING_SHA2_PASSWORD_PLUGIN { 2 } else { 1 };
+        try!(self.write_packet(&[request]));
+        let pld = try!(self.read_packet());
+        match pld.first() {
+            Some(&0x01) => (),
+            Some(&0xff) => {
+                let err = try!(ErrPacket::from_payload(&*pld, self.capability_flags));
+                return Err(MySqlError(err.into()));
+            },
+            _ => return Err(DriverError(UnexpectedPacket)),
+        }
+        let key = try!(rsa::Rsa::public_key_from_pem(&pld[1..]));
+        let mut response = vec![0; key.size() as usize];
+        let len = try!(key.public_encrypt(&*xor_password(pass, data),
+                                          &mut response,
+                                          rsa::PKCS1_OAEP_PADDING));
+        response.truncate(len);
+        Ok(response)
+    }
+
+    /// RSA encryption is not available without `ssl` feature, so `sha256_password` and full
+    /// authentication of `caching_sha2_password` require a secure transport (unix socket or
+    /// named pipe).
+    #[cfg(not(feature = "openssl"))]
+    #[allow(unused_variables)]
+    fn rsa_encrypt_password(&mut self,
+                            plugin: &[u8],
+                            pass: &[u8],
+                            data: &[u8]) -> MyResult<Vec<u8>> {
+        let name = String::from_utf8_lossy(plugin).into_owned();
+        Err(DriverError(UnsupportedAuthPlugin(name)))
+    }
+
+    fn get_mut_stream<'a>(&'a mut self) -> &'a mut IoStream {
         self.stream.as_mut().unwrap()
     }
 
@@ -776,7 +2140,7 @@ impl Conn {
     fn switch_to_ssl(&mut self) -> MyResult<()> {
         if self.stream.is_some() {
             let stream = self.stream.take().unwrap();
-            let stream = try!(stream.make_secure(self.opts.verify_peer, &self.opts.ssl_opts));
+            let stream = try!(stream.make_secure(&self.opts));
             self.stream = Some(stream);
         }
         Ok(())
@@ -788,14 +2152,15 @@ impl Conn {
             let mut full_name: String = r"\\.\pipe\".into();
             full_name.push_str(self.opts.pipe_name.as_ref().unwrap().as_ref());
             let pipe_stream = try!(np::PipeClient::connect(full_name));
-            self.stream = Some(Stream::PipeStream(BufStream::new(pipe_stream)));
+            self.stream = Some(IoStream::PipeStream(BufStream::new(pipe_stream)));
             Ok(())
         } else if self.opts.ip_or_hostname.is_some() {
-            match net::TcpStream::connect(&(self.opts.ip_or_hostname.as_ref().unwrap().as_ref(),
-                                             self.opts.tcp_port))
-            {
+            match self.connect_tcp() {
                 Ok(stream) => {
-                    self.stream = Some(Stream::TcpStream(Some(Insecure(BufStream::new(stream)))));
+                    if self.opts.proxy.is_none() {
+                        self.peer_addr = stream.peer_addr().ok();
+                    }
+                    self.stream = Some(IoStream::TcpStream(Some(Insecure(BufStream::new(stream)))));
                     Ok(())
                 },
                 _ => {
@@ -812,7 +2177,7 @@ impl Conn {
         if self.opts.unix_addr.is_some() {
             match us::UnixStream::connect(self.opts.unix_addr.as_ref().unwrap()) {
                 Ok(stream) => {
-                    self.stream = Some(Stream::UnixStream(BufStream::new(stream)));
+                    self.stream = Some(IoStream::UnixStream(BufStream::new(stream)));
                     Ok(())
                 },
                 _ => {
@@ -821,11 +2186,12 @@ impl Conn {
                 }
             }
         } else if self.opts.ip_or_hostname.is_some() {
-            match net::TcpStream::connect(&(&**self.opts.ip_or_hostname.as_ref().unwrap(),
-                                            self.opts.tcp_port))
-            {
+            match self.connect_tcp() {
                 Ok(stream) => {
-                    self.stream = Some(Stream::TcpStream(Some(Insecure(BufStream::new(stream)))));
+                    if self.opts.proxy.is_none() {
+                        self.peer_addr = stream.peer_addr().ok();
+                    }
+                    self.stream = Some(IoStream::TcpStream(Some(Insecure(BufStream::new(stream)))));
                     Ok(())
                 },
                 _ => {
@@ -840,11 +2206,12 @@ impl Conn {
     #[cfg(all(not(feature = "socket"), not(feature = "pipe")))]
     fn connect_stream(&mut self) -> MyResult<()> {
         if self.opts.ip_or_hostname.is_some() {
-            match net::TcpStream::connect(&(&**self.opts.ip_or_hostname.as_ref().unwrap(),
-                                            self.opts.tcp_port))
-            {
+            match self.connect_tcp() {
                 Ok(stream) => {
-                    self.stream = Some(Stream::TcpStream(Some(Insecure(BufStream::new(stream)))));
+                    if self.opts.proxy.is_none() {
+                        self.peer_addr = stream.peer_addr().ok();
+                    }
+                    self.stream = Some(IoStream::TcpStream(Some(Insecure(BufStream::new(stream)))));
                     Ok(())
                 },
                 _ => {
@@ -856,35 +2223,79 @@ impl Conn {
         }
     }
 
+    /// Connects to `Opts::ip_or_hostname` directly or through `Opts::proxy`.
+    fn connect_tcp(&self) -> io::Result<net::TcpStream> {
+        let elapsed = Elapsed::start();
+        let host = self.opts.ip_or_hostname.as_ref().unwrap();
+        let result = match self.opts.proxy {
+            Some(ref proxy_opts) => {
+                proxy::connect(proxy_opts, host, self.opts.tcp_port, &self.opts)
+            },
+            None => tcp_connect(host, self.opts.tcp_port, &self.opts),
+        };
+        match result {
+            Ok(_) => mysql_debug!("tcp connection to {}:{} established in {}",
+                                  host, self.opts.tcp_port, elapsed),
+            Err(ref err) => mysql_debug!("tcp connection to {}:{} failed in {}: {}",
+                                         host, self.opts.tcp_port, elapsed, err),
+        }
+        result
+    }
+
     fn read_packet(&mut self) -> MyResult<Vec<u8>> {
         let old_seq_id = self.seq_id;
         let (data, seq_id) = try!(self.get_mut_stream().read_packet(old_seq_id));
         self.seq_id = seq_id;
+        if let Some(ref tracer) = self.opts.packet_tracer {
+            tracer.trace(PacketDirection::Received, old_seq_id, &*data);
+        }
+        // Row packet never starts with `0xFF`, so this is an error packet of any command.
+        if self.cancelled.load(Ordering::SeqCst) && data.first() == Some(&0xFF) &&
+           data.len() < 0xFE {
+            let err = try!(ErrPacket::from_payload(&*data, self.capability_flags));
+            if err.error_code == cancel::ER_QUERY_INTERRUPTED {
+                self.cancelled.store(false, Ordering::SeqCst);
+                self.has_results = false;
+                return Err(DriverError(QueryCancelled));
+            }
+        }
         Ok(data)
     }
 
     fn write_packet(&mut self, data: &[u8]) -> MyResult<()> {
         let seq_id = self.seq_id;
         let max_allowed_packet = self.max_allowed_packet;
+        if let Some(ref tracer) = self.opts.packet_tracer {
+            tracer.trace(PacketDirection::Sent, seq_id, data);
+        }
         self.seq_id = try!(self.get_mut_stream().write_packet(data, seq_id, max_allowed_packet));
         Ok(())
     }
 
     fn handle_handshake(&mut self, hp: &HandshakePacket) {
         self.capability_flags = hp.capability_flags;
+        self.mariadb_capability_flags = hp.mariadb_capability_flags;
         self.status_flags = hp.status_flags;
         self.connection_id = hp.connection_id;
+        self.nonce = hp.auth_plugin_data.clone();
+        self.server_auth_plugin = hp.auth_plugin_name.clone();
         self.character_set = hp.character_set;
         self.server_version = hp.server_version;
+        self.server_version_str = hp.server_version_str.clone();
     }
 
     fn handle_ok(&mut self, op: &OkPacket) {
-        self.affected_rows = op.affected_rows;
-        self.last_insert_id = op.last_insert_id;
-        self.status_flags = op.status_flags;
+        self.affected_rows = op.affected_rows();
+        self.last_insert_id = op.last_insert_id();
+        self.warnings = op.warnings();
+        self.status_flags = op.status_flags();
+        if let Some(gtids) = op.gtids() {
+            self.last_gtid = gtids.parse().ok();
+        }
     }
 
     fn handle_eof(&mut self, eof: &EOFPacket) {
+        self.warnings = eof.warnings;
         self.status_flags = eof.status_flags;
     }
 
@@ -909,22 +2320,8 @@ impl Conn {
                     self.do_handshake_response(&handshake)
                 },
             }
-        }).and_then(|_| {
-            self.read_packet()
-        }).and_then(|pld| {
-            match pld[0] {
-                0u8 => {
-                    let ok = try!(OkPacket::from_payload(pld.as_ref()));
-                    self.handle_ok(&ok);
-                    Ok(())
-                },
-                0xffu8 => {
-                    let err = try!(ErrPacket::from_payload(pld.as_ref(),
-                                                           self.capability_flags));
-                    Err(MySqlError(err.into()))
-                },
-                _ => Err(DriverError(UnexpectedPacket))
-            }
+        }).and_then(|plugin| {
+            self.handle_auth_result(plugin)
         })
     }
 
@@ -946,7 +2343,7 @@ impl Conn {
                         return Err(DriverError(Protocol41NotSet));
                     }
                     self.handle_handshake(&handshake);
-                    if self.opts.ssl_opts.is_some() && self.stream.is_some() {
+                    if self.opts.ssl_enabled() && self.stream.is_some() {
                         if self.stream.as_ref().unwrap().is_insecure() {
                             if !handshake.capability_flags.contains(consts::CLIENT_SSL) {
                                 return Err(DriverError(SslNotSupported));
@@ -959,22 +2356,8 @@ impl Conn {
                     self.do_handshake_response(&handshake)
                 },
             }
-        }).and_then(|_| {
-            self.read_packet()
-        }).and_then(|pld| {
-            match pld[0] {
-                0u8 => {
-                    let ok = try!(OkPacket::from_payload(pld.as_ref()));
-                    self.handle_ok(&ok);
-                    Ok(())
-                },
-                0xffu8 => {
-                    let err = try!(ErrPacket::from_payload(pld.as_ref(),
-                                                           self.capability_flags));
-                    Err(MySqlError(err.into()))
-                },
-                _ => Err(DriverError(UnexpectedPacket))
-            }
+        }).and_then(|plugin| {
+            self.handle_auth_result(plugin)
         })
     }
 
@@ -985,17 +2368,23 @@ impl Conn {
                                consts::CLIENT_LONG_PASSWORD |
                                consts::CLIENT_TRANSACTIONS |
                                consts::CLIENT_LOCAL_FILES |
-                               consts::CLIENT_MULTI_STATEMENTS |
                                consts::CLIENT_MULTI_RESULTS |
                                consts::CLIENT_PS_MULTI_RESULTS |
-                               (self.capability_flags & consts::CLIENT_LONG_FLAG);
+                               (self.capability_flags & (consts::CLIENT_LONG_FLAG |
+                                                         consts::CLIENT_PLUGIN_AUTH));
+        if self.opts.multi_statements {
+            client_flags.insert(consts::CLIENT_MULTI_STATEMENTS);
+        }
+        if self.opts.track_gtids && self.server_version >= (5, 7, 6) && !self.is_mariadb() {
+            client_flags.insert(self.capability_flags & consts::CLIENT_SESSION_TRACK);
+        }
         if let Some(ref db_name) = self.opts.db_name {
             if db_name.len() > 0 {
                 client_flags.insert(consts::CLIENT_CONNECT_WITH_DB);
             }
         }
         if self.stream.is_some() && self.stream.as_ref().unwrap().is_insecure() {
-            if self.opts.ssl_opts.is_some() {
+            if self.opts.ssl_enabled() {
                 client_flags.insert(consts::CLIENT_SSL);
             }
         }
@@ -1009,10 +2398,16 @@ impl Conn {
                                consts::CLIENT_LONG_PASSWORD |
                                consts::CLIENT_TRANSACTIONS |
                                consts::CLIENT_LOCAL_FILES |
-                               consts::CLIENT_MULTI_STATEMENTS |
                                consts::CLIENT_MULTI_RESULTS |
                                consts::CLIENT_PS_MULTI_RESULTS |
-                               (self.capability_flags & consts::CLIENT_LONG_FLAG);
+                               (self.capability_flags & (consts::CLIENT_LONG_FLAG |
+                                                         consts::CLIENT_PLUGIN_AUTH));
+        if self.opts.multi_statements {
+            client_flags.insert(consts::CLIENT_MULTI_STATEMENTS);
+        }
+        if self.opts.track_gtids && self.server_version >= (5, 7, 6) && !self.is_mariadb() {
+            client_flags.insert(self.capability_flags & consts::CLIENT_SESSION_TRACK);
+        }
         if let Some(ref db_name) = self.opts.db_name {
             if db_name.len() > 0 {
                 client_flags.insert(consts::CLIENT_CONNECT_WITH_DB);
@@ -1027,59 +2422,55 @@ impl Conn {
         let mut writer = io::Cursor::new(Vec::with_capacity(4 + 4 + 1 + 23));
         try!(writer.write_u32::<LE>(client_flags.bits()));
         try!(writer.write_all(&[0u8; 4]));
-        try!(writer.write_u8(consts::UTF8_GENERAL_CI));
+        try!(writer.write_u8(self.opts.handshake_collation()));
         try!(writer.write_all(&[0u8; 23]));
         self.write_packet(writer.into_inner().borrow())
     }
 
-    fn do_handshake_response(&mut self, hp: &HandshakePacket) -> MyResult<()> {
+    /// Writes handshake response and returns auth plugin used to compute it.
+    fn do_handshake_response(&mut self, hp: &HandshakePacket) -> MyResult<&'static [u8]> {
         let client_flags = self.get_client_flags();
-        let scramble_buf = if let Some(ref pass) = self.opts.pass {
-            scramble(&*hp.auth_plugin_data, pass.as_bytes())
+        self.client_flags = client_flags;
+        self.multi_statements = client_flags.contains(consts::CLIENT_MULTI_STATEMENTS);
+        let plugin = initial_auth_plugin(hp);
+        let payload = try!(handshake_response(&self.opts, client_flags, hp, plugin));
+        try!(self.write_packet(payload.as_ref()));
+        Ok(plugin)
+    }
+
+    /// Returns an error if a result set of previous command is not consumed
+    /// (e.g. `QueryResult` was leaked via `mem::forget`), because server won't accept
+    /// a command until then.
+    fn check_no_pending_result(&self) -> MyResult<()> {
+        if self.has_results {
+            Err(DriverError(ResultNotConsumed))
         } else {
-            None
-        };
-        let user_len = self.opts.user.as_ref().map(|x| x.as_bytes().len()).unwrap_or(0);
-        let db_name_len = self.opts.db_name.as_ref().map(|x| x.as_bytes().len()).unwrap_or(0);
-        let scramble_buf_len = if scramble_buf.is_some() { 20 } else { 0 };
-        let mut payload_len = 4 + 4 + 1 + 23 + user_len + 1 + 1 + scramble_buf_len;
-        if db_name_len > 0 {
-            payload_len += db_name_len + 1;
-        }
-        let mut writer = io::Cursor::new(Vec::with_capacity(payload_len));
-        try!(writer.write_u32::<LE>(client_flags.bits()));
-        try!(writer.write_all(&[0u8; 4]));
-        try!(writer.write_u8(consts::UTF8_GENERAL_CI));
-        try!(writer.write_all(&[0u8; 23]));
-        if let Some(ref user) = self.opts.user {
-            try!(writer.write_all(user.as_bytes()));
-        }
-        try!(writer.write_u8(0u8));
-        try!(writer.write_u8(scramble_buf_len as u8));
-        if let Some(scr) = scramble_buf {
-            try!(writer.write_all(scr.as_ref()));
-        }
-        if db_name_len > 0 {
-            let db_name = self.opts.db_name.as_ref().unwrap();
-            try!(writer.write_all(db_name.as_bytes()));
-            try!(writer.write_u8(0u8));
+            Ok(())
         }
-        self.write_packet(writer.into_inner().borrow())
     }
 
-    fn write_command(&mut self, cmd: consts::Command) -> MyResult<()> {
+    fn write_simple_command(&mut self, cmd: consts::Command) -> MyResult<()> {
+        try!(self.check_no_pending_result());
+        self.cancelled.store(false, Ordering::SeqCst);
         self.seq_id = 0u8;
         self.last_command = cmd as u8;
         self.write_packet(&[cmd as u8])
     }
 
     fn write_command_data(&mut self, cmd: consts::Command, buf: &[u8]) -> MyResult<()> {
-        self.seq_id = 0u8;
-        self.last_command = cmd as u8;
         let mut writer = io::Cursor::new(Vec::with_capacity(buf.len() + 1));
         let _ = writer.write_u8(cmd as u8);
         let _ = writer.write_all(buf);
-        self.write_packet(writer.into_inner().borrow())
+        self.write_command_packet(writer.into_inner().borrow())
+    }
+
+    /// Writes command packet, which starts with the command byte.
+    fn write_command_packet(&mut self, data: &[u8]) -> MyResult<()> {
+        try!(self.check_no_pending_result());
+        self.cancelled.store(false, Ordering::SeqCst);
+        self.seq_id = 0u8;
+        self.last_command = data[0];
+        self.write_packet(data)
     }
 
     fn send_long_data(&mut self, stmt: &InnerStmt, params: &[Value], ids: Vec<u16>) -> MyResult<()> {
@@ -1102,69 +2493,117 @@ impl Conn {
         Ok(())
     }
 
-    fn _execute(&mut self, stmt: &InnerStmt, params: Params) -> MyResult<(Vec<Column>, Option<OkPacket>)> {
-        let mut writer: io::Cursor<_>;
+    fn _execute(&mut self, stmt: &mut InnerStmt, params: Params) -> MyResult<(Vec<Column>, Option<OkPacket>)> {
+        if stmt.epoch != self.stmt_epoch {
+            // Statement id belongs to a previous connection, so statement is prepared again
+            // from its SQL text (which already passed interceptors). New id is kept, so it is
+            // prepared only once.
+            let (cache_key, query) = (stmt.cache_key.clone(), stmt.query.clone());
+            *stmt = try!(self.prepare_cached(&*cache_key, &*query));
+        }
+        let stmt = &*stmt;
+        try!(self.intercept_before(QueryKind::Execute, &*stmt.query));
+        let rendered_params = if self.should_render_params() {
+            render_params(&params, stmt.named_params.as_ref())
+        } else {
+            Vec::new()
+        };
+        let started = Instant::now();
+        let result = self.do_execute(stmt, params);
+        self.intercept_after(QueryKind::Execute, &*stmt.query, &result);
+        self.record(QueryKind::Execute,
+                    &*stmt.query,
+                    rendered_params,
+                    started,
+                    result_summary(&result));
+        result
+    }
+
+    fn do_execute(&mut self, stmt: &InnerStmt, params: Params) -> MyResult<(Vec<Column>, Option<OkPacket>)> {
+        let params = match (params, stmt.named_params.as_ref()) {
+            (Params::Named(map), Some(names)) => {
+                let mut values = Vec::with_capacity(names.len());
+                for name in names.iter() {
+                    match map.get(name) {
+                        Some(value) => values.push(value.clone()),
+                        None => return Err(DriverError(MissingNamedParameter(name.clone()))),
+                    }
+                }
+                Params::Positional(values)
+            },
+            (Params::Named(_), None) => return Err(DriverError(NamedParamsForPositionalQuery)),
+            (params, _) => params,
+        };
+        // Payload is serialized into a buffer which is kept between executions, so executing a
+        // statement won't allocate unless its parameters grew.
+        let mut data = mem::replace(&mut self.exec_buf, Vec::new());
+        data.clear();
+        try!(data.write_u8(Command::COM_STMT_EXECUTE as u8));
+        try!(data.write_u32::<LE>(stmt.statement_id));
+        try!(data.write_u8(0u8));
+        try!(data.write_u32::<LE>(1u32));
+        let mut long_data_sent = false;
         match params {
             Params::Empty => {
                 if stmt.num_params != 0 {
                     return Err(DriverError(MismatchedStmtParams(stmt.num_params, 0)));
                 }
-                writer = io::Cursor::new(Vec::with_capacity(4 + 1 + 4));
-                try!(writer.write_u32::<LE>(stmt.statement_id));
-                try!(writer.write_u8(0u8));
-                try!(writer.write_u32::<LE>(1u32));
             },
-            Params::Positional(params) => {
+            Params::Positional(mut params) => {
                 if stmt.num_params != params.len() as u16 {
                     return Err(DriverError(MismatchedStmtParams(stmt.num_params, params.len())));
                 }
-                if let Some(ref sparams) = stmt.params {
-                    let (bitmap, values, large_ids) =
-                        try!(Value::to_bin_payload(sparams.as_ref(),
-                                                   &params,
-                                                   self.max_allowed_packet));
-                    match large_ids {
-                        Some(ids) => try!(self.send_long_data(stmt, &params, ids)),
-                        _ => ()
+                if self.opts.coerce_params {
+                    params = try!(coerce_params(stmt, params));
+                }
+                for param in params.iter() {
+                    // Server will reject a parameter larger than `max_allowed_packet` only
+                    // after all of its long data was sent.
+                    if let Bytes(ref x) = *param {
+                        if x.len() > self.max_allowed_packet {
+                            return Err(DriverError(PacketTooLarge(x.len(),
+                                                                  self.max_allowed_packet)));
+                        }
                     }
-                    writer = io::Cursor::new(Vec::with_capacity(9 + bitmap.len() + 1 +
-                                                                params.len() * 2 +
-                                                                values.len()));
-                    try!(writer.write_u32::<LE>(stmt.statement_id));
-                    try!(writer.write_u8(0u8));
-                    try!(writer.write_u32::<LE>(1u32));
-                    try!(writer.write_all(bitmap.as_ref()));
-                    try!(writer.write_u8(1u8));
-                    for i in 0..params.len() {
-                        match params[i] {
-                            NULL => try!(writer.write_all(
-                                &[sparams[i].column_type as u8, 0u8])),
-                            Bytes(..) => try!(
-                                writer.write_all(&[ColumnType::MYSQL_TYPE_VAR_STRING as u8, 0u8])),
-                            Int(..) => try!(
-                                writer.write_all(&[ColumnType::MYSQL_TYPE_LONGLONG as u8, 0u8])),
-                            UInt(..) => try!(
-                                writer.write_all(&[ColumnType::MYSQL_TYPE_LONGLONG as u8, 128u8])),
-                            Float(..) => try!(
-                                writer.write_all(&[ColumnType::MYSQL_TYPE_DOUBLE as u8, 0u8])),
-                            Date(..) => try!(
-                                writer.write_all(&[ColumnType::MYSQL_TYPE_DATETIME as u8, 0u8])),
-                            Time(..) => try!(
-                                writer.write_all(&[ColumnType::MYSQL_TYPE_TIME as u8, 0u8]))
+                }
+                if let Some(ref sparams) = stmt.params {
+                    let large_ids = try!(Value::write_bin_payload(sparams.as_ref(),
+                                                                  &params,
+                                                                  self.max_allowed_packet,
+                                                                  &mut data));
+                    if let Some(ids) = large_ids {
+                        long_data_sent = true;
+                        if let Err(err) = self.send_long_data(stmt, &params, ids) {
+                            return Err(self.discard_long_data(stmt, err));
                         }
                     }
-                    try!(writer.write_all(values.as_ref()));
                 } else {
                     unreachable!();
                 }
             },
-            Params::Named(_) => unimplemented!(),
+            Params::Named(_) => unreachable!(),
+        }
+        let elapsed = Elapsed::start();
+        let result = match self.write_command_packet(&*data) {
+            Ok(_) => self.handle_result_set(),
+            Err(err) => if long_data_sent {
+                Err(self.discard_long_data(stmt, err))
+            } else {
+                Err(err)
+            },
+        };
+        if data.capacity() <= MAX_EXEC_BUF_CAPACITY {
+            self.exec_buf = data;
         }
-        try!(self.write_command_data(Command::COM_STMT_EXECUTE, writer.into_inner().borrow()));
-        self.handle_result_set()
+        mysql_debug!("executed statement {} on connection {}: {} in {}",
+                     stmt.statement_id,
+                     self.connection_id,
+                     Outcome(&result),
+                     elapsed);
+        result
     }
 
-    fn execute<'a, T: Into<Params>>(&'a mut self, stmt: &InnerStmt, params: T) -> MyResult<QueryResult<'a>> {
+    fn execute<'a, T: Into<Params>>(&'a mut self, stmt: &mut InnerStmt, params: T) -> MyResult<QueryResult<'a>> {
         match self._execute(stmt, params.into()) {
             Ok((columns, ok_packet)) => {
                 Ok(QueryResult::new(ResultConnRef::ViaConnRef(self), columns, ok_packet, true))
@@ -1173,24 +2612,19 @@ impl Conn {
         }
     }
 
-    fn _start_transaction(&mut self,
-                          consistent_snapshot: bool,
-                          isolation_level: Option<IsolationLevel>,
-                          readonly: Option<bool>) -> MyResult<()> {
+    fn _start_transaction(&mut self, tx_opts: TxOpts) -> MyResult<()> {
+        let isolation_level = tx_opts.isolation_level.or(self.opts.default_isolation_level);
+        let access_mode = tx_opts.access_mode.or(self.opts.default_access_mode);
         if let Some(i_level) = isolation_level {
             let _ = try!(self.query(format!("SET TRANSACTION ISOLATION LEVEL {}", i_level)));
         }
-        if let Some(readonly) = readonly {
+        if let Some(access_mode) = access_mode {
             if self.server_version < (5, 6, 5) {
                 return Err(DriverError(ReadOnlyTransNotSupported));
             }
-            let _ = if readonly {
-                try!(self.query("SET TRANSACTION READ ONLY"))
-            } else {
-                try!(self.query("SET TRANSACTION READ WRITE"))
-            };
+            let _ = try!(self.query(format!("SET TRANSACTION {}", access_mode)));
         }
-        let _ = if consistent_snapshot {
+        let _ = if tx_opts.consistent_snapshot {
             try!(self.query("START TRANSACTION WITH CONSISTENT SNAPSHOT"))
         } else {
             try!(self.query("START TRANSACTION"))
@@ -1223,7 +2657,7 @@ impl Conn {
         try!(self.write_packet(&[]));
         let pld = try!(self.read_packet());
         if pld[0] == 0u8 {
-            let ok = try!(OkPacket::from_payload(pld.as_ref()));
+            let ok = try!(OkPacket::from_payload(pld.as_ref(), self.client_flags));
             self.handle_ok(&ok);
             return Ok(Some(ok));
         }
@@ -1234,7 +2668,7 @@ impl Conn {
         let pld = try!(self.read_packet());
         match pld[0] {
             0x00 => {
-                let ok = try!(OkPacket::from_payload(pld.as_ref()));
+                let ok = try!(OkPacket::from_payload(pld.as_ref(), self.client_flags));
                 self.handle_ok(&ok);
                 Ok((Vec::new(), Some(ok)))
             },
@@ -1259,37 +2693,463 @@ impl Conn {
                     let pld = try!(self.read_packet());
                     columns.push(try!(Column::from_payload(self.last_command, pld.as_ref())));
                 }
-                // skip eof packet
-                try!(self.read_packet());
+                // Status of eof packet tells whether it is a result set of OUT parameters.
+                let pld = try!(self.read_packet());
+                if pld.len() >= 5 && pld[0] == 0xfe {
+                    let eof = try!(EOFPacket::from_payload(pld.as_ref()));
+                    self.handle_eof(&eof);
+                }
                 self.has_results = true;
                 Ok((columns, None))
             }
         }
     }
 
-    fn _query(&mut self, query: &str) -> MyResult<(Vec<Column>, Option<OkPacket>)> {
-        try!(self.write_command_data(Command::COM_QUERY, query.as_bytes()));
-        self.handle_result_set()
-    }
+    /// Applies `Opts::interceptors` to a command before it is sent.
+    fn intercept_before<'q>(&self, kind: QueryKind, query: &'q str) -> MyResult<Cow<'q, str>> {
+        let mut query = Cow::Borrowed(query);
+        for interceptor in self.opts.interceptors.iter() {
+            if let Some(rewritten) = try!(interceptor.before(kind, &*query)) {
+                query = Cow::Owned(rewritten);
+            }
+        }
+        Ok(query)
+    }
+
+    /// Notifies `Opts::interceptors` about a result of a command.
+    fn intercept_after<T>(&self, kind: QueryKind, query: &str, result: &MyResult<T>) {
+        for interceptor in self.opts.interceptors.iter() {
+            interceptor.after(kind, query, result.as_ref().err());
+        }
+    }
+
+    /// Returns `true` if parameters of executions should be rendered for `Opts::recorder`
+    /// or `Opts::slow_query_handler`.
+    fn should_render_params(&self) -> bool {
+        self.opts.recorder.is_some() ||
+        (self.opts.slow_query_threshold.is_some() && self.opts.slow_query_handler.is_some())
+    }
+
+    /// Passes a record of a command to `Opts::recorder` and, if command took at least
+    /// `Opts::slow_query_threshold`, to `Opts::slow_query_handler`.
+    fn record(&self,
+              kind: QueryKind,
+              query: &str,
+              params: Vec<String>,
+              started: Instant,
+              summary: Result<(usize, Option<&OkPacket>), &Error>) {
+        let duration = started.elapsed();
+        #[cfg(feature = "metrics")]
+        metrics::record_query(duration, summary.is_err());
+        let slow_query_handler = match (self.opts.slow_query_threshold,
+                                        self.opts.slow_query_handler.as_ref()) {
+            (Some(threshold), Some(handler)) if duration >= threshold => Some(handler),
+            _ => None,
+        };
+        if self.opts.recorder.is_none() && slow_query_handler.is_none() {
+            return;
+        }
+        let (columns, ok_packet, error) = match summary {
+            Ok((columns, ok_packet)) => (columns, ok_packet, None),
+            Err(err) => (0, None, Some(err.to_string())),
+        };
+        let record = Record {
+            kind: kind,
+            query: query.into(),
+            params: params,
+            duration: duration,
+            columns: columns,
+            affected_rows: ok_packet.map(|ok| ok.affected_rows()).unwrap_or(0),
+            last_insert_id: ok_packet.map(|ok| ok.last_insert_id()).unwrap_or(0),
+            warnings: ok_packet.map(|ok| ok.warnings()).unwrap_or(0),
+            error: error,
+        };
+        if let Some(handler) = slow_query_handler {
+            handler.send(record.clone());
+        }
+        if let Some(ref recorder) = self.opts.recorder {
+            recorder.send(record);
+        }
+    }
+
+    fn _query(&mut self, query: &str) -> MyResult<(Vec<Column>, Option<OkPacket>)> {
+        let query = try!(self.intercept_before(QueryKind::Query, query));
+        let query = &*query;
+        let started = Instant::now();
+        let elapsed = Elapsed::start();
+        let result = self.write_command_data(Command::COM_QUERY, query.as_bytes())
+            .and_then(|_| self.handle_result_set());
+        mysql_debug!("query `{}` on connection {}: {} in {}",
+                     Sql(query, self.opts.log_sql_max_len),
+                     self.connection_id,
+                     Outcome(&result),
+                     elapsed);
+        self.intercept_after(QueryKind::Query, query, &result);
+        self.record(QueryKind::Query, query, Vec::new(), started, result_summary(&result));
+        result
+    }
+
+    /// Executes text query consisting of multiple statements separated by `;`
+    /// (e.g. a migration script).
+    ///
+    /// Returned [`MultiResult`](struct.MultiResult.html) yields a
+    /// [`ResultSet`](struct.ResultSet.html) for every statement in order. Server stops at
+    /// the first failed statement, so its error will be the last item. Remaining result sets
+    /// are consumed when `MultiResult` is dropped.
+    ///
+    /// [`Opts::result_budget`](struct.Opts.html#structfield.result_budget) bounds rows
+    /// buffered from all result sets of the query. A result set which exceeds it yields
+    /// `DriverError::ResultBudgetExceeded` (or is truncated).
+    ///
+    /// ```ignore
+    /// for result_set in try!(conn.query_multi("DELETE FROM a; SELECT COUNT(*) FROM b")) {
+    ///     let result_set = try!(result_set);
+    ///     println!("{} rows affected, {} rows returned",
+    ///              result_set.affected_rows(),
+    ///              result_set.rows().len());
+    /// }
+    /// ```
+    ///
+    /// Requires [`Opts::multi_statements`](struct.Opts.html#structfield.multi_statements) or
+    /// [`Conn::set_multi_statements`](#method.set_multi_statements).
+    pub fn query_multi<'a, T: AsRef<str>>(&'a mut self, query: T) -> MyResult<MultiResult<'a>> {
+        if !self.multi_statements {
+            return Err(DriverError(MultiStatementsDisabled));
+        }
+        let first = try!(self._query(query.as_ref()));
+        self.result_bytes_read = 0;
+        Ok(MultiResult {
+            conn: self,
+            next: Some(Ok(first)),
+        })
+    }
+
+    /// Enables or disables execution of multiple statements in a text query using
+    /// [`COM_SET_OPTION`](http://dev.mysql.com/doc/internals/en/com-set-option.html).
+    ///
+    /// Allows to keep [`Opts::multi_statements`](struct.Opts.html#structfield.multi_statements)
+    /// disabled and to enable it only where it is needed (e.g. for migrations). New value
+    /// affects only the current session, i.e. `Opts::multi_statements` is used again after
+    /// reconnect, and a pooled connection is switched back to it when returned to the pool.
+    pub fn set_multi_statements(&mut self, enabled: bool) -> MyResult<()> {
+        // MYSQL_OPTION_MULTI_STATEMENTS_ON is 0, MYSQL_OPTION_MULTI_STATEMENTS_OFF is 1.
+        let option: u16 = if enabled { 0 } else { 1 };
+        let mut buf = [0u8; 2];
+        LE::write_u16(&mut buf, option);
+        try!(self.write_command_data(Command::COM_SET_OPTION, &buf));
+        try!(self.read_ok_or_eof());
+        self.multi_statements = enabled;
+        Ok(())
+    }
+
+    /// Calls stored procedure via prepared statement `CALL proc_name(?, ?, ...)` with a
+    /// placeholder for every parameter and reads all of its results.
+    ///
+    /// `proc_name` could be qualified with a database name (`db.proc`), every part is quoted.
+    /// OUT and INOUT parameters should be passed as well (e.g. as `Value::NULL`), their values
+    /// are returned by [`ProcResult::out_params`](struct.ProcResult.html#method.out_params).
+    /// Named parameters are not supported since order of parameters is defined by a procedure.
+    /// Rows of all result sets are bounded by
+    /// [`Opts::result_budget`](struct.Opts.html#structfield.result_budget).
+    ///
+    /// ```ignore
+    /// // CREATE PROCEDURE add_one(IN a INT, OUT b INT) BEGIN SELECT a; SET b = a + 1; END
+    /// let result = try!(conn.call("add_one", (41, Value::NULL)));
+    /// assert_eq!(result.result_sets().len(), 1);
+    /// let (b,): (i32,) = from_row(result.out_params().unwrap().clone());
+    /// ```
+    pub fn call<T, P>(&mut self, proc_name: T, params: P) -> MyResult<ProcResult>
+    where T: AsRef<str>,
+          P: Into<Params> {
+        let params = params.into();
+        let count = match params {
+            Params::Empty => 0,
+            Params::Positional(ref values) => values.len(),
+            Params::Named(_) => return Err(DriverError(NamedParamsForPositionalQuery)),
+        };
+        let proc_name: Vec<String> = proc_name.as_ref().split('.').map(quote_identifier).collect();
+        let query = format!("CALL {}({})", proc_name.join("."), vec!["?"; count].join(", "));
+        let mut stmt = try!(self._prepare(&*query));
+        let result = self.call_stmt(&mut stmt, params);
+        self.release_stmt(&stmt);
+        result
+    }
+
+    fn call_stmt(&mut self, stmt: &mut InnerStmt, params: Params) -> MyResult<ProcResult> {
+        let mut header = try!(self._execute(stmt, params));
+        self.result_bytes_read = 0;
+        let mut exceeded = false;
+        let mut result = ProcResult {
+            result_sets: Vec::new(),
+            out_params: None,
+            affected_rows: 0,
+            last_insert_id: 0,
+            warnings: 0,
+            info: Vec::new(),
+        };
+        loop {
+            match header {
+                (_, Some(ok)) => {
+                    result.affected_rows = ok.affected_rows();
+                    result.last_insert_id = ok.last_insert_id();
+                    result.warnings = ok.warnings();
+                    result.info = ok.info().to_vec();
+                },
+                (columns, None) => {
+                    let is_out_params = self.status_flags.contains(consts::SERVER_PT_OUT_PARAMS);
+                    let columns = Arc::new(columns);
+                    let (mut rows, set_exceeded) = try!(self.buffer_rows(&columns, true));
+                    exceeded = exceeded || set_exceeded;
+                    if is_out_params {
+                        result.out_params = rows.pop();
+                    } else {
+                        result.result_sets.push(ResultSet {
+                            columns: columns,
+                            rows: rows,
+                            affected_rows: 0,
+                            last_insert_id: 0,
+                            warnings: self.warnings,
+                            info: Vec::new(),
+                        });
+                    }
+                },
+            }
+            if !self.status_flags.contains(consts::SERVER_MORE_RESULTS_EXISTS) {
+                try!(self.check_result_budget(exceeded));
+                return Ok(result);
+            }
+            header = try!(self.handle_result_set());
+        }
+    }
 
     /// Executes [`COM_PING`](http://dev.mysql.com/doc/internals/en/com-ping.html)
     /// on `Conn`. Return `true` on success or `false` on error.
+    ///
+    /// Use [`Conn::ping_err`](#method.ping_err) to get the error.
     pub fn ping(&mut self) -> bool {
-        match self.write_command(Command::COM_PING) {
-            Ok(_) => {
-                self.read_packet().is_ok()
+        self.ping_err().is_ok()
+    }
+
+    /// Executes [`COM_PING`](http://dev.mysql.com/doc/internals/en/com-ping.html)
+    /// on `Conn` and returns an underlying I/O or server error if it fails.
+    pub fn ping_err(&mut self) -> MyResult<()> {
+        try!(self.write_simple_command(Command::COM_PING));
+        let pld = try!(self.read_packet());
+        match pld[0] {
+            0x00 => {
+                let ok = try!(OkPacket::from_payload(&*pld, self.client_flags));
+                self.handle_ok(&ok);
+                Ok(())
+            },
+            0xff => {
+                let err = try!(ErrPacket::from_payload(&*pld, self.capability_flags));
+                Err(MySqlError(err.into()))
+            },
+            _ => Err(DriverError(UnexpectedPacket)),
+        }
+    }
+
+    /// Executes [`COM_STATISTICS`](http://dev.mysql.com/doc/internals/en/com-statistics.html)
+    /// and returns parsed server statistics.
+    pub fn statistics(&mut self) -> MyResult<ServerStatistics> {
+        try!(self.write_simple_command(Command::COM_STATISTICS));
+        let pld = try!(self.read_packet());
+        match pld.first() {
+            Some(&0xff) => {
+                let err = try!(ErrPacket::from_payload(&*pld, self.capability_flags));
+                Err(MySqlError(err.into()))
+            },
+            _ => Ok(ServerStatistics::parse(&*String::from_utf8_lossy(&*pld))),
+        }
+    }
+
+    /// Returns threads running on the server via `SHOW FULL PROCESSLIST`, which is what
+    /// deprecated [`COM_PROCESS_INFO`](http://dev.mysql.com/doc/internals/en/com-process-info.html)
+    /// does.
+    ///
+    /// Threads of other users are only visible with `PROCESS` privilege.
+    pub fn process_info(&mut self) -> MyResult<Vec<ProcessInfo>> {
+        admin::process_info(self)
+    }
+
+    /// Returns names of tables and views of database `db` in alphabetical order.
+    ///
+    /// Only objects the user has some privilege on are visible.
+    pub fn table_names(&mut self, db: &str) -> MyResult<Vec<String>> {
+        schema::table_names(self, db)
+    }
+
+    /// Returns columns of a table or a view in order of their definition (empty if there is
+    /// no such table).
+    pub fn columns(&mut self, db: &str, table: &str) -> MyResult<Vec<ColumnInfo>> {
+        schema::columns(self, db, table)
+    }
+
+    /// Returns names of primary key columns of a table in order of the key (empty if a table
+    /// has no primary key).
+    pub fn primary_key(&mut self, db: &str, table: &str) -> MyResult<Vec<String>> {
+        schema::primary_key(self, db, table)
+    }
+
+    /// Returns columns of a table or a view in order of their definition.
+    ///
+    /// `table` could be qualified with a database name (`db.table`), otherwise the current
+    /// database is used. Names containing `.` should be quoted with backticks
+    /// (e.g. `` `my.db`.users ``). Unlike [`Conn::columns`](#method.columns) it returns
+    /// server error (e.g. `ER_NO_SUCH_TABLE`) if table does not exist or is not visible to
+    /// the user.
+    ///
+    /// ```ignore
+    /// for column in try!(conn.describe("app.users")) {
+    ///     println!("{} {}{}", column.name, column.column_type,
+    ///              if column.is_primary_key() { " PRIMARY KEY" } else { "" });
+    /// }
+    /// ```
+    pub fn describe(&mut self, table: &str) -> MyResult<Vec<ColumnInfo>> {
+        schema::describe(self, table)
+    }
+
+    /// Asks server to write debug information into its error log using
+    /// [`COM_DEBUG`](http://dev.mysql.com/doc/internals/en/com-debug.html).
+    ///
+    /// Requires `SUPER` privilege.
+    pub fn dump_debug_info(&mut self) -> MyResult<()> {
+        try!(self.write_simple_command(Command::COM_DEBUG));
+        self.read_ok_or_eof()
+    }
+
+    /// Shuts server down.
+    ///
+    /// Uses `SHUTDOWN` statement on MySQL >= 5.7.9 and
+    /// [`COM_SHUTDOWN`](http://dev.mysql.com/doc/internals/en/com-shutdown.html) (removed in
+    /// MySQL 8.0) otherwise. Requires `SHUTDOWN` privilege. Connection is unusable afterwards.
+    pub fn shutdown(&mut self) -> MyResult<()> {
+        if self.server_version >= (5, 7, 9) {
+            try!(self.query("SHUTDOWN"));
+            Ok(())
+        } else {
+            try!(self.write_command_data(Command::COM_SHUTDOWN, &[0x00]));
+            self.read_ok_or_eof()
+        }
+    }
+
+    /// Reads response to a command which replies with OK or EOF packet on success.
+    fn read_ok_or_eof(&mut self) -> MyResult<()> {
+        let pld = try!(self.read_packet());
+        match pld.first() {
+            Some(&0x00) => {
+                let ok = try!(OkPacket::from_payload(&*pld, self.client_flags));
+                self.handle_ok(&ok);
+                Ok(())
+            },
+            Some(&0xfe) => {
+                if pld.len() >= 5 {
+                    let eof = try!(EOFPacket::from_payload(&*pld));
+                    self.handle_eof(&eof);
+                }
+                Ok(())
+            },
+            Some(&0xff) => {
+                let err = try!(ErrPacket::from_payload(&*pld, self.capability_flags));
+                Err(MySqlError(err.into()))
+            },
+            _ => Err(DriverError(UnexpectedPacket)),
+        }
+    }
+
+    /// Sends command `cmd` with `payload` and reads the first packet of a response.
+    ///
+    /// Escape hatch for commands which are not wrapped by the crate yet (e.g.
+    /// `COM_REGISTER_SLAVE`). Server error is returned as `Error::MySqlError`.
+    ///
+    /// Connection state (cached statements, transaction status, pending results) is not
+    /// updated by this method, so it is on the caller to read the whole response via
+    /// [`Conn::read_raw_packet`](#method.read_raw_packet) and to not send commands which change
+    /// state the connection relies on. Otherwise connection may become unusable.
+    ///
+    /// ```ignore
+    /// // MYSQL_OPTION_MULTI_STATEMENTS_OFF
+    /// let response = try!(conn.write_command(Command::COM_SET_OPTION as u8, &[1, 0]));
+    /// assert_eq!(response, RawResponse::Eof);
+    /// ```
+    pub fn write_command(&mut self, cmd: u8, payload: &[u8]) -> MyResult<RawResponse> {
+        let mut data = Vec::with_capacity(payload.len() + 1);
+        data.push(cmd);
+        data.extend_from_slice(payload);
+        try!(self.write_command_packet(&*data));
+        let pld = try!(self.read_packet());
+        match pld[0] {
+            0x00 => {
+                let ok = try!(OkPacket::from_payload(&*pld, self.client_flags));
+                self.handle_ok(&ok);
+                Ok(RawResponse::Ok(ok))
+            },
+            0xfe if pld.len() < 9 => Ok(RawResponse::Eof),
+            0xff => {
+                let err = try!(ErrPacket::from_payload(&*pld, self.capability_flags));
+                Err(MySqlError(err.into()))
             },
-            _ => false
+            _ => Ok(RawResponse::Packet(pld)),
         }
     }
 
+    /// Reads next packet of a response to a command sent via
+    /// [`Conn::write_command`](#method.write_command) as is.
+    pub fn read_raw_packet(&mut self) -> MyResult<Vec<u8>> {
+        self.read_packet()
+    }
+
+    /// Returns parsed output of `SHOW MASTER STATUS` or `None` if binary logging is disabled.
+    ///
+    /// `SHOW BINARY LOG STATUS` is used on servers where `SHOW MASTER STATUS` was removed.
+    pub fn master_status(&mut self) -> MyResult<Option<MasterStatus>> {
+        replication::master_status(self)
+    }
+
+    /// Returns parsed output of `SHOW REPLICA STATUS` (`SHOW SLAVE STATUS` on older servers).
+    ///
+    /// There is one item per replication channel and the result is empty if `Conn` is not
+    /// connected to a replica.
+    pub fn replica_status(&mut self) -> MyResult<Vec<ReplicaStatus>> {
+        replication::replica_status(self)
+    }
+
+    /// Returns parsed value of `@@GLOBAL.gtid_executed`.
+    pub fn gtid_executed(&mut self) -> MyResult<GtidSet> {
+        replication::gtid_executed(self)
+    }
+
+    /// Returns `true` if a transaction is active on this connection according to status flags
+    /// reported by server with the last OK or EOF packet (`SERVER_STATUS_IN_TRANS`).
+    pub fn in_transaction(&self) -> bool {
+        self.status_flags.contains(consts::SERVER_STATUS_IN_TRANS)
+    }
+
+    /// Returns GTID of the last transaction committed by this connection.
+    ///
+    /// It is reported by server via session state tracking, so it is `None` unless
+    /// `Opts::track_gtids` is enabled and server is MySQL 5.7.6 or later with GTIDs enabled.
+    pub fn last_gtid(&self) -> Option<&GtidSet> {
+        self.last_gtid.as_ref()
+    }
+
+    /// Waits until server has executed every transaction of `gtid_set` via
+    /// `WAIT_FOR_EXECUTED_GTID_SET` and returns `false` if `timeout` has expired first.
+    ///
+    /// Use it on a replica with a GTID obtained from a primary (see
+    /// [`Transaction::commit_with_gtid`](struct.Transaction.html#method.commit_with_gtid)) to
+    /// read your own writes. `None` waits indefinitely.
+    pub fn wait_for_gtid(&mut self,
+                         gtid_set: &GtidSet,
+                         timeout: Option<StdDuration>) -> MyResult<bool> {
+        replication::wait_for_gtid(self, gtid_set, timeout)
+    }
+
     /// Starts new transaction with provided options.
-    /// `readonly` is only available since MySQL 5.6.5.
-    pub fn start_transaction<'a>(&'a mut self,
-                                 consistent_snapshot: bool,
-                                 isolation_level: Option<IsolationLevel>,
-                                 readonly: Option<bool>) -> MyResult<Transaction<'a>> {
-        let _ = try!(self._start_transaction(consistent_snapshot, isolation_level, readonly));
+    ///
+    /// Access mode is only available since MySQL 5.6.5.
+    pub fn start_transaction<'a>(&'a mut self, tx_opts: TxOpts) -> MyResult<Transaction<'a>> {
+        let _ = try!(self._start_transaction(tx_opts));
         Ok(Transaction::new(self))
     }
 
@@ -1307,6 +3167,24 @@ impl Conn {
     }
 
     fn _true_prepare(&mut self, query: &str) -> MyResult<InnerStmt> {
+        let elapsed = Elapsed::start();
+        let result = self.do_prepare(query);
+        match result {
+            Ok(ref stmt) => mysql_debug!("prepared `{}` as statement {} on connection {} in {}",
+                                         Sql(query, self.opts.log_sql_max_len),
+                                         stmt.statement_id,
+                                         self.connection_id,
+                                         elapsed),
+            Err(ref err) => mysql_debug!("failed to prepare `{}` on connection {} in {}: {}",
+                                         Sql(query, self.opts.log_sql_max_len),
+                                         self.connection_id,
+                                         elapsed,
+                                         err),
+        }
+        result
+    }
+
+    fn do_prepare(&mut self, query: &str) -> MyResult<InnerStmt> {
         try!(self.write_command_data(Command::COM_STMT_PREPARE, query.as_bytes()));
         let pld = try!(self.read_packet());
         match pld[0] {
@@ -1340,15 +3218,102 @@ impl Conn {
     }
 
     fn _prepare(&mut self, query: &str) -> MyResult<InnerStmt> {
-        if let Some(inner_st) = self.stmts.get(query) {
+        let rewritten = try!(self.intercept_before(QueryKind::Prepare, query));
+        let started = Instant::now();
+        let result = self.prepare_cached(query, &*rewritten);
+        self.intercept_after(QueryKind::Prepare, &*rewritten, &result);
+        let summary = match result {
+            Ok(ref stmt) => Ok((stmt.num_columns as usize, None)),
+            Err(ref err) => Err(err),
+        };
+        self.record(QueryKind::Prepare, &*rewritten, Vec::new(), started, summary);
+        result
+    }
+
+    /// Takes statement from cache by `cache_key` (SQL text passed by user) or prepares `query`
+    /// (SQL text rewritten by interceptors), so that interceptors which rewrite queries
+    /// differently on every call (e.g. to add a trace id) do not flood the cache.
+    fn prepare_cached(&mut self, cache_key: &str, query: &str) -> MyResult<InnerStmt> {
+        if let Some(inner_st) = self.stmts.get(cache_key) {
             return Ok(inner_st.clone());
         }
 
-        let inner_st = try!(self._true_prepare(query));
-        self.stmts.insert(query.to_owned(), inner_st.clone());
+        if let Some(cache_size) = self.opts.stmt_cache_size {
+            while !self.stmt_order.is_empty() && self.stmts.len() >= cache_size {
+                let oldest = self.stmt_order.pop_front().unwrap();
+                if let Some(stmt) = self.stmts.remove(&oldest) {
+                    try!(self.close_stmt(stmt.statement_id));
+                }
+            }
+        }
+
+        let (named_params, real_query) = named_params::parse_named_params(query);
+        let mut inner_st = try!(self._true_prepare(&*real_query));
+        inner_st.named_params = named_params;
+        inner_st.query = Arc::new(query.to_owned());
+        inner_st.cache_key = Arc::new(cache_key.to_owned());
+        inner_st.epoch = self.stmt_epoch;
+        if self.opts.stmt_cache_size != Some(0) {
+            self.stmts.insert(cache_key.to_owned(), inner_st.clone());
+            self.stmt_order.push_back(cache_key.to_owned());
+        }
         Ok(inner_st)
     }
 
+    fn reset_stmt(&mut self, statement_id: u32) -> MyResult<()> {
+        let mut data = [0u8; 4];
+        LE::write_u32(&mut data, statement_id);
+        try!(self.write_command_data(Command::COM_STMT_RESET, &data));
+        let pld = try!(self.read_packet());
+        match pld[0] {
+            0x00 => {
+                let ok = try!(OkPacket::from_payload(&*pld, self.client_flags));
+                self.handle_ok(&ok);
+                Ok(())
+            },
+            _ => {
+                let err = try!(ErrPacket::from_payload(&*pld, self.capability_flags));
+                Err(MySqlError(err.into()))
+            },
+        }
+    }
+
+    /// Resets a statement if its execution failed after long data was sent, otherwise
+    /// server would prepend this data to parameters of the next execution.
+    ///
+    /// Connection is unusable after an io error, so there is nothing to reset.
+    fn discard_long_data(&mut self, stmt: &InnerStmt, err: Error) -> Error {
+        match err {
+            IoError(_) => err,
+            err => {
+                let _ = self.reset_stmt(stmt.statement_id);
+                err
+            },
+        }
+    }
+
+    /// Closes statement which is no longer used if it is not kept in the statement cache
+    /// (e.g. if `Opts::stmt_cache_size` is `Some(0)`).
+    fn release_stmt(&mut self, stmt: &InnerStmt) {
+        if stmt.epoch != self.stmt_epoch {
+            // Statement was closed along with a previous connection.
+            return;
+        }
+        let is_cached = match self.stmts.get(&*stmt.cache_key) {
+            Some(cached) => cached.statement_id == stmt.statement_id,
+            None => false,
+        };
+        if !is_cached {
+            let _ = self.close_stmt(stmt.statement_id);
+        }
+    }
+
+    fn close_stmt(&mut self, statement_id: u32) -> MyResult<()> {
+        let mut data = [0u8; 4];
+        LE::write_u32(&mut data, statement_id);
+        self.write_command_data(Command::COM_STMT_CLOSE, &data)
+    }
+
     /// Implements binary protocol of mysql server.
     ///
     /// Prepares mysql statement on `Conn`. [`Stmt`](struct.Stmt.html) will
@@ -1371,6 +3336,163 @@ impl Conn {
         try!(self.prepare(query)).prep_exec(params.into())
     }
 
+    /// Same as [`Conn::prep_exec`](#method.prep_exec) but reads the whole result set into
+    /// memory, so that connection is not borrowed while rows are processed.
+    ///
+    /// Rows of subsequent result sets are dropped. Respects
+    /// [`Opts::result_budget`](struct.Opts.html#structfield.result_budget).
+    pub fn prep_exec_buffered<A, T>(&mut self, query: A, params: T) -> MyResult<Vec<Row>>
+    where A: AsRef<str>,
+          T: Into<Params> {
+        let mut result = try!(self.prep_exec(query, params));
+        let rows: Vec<Row> = try!(result.collect_within_budget(Ok));
+        try!(result.check_row_limit());
+        Ok(rows)
+    }
+
+    /// Prepares and executes statement and converts every row of the result to `T`.
+    ///
+    /// ```ignore
+    /// let users: Vec<(u32, String)> = try!(conn.exec("SELECT id, name FROM users", ()));
+    /// ```
+    ///
+    /// This call will take statement from cache if has been prepared on this connection.
+    pub fn exec<T, A, P>(&mut self, query: A, params: P) -> MyResult<Vec<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params)).collect_all_rows()
+    }
+
+    /// Prepares and executes statement and converts first row of the result to `T`.
+    ///
+    /// Returns `Ok(None)` if result is empty. Other rows will be dropped.
+    pub fn exec_first<T, A, P>(&mut self, query: A, params: P) -> MyResult<Option<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params)).first_row_checked()
+    }
+
+    /// Prepares and executes statement and drops its result.
+    pub fn exec_drop<A, P>(&mut self, query: A, params: P) -> MyResult<()>
+    where A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params));
+        Ok(())
+    }
+
+    /// Inserts `rows` into `table` via multi-row `INSERT` statements of up to `chunk_size` rows
+    /// each and returns total number of affected rows.
+    ///
+    /// Every row should be either positional params with a value per column or named params
+    /// keyed by column names (e.g. a struct with `#[derive(IntoParams)]`). Values are sent via
+    /// binary protocol and identifiers are quoted with backticks. `chunk_size` is reduced so that
+    /// statement has at most 65535 placeholders.
+    ///
+    /// Rows of previous chunks stay inserted if a chunk fails, so use a transaction if
+    /// it matters.
+    ///
+    /// ```ignore
+    /// let rows = users.iter().map(|user| (user.id, user.name.clone()));
+    /// let inserted = try!(conn.insert_many("users", &["id", "name"], rows, 1000));
+    /// ```
+    pub fn insert_many<T, C, I, P>(&mut self,
+                                   table: T,
+                                   columns: &[C],
+                                   rows: I,
+                                   chunk_size: usize) -> MyResult<u64>
+    where T: AsRef<str>,
+          C: AsRef<str>,
+          I: IntoIterator<Item=P>,
+          P: Into<Params> {
+        let columns: Vec<&str> = columns.iter().map(|column| column.as_ref()).collect();
+        let max_rows = if columns.is_empty() { chunk_size } else { 65535 / columns.len() };
+        let chunk_size = cmp::max(1, cmp::min(chunk_size, max_rows));
+        let capacity = chunk_size * columns.len();
+        let mut values = Vec::with_capacity(capacity);
+        let mut count = 0;
+        let mut affected_rows = 0;
+        for row in rows {
+            try!(push_insert_row(&mut values, &*columns, row.into()));
+            count += 1;
+            if count == chunk_size {
+                let chunk = mem::replace(&mut values, Vec::with_capacity(capacity));
+                affected_rows += try!(self.insert_chunk(table.as_ref(), &*columns, count, chunk));
+                count = 0;
+            }
+        }
+        if count > 0 {
+            affected_rows += try!(self.insert_chunk(table.as_ref(), &*columns, count, values));
+        }
+        Ok(affected_rows)
+    }
+
+    fn insert_chunk(&mut self,
+                    table: &str,
+                    columns: &[&str],
+                    rows: usize,
+                    values: Vec<Value>) -> MyResult<u64> {
+        let params = if values.is_empty() { Params::Empty } else { Params::Positional(values) };
+        let result = try!(self.prep_exec(insert_query(table, columns, rows), params));
+        Ok(result.affected_rows())
+    }
+
+    /// Executes text query and maps every row of its result set (converted via `FromRow`)
+    /// using `f`.
+    ///
+    /// Result set is consumed before return, so `Conn` is not borrowed afterwards:
+    ///
+    /// ```ignore
+    /// let names = try!(conn.query_map("SELECT name FROM users", |name: String| {
+    ///     name.to_uppercase()
+    /// }));
+    /// try!(conn.query("DELETE FROM users"));
+    /// ```
+    pub fn query_map<T, U, Q, F>(&mut self, query: Q, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          Q: AsRef<str>,
+          F: FnMut(T) -> U {
+        try!(self.query(query)).map_all_rows(f)
+    }
+
+    /// Executes text query and folds every row of its result set (converted via `FromRow`)
+    /// using `f` without buffering rows:
+    ///
+    /// ```ignore
+    /// let total = try!(conn.query_fold("SELECT amount FROM orders", 0, |acc, amount: u64| {
+    ///     acc + amount
+    /// }));
+    /// ```
+    pub fn query_fold<T, U, Q, F>(&mut self, query: Q, init: U, f: F) -> MyResult<U>
+    where T: FromRow,
+          Q: AsRef<str>,
+          F: FnMut(U, T) -> U {
+        try!(self.query(query)).fold_all_rows(init, f)
+    }
+
+    /// Same as [`Conn::query_map`](#method.query_map) but prepares and executes statement.
+    pub fn prep_exec_map<T, U, A, P, F>(&mut self, query: A, params: P, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params>,
+          F: FnMut(T) -> U {
+        try!(self.prep_exec(query, params)).map_all_rows(f)
+    }
+
+    /// Same as [`Conn::query_fold`](#method.query_fold) but prepares and executes statement.
+    pub fn prep_exec_fold<T, U, A, P, F>(&mut self,
+                                         query: A,
+                                         params: P,
+                                         init: U,
+                                         f: F) -> MyResult<U>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params>,
+          F: FnMut(U, T) -> U {
+        try!(self.prep_exec(query, params)).fold_all_rows(init, f)
+    }
+
     fn more_results_exists(&self) -> bool {
         self.has_results
     }
@@ -1379,7 +3501,23 @@ impl Conn {
         if self.connected {
             return Ok(());
         }
+        let elapsed = Elapsed::start();
+        let result = self.do_connect();
+        match result {
+            Ok(_) => mysql_debug!("connection {} to server {} established in {}",
+                                  self.connection_id,
+                                  self.server_version_str,
+                                  elapsed),
+            Err(ref err) => mysql_debug!("handshake failed in {}: {}", elapsed, err),
+        }
+        result
+    }
+
+    fn do_connect(&mut self) -> MyResult<()> {
         self.do_handshake().and_then(|_| {
+            if let Some(max_allowed_packet) = self.opts.max_allowed_packet {
+                return Ok(max_allowed_packet);
+            }
             Ok(from_value_opt::<usize>(self.get_system_var("max_allowed_packet").unwrap_or(NULL))
                .unwrap_or(0))
         }).and_then(|max_allowed_packet| {
@@ -1393,6 +3531,39 @@ impl Conn {
         })
     }
 
+    /// Returns warnings count reported by the last executed statement.
+    ///
+    /// For a result set it is known only after the last row was read.
+    pub fn warnings(&self) -> u16 {
+        self.warnings
+    }
+
+    /// Returns warnings of the last executed statement using `SHOW WARNINGS`.
+    ///
+    /// Query is executed only if warnings count of the last statement is not zero.
+    ///
+    /// ```ignore
+    /// conn.query("INSERT INTO tbl (tiny_int_col) VALUES (1000)").unwrap();
+    /// for warning in conn.get_warnings().unwrap() {
+    ///     println!("{}: {} ({})", warning.level, warning.message, warning.code);
+    /// }
+    /// ```
+    pub fn get_warnings(&mut self) -> MyResult<Vec<Warning>> {
+        if self.warnings == 0 {
+            return Ok(Vec::new());
+        }
+        let mut warnings = Vec::with_capacity(self.warnings as usize);
+        for row in try!(self.query("SHOW WARNINGS")) {
+            let (level, code, message) = try!(from_row_opt(try!(row)));
+            warnings.push(Warning {
+                level: level,
+                code: code,
+                message: message,
+            });
+        }
+        Ok(warnings)
+    }
+
     fn get_system_var(&mut self, name: &str) -> Option<Value> {
         for row in self.query(format!("SELECT @@{};", name)).unwrap() {
             match row {
@@ -1406,7 +3577,8 @@ impl Conn {
         return None;
     }
 
-    fn next_bin(&mut self, columns: &Vec<Column>) -> MyResult<Option<Vec<Value>>> {
+    /// Reads next row packet of a result set. Returns `None` if result set is over.
+    fn next_row_packet(&mut self, is_bin: bool) -> MyResult<Option<Vec<u8>>> {
         if ! self.has_results {
             return Ok(None);
         }
@@ -1417,6 +3589,7 @@ impl Conn {
                 return Err(e);
             }
         };
+        self.result_bytes_read += pld.len() as u64;
         let x = pld[0];
         if x == 0xfe && pld.len() < 0xfe {
             self.has_results = false;
@@ -1424,45 +3597,91 @@ impl Conn {
             self.handle_eof(&p);
             return Ok(None);
         }
-        let res = Value::from_bin_payload(pld.as_ref(), columns.as_ref());
-        match res {
-            Ok(p) => Ok(Some(p)),
-            Err(e) => {
-                self.has_results = false;
-                Err(IoError(e))
+        if !is_bin && x == 0xff && pld.len() < 0xfe {
+            self.has_results = false;
+            let p = ErrPacket::from_payload(pld.as_ref(), self.capability_flags);
+            match p {
+                Ok(p) => return Err(MySqlError(p.into())),
+                Err(err) => return Err(IoError(err))
             }
         }
+        Ok(Some(pld))
     }
 
-    fn next_text(&mut self, col_count: usize) -> MyResult<Option<Vec<Value>>> {
-        if ! self.has_results {
-            return Ok(None);
-        }
-        let pld = match self.read_packet() {
-            Ok(pld) => pld,
-            Err(e) => {
-                self.has_results = false;
-                return Err(e);
+    /// Reads rows of the current result set into memory respecting
+    /// [`Opts::result_budget`](struct.Opts.html#structfield.result_budget).
+    ///
+    /// Rows beyond the budget are read and discarded, so that connection remains usable.
+    /// Returns buffered rows and whether the budget was exceeded.
+    fn buffer_rows(&mut self,
+                   columns: &Arc<Vec<Column>>,
+                   is_bin: bool) -> MyResult<(Vec<Row>, bool)> {
+        let budget = self.opts.result_budget;
+        let mut rows = Vec::new();
+        let mut exceeded = false;
+        loop {
+            let values = if is_bin {
+                try!(self.next_bin(columns))
+            } else {
+                try!(self.next_text(&**columns))
+            };
+            let values = match values {
+                Some(values) => values,
+                None => break,
+            };
+            if let Some(budget) = budget {
+                exceeded = exceeded || self.result_bytes_read > budget.max_bytes;
             }
-        };
-        let x = pld[0];
-        if (x == 0xfe || x == 0xff) && pld.len() < 0xfe {
-            self.has_results = false;
-            if x == 0xfe {
-                let p = try!(EOFPacket::from_payload(pld.as_ref()));
-                self.handle_eof(&p);
-                return Ok(None);
-            } else /* x == 0xff */ {
-                let p = ErrPacket::from_payload(pld.as_ref(), self.capability_flags);
-                match p {
-                    Ok(p) => return Err(MySqlError(p.into())),
-                    Err(err) => return Err(IoError(err))
-                }
+            if !exceeded {
+                rows.push(Row::new_with_columns(values, columns.clone()));
             }
         }
-        let res = Value::from_payload(pld.as_ref(), col_count);
+        Ok((rows, exceeded))
+    }
+
+    /// Returns `DriverError::ResultBudgetExceeded` if budget was `exceeded` and
+    /// `Opts::result_budget` asks for an error rather than truncation.
+    fn check_result_budget(&self, exceeded: bool) -> MyResult<()> {
+        match self.opts.result_budget {
+            Some(budget) if exceeded && budget.on_exceed == OnBudgetExceeded::Error => {
+                Err(DriverError(ResultBudgetExceeded(budget.max_bytes)))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn next_bin(&mut self, columns: &Vec<Column>) -> MyResult<Option<Vec<Value>>> {
+        let pld = match try!(self.next_row_packet(true)) {
+            Some(pld) => pld,
+            None => return Ok(None),
+        };
+        let res = Value::from_bin_payload(pld.as_ref(), columns.as_ref());
         match res {
             Ok(p) => Ok(Some(p)),
+            Err(e) => {
+                self.has_results = false;
+                Err(IoError(e))
+            }
+        }
+    }
+
+    fn next_text(&mut self, columns: &[Column]) -> MyResult<Option<Vec<Value>>> {
+        let pld = match try!(self.next_row_packet(false)) {
+            Some(pld) => pld,
+            None => return Ok(None),
+        };
+        let res = Value::from_payload(pld.as_ref(), columns.len());
+        match res {
+            Ok(values) => {
+                if self.opts.text_coercion == TextCoercion::AsIs {
+                    return Ok(Some(values));
+                }
+                let values = values.into_iter()
+                                   .zip(columns)
+                                   .map(|(value, column)| value.coerce_text(column))
+                                   .collect();
+                Ok(Some(values))
+            },
             Err(err) => {
                 self.has_results = false;
                 Err(IoError(err))
@@ -1480,11 +3699,7 @@ impl Drop for Conn {
         let keys: Vec<String> = self.stmts.keys().map(Clone::clone).collect();
         for key in keys {
             for stmt in self.stmts.remove(&key) {
-                let data: [u8; 4] = [(stmt.statement_id & 0x000000FF) as u8,
-                                     ((stmt.statement_id & 0x0000FF00) >> 08) as u8,
-                                     ((stmt.statement_id & 0x00FF0000) >> 16) as u8,
-                                     ((stmt.statement_id & 0xFF000000) >> 24) as u8,];
-                let _ = self.write_command_data(Command::COM_STMT_CLOSE, &data);
+                let _ = self.close_stmt(stmt.statement_id);
             }
         }
     }
@@ -1575,31 +3790,139 @@ impl<'a> DerefMut for ResultConnRef<'a> {
 #[derive(Debug)]
 pub struct QueryResult<'a> {
     conn: ResultConnRef<'a>,
-    columns: Vec<Column>,
+    columns: Arc<Vec<Column>>,
     ok_packet: Option<OkPacket>,
     is_bin: bool,
+    budget: Option<ResultBudget>,
+    max_rows: Option<u64>,
+    /// Number of rows returned from the current result set.
+    set_rows: u64,
+    truncated: bool,
+    /// Whether rows were discarded because of `max_rows`.
+    limited: bool,
+    out_params: Option<Row>,
 }
 
 impl<'a> QueryResult<'a> {
-    fn new(conn: ResultConnRef<'a>,
+    fn new(mut conn: ResultConnRef<'a>,
            columns: Vec<Column>,
            ok_packet: Option<OkPacket>,
            is_bin: bool) -> QueryResult<'a>
     {
+        conn.result_bytes_read = 0;
+        let budget = conn.opts.result_budget;
+        let max_rows = conn.opts.max_rows;
         QueryResult {
             conn: conn,
-            columns: columns,
+            columns: Arc::new(columns),
             ok_packet: ok_packet,
-            is_bin: is_bin
+            is_bin: is_bin,
+            budget: budget,
+            max_rows: max_rows,
+            set_rows: 0,
+            truncated: false,
+            limited: false,
+            out_params: None,
+        }
+    }
+
+    /// Returns number of bytes of row packets read so far by this `QueryResult`
+    /// (across all of its result sets).
+    pub fn bytes_read(&self) -> u64 {
+        self.conn.result_bytes_read
+    }
+
+    /// Overrides [`Opts::result_budget`](struct.Opts.html#structfield.result_budget) for
+    /// this `QueryResult`.
+    pub fn set_budget(&mut self, budget: Option<ResultBudget>) {
+        self.budget = budget;
+    }
+
+    /// Overrides [`Opts::max_rows`](struct.Opts.html#structfield.max_rows) for this
+    /// `QueryResult`.
+    ///
+    /// ```ignore
+    /// let mut result = try!(conn.query("SELECT * FROM log"));
+    /// result.limit(Some(100));
+    /// let rows: Vec<Row> = try!(result.by_ref().collect());
+    /// if result.is_truncated() {
+    ///     println!("showing first 100 rows");
+    /// }
+    /// ```
+    pub fn limit(&mut self, max_rows: Option<u64>) {
+        self.max_rows = max_rows;
+    }
+
+    /// Returns `true` if buffering of rows was stopped because of
+    /// `OnBudgetExceeded::Truncate` budget or if rows were discarded because of
+    /// [`QueryResult::limit`](#method.limit).
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns OUT and INOUT parameters of a stored procedure called via prepared statement.
+    ///
+    /// Server sends them as the last result set, so remaining rows of other result sets
+    /// are skipped. Returns `None` if there are no such parameters, e.g. for `CALL` executed
+    /// as a text query (session variables should be selected in this case).
+    ///
+    /// ```ignore
+    /// // CREATE PROCEDURE add_one(IN a INT, OUT b INT) BEGIN SET b = a + 1; END
+    /// let mut result = try!(conn.prep_exec("CALL add_one(?, ?)", (41, Value::NULL)));
+    /// let (b,): (i32,) = from_row(try!(result.out_params()).unwrap());
+    /// ```
+    pub fn out_params(&mut self) -> MyResult<Option<Row>> {
+        loop {
+            try!(self.fetch_out_params());
+            if self.out_params.is_some() || !self.more_results_exists() {
+                break;
+            }
+            while let Some(row) = self.next() {
+                try!(row);
+            }
+        }
+        Ok(self.out_params.clone())
+    }
+
+    /// Reads result set of OUT parameters if it is the current one, so that it is never
+    /// returned as regular rows.
+    fn fetch_out_params(&mut self) -> MyResult<()> {
+        if !self.is_bin || self.columns.is_empty() || !self.conn.has_results ||
+           !self.conn.status_flags.contains(consts::SERVER_PT_OUT_PARAMS) {
+            return Ok(());
+        }
+        while let Some(values) = try!(self.conn.next_bin(&self.columns)) {
+            self.out_params = Some(Row::new_with_columns(values, self.columns.clone()));
+        }
+        match self.handle_if_more_results() {
+            Some(Err(err)) => Err(err),
+            _ => Ok(()),
         }
     }
 
+    /// Reads and discards remaining rows of the current result set once row limit is reached.
+    fn discard_rows(&mut self) -> Option<MyResult<Row>> {
+        let is_bin = self.is_bin;
+        loop {
+            match self.conn.next_row_packet(is_bin) {
+                Ok(Some(_)) => {
+                    self.truncated = true;
+                    self.limited = true;
+                },
+                Ok(None) => break,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        self.handle_if_more_results()
+    }
+
     fn handle_if_more_results(&mut self) -> Option<MyResult<Row>> {
         if self.conn.status_flags.contains(consts::SERVER_MORE_RESULTS_EXISTS) {
             match self.conn.handle_result_set() {
                 Ok((cols, ok_p)) => {
-                    self.columns = cols;
+                    self.columns = Arc::new(cols);
                     self.ok_packet = ok_p;
+                    self.set_rows = 0;
                     None
                 },
                 Err(e) => return Some(Err(e)),
@@ -1623,11 +3946,25 @@ impl<'a> QueryResult<'a> {
         self.conn.last_insert_id
     }
 
-    /// Returns
-    /// [`OkPacket`'s](http://dev.mysql.com/doc/internals/en/packet-OK_Packet.html)
-    /// warnings count.
+    /// Returns warnings count reported by
+    /// [`OkPacket`](http://dev.mysql.com/doc/internals/en/packet-OK_Packet.html)
+    /// or, for a fully consumed result set, by the terminating EOF packet.
+    ///
+    /// Use [`Conn::get_warnings`](struct.Conn.html#method.get_warnings) to get the warnings.
     pub fn warnings(&self) -> u16 {
-        self.ok_packet.as_ref().map(|ok_p| ok_p.warnings).unwrap_or(0u16)
+        self.conn.warnings
+    }
+
+    /// Returns [`OkPacket`](struct.OkPacket.html) of a statement without a result set
+    /// (`None` for a result set).
+    ///
+    /// ```ignore
+    /// let result = try!(conn.query("UPDATE tbl SET a = 1"));
+    /// let ok = result.ok_packet().unwrap();
+    /// println!("{} ({} warnings)", ok.info_str(), ok.warnings());
+    /// ```
+    pub fn ok_packet(&self) -> Option<&OkPacket> {
+        self.ok_packet.as_ref()
     }
 
     /// Returns
@@ -1635,7 +3972,7 @@ impl<'a> QueryResult<'a> {
     /// info.
     pub fn info(&self) -> Vec<u8> {
         if self.ok_packet.is_some() {
-            self.ok_packet.as_ref().unwrap().info.clone()
+            self.ok_packet.as_ref().unwrap().info().to_vec()
         } else {
             Vec::with_capacity(0)
         }
@@ -1664,7 +4001,7 @@ impl<'a> QueryResult<'a> {
     /// Returns a slice of a [`Column`s](struct.Column.html) which represents
     /// `QueryResult`'s columns if any.
     pub fn columns_ref(&self) -> &[Column] {
-        self.columns.as_ref()
+        &*self.columns
     }
 
     /// This predicate will help you if you are expecting multiple result sets.
@@ -1689,133 +4026,1664 @@ impl<'a> QueryResult<'a> {
     pub fn more_results_exists(&self) -> bool {
         self.conn.has_results
     }
+
+    /// Converts every remaining row of the current result set to `T` via `FromRow` and maps
+    /// it using `f`.
+    ///
+    /// Returns first error encountered while reading or converting rows. Respects
+    /// [`Opts::result_budget`](struct.Opts.html#structfield.result_budget).
+    ///
+    /// ```ignore
+    /// let users = try!(try!(conn.query("SELECT id, name FROM users")).map_rows(|(id, name)| {
+    ///     User { id: id, name: name }
+    /// }));
+    /// ```
+    ///
+    /// It is named so to not shadow `Iterator::map` implemented on `QueryResult`.
+    pub fn map_rows<T, U, F>(&mut self, mut f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          F: FnMut(T) -> U {
+        self.collect_within_budget(|row| from_row_opt(row).map(&mut f))
+    }
+
+    /// Converts every remaining row of the current result set using `f` respecting
+    /// [`Opts::result_budget`](struct.Opts.html#structfield.result_budget).
+    fn collect_within_budget<U, F>(&mut self, mut f: F) -> MyResult<Vec<U>>
+    where F: FnMut(Row) -> MyResult<U> {
+        let mut output = Vec::new();
+        while let Some(row) = self.next() {
+            let row = try!(row);
+            if let Some(budget) = self.budget {
+                if self.bytes_read() > budget.max_bytes {
+                    match budget.on_exceed {
+                        OnBudgetExceeded::Error => {
+                            return Err(DriverError(ResultBudgetExceeded(budget.max_bytes)));
+                        },
+                        OnBudgetExceeded::Truncate => {
+                            self.truncated = true;
+                            break;
+                        },
+                    }
+                }
+            }
+            output.push(try!(f(row)));
+        }
+        Ok(output)
+    }
+
+    /// Converts every remaining row of the current result set to `T` via `FromRow` and folds
+    /// it using `f`.
+    ///
+    /// Unlike [`QueryResult::map_rows`](#method.map_rows) it does not buffer rows, so
+    /// `Opts::result_budget` is not applied.
+    pub fn fold_rows<T, U, F>(&mut self, init: U, mut f: F) -> MyResult<U>
+    where T: FromRow,
+          F: FnMut(U, T) -> U {
+        let mut acc = init;
+        while let Some(row) = self.next() {
+            acc = f(acc, try!(from_row_opt(try!(row))));
+        }
+        Ok(acc)
+    }
+
+    /// Converts every remaining row of the current result set to `T` via `FromRow`.
+    ///
+    /// ```ignore
+    /// let pairs: Vec<(u32, String)> = try!(try!(conn.query("SELECT id, name FROM users"))
+    ///     .collect_rows());
+    /// ```
+    pub fn collect_rows<T: FromRow>(&mut self) -> MyResult<Vec<T>> {
+        self.map_rows(|row| row)
+    }
+
+    /// Returns `DriverError::RowLimitExceeded` if rows were discarded because of
+    /// [`QueryResult::limit`](#method.limit).
+    fn check_row_limit(&self) -> MyResult<()> {
+        if self.limited {
+            Err(DriverError(RowLimitExceeded(self.max_rows.unwrap_or(0))))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Same as [`QueryResult::map_rows`](#method.map_rows) but consumes `QueryResult` and
+    /// returns `DriverError::RowLimitExceeded` instead of silently truncated rows.
+    fn map_all_rows<T, U, F>(mut self, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          F: FnMut(T) -> U {
+        let output = try!(self.map_rows(f));
+        try!(self.check_row_limit());
+        Ok(output)
+    }
+
+    /// Same as [`QueryResult::fold_rows`](#method.fold_rows) but consumes `QueryResult` and
+    /// returns `DriverError::RowLimitExceeded` instead of silently truncated rows.
+    fn fold_all_rows<T, U, F>(mut self, init: U, f: F) -> MyResult<U>
+    where T: FromRow,
+          F: FnMut(U, T) -> U {
+        let acc = try!(self.fold_rows(init, f));
+        try!(self.check_row_limit());
+        Ok(acc)
+    }
+
+    /// Same as [`QueryResult::collect_rows`](#method.collect_rows) but consumes `QueryResult`
+    /// and returns `DriverError::RowLimitExceeded` instead of silently truncated rows.
+    fn collect_all_rows<T: FromRow>(self) -> MyResult<Vec<T>> {
+        self.map_all_rows(|row| row)
+    }
+
+    /// Same as [`QueryResult::first_row`](#method.first_row) but consumes `QueryResult` and
+    /// returns `DriverError::RowLimitExceeded` if the first row was discarded (i.e. limit is
+    /// zero).
+    fn first_row_checked<T: FromRow>(mut self) -> MyResult<Option<T>> {
+        let row = try!(self.first_row());
+        try!(self.check_row_limit());
+        Ok(row)
+    }
+
+    /// Converts next row of the current result set to `T` via `FromRow`.
+    ///
+    /// Returns `Ok(None)` if result set is over.
+    pub fn first_row<T: FromRow>(&mut self) -> MyResult<Option<T>> {
+        match self.next() {
+            Some(row) => from_row_opt(try!(row)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns streaming iterator over rows of this result set which borrow their values
+    /// from the network buffer.
+    ///
+    /// Unlike `Iterator` implementation on `QueryResult` it won't allocate a `Vec<u8>` for
+    /// every string or blob cell. Every [`RowRef`](struct.RowRef.html) is valid until the next
+    /// call to `next`:
+    ///
+    /// ```ignore
+    /// let mut result = conn.query("SELECT name, email FROM users").unwrap();
+    /// let mut rows = result.by_ref_rows();
+    /// while let Some(row) = rows.next() {
+    ///     let row = row.unwrap();
+    ///     let name: &str = row[0].as_str().unwrap();
+    /// }
+    /// ```
+    pub fn by_ref_rows<'b>(&'b mut self) -> RowsRef<'b, 'a> {
+        RowsRef {
+            result: self,
+            pld: Vec::new(),
+        }
+    }
+
+    /// Writes remaining rows of the current result set to `writer` as CSV (or TSV, see
+    /// [`CsvOptions::tsv`](struct.CsvOptions.html#method.tsv)) and returns number of written
+    /// rows.
+    ///
+    /// Rows are streamed one by one, so memory usage does not depend on result size. Wrap
+    /// `writer` into `BufWriter` if it is unbuffered.
+    ///
+    /// ```ignore
+    /// let file = BufWriter::new(try!(File::create("users.csv")));
+    /// try!(try!(conn.query("SELECT * FROM users")).write_csv(file, CsvOptions::default()));
+    /// ```
+    pub fn write_csv<W: io::Write>(&mut self, writer: W, options: CsvOptions) -> MyResult<u64> {
+        export::write_csv(self, writer, &options)
+    }
+}
+
+/// Streaming iterator over [`RowRef`s](struct.RowRef.html) of a
+/// [`QueryResult`](struct.QueryResult.html).
+///
+/// See [`QueryResult::by_ref_rows`](struct.QueryResult.html#method.by_ref_rows).
+#[derive(Debug)]
+pub struct RowsRef<'b, 'a: 'b> {
+    result: &'b mut QueryResult<'a>,
+    pld: Vec<u8>,
+}
+
+impl<'b, 'a: 'b> RowsRef<'b, 'a> {
+    /// Returns next row of a result set. Same as `Iterator` on `QueryResult` it will
+    /// return `None` at the end of every result set.
+    pub fn next(&mut self) -> Option<MyResult<RowRef>> {
+        if let Err(err) = self.result.fetch_out_params() {
+            return Some(Err(err));
+        }
+        if let Some(max_rows) = self.result.max_rows {
+            if self.result.set_rows >= max_rows && self.result.conn.has_results {
+                return match self.result.discard_rows() {
+                    Some(Err(e)) => Some(Err(e)),
+                    _ => None,
+                };
+            }
+        }
+        match self.result.conn.next_row_packet(self.result.is_bin) {
+            Ok(Some(pld)) => {
+                self.result.set_rows += 1;
+                self.pld = pld;
+            },
+            Ok(None) => {
+                return match self.result.handle_if_more_results() {
+                    Some(Err(e)) => Some(Err(e)),
+                    _ => None,
+                };
+            },
+            Err(e) => return Some(Err(e)),
+        }
+        let values = if self.result.is_bin {
+            ValueRef::from_bin_payload(self.pld.as_ref(), &*self.result.columns)
+        } else {
+            ValueRef::from_payload(self.pld.as_ref(), self.result.columns.len())
+        };
+        match values {
+            Ok(values) => {
+                if self.result.is_bin ||
+                   self.result.conn.opts.text_coercion == TextCoercion::AsIs {
+                    return Some(Ok(RowRef::new(values)));
+                }
+                let values = values.into_iter()
+                                   .zip(self.result.columns.iter())
+                                   .map(|(value, column)| value.coerce_text(column))
+                                   .collect();
+                Some(Ok(RowRef::new(values)))
+            },
+            Err(e) => {
+                self.result.conn.has_results = false;
+                Some(Err(IoError(e)))
+            }
+        }
+    }
 }
 
 impl<'a> Iterator for QueryResult<'a> {
     type Item = MyResult<Row>;
 
     fn next(&mut self) -> Option<MyResult<Row>> {
+        if let Err(err) = self.fetch_out_params() {
+            return Some(Err(err));
+        }
+        if let Some(max_rows) = self.max_rows {
+            if self.set_rows >= max_rows && self.conn.has_results {
+                return self.discard_rows();
+            }
+        }
         let values = if self.is_bin {
             self.conn.next_bin(&self.columns)
         } else {
-            self.conn.next_text(self.columns.len())
+            self.conn.next_text(&*self.columns)
         };
         match values {
             Ok(values) => {
                 match values {
-                    Some(values) => Some(Ok(Row::new(values))),
+                    Some(values) => {
+                        self.set_rows += 1;
+                        Some(Ok(Row::new_with_columns(values, self.columns.clone())))
+                    },
                     None => self.handle_if_more_results(),
                 }
-            },
-            Err(e) => Some(Err(e)),
+            },
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'a> Drop for QueryResult<'a> {
+    fn drop(&mut self) {
+        while self.conn.more_results_exists() {
+            while let Some(_) = self.next() {}
+        }
+    }
+}
+
+/// Result of a single statement of a multi-statement query.
+///
+/// See [`Conn::query_multi`](struct.Conn.html#method.query_multi).
+#[derive(Clone, PartialEq, Debug)]
+pub struct ResultSet {
+    columns: Arc<Vec<Column>>,
+    rows: Vec<Row>,
+    affected_rows: u64,
+    last_insert_id: u64,
+    warnings: u16,
+    info: Vec<u8>,
+}
+
+impl ResultSet {
+    /// Returns columns of this result set (empty slice if statement returns no rows).
+    pub fn columns_ref(&self) -> &[Column] {
+        &*self.columns
+    }
+
+    /// Returns rows of this result set.
+    pub fn rows(&self) -> &[Row] {
+        &*self.rows
+    }
+
+    /// Unwraps rows of this result set.
+    pub fn into_rows(self) -> Vec<Row> {
+        self.rows
+    }
+
+    /// Returns affected rows of a statement which does not return rows.
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    /// Returns last insert id of a statement which does not return rows.
+    pub fn last_insert_id(&self) -> u64 {
+        self.last_insert_id
+    }
+
+    /// Returns warnings count of a statement.
+    pub fn warnings(&self) -> u16 {
+        self.warnings
+    }
+
+    /// Returns info of a statement which does not return rows.
+    pub fn info(&self) -> &[u8] {
+        &*self.info
+    }
+}
+
+/// Iterator over [`ResultSet`s](struct.ResultSet.html) of a multi-statement query.
+///
+/// See [`Conn::query_multi`](struct.Conn.html#method.query_multi).
+#[derive(Debug)]
+pub struct MultiResult<'a> {
+    conn: &'a mut Conn,
+    /// Header of the next result set.
+    next: Option<MyResult<(Vec<Column>, Option<OkPacket>)>>,
+}
+
+impl<'a> MultiResult<'a> {
+    fn read_set(&mut self,
+                columns: Vec<Column>,
+                ok_packet: Option<OkPacket>) -> MyResult<ResultSet> {
+        let columns = Arc::new(columns);
+        let (rows, exceeded) = try!(self.conn.buffer_rows(&columns, false));
+        try!(self.conn.check_result_budget(exceeded));
+        Ok(match ok_packet {
+            Some(ok) => ResultSet {
+                columns: columns,
+                rows: rows,
+                affected_rows: ok.affected_rows(),
+                last_insert_id: ok.last_insert_id(),
+                warnings: ok.warnings(),
+                info: ok.info().to_vec(),
+            },
+            None => ResultSet {
+                columns: columns,
+                rows: rows,
+                affected_rows: 0,
+                last_insert_id: 0,
+                warnings: self.conn.warnings,
+                info: Vec::new(),
+            },
+        })
+    }
+}
+
+impl<'a> Iterator for MultiResult<'a> {
+    type Item = MyResult<ResultSet>;
+
+    fn next(&mut self) -> Option<MyResult<ResultSet>> {
+        let (columns, ok_packet) = match self.next.take() {
+            Some(Ok(header)) => header,
+            Some(Err(err)) => return Some(Err(err)),
+            None => return None,
+        };
+        let result = self.read_set(columns, ok_packet);
+        // Result set is read completely even if budget is exceeded.
+        let is_read = match result {
+            Ok(_) | Err(DriverError(ResultBudgetExceeded(_))) => true,
+            Err(_) => false,
+        };
+        if is_read && self.conn.status_flags.contains(consts::SERVER_MORE_RESULTS_EXISTS) {
+            self.next = Some(self.conn.handle_result_set());
+        }
+        Some(result)
+    }
+}
+
+impl<'a> Drop for MultiResult<'a> {
+    fn drop(&mut self) {
+        while let Some(_) = self.next() {}
+    }
+}
+
+/// Results of a stored procedure call.
+///
+/// See [`Conn::call`](struct.Conn.html#method.call).
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProcResult {
+    result_sets: Vec<ResultSet>,
+    out_params: Option<Row>,
+    affected_rows: u64,
+    last_insert_id: u64,
+    warnings: u16,
+    info: Vec<u8>,
+}
+
+impl ProcResult {
+    /// Returns result sets produced by statements of a procedure in order (OUT parameters
+    /// are not included).
+    pub fn result_sets(&self) -> &[ResultSet] {
+        &*self.result_sets
+    }
+
+    /// Unwraps result sets of a procedure.
+    pub fn into_result_sets(self) -> Vec<ResultSet> {
+        self.result_sets
+    }
+
+    /// Returns OUT and INOUT parameters of a procedure (`None` if it has no such parameters).
+    pub fn out_params(&self) -> Option<&Row> {
+        self.out_params.as_ref()
+    }
+
+    /// Returns affected rows reported by the final OK packet of a call, i.e. by the last
+    /// statement of a procedure.
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    /// Returns last insert id reported by the final OK packet of a call.
+    pub fn last_insert_id(&self) -> u64 {
+        self.last_insert_id
+    }
+
+    /// Returns warnings count reported by the final OK packet of a call.
+    pub fn warnings(&self) -> u16 {
+        self.warnings
+    }
+
+    /// Returns info reported by the final OK packet of a call.
+    pub fn info(&self) -> &[u8] {
+        &*self.info
+    }
+}
+
+/***
+ *    88888888888                   888
+ *        888                       888
+ *        888                       888
+ *        888      .d88b.  .d8888b  888888 .d8888b
+ *        888     d8P  Y8b 88K      888    88K
+ *        888     88888888 "Y8888b. 888    "Y8888b.
+ *        888     Y8b.          X88 Y88b.       X88
+ *        888      "Y8888   88888P'  "Y888  88888P'
+ *
+ *
+ *
+ */
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod test {
+    use std::borrow::ToOwned;
+    use std::default::Default;
+    use super::Opts;
+
+    static USER: &'static str = "root";
+    static PASS: &'static str = "password";
+    static ADDR: &'static str = "127.0.0.1";
+    static PORT: u16          = 3307;
+
+    #[cfg(feature = "openssl")]
+    pub fn get_opts() -> Opts {
+        let pwd: String = ::std::env::var("MYSQL_SERVER_PASS").unwrap_or(PASS.to_string());
+        let port: u16 = ::std::env::var("MYSQL_SERVER_PORT").ok()
+                                   .map(|my_port| my_port.parse().ok().unwrap_or(PORT))
+                                   .unwrap_or(PORT);
+        Opts {
+            user: Some(USER.to_string()),
+            pass: Some(pwd),
+            ip_or_hostname: Some(ADDR.to_string()),
+            tcp_port: port,
+            init: vec!["SET GLOBAL sql_mode = 'TRADITIONAL'".to_owned()],
+            ssl_opts: Some((::std::path::PathBuf::from("tests/ca-cert.pem"), None).into()),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(not(feature = "ssl"))]
+    pub fn get_opts() -> Opts {
+        let pwd: String = ::std::env::var("MYSQL_SERVER_PASS").unwrap_or(PASS.to_string());
+        let port: u16 = ::std::env::var("MYSQL_SERVER_PORT").ok()
+                                   .map(|my_port| my_port.parse().ok().unwrap_or(PORT))
+                                   .unwrap_or(PORT);
+        Opts {
+            user: Some(USER.to_string()),
+            pass: Some(pwd),
+            ip_or_hostname: Some(ADDR.to_string()),
+            tcp_port: port,
+            init: vec!["SET GLOBAL sql_mode = 'TRADITIONAL'".to_owned()],
+            ..Default::default()
+        }
+    }
+
+    mod my_conn {
+        use std::collections::HashMap;
+        use std::iter;
+        use std::borrow::ToOwned;
+        use std::fs;
+        use std::io::Write;
+        use time::{Tm, now};
+        use super::super::{AccessMode, Conn, CsvOptions, DropBehavior, InitFn, IsolationLevel};
+        use super::super::{OnBudgetExceeded, Opts, RawResponse};
+        use super::super::{ResultBudget, Row, TxOpts};
+        use super::super::{handshake_response, initial_auth_plugin};
+        use super::super::{in_list, insert_query, parse_auth_switch_request};
+        #[cfg(feature = "openssl")]
+        use super::super::xor_password;
+        use super::super::super::consts;
+        use super::super::super::error::{DriverError, Error};
+        use super::super::super::packet::HandshakePacket;
+        use super::super::super::value::{Params, ToValue, Value, ValueRef};
+        use super::super::super::value::{from_row, from_row_opt, from_value};
+        use super::super::super::value::Value::{NULL, Int, UInt, Bytes, Date};
+        use super::get_opts;
+
+        #[test]
+        fn should_connect() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mode = conn.query("SELECT @@GLOBAL.sql_mode").unwrap().next().unwrap().unwrap().take(0).unwrap();
+            let mode = from_value::<String>(mode);
+            assert!(mode.contains("TRADITIONAL"));
+            assert!(conn.ping());
+            conn.ping_err().unwrap();
+        }
+        #[test]
+        fn should_connect_with_database() {
+            let mut conn = Conn::new(Opts {
+                db_name: Some("mysql".to_string()),
+                ..get_opts()
+            }).unwrap();
+            assert_eq!(conn.query("SELECT DATABASE()").unwrap().next().unwrap().unwrap().unwrap(),
+                       vec![Bytes(b"mysql".to_vec())]);
+        }
+        #[test]
+        fn should_connect_by_hostname() {
+            let mut conn = Conn::new(Opts {
+                db_name: Some("mysql".to_string()),
+                ip_or_hostname: Some("localhost".to_string()),
+                ..get_opts()
+            }).unwrap();
+            assert_eq!(conn.query("SELECT DATABASE()").unwrap().next().unwrap().unwrap().unwrap(),
+                       vec![Bytes(b"mysql".to_vec())]);
+        }
+        #[test]
+        fn should_call_init_fn() {
+            let mut conn = Conn::new(Opts {
+                init: vec!["SET @a = 1".to_owned()],
+                init_fn: Some(InitFn::new(|conn| {
+                    conn.query("SET @b = @a + 1").map(|_| ())
+                })),
+                ..get_opts()
+            }).unwrap();
+            assert_eq!(conn.query("SELECT @b").unwrap().next().unwrap().unwrap().unwrap(),
+                       vec![Bytes(b"2".to_vec())]);
+            assert!(Conn::new(Opts {
+                init_fn: Some(InitFn::new(|conn| conn.query("SELECT * FROM unexisted").map(|_| ()))),
+                ..get_opts()
+            }).is_err());
+        }
+        #[test]
+        fn should_execute_statement_with_named_params() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut params = HashMap::default();
+            params.insert("a".to_string(), Value::from(1));
+            params.insert("b".to_string(), Value::from("foo"));
+            let row = conn.prep_exec("SELECT :a AS x, ':a', :b, :a + 1", Params::Named(params))
+                          .unwrap().next().unwrap().unwrap();
+            assert_eq!(row.column_index("x"), Some(0));
+            assert_eq!(row.columns_ref().len(), 4);
+            assert_eq!(row.unwrap(),
+                       vec![Int(1), Bytes(b":a".to_vec()), Bytes(b"foo".to_vec()), Int(2)]);
+            let mut params = HashMap::default();
+            params.insert("a".to_string(), Value::from(1));
+            match conn.prep_exec("SELECT :a, :b", Params::Named(params.clone())) {
+                Err(Error::DriverError(DriverError::MissingNamedParameter(ref name))) => {
+                    assert_eq!(name, "b");
+                },
+                _ => panic!("expected MissingNamedParameter"),
+            }
+            match conn.prep_exec("SELECT ?", Params::Named(params)) {
+                Err(Error::DriverError(DriverError::NamedParamsForPositionalQuery)) => (),
+                _ => panic!("expected NamedParamsForPositionalQuery"),
+            }
+        }
+        #[test]
+        fn should_cancel_query() {
+            use std::thread;
+            use std::time::Duration as StdDuration;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let token = conn.cancellation_token();
+            assert_eq!(token.connection_id(), conn.id());
+            let handle = thread::spawn(move || {
+                thread::sleep(StdDuration::from_millis(300));
+                token.cancel().unwrap();
+            });
+            match conn.query("SELECT 1 FROM (SELECT 1) AS t WHERE SLEEP(20)") {
+                Err(Error::DriverError(DriverError::QueryCancelled)) => (),
+                _ => panic!("Expected QueryCancelled error"),
+            }
+            handle.join().unwrap();
+            let value: Option<u8> = conn.exec_first("SELECT 1", ()).unwrap();
+            assert_eq!(value, Some(1));
+        }
+        #[test]
+        fn should_expand_duplicated_named_params() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(a INT, b INT)").unwrap();
+            conn.query("INSERT INTO x.tbl VALUES (1, 2), (2, 1), (3, 3)").unwrap();
+            let mut params = HashMap::default();
+            params.insert("x".to_string(), Value::from(1));
+            let rows: Vec<(i32, i32)> = conn.exec("SELECT a, b FROM x.tbl \
+                                                   WHERE a = :x OR b = :x ORDER BY a",
+                                                  Params::Named(params))
+                                            .unwrap();
+            assert_eq!(rows, vec![(1, 2), (2, 1)]);
+        }
+        #[test]
+        fn should_execute_in_list() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(a INT)").unwrap();
+            conn.query("INSERT INTO x.tbl VALUES (1), (2), (3)").unwrap();
+            let query = "SELECT a FROM x.tbl WHERE a IN (:ids) ORDER BY a";
+            let (list_query, params) = in_list(query, "ids", &[1, 3]);
+            assert_eq!(conn.exec::<i32, _, _>(list_query, params).unwrap(), vec![1, 3]);
+            let (list_query, params) = in_list(query, "ids", Vec::<i32>::new());
+            assert!(conn.exec::<i32, _, _>(list_query, params).unwrap().is_empty());
+        }
+        #[test]
+        fn should_parse_auth_switch_request() {
+            let pld = b"mysql_native_password\x00abcdefghij0123456789\x00";
+            assert_eq!(parse_auth_switch_request(pld),
+                       (&b"mysql_native_password"[..], &b"abcdefghij0123456789"[..]));
+            assert_eq!(parse_auth_switch_request(b"mysql_clear_password\x00"),
+                       (&b"mysql_clear_password"[..], &b""[..]));
+            assert_eq!(parse_auth_switch_request(b"mysql_clear_password"),
+                       (&b"mysql_clear_password"[..], &b""[..]));
+        }
+        #[test]
+        fn should_use_default_caching_sha2_password_plugin() {
+            let mut payload = b"\x0a8.0.11\x00\x01\x00\x00\x00abcdefgh\x00\xff\xff\x21\x02\x00\
+                                \x08\x00\x15".to_vec();
+            payload.extend(iter::repeat(0u8).take(10));
+            payload.extend(b"ijklmnopqrst\x00caching_sha2_password\x00".iter().cloned());
+            let mut hp = HandshakePacket::from_payload(&*payload).unwrap();
+            assert_eq!(initial_auth_plugin(&hp), b"caching_sha2_password");
+            let opts = Opts {
+                user: Some("root".to_string()),
+                pass: Some("password".to_string()),
+                ..Opts::default()
+            };
+            let flags = consts::CLIENT_PROTOCOL_41 | consts::CLIENT_SECURE_CONNECTION |
+                        consts::CLIENT_PLUGIN_AUTH;
+            let response = handshake_response(&opts, flags, &hp, initial_auth_plugin(&hp)).unwrap();
+            // Length of scramble follows fixed header and user name.
+            assert_eq!(response[32 + 5], 32);
+            assert!(response.ends_with(b"caching_sha2_password\x00"));
+            hp.auth_plugin_name = b"sha256_password".to_vec();
+            assert_eq!(initial_auth_plugin(&hp), b"mysql_native_password");
+            let response = handshake_response(&opts, flags, &hp, initial_auth_plugin(&hp)).unwrap();
+            assert_eq!(response[32 + 5], 20);
+        }
+        #[test]
+        #[cfg(feature = "openssl")]
+        fn should_xor_password_with_scramble() {
+            assert_eq!(xor_password(b"ab", b"\x01\x02"), vec![b'a' ^ 1, b'b' ^ 2, 1]);
+            assert_eq!(xor_password(b"", b""), vec![0]);
+        }
+        #[test]
+        fn should_authenticate_with_sha256_password() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            // MariaDB has no `sha256_password` plugin.
+            if conn.server_version < (5, 7, 8) || conn.is_mariadb() {
+                return;
+            }
+            conn.query("DROP USER IF EXISTS 'sha256_user'@'%'").unwrap();
+            conn.query("CREATE USER 'sha256_user'@'%' \
+                        IDENTIFIED WITH sha256_password BY 'sha256_pass'").unwrap();
+            let opts = Opts {
+                user: Some("sha256_user".to_string()),
+                pass: Some("sha256_pass".to_string()),
+                ..get_opts()
+            };
+            let mut sha256_conn = Conn::new(opts).unwrap();
+            assert_eq!(sha256_conn.exec_first::<u8, _, _>("SELECT 1", ()).unwrap(), Some(1));
+            conn.query("DROP USER 'sha256_user'@'%'").unwrap();
+        }
+        #[test]
+        fn should_map_and_fold_rows() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let query = "SELECT 1, 'a' UNION ALL SELECT 2, 'b'";
+            let names = conn.query_map(query, |(_, name): (u8, String)| name).unwrap();
+            assert_eq!(names, vec!["a".to_string(), "b".to_string()]);
+            let sum = conn.query_fold(query, 0, |acc, (id, _): (u8, String)| acc + id).unwrap();
+            assert_eq!(sum, 3);
+            let ids = conn.prep_exec_map("SELECT ? UNION ALL SELECT ?", (1, 2), |id: u8| id * 10)
+                .unwrap();
+            assert_eq!(ids, vec![10, 20]);
+            let count = conn.prep_exec_fold("SELECT ? UNION ALL SELECT ?", (1, 2), 0,
+                                            |acc, _: u8| acc + 1).unwrap();
+            assert_eq!(count, 2);
+            assert!(conn.query_map("SELECT 'a'", |x: u8| x).is_err());
+            // Connection should be usable after a conversion error.
+            let mut tx = conn.start_transaction(TxOpts::new()).unwrap();
+            assert_eq!(tx.query_map("SELECT 42", |x: u8| x).unwrap(), vec![42]);
+        }
+        #[test]
+        fn should_escape_string_according_to_sql_mode() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let value = "it's \"quoted\" \\ \n\x00";
+            for mode in &["", "NO_BACKSLASH_ESCAPES"] {
+                conn.query(format!("SET SESSION sql_mode = '{}'", mode)).unwrap();
+                assert_eq!(conn.no_backslash_escapes(), mode.len() > 0);
+                let query = format!("SELECT '{}'", conn.escape_string(value));
+                let selected: Vec<String> = conn.query_map(query, |x: String| x).unwrap();
+                assert_eq!(selected, vec![value.to_string()]);
+            }
+        }
+        #[test]
+        fn should_get_and_set_sql_mode() {
+            use super::super::super::consts;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mode = consts::MODE_STRICT_TRANS_TABLES | consts::MODE_NO_BACKSLASH_ESCAPES;
+            conn.set_sql_mode(mode).unwrap();
+            assert_eq!(conn.sql_mode().unwrap(), mode);
+            assert!(conn.no_backslash_escapes());
+            assert_eq!(conn.escape_string("a'b\\c"), "a''b\\c");
+            conn.set_sql_mode(consts::SqlMode::empty()).unwrap();
+            assert_eq!(conn.sql_mode().unwrap(), consts::SqlMode::empty());
+            assert!(!conn.no_backslash_escapes());
+            assert_eq!(conn.escape_string("a'b"), "a\\'b");
+        }
+        #[test]
+        fn should_update_sql_mode() {
+            use super::super::super::consts;
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.set_sql_mode(consts::MODE_ANSI_QUOTES).unwrap();
+            conn.update_sql_mode(|mode| mode | consts::MODE_NO_BACKSLASH_ESCAPES).unwrap();
+            assert_eq!(conn.sql_mode().unwrap(),
+                       consts::MODE_ANSI_QUOTES | consts::MODE_NO_BACKSLASH_ESCAPES);
+            conn.update_sql_mode(|mode| mode - consts::MODE_ANSI_QUOTES).unwrap();
+            assert_eq!(conn.sql_mode().unwrap(), consts::MODE_NO_BACKSLASH_ESCAPES);
+        }
+        #[test]
+        fn should_build_insert_query() {
+            assert_eq!(insert_query("db.t`bl", &["a", "b"], 2),
+                       "INSERT INTO `db`.`t``bl` (`a`, `b`) VALUES (?, ?), (?, ?)");
+        }
+        #[test]
+        fn should_insert_many() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(id INT, name TEXT)").unwrap();
+            let rows = (0..5).map(|i| (i, format!("name{}", i)));
+            assert_eq!(conn.insert_many("x.tbl", &["id", "name"], rows, 2).unwrap(), 5);
+            let mut named = HashMap::default();
+            named.insert("name".to_string(), Value::from("named"));
+            named.insert("id".to_string(), Value::from(5));
+            let rows = vec![Params::Named(named)];
+            assert_eq!(conn.insert_many("x.tbl", &["id", "name"], rows, 100).unwrap(), 1);
+            let rows: Vec<(u8, String)> = conn.exec("SELECT id, name FROM x.tbl ORDER BY id", ())
+                                              .unwrap();
+            assert_eq!(rows.len(), 6);
+            assert_eq!(rows[4], (4, "name4".to_string()));
+            assert_eq!(rows[5], (5, "named".to_string()));
+            match conn.insert_many("x.tbl", &["id", "name"], vec![(1,)], 10) {
+                Err(Error::DriverError(DriverError::MismatchedStmtParams(2, 1))) => (),
+                _ => panic!("expected MismatchedStmtParams"),
+            }
+        }
+        #[test]
+        fn should_report_column_of_failed_conversion() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut result = conn.query("SELECT 1 AS id, NULL AS created_at").unwrap();
+            let mut row = result.next().unwrap().unwrap();
+            assert_eq!(row.get::<u8, _>("id"), Some(1));
+            assert_eq!(row.get::<u8, _>(0), Some(1));
+            assert_eq!(row.get::<u8, _>("missing"), None);
+            match row.get_opt::<i64, _>("created_at") {
+                Some(Err(Error::FromColumnError(err))) => {
+                    assert_eq!(err.index, 1);
+                    assert_eq!(err.name, Some("created_at".to_string()));
+                    assert_eq!(err.value, Value::NULL);
+                    assert_eq!(err.type_name, "i64");
+                    assert_eq!(format!("{}", err),
+                               "column `created_at' (index 1): cannot convert NULL to `i64'");
+                },
+                _ => panic!("expected FromColumnError"),
+            }
+            match from_row_opt::<(u8, i64)>(row.clone()) {
+                Err(Error::FromRowError(row)) => {
+                    let err = row.conversion_error().unwrap();
+                    assert_eq!(err.index, 1);
+                    assert_eq!(err.name, Some("created_at".to_string()));
+                    assert_eq!(err.type_name, "i64");
+                    assert_eq!(row.as_ref(1), Some(&Value::NULL));
+                    assert_eq!(format!("{}", Error::FromRowError(row.clone())),
+                               "from row conversion error: column `created_at' (index 1): \
+                                cannot convert NULL to `i64'");
+                },
+                _ => panic!("expected FromRowError"),
+            }
+            assert!(row.take_opt::<i64, _>(1).unwrap().is_err());
+            assert_eq!(row.take_opt::<Option<i64>, _>(1).unwrap().unwrap(), None);
+            assert!(row.take_opt::<Option<i64>, _>(1).is_none());
+        }
+        #[test]
+        #[should_panic(expected = "column `created_at' (index 1): cannot convert NULL to `i64'")]
+        fn should_report_column_of_failed_from_row() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut result = conn.query("SELECT 1 AS id, NULL AS created_at").unwrap();
+            let row = result.next().unwrap().unwrap();
+            let _: (u8, i64) = from_row(row);
+        }
+        #[test]
+        fn should_set_charset() {
+            let opts = Opts { charset: Some("utf8mb4".into()), ..get_opts() };
+            let mut conn = Conn::new(opts).unwrap();
+            assert_eq!(conn.charset(), "utf8mb4");
+            let charset: Option<String> = conn.exec_first("SELECT @@character_set_client", ())
+                                              .unwrap();
+            assert_eq!(charset, Some("utf8mb4".to_string()));
+            let opts = Opts { charset: Some("cp1250".into()), ..get_opts() };
+            let mut conn = Conn::new(opts).unwrap();
+            let charset: Option<String> = conn.exec_first("SELECT @@character_set_client", ())
+                                              .unwrap();
+            assert_eq!(charset, Some("cp1250".to_string()));
+        }
+        #[test]
+        fn should_query_replication_status() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.master_status().unwrap();
+            assert!(conn.replica_status().unwrap().is_empty());
+            if conn.server_version() >= (5, 6, 5) {
+                conn.gtid_executed().unwrap();
+            }
+        }
+        #[test]
+        fn should_track_gtid_of_committed_transaction() {
+            use std::time::Duration;
+
+            let mut conn = Conn::new(Opts { track_gtids: true, ..get_opts() }).unwrap();
+            if conn.server_version() < (5, 7, 6) || conn.is_mariadb() {
+                return;
+            }
+            let gtid_mode: String = conn.exec_first("SELECT @@GLOBAL.gtid_mode", ())
+                .unwrap()
+                .unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(a INT)").unwrap();
+            let mut tx = conn.start_transaction(TxOpts::new()).unwrap();
+            tx.query("INSERT INTO x.tbl(a) VALUES (1)").unwrap();
+            let gtid = tx.commit_with_gtid().unwrap();
+            if gtid_mode == "ON" {
+                let gtid = gtid.unwrap();
+                assert_eq!(conn.last_gtid(), Some(&gtid));
+                assert!(conn.wait_for_gtid(&gtid, Some(Duration::from_secs(1))).unwrap());
+            } else {
+                assert_eq!(gtid, None);
+            }
+        }
+        #[test]
+        fn should_write_result_as_csv() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut output = Vec::new();
+            let count = conn.query("SELECT 1 AS a, NULL AS b, 'x,\"y\"' AS c UNION ALL \
+                                    SELECT 2, '', 'z'").unwrap()
+                            .write_csv(&mut output, CsvOptions::default()).unwrap();
+            assert_eq!(count, 2);
+            assert_eq!(output, b"a,b,c\r\n1,,\"x,\"\"y\"\"\"\r\n2,\"\",z\r\n".to_vec());
+        }
+        #[test]
+        fn should_run_admin_commands() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let stats = conn.statistics().unwrap();
+            assert!(stats.threads > 0);
+            assert!(stats.raw.starts_with("Uptime"));
+            let id = conn.id() as u64;
+            let processes = conn.process_info().unwrap();
+            let process = processes.iter().find(|process| process.id == id).unwrap();
+            assert_eq!(process.command, "Query");
+            assert_eq!(process.info, Some("SHOW FULL PROCESSLIST".to_string()));
+            conn.dump_debug_info().unwrap();
+            assert!(conn.ping());
+        }
+        #[test]
+        fn should_send_raw_commands() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            match conn.write_command(consts::Command::COM_PING as u8, &[]).unwrap() {
+                RawResponse::Ok(ok) => assert_eq!(ok.affected_rows(), 0),
+                response => panic!("Unexpected response {:?}", response),
+            }
+            match conn.write_command(consts::Command::COM_STATISTICS as u8, &[]).unwrap() {
+                RawResponse::Packet(pld) => assert!(pld.starts_with(b"Uptime")),
+                response => panic!("Unexpected response {:?}", response),
+            }
+            assert_eq!(conn.write_command(consts::Command::COM_SET_OPTION as u8, &[1, 0]).unwrap(),
+                       RawResponse::Eof);
+            assert!(conn.write_command(consts::Command::COM_INIT_DB as u8, b"no_such_db").is_err());
+            match conn.write_command(consts::Command::COM_QUERY as u8, b"SELECT 1").unwrap() {
+                RawResponse::Packet(pld) => assert_eq!(pld, vec![1]),
+                response => panic!("Unexpected response {:?}", response),
+            }
+            // Column definition, EOF, row and EOF.
+            for _ in 0..4 {
+                conn.read_raw_packet().unwrap();
+            }
+            assert!(conn.ping());
+        }
+        #[test]
+        fn should_reuse_exec_buffer() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.prep_exec("SELECT ?, ?", (1, "foo")).unwrap();
+            let ptr = conn.exec_buf.as_ptr();
+            assert!(conn.exec_buf.capacity() > 0);
+            {
+                let mut stmt = conn.prepare("SELECT ?, ?").unwrap();
+                for _ in 0..3 {
+                    stmt.execute((2, "bar")).unwrap();
+                }
+            }
+            assert_eq!(conn.exec_buf.as_ptr(), ptr);
+            let blob = vec![0u8; 2 * 1024 * 1024];
+            conn.prep_exec("SELECT LENGTH(?)", (blob,)).unwrap();
+            assert!(conn.exec_buf.capacity() <= 1024 * 1024);
+        }
+        #[test]
+        fn should_introspect_schema() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("DROP VIEW IF EXISTS x.schema_view").unwrap();
+            conn.query("DROP TABLE IF EXISTS x.schema_tbl").unwrap();
+            conn.query("CREATE TABLE x.schema_tbl (
+                            a INT UNSIGNED NOT NULL AUTO_INCREMENT,
+                            b VARCHAR(16) CHARACTER SET utf8mb4 DEFAULT 'foo' COMMENT 'bar',
+                            c INT NOT NULL,
+                            d INT AS (c * 2) VIRTUAL,
+                            PRIMARY KEY (c, a)
+                        )").unwrap();
+            conn.query("CREATE VIEW x.schema_view AS SELECT b FROM x.schema_tbl").unwrap();
+            let names = conn.table_names("x").unwrap();
+            assert!(names.contains(&"schema_tbl".to_string()));
+            assert!(names.contains(&"schema_view".to_string()));
+            let columns = conn.columns("x", "schema_tbl").unwrap();
+            assert_eq!(columns.iter().map(|c| &*c.name).collect::<Vec<_>>(),
+                       vec!["a", "b", "c", "d"]);
+            assert_eq!(columns[0].ordinal_position, 1);
+            assert_eq!(columns[0].data_type, "int");
+            assert!(columns[0].column_type.ends_with("unsigned"));
+            assert!(!columns[0].nullable);
+            assert_eq!(columns[0].extra, "auto_increment");
+            assert_eq!(columns[1].column_type, "varchar(16)");
+            assert!(columns[1].nullable);
+            assert_eq!(columns[1].default, Some("foo".to_string()));
+            assert_eq!(columns[1].character_set, Some("utf8mb4".to_string()));
+            assert_eq!(columns[1].comment, "bar");
+            assert!(columns[3].is_generated() && !columns[3].is_insertable());
+            assert!(columns[3].generation_expression.as_ref().unwrap().contains("c"));
+            assert!(!columns[1].is_generated() && columns[1].is_insertable());
+            assert_eq!(columns[1].generation_expression, None);
+            assert!(!columns[1].has_default_expression() && !columns[2].is_invisible());
+            assert_eq!(conn.columns("x", "schema_view").unwrap().len(), 1);
+            assert!(conn.columns("x", "no_such_table").unwrap().is_empty());
+            assert_eq!(conn.primary_key("x", "schema_tbl").unwrap(), vec!["c", "a"]);
+            assert!(conn.primary_key("x", "schema_view").unwrap().is_empty());
+            let columns = conn.describe("x.schema_tbl").unwrap();
+            assert!(columns[0].is_primary_key() && columns[0].is_unsigned());
+            assert!(columns[0].is_auto_increment());
+            assert!(!columns[1].is_primary_key() && !columns[2].is_unsigned());
+            match conn.describe("x.no_such_table") {
+                Err(Error::MySqlError(ref err)) if err.code == 1146 => (),
+                _ => panic!("expected ER_NO_SUCH_TABLE"),
+            }
+            match conn.describe("schema_tbl") {
+                Err(Error::MySqlError(ref err)) if err.code == 1046 => (),
+                _ => panic!("expected ER_NO_DB_ERROR"),
+            }
+            conn.select_db("x").unwrap();
+            assert_eq!(conn.describe("schema_tbl").unwrap(), columns);
+            assert_eq!(conn.describe("`x`.`schema_tbl`").unwrap(), columns);
+            conn.query("DROP VIEW x.schema_view").unwrap();
+            conn.query("DROP TABLE x.schema_tbl").unwrap();
+        }
+        #[test]
+        fn should_exec_and_collect_typed_rows() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.exec_drop("CREATE TEMPORARY TABLE x.tbl(a INT, b TEXT)", ()).unwrap();
+            conn.exec_drop("INSERT INTO x.tbl(a, b) VALUES (?, ?), (?, ?)", (1, "foo", 2, "bar"))
+                .unwrap();
+            let rows: Vec<(u8, String)> = conn.exec("SELECT a, b FROM x.tbl ORDER BY a", ())
+                                              .unwrap();
+            assert_eq!(rows, vec![(1, "foo".to_string()), (2, "bar".to_string())]);
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT a FROM x.tbl ORDER BY a DESC", ())
+                           .unwrap(),
+                       Some(2));
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT a FROM x.tbl WHERE a > ?", (2,))
+                           .unwrap(),
+                       None);
+            let mut t = conn.start_transaction(TxOpts::new()).unwrap();
+            t.exec_drop("DELETE FROM x.tbl WHERE a = ?", (1,)).unwrap();
+            assert_eq!(t.exec::<u8, _, _>("SELECT a FROM x.tbl", ()).unwrap(), vec![2]);
+        }
+        #[test]
+        fn should_check_max_allowed_packet_before_writing() {
+            let mut conn = Conn::new(Opts {
+                max_allowed_packet: Some(1024),
+                ..get_opts()
+            }).unwrap();
+            let long = vec![b'a'; 2048];
+            match conn.prep_exec("SELECT ?", (long.clone(),)) {
+                Err(Error::DriverError(DriverError::PacketTooLarge(2048, 1024))) => (),
+                _ => panic!("Expected PacketTooLarge error"),
+            }
+            let query = format!("SELECT '{}'", String::from_utf8(long).unwrap());
+            match conn.query(query) {
+                Err(Error::DriverError(DriverError::PacketTooLarge(2058, 1024))) => (),
+                _ => panic!("Expected PacketTooLarge error"),
+            }
+            // Connection should stay usable.
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT ?", (1,)).unwrap(), Some(1));
+            // Parameter less than `max_allowed_packet` should be sent as long data.
+            let value = vec![b'b'; 1000];
+            let result = conn.exec_first::<Vec<u8>, _, _>("SELECT ?", (value.clone(),));
+            assert_eq!(result.unwrap(), Some(value));
+        }
+        #[test]
+        fn should_reset_stmt() {
+            let mut conn = Conn::new(Opts {
+                max_allowed_packet: Some(1024),
+                ..get_opts()
+            }).unwrap();
+            let mut stmt = conn.prepare("SELECT ?, ? UNION ALL SELECT 1, 2").unwrap();
+            stmt.reset().unwrap();
+            let value = vec![b'a'; 1000];
+            {
+                // Partially consumed result.
+                let mut result = stmt.execute((value.clone(), 0)).unwrap();
+                result.next().unwrap().unwrap();
+            }
+            stmt.reset().unwrap();
+            let rows = stmt.execute((value.clone(), 0))
+                .and_then(|mut result| result.collect_rows::<(Vec<u8>, u8)>())
+                .unwrap();
+            assert_eq!(rows, vec![(value, 0), (b"1".to_vec(), 2)]);
+        }
+        #[test]
+        fn should_reset_stmt_after_failed_long_data_execution() {
+            let mut conn = Conn::new(Opts {
+                max_allowed_packet: Some(1024),
+                ..get_opts()
+            }).unwrap();
+            let mut stmt = conn._prepare("SELECT ?").unwrap();
+            // Long data was sent but `COM_STMT_EXECUTE` was not.
+            conn.send_long_data(&stmt, &[Bytes(vec![b'a'; 2000])], vec![0]).unwrap();
+            match conn.discard_long_data(&stmt, Error::DriverError(DriverError::QueryCancelled)) {
+                Error::DriverError(DriverError::QueryCancelled) => (),
+                _ => panic!("Expected QueryCancelled error"),
+            }
+            let value = conn.execute(&mut stmt, (b"b".to_vec(),)).unwrap()
+                .first_row::<Vec<u8>>().unwrap();
+            assert_eq!(value, Some(b"b".to_vec()));
+        }
+        #[test]
+        fn should_prepare_stmt_again_after_reconnect() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut stmt = conn._prepare("SELECT ?").unwrap();
+            conn.reset().unwrap();
+            assert!(!conn.has_stmt("SELECT ?"));
+            let value = conn.execute(&mut stmt, (42,)).unwrap().first_row::<u8>().unwrap();
+            assert_eq!(value, Some(42));
+            assert!(conn.has_stmt("SELECT ?"));
+        }
+        #[test]
+        fn should_close_stmts_if_stmt_cache_is_disabled() {
+            fn session_status(conn: &mut Conn, name: &str) -> u64 {
+                let query = format!("SHOW SESSION STATUS LIKE '{}'", name);
+                let row: Option<(String, u64)> = conn.query(query).unwrap().first_row().unwrap();
+                row.unwrap().1
+            }
+
+            let mut conn = Conn::new(Opts { stmt_cache_size: Some(0), ..get_opts() }).unwrap();
+            let closed = session_status(&mut conn, "Com_stmt_close");
+            for _ in 0..3 {
+                let value: Option<u8> = conn.exec_first("SELECT ?", (1,)).unwrap();
+                assert_eq!(value, Some(1));
+            }
+            assert!(!conn.has_stmt("SELECT ?"));
+            assert_eq!(session_status(&mut conn, "Com_stmt_close"), closed + 3);
+
+            let mut stmt = conn._prepare("SELECT ?").unwrap();
+            conn.reset().unwrap();
+            let prepared = session_status(&mut conn, "Com_stmt_prepare");
+            for _ in 0..3 {
+                let value = conn.execute(&mut stmt, (42,)).unwrap().first_row::<u8>().unwrap();
+                assert_eq!(value, Some(42));
+            }
+            // Statement is prepared again only once.
+            assert_eq!(session_status(&mut conn, "Com_stmt_prepare"), prepared + 1);
+            let closed = session_status(&mut conn, "Com_stmt_close");
+            conn.release_stmt(&stmt);
+            assert_eq!(session_status(&mut conn, "Com_stmt_close"), closed + 1);
+        }
+        #[test]
+        #[cfg(feature = "ssl")]
+        fn should_connect_with_in_memory_certificates() {
+            use std::io::Read;
+            use super::super::{Certificate, Identity, SslOpts};
+
+            let read = |path: &str| {
+                let mut data = Vec::new();
+                fs::File::open(path).unwrap().read_to_end(&mut data).unwrap();
+                data
+            };
+            let mut conn = Conn::new(Opts {
+                ssl_opts: Some(SslOpts {
+                    root_cert: Some(Certificate::Pem(read("tests/ca-cert.pem"))),
+                    client_identity: Some(Identity::CertKey(
+                        Certificate::Pem(read("tests/client-cert.pem")),
+                        read("tests/client-key.pem"))),
+                    danger_skip_domain_validation: true,
+                    ..SslOpts::default()
+                }),
+                ..get_opts()
+            }).unwrap();
+            assert!(conn.is_secure_transport());
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT 1", ()).unwrap(), Some(1));
+        }
+        #[test]
+        fn should_respect_result_budget() {
+            let query = "SELECT REPEAT('a', 100) FROM \
+                         (SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3) AS t";
+            let mut conn = Conn::new(Opts {
+                result_budget: Some(ResultBudget::new(150)),
+                ..get_opts()
+            }).unwrap();
+            match conn.query(query).and_then(|mut result| result.collect_rows::<String>()) {
+                Err(Error::DriverError(DriverError::ResultBudgetExceeded(150))) => (),
+                _ => panic!("Expected ResultBudgetExceeded error"),
+            }
+            assert!(conn.exec::<String, _, _>(query, ()).is_err());
+            match conn.prep_exec_buffered(query, ()) {
+                Err(Error::DriverError(DriverError::ResultBudgetExceeded(150))) => (),
+                _ => panic!("Expected ResultBudgetExceeded error"),
+            }
+            {
+                let mut result = conn.query(query).unwrap();
+                result.set_budget(Some(ResultBudget {
+                    max_bytes: 150,
+                    on_exceed: OnBudgetExceeded::Truncate,
+                }));
+                assert_eq!(result.collect_rows::<String>().unwrap().len(), 1);
+                assert!(result.is_truncated());
+            }
+            let mut result = conn.query(query).unwrap();
+            result.set_budget(None);
+            assert_eq!(result.collect_rows::<String>().unwrap().len(), 3);
+            assert!(result.bytes_read() > 300);
+            assert!(!result.is_truncated());
+        }
+        #[test]
+        fn should_limit_rows() {
+            let query = "SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3";
+            let mut conn = Conn::new(Opts {
+                max_rows: Some(2),
+                ..get_opts()
+            }).unwrap();
+            {
+                let mut result = conn.query(query).unwrap();
+                assert_eq!(result.collect_rows::<u8>().unwrap(), vec![1, 2]);
+                assert!(result.is_truncated());
+            }
+            match conn.exec::<u8, _, _>(query, ()) {
+                Err(Error::DriverError(DriverError::RowLimitExceeded(2))) => (),
+                _ => panic!("Expected RowLimitExceeded error"),
+            }
+            match conn.query_fold(query, 0, |acc, x: u8| acc + x) {
+                Err(Error::DriverError(DriverError::RowLimitExceeded(2))) => (),
+                _ => panic!("Expected RowLimitExceeded error"),
+            }
+            assert_eq!(conn.exec_first::<u8, _, _>(query, ()).unwrap(), Some(1));
+            assert_eq!(conn.exec::<u8, _, _>("SELECT 1 UNION ALL SELECT 2", ()).unwrap(),
+                       vec![1, 2]);
+            {
+                let mut result = conn.query("SELECT 1 UNION ALL SELECT 2").unwrap();
+                assert_eq!(result.collect_rows::<u8>().unwrap(), vec![1, 2]);
+                assert!(!result.is_truncated());
+            }
+            {
+                let mut result = conn.query(query).unwrap();
+                let mut count = 0;
+                {
+                    let mut rows = result.by_ref_rows();
+                    while let Some(row) = rows.next() {
+                        row.unwrap();
+                        count += 1;
+                    }
+                }
+                assert_eq!(count, 2);
+                assert!(result.is_truncated());
+            }
+            let mut result = conn.query(query).unwrap();
+            result.limit(None);
+            assert_eq!(result.collect_rows::<u8>().unwrap(), vec![1, 2, 3]);
+            assert!(!result.is_truncated());
         }
-    }
-}
+        #[test]
+        fn should_not_truncate_buffered_rows() {
+            let mut conn = Conn::new(Opts {
+                max_rows: Some(1),
+                ..get_opts()
+            }).unwrap();
+            match conn.prep_exec_buffered("SELECT 1 UNION ALL SELECT 2", ()) {
+                Err(Error::DriverError(DriverError::RowLimitExceeded(1))) => (),
+                _ => panic!("Expected RowLimitExceeded error"),
+            }
+            assert_eq!(conn.prep_exec_buffered("SELECT 1", ()).unwrap().len(), 1);
+        }
+        #[test]
+        fn should_query_multi() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let sets = conn.query_multi("CREATE TEMPORARY TABLE x.tbl(a INT); \
+                                         INSERT INTO x.tbl VALUES (1), (2); \
+                                         SELECT a FROM x.tbl; \
+                                         DO 1")
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(sets.len(), 4);
+            assert!(sets[0].columns_ref().is_empty());
+            assert_eq!(sets[1].affected_rows(), 2);
+            assert_eq!(sets[2].columns_ref().len(), 1);
+            let values: Vec<u8> = sets[2].clone().into_rows().into_iter().map(from_row).collect();
+            assert_eq!(values, vec![1, 2]);
+            assert!(sets[3].rows().is_empty());
 
-impl<'a> Drop for QueryResult<'a> {
-    fn drop(&mut self) {
-        while self.conn.more_results_exists() {
-            while let Some(_) = self.next() {}
+            {
+                let mut result = conn.query_multi("SELECT 1; SELECT * FROM x.nope; SELECT 3")
+                    .unwrap();
+                assert!(result.next().unwrap().is_ok());
+                assert!(result.next().unwrap().is_err());
+                assert!(result.next().is_none());
+            }
+            {
+                // Unconsumed result sets should be drained on drop.
+                let mut result = conn.query_multi("SELECT 1; DO 2; SELECT 3").unwrap();
+                result.next().unwrap().unwrap();
+            }
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT 4", ()).unwrap(), Some(4));
+
+            let mut conn = Conn::new(Opts { multi_statements: false, ..get_opts() }).unwrap();
+            match conn.query_multi("SELECT 1; SELECT 2") {
+                Err(Error::DriverError(DriverError::MultiStatementsDisabled)) => (),
+                _ => panic!("Expected MultiStatementsDisabled error"),
+            }
+            assert!(conn.query("SELECT 1; SELECT 2").is_err());
+
+            conn.set_multi_statements(true).unwrap();
+            assert_eq!(conn.query_multi("SELECT 1; SELECT 2").unwrap().count(), 2);
+            conn.set_multi_statements(false).unwrap();
+            assert!(conn.query("SELECT 1; SELECT 2").is_err());
+            assert!(conn.ping());
         }
-    }
-}
+        #[test]
+        fn should_handle_not_consumed_result() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            {
+                // Partially consumed result should be drained on drop.
+                let mut result = conn.query("SELECT 1 UNION ALL SELECT 2; SELECT 3").unwrap();
+                result.next().unwrap().unwrap();
+            }
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT 4", ()).unwrap(), Some(4));
+            {
+                let result = conn.query("SELECT 1 UNION ALL SELECT 2").unwrap();
+                ::std::mem::forget(result);
+            }
+            match conn.query("SELECT 3") {
+                Err(Error::DriverError(DriverError::ResultNotConsumed)) => (),
+                _ => panic!("Expected ResultNotConsumed error"),
+            }
+            conn.reset().unwrap();
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT 5", ()).unwrap(), Some(5));
+        }
+        #[test]
+        fn should_change_user_and_select_db() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE DATABASE IF NOT EXISTS x").unwrap();
+            conn.query("CREATE DATABASE IF NOT EXISTS y").unwrap();
+            conn.prep_exec("SELECT DATABASE()", ()).unwrap().count();
+            assert!(conn.has_stmt("SELECT DATABASE()"));
+
+            conn.select_db("x").unwrap();
+            assert!(!conn.has_stmt("SELECT DATABASE()"));
+            let db = conn.exec_first::<Option<String>, _, _>("SELECT DATABASE()", ());
+            assert_eq!(db.unwrap(), Some(Some("x".to_string())));
+            assert!(conn.select_db("non_existent_db").is_err());
+
+            conn.query("SET @a = 1").unwrap();
+            let pass = get_opts().pass;
+            conn.change_user(Some("root"), pass.as_ref().map(|x| &**x), Some("y")).unwrap();
+            let (db, a) = conn.query("SELECT DATABASE(), @a").unwrap()
+                .first_row::<(Option<String>, Option<u8>)>()
+                .unwrap()
+                .unwrap();
+            assert_eq!(db, Some("y".to_string()));
+            assert_eq!(a, None);
+            assert!(conn.change_user(Some("root"), Some("wrong password"), None).is_err());
+            assert_eq!(conn.opts.pass, pass);
+            assert_eq!(conn.opts.db_name, Some("y".to_string()));
+        }
+        #[test]
+        fn should_report_auth_failure() {
+            let opts = Opts { pass: Some("wrong password".into()), ..get_opts() };
+            match Conn::new(opts) {
+                Err(ref err @ Error::DriverError(DriverError::AuthFailed(..))) => {
+                    assert_eq!(err.server_error().map(|err| err.code), Some(1045));
+                },
+                _ => panic!("Expected AuthFailed error"),
+            }
+        }
+        #[test]
+        fn should_call_interceptors() {
+            use std::sync::{Arc, Mutex};
+            use super::super::{Interceptor, QueryInterceptor, QueryKind};
+            use super::super::super::error::Result as MyResult;
 
-/***
- *    88888888888                   888
- *        888                       888
- *        888                       888
- *        888      .d88b.  .d8888b  888888 .d8888b
- *        888     d8P  Y8b 88K      888    88K
- *        888     88888888 "Y8888b. 888    "Y8888b.
- *        888     Y8b.          X88 Y88b.       X88
- *        888      "Y8888   88888P'  "Y888  88888P'
- *
- *
- *
- */
+            struct Audit(Arc<Mutex<Vec<String>>>);
 
-#[cfg(test)]
-#[allow(non_snake_case)]
-mod test {
-    use std::borrow::ToOwned;
-    use std::default::Default;
-    use super::Opts;
+            impl QueryInterceptor for Audit {
+                fn before(&self, kind: QueryKind, query: &str) -> MyResult<Option<String>> {
+                    if query.starts_with("DROP") {
+                        return Err(Error::DriverError(DriverError::SetupError));
+                    }
+                    match kind {
+                        QueryKind::Execute => Ok(None),
+                        _ => Ok(Some(format!("{} /* audited */", query))),
+                    }
+                }
+                fn after(&self, kind: QueryKind, query: &str, error: Option<&Error>) {
+                    let entry = format!("{:?} {} {}", kind, query, error.is_none());
+                    self.0.lock().unwrap().push(entry);
+                }
+            }
 
-    static USER: &'static str = "root";
-    static PASS: &'static str = "password";
-    static ADDR: &'static str = "127.0.0.1";
-    static PORT: u16          = 3307;
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.opts.interceptors = vec![Interceptor::new(Audit(log.clone()))];
+            conn.query("SELECT 1").unwrap();
+            conn.prep_exec("SELECT ?", (1,)).unwrap();
+            assert!(conn.query("SELEC 1").is_err());
+            assert!(conn.query("DROP TABLE x.y").is_err());
+            assert_eq!(*log.lock().unwrap(), vec![
+                "Query SELECT 1 /* audited */ true",
+                "Prepare SELECT ? /* audited */ true",
+                "Execute SELECT ? /* audited */ true",
+                "Query SELEC 1 /* audited */ false",
+            ]);
+            // Statement cache is keyed by SQL text passed by user.
+            assert!(conn.has_stmt("SELECT ?"));
+            assert!(!conn.has_stmt("SELECT ? /* audited */"));
+        }
+        #[test]
+        fn should_record_commands() {
+            use std::sync::{Arc, Mutex};
+            use super::super::{Record, Recorder};
 
-    #[cfg(feature = "openssl")]
-    pub fn get_opts() -> Opts {
-        let pwd: String = ::std::env::var("MYSQL_SERVER_PASS").unwrap_or(PASS.to_string());
-        let port: u16 = ::std::env::var("MYSQL_SERVER_PORT").ok()
-                                   .map(|my_port| my_port.parse().ok().unwrap_or(PORT))
-                                   .unwrap_or(PORT);
-        Opts {
-            user: Some(USER.to_string()),
-            pass: Some(pwd),
-            ip_or_hostname: Some(ADDR.to_string()),
-            tcp_port: port,
-            init: vec!["SET GLOBAL sql_mode = 'TRADITIONAL'".to_owned()],
-            ssl_opts: Some((::std::convert::From::from("tests/ca-cert.pem"), None)),
-            ..Default::default()
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let sink = log.clone();
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.opts.recorder = Some(Recorder::new(move |record: Record| {
+                sink.lock().unwrap().push(record.to_string());
+            }));
+            conn.query("CREATE TEMPORARY TABLE x.rec (id INT AUTO_INCREMENT PRIMARY KEY, a TEXT)")
+                .unwrap();
+            let mut params = HashMap::default();
+            params.insert("a".to_string(), Value::from("foo"));
+            conn.prep_exec("INSERT INTO x.rec (a) VALUES (:a)", Params::Named(params)).unwrap();
+            conn.prep_exec("SELECT a FROM x.rec WHERE id = ?", (1,)).unwrap();
+            assert!(conn.query("SELEC 1").is_err());
+            let log = log.lock().unwrap();
+            assert_eq!(log[..5].to_vec(), vec![
+                "QUERY CREATE TEMPORARY TABLE x.rec (id INT AUTO_INCREMENT PRIMARY KEY, a TEXT) \
+                 -- affected_rows: 0, last_insert_id: 0, warnings: 0",
+                "PREPARE INSERT INTO x.rec (a) VALUES (:a) \
+                 -- affected_rows: 0, last_insert_id: 0, warnings: 0",
+                "EXECUTE INSERT INTO x.rec (a) VALUES (:a) -- params: :a = 'foo' \
+                 -- affected_rows: 1, last_insert_id: 1, warnings: 0",
+                "PREPARE SELECT a FROM x.rec WHERE id = ? -- columns: 1",
+                "EXECUTE SELECT a FROM x.rec WHERE id = ? -- params: 1 -- columns: 1",
+            ]);
+            assert!(log[5].starts_with("QUERY SELEC 1 -- error: "));
         }
-    }
+        #[test]
+        fn should_report_slow_queries() {
+            use std::sync::{Arc, Mutex};
+            use std::time::Duration as StdDuration;
+            use super::super::{Record, Recorder};
 
-    #[cfg(not(feature = "ssl"))]
-    pub fn get_opts() -> Opts {
-        let pwd: String = ::std::env::var("MYSQL_SERVER_PASS").unwrap_or(PASS.to_string());
-        let port: u16 = ::std::env::var("MYSQL_SERVER_PORT").ok()
-                                   .map(|my_port| my_port.parse().ok().unwrap_or(PORT))
-                                   .unwrap_or(PORT);
-        Opts {
-            user: Some(USER.to_string()),
-            pass: Some(pwd),
-            ip_or_hostname: Some(ADDR.to_string()),
-            tcp_port: port,
-            init: vec!["SET GLOBAL sql_mode = 'TRADITIONAL'".to_owned()],
-            ..Default::default()
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let sink = log.clone();
+            let mut conn = Conn::new(Opts {
+                slow_query_threshold: Some(StdDuration::from_millis(100)),
+                slow_query_handler: Some(Recorder::new(move |record: Record| {
+                    sink.lock().unwrap().push(record);
+                })),
+                ..get_opts()
+            }).unwrap();
+            conn.query("SELECT 1").unwrap();
+            conn.prep_exec("SELECT SLEEP(?)", (0.2,)).unwrap();
+            conn.query("DO SLEEP(0.2)").unwrap();
+            let log = log.lock().unwrap();
+            assert_eq!(log.len(), 2);
+            assert_eq!(log[0].to_string(), "EXECUTE SELECT SLEEP(?) -- params: 0.2 -- columns: 1");
+            assert!(log[0].duration >= StdDuration::from_millis(100));
+            assert_eq!(log[1].query, "DO SLEEP(0.2)");
         }
-    }
+        #[test]
+        fn should_connect_with_custom_stream() {
+            use std::net::TcpStream;
+
+            let opts = get_opts();
+            let host = opts.ip_or_hostname.clone().unwrap();
+            let stream = TcpStream::connect((&*host, opts.tcp_port)).unwrap();
+            let mut conn = Conn::new_with_stream(stream, opts).unwrap();
+            assert!(conn.stream.as_ref().unwrap().is_custom());
+            assert_eq!(conn.query("SELECT 1").unwrap().next().unwrap().unwrap().unwrap(),
+                       vec![Bytes(b"1".to_vec())]);
+            assert!(conn.ping());
+        }
+        #[test]
+        #[cfg(feature = "ssl")]
+        fn should_not_connect_with_custom_stream_if_ssl_requested() {
+            use std::net::TcpStream;
+            use super::super::SslOpts;
+
+            let opts = Opts { ssl_opts: Some(SslOpts::default()), ..get_opts() };
+            let host = opts.ip_or_hostname.clone().unwrap();
+            let stream = TcpStream::connect((&*host, opts.tcp_port)).unwrap();
+            match Conn::new_with_stream(stream, opts) {
+                Err(Error::DriverError(DriverError::SslOverCustomStream)) => (),
+                _ => panic!("expected SslOverCustomStream"),
+            }
+        }
+        #[test]
+        fn should_coerce_text_values_using_column_types() {
+            use super::super::TextCoercion;
 
-    mod my_conn {
-        use std::iter;
-        use std::borrow::ToOwned;
-        use std::fs;
-        use std::io::Write;
-        use time::{Tm, now};
-        use super::super::{Conn, Opts, Row};
-        use super::super::super::value::{ToValue, from_value};
-        use super::super::super::value::Value::{NULL, Int, Bytes, Date};
-        use super::get_opts;
+            let query = "SELECT CAST(-1 AS SIGNED), CAST(18446744073709551615 AS UNSIGNED), \
+                         1.5e0, CAST(1.25 AS DECIMAL(4, 2)), 'foo', NULL, \
+                         CAST('2016-02-29 01:02:03.000004' AS DATETIME(6)), \
+                         CAST('-25:00:01' AS TIME)";
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let row = conn.query(query).unwrap().next().unwrap().unwrap();
+            assert_eq!(row.get::<Value, _>(0), Some(Bytes(b"-1".to_vec())));
 
+            let mut conn = Conn::new(Opts {
+                text_coercion: TextCoercion::CoerceUsingColumnType,
+                ..get_opts()
+            }).unwrap();
+            let expected = vec![Int(-1),
+                                UInt(18446744073709551615),
+                                Value::Float(1.5),
+                                Bytes(b"1.25".to_vec()),
+                                Bytes(b"foo".to_vec()),
+                                NULL,
+                                Date(2016, 2, 29, 1, 2, 3, 4),
+                                Value::Time(true, 1, 1, 0, 1, 0)];
+            let row = conn.query(query).unwrap().next().unwrap().unwrap();
+            assert_eq!(row.unwrap(), expected);
+            let row = conn.prep_exec(query, ()).unwrap().next().unwrap().unwrap();
+            assert_eq!(row.unwrap(), expected);
+        }
         #[test]
-        fn should_connect() {
-            let mut conn = Conn::new(get_opts()).unwrap();
-            let mode = conn.query("SELECT @@GLOBAL.sql_mode").unwrap().next().unwrap().unwrap().take(0).unwrap();
-            let mode = from_value::<String>(mode);
-            assert!(mode.contains("TRADITIONAL"));
-            assert!(conn.ping());
+        fn should_trace_packets() {
+            use std::sync::{Arc, Mutex};
+            use super::super::{Packet, PacketDirection, PacketTracer};
+
+            let packets = Arc::new(Mutex::new(Vec::new()));
+            let sink = packets.clone();
+            let opts = Opts {
+                packet_tracer: Some(PacketTracer::new(move |packet: Packet| {
+                    sink.lock().unwrap().push(packet);
+                })),
+                ..get_opts()
+            };
+            let mut conn = Conn::new(opts).unwrap();
+            {
+                let packets = packets.lock().unwrap();
+                assert_eq!(packets[0].direction, PacketDirection::Received);
+                assert_eq!(packets[0].seq_id, 0);
+                assert_eq!(packets[1].direction, PacketDirection::Sent);
+                assert_eq!(packets[1].seq_id, 1);
+            }
+            packets.lock().unwrap().clear();
+            conn.query("SELECT 1").unwrap();
+            let packets = packets.lock().unwrap();
+            assert_eq!(packets[0].payload, b"\x03SELECT 1");
+            assert_eq!(packets[0].description(), "COM_QUERY");
+            assert_eq!(packets.last().unwrap().direction, PacketDirection::Received);
         }
         #[test]
-        fn should_connect_with_database() {
+        fn should_apply_default_transaction_options() {
             let mut conn = Conn::new(Opts {
-                db_name: Some("mysql".to_string()),
+                default_access_mode: Some(AccessMode::ReadOnly),
+                default_isolation_level: Some(IsolationLevel::Serializable),
                 ..get_opts()
             }).unwrap();
-            assert_eq!(conn.query("SELECT DATABASE()").unwrap().next().unwrap().unwrap().unwrap(),
-                       vec![Bytes(b"mysql".to_vec())]);
+            conn.query("CREATE DATABASE IF NOT EXISTS x").unwrap();
+            conn.query("DROP TABLE IF EXISTS x.tx_opts").unwrap();
+            conn.query("CREATE TABLE x.tx_opts (a INT)").unwrap();
+            {
+                let mut t = conn.start_transaction(TxOpts::new()).unwrap();
+                assert!(t.query("INSERT INTO x.tx_opts (a) VALUES (1)").is_err());
+                t.rollback().unwrap();
+            }
+            {
+                let tx_opts = TxOpts::new().with_access_mode(AccessMode::ReadWrite);
+                let mut t = conn.start_transaction(tx_opts).unwrap();
+                t.query("INSERT INTO x.tx_opts (a) VALUES (1)").unwrap();
+                t.commit().unwrap();
+            }
+            conn.query("DROP TABLE x.tx_opts").unwrap();
+            assert_eq!(TxOpts::new().with_consistent_snapshot(true).isolation_level(), None);
         }
         #[test]
-        fn should_connect_by_hostname() {
+        fn should_apply_transaction_drop_behavior() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE DATABASE IF NOT EXISTS x").unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tx_drop (a INT)").unwrap();
+            assert!(!conn.in_transaction());
+            {
+                let mut t = conn.start_transaction(TxOpts::new()).unwrap();
+                assert_eq!(t.drop_behavior(), DropBehavior::Rollback);
+                t.query("INSERT INTO x.tx_drop (a) VALUES (1)").unwrap();
+                assert!(t.in_transaction());
+                t.set_rollback_on_drop(false);
+            }
+            assert!(!conn.in_transaction());
+            {
+                let mut t = conn.start_transaction(TxOpts::new()).unwrap();
+                t.query("INSERT INTO x.tx_drop (a) VALUES (2)").unwrap();
+            }
+            {
+                let mut t = conn.start_transaction(TxOpts::new()).unwrap();
+                t.set_drop_behavior(DropBehavior::Panic);
+                t.query("INSERT INTO x.tx_drop (a) VALUES (3)").unwrap();
+                t.commit().unwrap();
+            }
+            let values: Vec<u8> = conn.exec("SELECT a FROM x.tx_drop ORDER BY a", ()).unwrap();
+            assert_eq!(values, vec![1, 3]);
+        }
+        #[test]
+        fn should_expose_stmt_metadata() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            {
+                let stmt = conn.prepare("SELECT ?, ? AS b, 1 AS c").unwrap();
+                assert_eq!(stmt.num_params(), 2);
+                assert_eq!(stmt.params().len(), 2);
+                assert_eq!(stmt.num_columns(), 3);
+                let names: Vec<&[u8]> = stmt.columns().iter().map(|c| &*c.name).collect();
+                assert_eq!(names[1..].to_vec(), vec![&b"b"[..], &b"c"[..]]);
+                assert_eq!(stmt.named_params(), None);
+            }
+            {
+                let stmt = conn.prepare("SELECT :a, :b, :a").unwrap();
+                assert_eq!(stmt.num_params(), 3);
+                assert_eq!(stmt.named_params().unwrap(),
+                           &["a".to_string(), "b".to_string(), "a".to_string()][..]);
+            }
+            let stmt = conn.prepare("DO 1").unwrap();
+            assert_eq!(stmt.num_params(), 0);
+            assert!(stmt.params().is_empty());
+            assert_eq!(stmt.num_columns(), 0);
+            assert!(stmt.columns().is_empty());
+        }
+        #[test]
+        fn should_get_warnings() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            // Out of range value is an error in strict mode.
+            conn.query("SET SESSION sql_mode = ''").unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl (a TINYINT)").unwrap();
+            assert_eq!(conn.get_warnings().unwrap(), vec![]);
+            {
+                let result = conn.query("INSERT INTO x.tbl (a) VALUES (1000)").unwrap();
+                assert_eq!(result.warnings(), 1);
+            }
+            let warnings = conn.get_warnings().unwrap();
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].level, "Warning");
+            assert_eq!(warnings[0].code, 1264);
+
+            conn.query("SELECT 1").unwrap().count();
+            assert_eq!(conn.warnings(), 0);
+            assert_eq!(conn.get_warnings().unwrap(), vec![]);
+        }
+        #[test]
+        fn should_evict_statements_over_cache_size() {
             let mut conn = Conn::new(Opts {
-                db_name: Some("mysql".to_string()),
-                ip_or_hostname: Some("localhost".to_string()),
+                stmt_cache_size: Some(2),
                 ..get_opts()
             }).unwrap();
-            assert_eq!(conn.query("SELECT DATABASE()").unwrap().next().unwrap().unwrap().unwrap(),
-                       vec![Bytes(b"mysql".to_vec())]);
+            for query in &["SELECT 1", "SELECT 2", "SELECT 3"] {
+                conn.prep_exec(*query, ()).unwrap();
+            }
+            assert!(!conn.has_stmt("SELECT 1"));
+            assert!(conn.has_stmt("SELECT 2"));
+            assert!(conn.has_stmt("SELECT 3"));
+            // Evicted statement should be prepared again.
+            conn.prep_exec("SELECT 1", ()).unwrap();
+            assert!(conn.has_stmt("SELECT 1"));
+            assert!(!conn.has_stmt("SELECT 2"));
+        }
+        #[test]
+        fn should_expose_handshake_info() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let version = from_value::<String>(conn.query("SELECT VERSION()").unwrap()
+                                                   .next().unwrap().unwrap().take(0).unwrap());
+            assert_eq!(conn.server_version_str(), version);
+            let (major, minor, patch) = conn.server_version();
+            assert!(version.starts_with(&*format!("{}.{}.{}", major, minor, patch)));
+            assert!(conn.capabilities().contains(consts::CLIENT_PROTOCOL_41));
+            assert!(conn.character_set() > 0);
+        }
+        #[test]
+        fn should_reset_connection_and_kill() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let id = conn.id();
+            assert_eq!(conn.query("SELECT CONNECTION_ID()").unwrap().next().unwrap().unwrap().unwrap(),
+                       vec![Bytes(id.to_string().into_bytes())]);
+            conn.query("SET @a = 1").unwrap();
+            conn.reset_connection().unwrap();
+            assert_eq!(conn.id(), id);
+            assert_eq!(conn.query("SELECT @a").unwrap().next().unwrap().unwrap().unwrap(),
+                       vec![NULL]);
+            let mut other = Conn::new(get_opts()).unwrap();
+            other.kill(id).unwrap();
+            assert!(!conn.ping());
+            assert!(conn.ping_err().is_err());
+        }
+        #[test]
+        fn should_return_ok_packet() {
+            let mut conn = Conn::new(Opts { track_gtids: true, ..get_opts() }).unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(a TEXT, b INT)").unwrap();
+            conn.query("INSERT INTO x.tbl(a, b) VALUES ('hello', -123), ('world', -321)").unwrap();
+            {
+                let result = conn.query("UPDATE x.tbl SET a = 'world' WHERE b < 0").unwrap();
+                let ok = result.ok_packet().unwrap();
+                assert_eq!(ok.affected_rows(), 1);
+                assert_eq!(ok.warnings(), 0);
+                assert!(ok.status_flags().contains(consts::SERVER_STATUS_AUTOCOMMIT));
+                assert_eq!(ok.info_str(), "Rows matched: 2  Changed: 1  Warnings: 0");
+            }
+            assert!(conn.query("SELECT * FROM x.tbl").unwrap().ok_packet().is_none());
+            if conn.client_flags.contains(consts::CLIENT_SESSION_TRACK) {
+                // `session_track_schema` is enabled by default.
+                let result = conn.query("USE x").unwrap();
+                let ok = result.ok_packet().unwrap();
+                assert!(ok.status_flags().contains(consts::SERVER_SESSION_STATE_CHANGED));
+                assert!(!ok.session_state_changes().is_empty());
+            }
         }
         #[test]
         fn should_execute_queryes_and_parse_results() {
@@ -1930,6 +5798,16 @@ mod test {
             assert_eq!(from_value::<f32>(row.take(2).unwrap()), 1.1);
         }
         #[test]
+        fn should_roundtrip_unsigned_bigint() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert!(conn.query("CREATE TEMPORARY TABLE x.tbl(a BIGINT UNSIGNED)").is_ok());
+            assert!(conn.prep_exec("INSERT INTO x.tbl(a) VALUES (?)", (::std::u64::MAX,)).is_ok());
+            let row = conn.prep_exec("SELECT a FROM x.tbl", ()).unwrap().next().unwrap().unwrap();
+            assert_eq!(row.unwrap(), vec![UInt(::std::u64::MAX)]);
+            let row = conn.query("SELECT a FROM x.tbl").unwrap().next().unwrap().unwrap();
+            assert_eq!(from_value::<u64>(row.unwrap().pop().unwrap()), ::std::u64::MAX);
+        }
+        #[test]
         fn should_parse_large_binary_result() {
             let mut conn = Conn::new(get_opts()).unwrap();
             let mut stmt = conn.prepare("SELECT REPEAT('A', 20000000);").unwrap();
@@ -1942,7 +5820,7 @@ mod test {
         fn should_start_commit_and_rollback_transactions() {
             let mut conn = Conn::new(get_opts()).unwrap();
             assert!(conn.query("CREATE TEMPORARY TABLE x.tbl(a INT)").is_ok());
-            let _ = conn.start_transaction(false, None, None).and_then(|mut t| {
+            let _ = conn.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(1)").is_ok());
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(2)").is_ok());
                 assert!(t.commit().is_ok());
@@ -1950,14 +5828,14 @@ mod test {
             }).unwrap();
             assert_eq!(conn.query("SELECT COUNT(a) from x.tbl").unwrap().next().unwrap().unwrap(),
                        Row::new(vec![Bytes(b"2".to_vec())]));
-            let _ = conn.start_transaction(false, None, None).and_then(|mut t| {
+            let _ = conn.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO tbl(a) VALUES(1)").is_err());
                 Ok(())
                 // implicit rollback
             }).unwrap();
             assert_eq!(conn.query("SELECT COUNT(a) from x.tbl").unwrap().next().unwrap().unwrap(),
                        Row::new(vec![Bytes(b"2".to_vec())]));
-            let _ = conn.start_transaction(false, None, None).and_then(|mut t| {
+            let _ = conn.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(1)").is_ok());
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(2)").is_ok());
                 assert!(t.rollback().is_ok());
@@ -1965,7 +5843,7 @@ mod test {
             }).unwrap();
             assert_eq!(conn.query("SELECT COUNT(a) from x.tbl").unwrap().next().unwrap().unwrap(),
                        Row::new(vec![Bytes(b"2".to_vec())]));
-            let _ = conn.start_transaction(false, None, None).and_then(|mut t| {
+            let _ = conn.start_transaction(TxOpts::new()).and_then(|mut t| {
                 let _ = t.prepare("INSERT INTO x.tbl(a) VALUES(?)")
                 .and_then(|mut stmt| {
                     assert!(stmt.execute((3,)).is_ok());
@@ -1977,11 +5855,14 @@ mod test {
             }).unwrap();
             assert_eq!(conn.query("SELECT COUNT(a) from x.tbl").unwrap().next().unwrap().unwrap(),
                        Row::new(vec![Bytes(b"4".to_vec())]));
-            let _ = conn.start_transaction(false, None, None). and_then(|mut t| {
+            let _ = conn.start_transaction(TxOpts::new()). and_then(|mut t| {
                 t.prep_exec("INSERT INTO x.tbl(a) VALUES(?)", (5,)).unwrap();
                 t.prep_exec("INSERT INTO x.tbl(a) VALUES(?)", (6,)).unwrap();
                 Ok(())
+                // implicit rollback
             }).unwrap();
+            assert_eq!(conn.query("SELECT COUNT(a) from x.tbl").unwrap().next().unwrap().unwrap(),
+                       Row::new(vec![Bytes(b"4".to_vec())]));
         }
         #[test]
         fn should_handle_LOCAL_INFILE() {
@@ -2053,6 +5934,143 @@ mod test {
             }
             assert_eq!(i, 4);
         }
+        #[test]
+        fn should_fetch_out_params() {
+            let mut conn = Conn::new(Opts {
+                db_name: Some("mysql".to_string()),
+                ..get_opts()
+            }).unwrap();
+            conn.query("DROP PROCEDURE IF EXISTS out_params").unwrap();
+            conn.query(r#"CREATE PROCEDURE out_params(IN a INT, INOUT b INT, OUT c TEXT) BEGIN
+                              SELECT a;
+                              SET b = a + b;
+                              SET c = 'foo';
+                          END"#).unwrap();
+            {
+                let mut result = conn.prep_exec("CALL out_params(?, ?, ?)", (1, 2, NULL))
+                                     .unwrap();
+                let row = result.out_params().unwrap().unwrap();
+                assert_eq!(from_row::<(i32, String)>(row), (3, "foo".to_string()));
+            }
+            {
+                let mut result = conn.prep_exec("CALL out_params(?, ?, ?)", (1, 2, NULL))
+                                     .unwrap();
+                let rows = result.by_ref().map(|row| row.unwrap()).collect::<Vec<_>>();
+                assert_eq!(rows.len(), 1);
+                assert_eq!(from_row::<i32>(rows[0].clone()), 1);
+                while result.more_results_exists() {
+                    assert!(result.by_ref().next().is_none());
+                }
+                assert!(result.out_params().unwrap().is_some());
+            }
+            let mut result = conn.query("SELECT 1").unwrap();
+            assert!(result.out_params().unwrap().is_none());
+        }
+        #[test]
+        fn should_respect_result_budget_of_buffered_results() {
+            let query = "SELECT REPEAT('a', 100) FROM \
+                         (SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3) AS t";
+            let mut conn = Conn::new(Opts {
+                result_budget: Some(ResultBudget::new(150)),
+                db_name: Some("mysql".to_string()),
+                ..get_opts()
+            }).unwrap();
+            {
+                let mut result = conn.query_multi(format!("{}; SELECT 1", query)).unwrap();
+                match result.next() {
+                    Some(Err(Error::DriverError(DriverError::ResultBudgetExceeded(150)))) => (),
+                    _ => panic!("Expected ResultBudgetExceeded error"),
+                }
+            }
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT 4", ()).unwrap(), Some(4));
+            conn.query("DROP PROCEDURE IF EXISTS budget_proc").unwrap();
+            conn.query(format!("CREATE PROCEDURE budget_proc() BEGIN {}; SELECT 1; END", query))
+                .unwrap();
+            match conn.call("budget_proc", ()) {
+                Err(Error::DriverError(DriverError::ResultBudgetExceeded(150))) => (),
+                _ => panic!("Expected ResultBudgetExceeded error"),
+            }
+            assert!(conn.ping());
+            let mut conn = Conn::new(Opts {
+                result_budget: Some(ResultBudget {
+                    max_bytes: 150,
+                    on_exceed: OnBudgetExceeded::Truncate,
+                }),
+                db_name: Some("mysql".to_string()),
+                ..get_opts()
+            }).unwrap();
+            let result = conn.call("budget_proc", ()).unwrap();
+            assert_eq!(result.result_sets()[0].rows().len(), 1);
+            conn.query("DROP PROCEDURE budget_proc").unwrap();
+        }
+        #[test]
+        fn should_call_procedure() {
+            let mut conn = Conn::new(Opts {
+                db_name: Some("mysql".to_string()),
+                ..get_opts()
+            }).unwrap();
+            conn.query("DROP PROCEDURE IF EXISTS call_proc").unwrap();
+            conn.query(r#"CREATE PROCEDURE call_proc(IN a INT, OUT b INT) BEGIN
+                              SELECT a;
+                              SELECT a + 1, 'foo' UNION ALL SELECT a + 2, 'bar';
+                              SET b = a * 2;
+                          END"#).unwrap();
+            let result = conn.call("mysql.call_proc", (21, NULL)).unwrap();
+            assert_eq!(result.result_sets().len(), 2);
+            assert_eq!(from_row::<i32>(result.result_sets()[0].rows()[0].clone()), 21);
+            assert_eq!(result.result_sets()[1].rows().len(), 2);
+            assert_eq!(from_row::<i32>(result.out_params().unwrap().clone()), 42);
+            conn.query("DROP PROCEDURE IF EXISTS call_proc_no_params").unwrap();
+            conn.query("CREATE PROCEDURE call_proc_no_params() BEGIN END").unwrap();
+            let result = conn.call("call_proc_no_params", ()).unwrap();
+            assert!(result.result_sets().is_empty());
+            assert!(result.out_params().is_none());
+            assert!(conn.ping());
+        }
+        #[test]
+        fn should_collect_and_map_rows() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let pairs = conn.query("SELECT 1, 'foo' UNION ALL SELECT 2, 'bar'")
+                            .unwrap().collect_rows::<(u8, String)>().unwrap();
+            assert_eq!(pairs, vec![(1, "foo".to_owned()), (2, "bar".to_owned())]);
+            let sums = conn.prep_exec("SELECT ? + 1 UNION ALL SELECT ? + 1", (1, 2))
+                           .unwrap().map_rows(|x: i64| x * 2).unwrap();
+            assert_eq!(sums, vec![4, 6]);
+            let first = conn.query("SELECT 42").unwrap().first_row::<u32>().unwrap();
+            assert_eq!(first, Some(42));
+            let none = conn.query("DO 1").unwrap().first_row::<u32>().unwrap();
+            assert_eq!(none, None);
+            assert!(conn.query("SELECT 'foo'").unwrap().collect_rows::<u32>().is_err());
+        }
+        #[test]
+        fn should_iterate_borrowed_rows() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut result = conn.query("SELECT 'foo', NULL UNION ALL SELECT 'bar', 42").unwrap();
+            let mut i = 0;
+            {
+                let mut rows = result.by_ref_rows();
+                while let Some(row) = rows.next() {
+                    let row = row.unwrap();
+                    match i {
+                        0 => {
+                            assert_eq!(row[0].as_str(), Some("foo"));
+                            assert_eq!(row[1], ValueRef::NULL);
+                        },
+                        1 => assert_eq!(row.to_row(), Row::new(vec![Bytes(b"bar".to_vec()),
+                                                                    Bytes(b"42".to_vec())])),
+                        _ => unreachable!(),
+                    }
+                    i += 1;
+                }
+            }
+            assert_eq!(i, 2);
+            let mut stmt = conn.prepare("SELECT ?, ?").unwrap();
+            let mut result = stmt.execute((-1, "baz")).unwrap();
+            let mut rows = result.by_ref_rows();
+            let row = rows.next().unwrap().unwrap();
+            assert_eq!(row[0], ValueRef::Int(-1));
+            assert_eq!(row[1], ValueRef::Bytes(b"baz"));
+        }
     }
 
     #[cfg(feature = "nightly")]
@@ -0,0 +1,164 @@
+//! Reader of MySQL option files (`my.cnf`, `~/.my.cnf` and friends).
+//!
+//! Supports `[group]` headers, `#` and `;` comments, quoted values, escape sequences,
+//! `loose-` prefixes and `!include`/`!includedir` directives. Dashes and underscores in option
+//! names are equivalent, so names are returned with dashes only.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::super::error::Result as MyResult;
+
+/// Maximal depth of nested `!include` directives (protects from include cycles).
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// Reads options of given `groups` from an option file in order of appearance, so latter ones
+/// should take precedence. Value is `None` for options given without `=`.
+pub fn read_groups(path: &Path, groups: &[&str]) -> MyResult<Vec<(String, Option<String>)>> {
+    let mut options = Vec::new();
+    try!(read_file(path, groups, &mut options, 0));
+    Ok(options)
+}
+
+fn read_file(path: &Path,
+             groups: &[&str],
+             options: &mut Vec<(String, Option<String>)>,
+             depth: usize) -> MyResult<()> {
+    let mut text = String::new();
+    try!(try!(fs::File::open(path)).read_to_string(&mut text));
+    parse(&*text, groups, options, depth)
+}
+
+fn parse(text: &str,
+         groups: &[&str],
+         options: &mut Vec<(String, Option<String>)>,
+         depth: usize) -> MyResult<()> {
+    let mut in_group = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        } else if line.starts_with("!includedir") {
+            if depth < MAX_INCLUDE_DEPTH {
+                let dir = line["!includedir".len()..].trim();
+                let mut paths: Vec<PathBuf> = try!(fs::read_dir(dir))
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().map(|ext| ext == "cnf").unwrap_or(false))
+                    .collect();
+                paths.sort();
+                for path in paths {
+                    try!(read_file(&*path, groups, options, depth + 1));
+                }
+            }
+        } else if line.starts_with("!include") {
+            if depth < MAX_INCLUDE_DEPTH {
+                let path = Path::new(line["!include".len()..].trim());
+                try!(read_file(path, groups, options, depth + 1));
+            }
+        } else if line.starts_with('[') {
+            let name = match line.find(']') {
+                Some(end) => line[1..end].trim().to_lowercase(),
+                None => line[1..].trim().to_lowercase(),
+            };
+            in_group = groups.iter().any(|group| group.to_lowercase() == name);
+        } else if in_group {
+            options.push(parse_option(line));
+        }
+    }
+    Ok(())
+}
+
+fn parse_option(line: &str) -> (String, Option<String>) {
+    let (name, value) = match line.find('=') {
+        Some(idx) => (&line[..idx], Some(parse_value(line[idx + 1..].trim()))),
+        None => (line, None),
+    };
+    let mut name = name.trim().replace('_', "-");
+    if name.starts_with("loose-") {
+        name = name["loose-".len()..].to_string();
+    }
+    (name, value)
+}
+
+fn parse_value(value: &str) -> String {
+    let mut chars = value.chars();
+    let quote = match value.chars().next() {
+        Some(c @ '\'') | Some(c @ '"') => {
+            chars.next();
+            Some(c)
+        },
+        _ => None,
+    };
+    let mut output = String::with_capacity(value.len());
+    let mut prev_is_space = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('b') => output.push('\x08'),
+                Some('t') => output.push('\t'),
+                Some('n') => output.push('\n'),
+                Some('r') => output.push('\r'),
+                Some('s') => output.push(' '),
+                Some('\\') => output.push('\\'),
+                Some(c @ '\'') | Some(c @ '"') => output.push(c),
+                Some(c) => {
+                    // Unknown sequences are kept as is (e.g. Windows paths).
+                    output.push('\\');
+                    output.push(c);
+                },
+                None => output.push('\\'),
+            },
+            c if Some(c) == quote => break,
+            '#' if quote.is_none() && prev_is_space => break,
+            c => output.push(c),
+        }
+        prev_is_space = c.is_whitespace();
+    }
+    if quote.is_none() {
+        let len = output.trim_right().len();
+        output.truncate(len);
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    fn parse_groups(text: &str, groups: &[&str]) -> Vec<(String, Option<String>)> {
+        let mut options = Vec::new();
+        parse(text, groups, &mut options, 0).unwrap();
+        options
+    }
+
+    #[test]
+    fn should_parse_option_file() {
+        let text = "# comment\n\
+                    [mysqld]\n\
+                    port = 3307\n\
+                    [client]\n\
+                    ; comment\n\
+                    user=root\n\
+                    password = \"p#ss'word\" # comment\n\
+                    host = db.local # comment\n\
+                    loose_ssl_ca = C:\\certs\\ca.pem\n\
+                    init-command = 'SET\\sa=1'\n\
+                    ssl-verify-server-cert\n\
+                    [ MyApp ]\n\
+                    port=3308\n";
+        assert_eq!(parse_groups(text, &["client", "myapp"]), vec![
+            ("user".to_string(), Some("root".to_string())),
+            ("password".to_string(), Some("p#ss'word".to_string())),
+            ("host".to_string(), Some("db.local".to_string())),
+            ("ssl-ca".to_string(), Some("C:\\certs\\ca.pem".to_string())),
+            ("init-command".to_string(), Some("SET a=1".to_string())),
+            ("ssl-verify-server-cert".to_string(), None),
+            ("port".to_string(), Some("3308".to_string())),
+        ]);
+        assert_eq!(parse_groups(text, &["mysqld"]), vec![
+            ("port".to_string(), Some("3307".to_string())),
+        ]);
+        assert_eq!(parse_groups(text, &["mysqldump"]), vec![]);
+    }
+}
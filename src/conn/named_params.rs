@@ -0,0 +1,215 @@
+//! Support for `:name` placeholders in prepared statements.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use super::super::value::{Params, Value};
+
+fn is_name_start(c: char) -> bool {
+    c == '_' || (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z')
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start(c) || (c >= '0' && c <= '9')
+}
+
+/// Copies `query` into a new string, but calls `f` with name of every `:name` placeholder
+/// instead of copying it. Placeholders within string literals and quoted identifiers are
+/// ignored.
+///
+/// Returns new query and number of `?` placeholders.
+fn map_placeholders<F>(query: &str, mut f: F) -> (String, usize)
+where F: FnMut(String, &mut String) {
+    let mut output = String::with_capacity(query.len());
+    let mut positional = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q != '`' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+                output.push(c);
+            },
+            None => {
+                let is_placeholder = c == ':' && chars.peek().map(|&x| is_name_start(x))
+                                                             .unwrap_or(false);
+                if is_placeholder {
+                    let mut name = String::new();
+                    while let Some(&x) = chars.peek() {
+                        if !is_name_char(x) {
+                            break;
+                        }
+                        name.push(x);
+                        chars.next();
+                    }
+                    f(name, &mut output);
+                } else {
+                    if c == '\'' || c == '"' || c == '`' {
+                        quote = Some(c);
+                    } else if c == '?' {
+                        positional += 1;
+                    }
+                    output.push(c);
+                }
+            },
+        }
+    }
+    (output, positional)
+}
+
+/// Replaces `:name` placeholders with `?`.
+///
+/// Returns names of placeholders in order of appearance (`None` if there are no named
+/// placeholders) and a query suitable for `COM_STMT_PREPARE`.
+pub fn parse_named_params(query: &str) -> (Option<Vec<String>>, Cow<str>) {
+    let mut names = Vec::new();
+    let (output, _) = map_placeholders(query, |name, output| {
+        names.push(name);
+        output.push('?');
+    });
+    if names.is_empty() {
+        (None, Cow::Borrowed(query))
+    } else {
+        (Some(names), Cow::Owned(output))
+    }
+}
+
+/// Replaces `:name` placeholders with `count` placeholders `:name_0, :name_1, ...`
+/// (with `NULL` if `count` is zero).
+fn expand_placeholder(query: &str, name: &str, count: usize) -> String {
+    let (output, _) = map_placeholders(query, |x, output| {
+        if x != name {
+            output.push(':');
+            output.push_str(&*x);
+        } else if count == 0 {
+            output.push_str("NULL");
+        } else {
+            for i in 0..count {
+                if i > 0 {
+                    output.push_str(", ");
+                }
+                output.push_str(&*format!(":{}_{}", name, i));
+            }
+        }
+    });
+    output
+}
+
+/// Returns number of `?` placeholders of `query` ignoring string literals and quoted
+/// identifiers.
+pub fn count_placeholders(query: &str) -> usize {
+    map_placeholders(query, |name, output| {
+        output.push(':');
+        output.push_str(&*name);
+    }).1
+}
+
+/// Expands `:name` placeholder of `query` into a placeholder per item of `values`, e.g. to
+/// bind a list to `IN (:name)` without formatting values into a query.
+///
+/// Returns new query and `Params::Named` which binds items to `:name_0`, `:name_1`, etc.
+/// Placeholder is replaced with `NULL` if `values` is empty, so `IN (:name)` matches no rows
+/// instead of being a syntax error. Other named parameters of the query should be added to
+/// the returned map. `Params::Empty` is returned if there is no named parameter left in the
+/// query (i.e. `values` is empty and query has no other named parameters).
+///
+/// Note that `x NOT IN (NULL)` is `NULL` rather than `TRUE`, so `NOT IN (:name)` with an empty
+/// list also matches no rows (while every row would be expected). Check the list for emptiness
+/// before using it with `NOT IN`:
+///
+/// ```ignore
+/// let users: Vec<(u64, String)> = if banned.is_empty() {
+///     try!(conn.exec("SELECT id, name FROM users", ()))
+/// } else {
+///     let (query, params) = in_list("SELECT id, name FROM users WHERE id NOT IN (:banned)",
+///                                   "banned",
+///                                   &banned);
+///     try!(conn.exec(query, params))
+/// };
+/// ```
+///
+/// ```ignore
+/// let (query, params) = in_list("SELECT id, name FROM users WHERE id IN (:ids)", "ids", &ids);
+/// let users: Vec<(u64, String)> = try!(conn.exec(query, params));
+/// ```
+pub fn in_list<I>(query: &str, name: &str, values: I) -> (String, Params)
+where I: IntoIterator,
+      I::Item: Into<Value> {
+    let mut params = HashMap::default();
+    for (i, value) in values.into_iter().enumerate() {
+        params.insert(format!("{}_{}", name, i), value.into());
+    }
+    let query = expand_placeholder(query, name, params.len());
+    if params.is_empty() && parse_named_params(&*query).0.is_none() {
+        return (query, Params::Empty);
+    }
+    (query, Params::Named(params))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{count_placeholders, expand_placeholder, in_list, parse_named_params};
+    use value::{Params, Value};
+
+    #[test]
+    fn should_parse_named_params() {
+        let (names, query) = parse_named_params("SELECT :a, :b_1 FROM t WHERE c = :a");
+        assert_eq!(names, Some(vec!["a".to_string(), "b_1".to_string(), "a".to_string()]));
+        assert_eq!(query, "SELECT ?, ? FROM t WHERE c = ?");
+    }
+
+    #[test]
+    fn should_ignore_quoted_and_positional_queries() {
+        let (names, query) = parse_named_params("SELECT ?, ':a', \"\\\":b\", `:c`, 1:2");
+        assert_eq!(names, None);
+        assert_eq!(query, "SELECT ?, ':a', \"\\\":b\", `:c`, 1:2");
+        let (names, query) = parse_named_params("SELECT 'it''s', :x");
+        assert_eq!(names, Some(vec!["x".to_string()]));
+        assert_eq!(query, "SELECT 'it''s', ?");
+    }
+
+    #[test]
+    fn should_count_placeholders() {
+        assert_eq!(count_placeholders("SELECT ?, ?"), 2);
+        assert_eq!(count_placeholders("SELECT '?', \"?\", `?`, 'it\\'s?', ?"), 1);
+        assert_eq!(count_placeholders("SELECT :a"), 0);
+    }
+
+    #[test]
+    fn should_expand_list_placeholder() {
+        let query = "SELECT * FROM t WHERE a = :a AND id IN (:ids) AND ':ids' <> :ids_x";
+        assert_eq!(expand_placeholder(query, "ids", 3),
+                   "SELECT * FROM t WHERE a = :a AND id IN (:ids_0, :ids_1, :ids_2) \
+                    AND ':ids' <> :ids_x");
+        assert_eq!(expand_placeholder(query, "ids", 0),
+                   "SELECT * FROM t WHERE a = :a AND id IN (NULL) AND ':ids' <> :ids_x");
+
+        let (query, params) = in_list("SELECT :ids", "ids", &[1u32, 2]);
+        assert_eq!(query, "SELECT :ids_0, :ids_1");
+        match params {
+            Params::Named(map) => {
+                assert_eq!(map.len(), 2);
+                assert_eq!(map.get("ids_0"), Some(&Value::from(1u32)));
+                assert_eq!(map.get("ids_1"), Some(&Value::from(2u32)));
+            },
+            _ => panic!("Expected named params"),
+        }
+
+        let (query, params) = in_list("SELECT 1 IN (:ids)", "ids", Vec::<u32>::new());
+        assert_eq!(query, "SELECT 1 IN (NULL)");
+        assert_eq!(params, Params::Empty);
+        let (query, params) = in_list("SELECT :a IN (:ids)", "ids", Vec::<u32>::new());
+        assert_eq!(query, "SELECT :a IN (NULL)");
+        match params {
+            Params::Named(map) => assert!(map.is_empty()),
+            _ => panic!("Expected named params"),
+        }
+    }
+}
@@ -1,22 +1,141 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
 use std::fmt;
-use std::sync::{Arc, Mutex, Condvar};
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard, Condvar};
+use std::thread;
 use std::time::Duration as StdDuration;
+use std::time::Instant;
 
 use time::{Duration, SteadyTime};
 
-use super::IsolationLevel;
 use super::Transaction;
+use super::TxOpts;
 use super::super::error::{Error, DriverError};
-use super::super::value::Params;
-use super::{Conn, Opts, Stmt, QueryResult};
+use super::super::value::{FromRow, Params};
+use super::{Conn, MultiResult, Opts, ProcResult, RetryPolicy, Row, Stmt, QueryResult};
+use super::instrument::Elapsed;
+#[cfg(feature = "metrics")]
+use super::metrics::{self, PoolMetrics};
 use super::super::error::Result as MyResult;
 
+/// Listener of pool events registered via
+/// [`Pool::add_event_handler`](struct.Pool.html#method.add_event_handler).
+///
+/// Could be used to emit metrics or to enforce policies. Handlers are called while the pool
+/// is locked, so they should be fast and must not use the pool. Connections are identified by
+/// [`Conn::id`](../struct.Conn.html#method.id).
+pub trait PoolEventHandler: Send + Sync {
+    /// New connection was established by the pool.
+    #[allow(unused_variables)]
+    fn connection_created(&self, id: u32) {}
+
+    /// Connection was taken from the pool by a caller who waited for `wait`.
+    #[allow(unused_variables)]
+    fn checked_out(&self, id: u32, wait: StdDuration) {}
+
+    /// Connection was returned to the pool.
+    #[allow(unused_variables)]
+    fn checked_in(&self, id: u32) {}
+
+    /// Connection was closed by the pool (e.g. it is broken, invalidated or above `min`).
+    #[allow(unused_variables)]
+    fn discarded(&self, id: u32) {}
+
+    /// Caller gave up waiting for a connection after `wait`.
+    #[allow(unused_variables)]
+    fn checkout_timed_out(&self, wait: StdDuration) {}
+}
+
+#[derive(Clone, Default)]
+struct EventHandlers(Vec<Arc<PoolEventHandler>>);
+
+impl EventHandlers {
+    /// Calls every handler. Panic of a handler is caught, so that it does not leave the pool
+    /// in the middle of an update.
+    fn emit<F: Fn(&PoolEventHandler)>(&self, f: F) {
+        for handler in self.0.iter() {
+            if panic::catch_unwind(AssertUnwindSafe(|| f(&**handler))).is_err() {
+                mysql_debug!("pool event handler panicked");
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EventHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EventHandlers({})", self.0.len())
+    }
+}
+
+fn since(start: SteadyTime) -> StdDuration {
+    (SteadyTime::now() - start).to_std().unwrap_or(StdDuration::from_secs(0))
+}
+
+fn millis(ms: Option<u32>) -> Option<StdDuration> {
+    ms.map(|ms| StdDuration::from_millis(ms as u64))
+}
+
+/// Calls `f` until it succeeds or `policy` gives up.
+fn retry<T, F: FnMut() -> MyResult<T>>(policy: RetryPolicy, mut f: F) -> MyResult<T> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(ref err) if policy.should_retry(err, attempt) => {
+                let delay = policy.backoff(attempt);
+                mysql_debug!("attempt {} failed: {}, retrying in {:?}", attempt, err, delay);
+                thread::sleep(delay);
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Establishes connection and prepares statements broadcasted via `Pool::prepare_on_all`.
+fn connect(opts: Opts, stmts: &[String]) -> MyResult<Conn> {
+    let mut conn = match Conn::new(opts) {
+        Ok(conn) => conn,
+        Err(err) => {
+            #[cfg(feature = "metrics")]
+            metrics::increment_counter(metrics::POOL_CONNECTION_ERRORS);
+            return Err(err);
+        },
+    };
+    for stmt in stmts.iter() {
+        // Failed statement will be prepared (and will report an error) lazily.
+        let _ = conn._prepare(stmt);
+    }
+    Ok(conn)
+}
+
 #[derive(Debug)]
 struct InnerPool {
     opts: Opts,
     pool: Vec<Conn>,
+    /// Statements which was broadcasted via `Pool::prepare_on_all`.
+    stmts: Vec<String>,
+    /// Whether to call `Conn::reset_connection` when connection is returned to the pool.
+    reset_on_return: bool,
+    /// Default timeout for `Pool::get_conn` in milliseconds.
+    checkout_timeout: Option<u32>,
+    /// Tickets of callers waiting for a connection (in order of arrival).
+    waiters: VecDeque<u64>,
+    next_ticket: u64,
     min: usize,
     max: usize,
+    /// Number of connections allowed above `max`.
+    overflow: usize,
+    /// Incremented by `Pool::clear`. Connections checked out before that are not returned
+    /// to the pool.
+    generation: u64,
+    event_handlers: EventHandlers,
+    /// Addresses `Opts::ip_or_hostname` was last resolved to if `PoolOpts::dns_ttl_ms` is set.
+    resolved_addrs: Option<Vec<SocketAddr>>,
+    resolved_at: Option<Instant>,
     count: usize
 }
 
@@ -26,27 +145,67 @@ impl InnerPool {
             return Err(Error::DriverError(DriverError::InvalidPoolConstraints));
         }
         let mut pool = InnerPool {
+            checkout_timeout: opts.pool_opts.checkout_timeout_ms,
+            overflow: opts.pool_opts.overflow,
             opts: opts,
             pool: Vec::with_capacity(max),
+            stmts: Vec::new(),
+            reset_on_return: false,
+            waiters: VecDeque::new(),
+            next_ticket: 0,
+            generation: 0,
+            event_handlers: EventHandlers::default(),
+            resolved_addrs: None,
+            resolved_at: None,
             max: max,
             min: min,
             count: 0
         };
+        #[cfg(feature = "metrics")]
+        pool.event_handlers.0.push(Arc::new(PoolMetrics));
+        let retry_policy = pool.opts.pool_opts.retry_policy;
         for _ in 0..min {
-            try!(pool.new_conn());
+            try!(retry(retry_policy, || pool.new_conn()));
         }
         Ok(pool)
     }
     fn new_conn(&mut self) -> MyResult<()> {
-        match Conn::new(self.opts.clone()) {
+        match connect(self.opts.clone(), &*self.stmts) {
             Ok(conn) => {
+                let id = conn.id();
                 self.pool.push(conn);
                 self.count += 1;
+                self.event_handlers.emit(|handler| handler.connection_created(id));
                 Ok(())
             },
             Err(err) => Err(err)
         }
     }
+
+    /// Returns `true` if connection exceeded `PoolOpts::max_lifetime_ms`.
+    fn is_expired(&self, conn: &Conn) -> bool {
+        match millis(self.opts.pool_opts.max_lifetime_ms) {
+            Some(max_lifetime) => conn.created_at.elapsed() >= max_lifetime,
+            None => false,
+        }
+    }
+
+    /// Returns `true` if connection is connected to an address which `Opts::ip_or_hostname`
+    /// no longer resolves to.
+    fn is_stale(&self, conn: &Conn) -> bool {
+        match (self.resolved_addrs.as_ref(), conn.peer_addr) {
+            (Some(addrs), Some(ref addr)) => !addrs.contains(addr),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if idle connection exceeded `PoolOpts::idle_timeout_ms`.
+    fn is_idle_for_too_long(&self, conn: &Conn) -> bool {
+        match millis(self.opts.pool_opts.idle_timeout_ms) {
+            Some(idle_timeout) => conn.idle_since.elapsed() >= idle_timeout,
+            None => false,
+        }
+    }
 }
 
 /// `Pool` serves to provide you with a [`PooledConn`](struct.PooledConn.html)'s.
@@ -101,16 +260,32 @@ impl InnerPool {
 pub struct Pool(Arc<(Mutex<InnerPool>, Condvar)>);
 
 impl Pool {
+    /// Locks inner pool.
+    ///
+    /// Poisoning is ignored, because the pool is consistent between its updates and a thread
+    /// which panicked while holding the lock (e.g. in `Conn::new`) should not make the whole
+    /// pool unusable.
+    fn lock(&self) -> MutexGuard<InnerPool> {
+        (self.0).0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
     /// Will return connection taken from a pool.
     ///
-    /// Will verify and fix it via `Conn::ping` and `Conn::reset` if `call_ping` is `true`.
+    /// Will verify and fix it via `Conn::ping_err` and `Conn::reset` if `call_ping` is `true`.
     /// Will try to get concrete connection if `id` is `Some(_)`.
-    /// Will wait til timeout if `timeout_ms` is `Some(_)`
+    /// Will wait til timeout if `timeout_ms` (or pool-wide checkout timeout) is `Some(_)`.
+    ///
+    /// Waiting callers are served in FIFO order.
     fn _get_conn<T: AsRef<str>>(&self,
                                 stmt: Option<T>,
                                 timeout_ms: Option<u32>,
                                 call_ping: bool) -> MyResult<PooledConn> {
-        let times = if let Some(timeout_ms) = timeout_ms {
+        let elapsed = Elapsed::start();
+        let checkout_start = SteadyTime::now();
+        let condvar = &(self.0).1;
+        let mut pool = self.lock();
+
+        let times = if let Some(timeout_ms) = timeout_ms.or(pool.checkout_timeout) {
             Some ((
                 SteadyTime::now(),
                 Duration::milliseconds(timeout_ms as i64),
@@ -120,12 +295,55 @@ impl Pool {
             None
         };
 
-        let &(ref inner_pool, ref condvar) = &*self.0;
-        let mut pool = match inner_pool.lock() {
-            Ok(mutex) => mutex,
-            _ => return Err(Error::DriverError(DriverError::PoisonedPoolMutex)),
-        };
+        let ticket = pool.next_ticket;
+        pool.next_ticket = pool.next_ticket.wrapping_add(1);
+        pool.waiters.push_back(ticket);
+
+        let mut result = Ok(());
+        loop {
+            if pool.waiters.front() == Some(&ticket) {
+                if !pool.pool.is_empty() {
+                    break;
+                } else if pool.count < pool.max + pool.overflow {
+                    match panic::catch_unwind(AssertUnwindSafe(|| pool.new_conn())) {
+                        Ok(Err(err)) => result = Err(err),
+                        Ok(Ok(_)) => (),
+                        Err(payload) => {
+                            // Do not block the queue with a ticket of a dead caller.
+                            pool.waiters.retain(|x| *x != ticket);
+                            condvar.notify_all();
+                            drop(pool);
+                            panic::resume_unwind(payload);
+                        },
+                    }
+                    break;
+                }
+            }
+            pool = if let Some((start, timeout, std_timeout)) = times {
+                let elapsed = SteadyTime::now() - start;
+                if elapsed > timeout {
+                    let wait = since(checkout_start);
+                    pool.event_handlers.emit(|handler| handler.checkout_timed_out(wait));
+                    result = Err(DriverError::Timeout.into());
+                    break;
+                }
+                let left = (timeout - elapsed).to_std().unwrap_or(std_timeout);
+                match condvar.wait_timeout(pool, left) {
+                    Ok((mutex, _)) => mutex,
+                    Err(err) => err.into_inner().0,
+                }
+            } else {
+                condvar.wait(pool).unwrap_or_else(|err| err.into_inner())
+            }
+        }
 
+        // Let the next waiter in line check the pool.
+        pool.waiters.retain(|x| *x != ticket);
+        condvar.notify_all();
+        try!(result);
+
+        // Search for the connection with cached statement only after waiting
+        // so that connections returned to the pool meanwhile are also considered.
         let mut id = None;
         if let Some(query) = stmt {
             for (i, conn) in pool.pool.iter().enumerate() {
@@ -136,34 +354,6 @@ impl Pool {
             }
         }
 
-        loop {
-            if pool.pool.is_empty() {
-                if pool.count < pool.max {
-                    match pool.new_conn() {
-                        Ok(()) => break,
-                        Err(err) => return Err(err),
-                    }
-                } else {
-                    pool = if let Some((start, timeout, std_timeout)) = times {
-                        if SteadyTime::now() - start > timeout {
-                            return Err(DriverError::Timeout.into());
-                        }
-                        match condvar.wait_timeout(pool, std_timeout) {
-                            Ok((mutex, _)) => mutex,
-                            _ => return Err(Error::DriverError(DriverError::PoisonedPoolMutex)),
-                        }
-                    } else {
-                        match condvar.wait(pool) {
-                            Ok(mutex) => mutex,
-                            _ => return Err(Error::DriverError(DriverError::PoisonedPoolMutex)),
-                        }
-                    }
-                }
-            } else {
-                break;
-            }
-        }
-
         let mut conn = if let Some(id) = id {
             if id < pool.pool.len() {
                 pool.pool.remove(id)
@@ -175,23 +365,198 @@ impl Pool {
         };
 
         if call_ping {
-            if !conn.ping() {
-                try!(conn.reset());
+            if let Err(err) = conn.ping_err() {
+                mysql_debug!("ping of connection {} failed: {}, reconnecting",
+                             conn.connection_id,
+                             err);
+                if let Err(err) = conn.reset() {
+                    // Connection is lost, so the pool should be able to create a new one.
+                    let id = conn.id();
+                    pool.event_handlers.emit(|handler| handler.discarded(id));
+                    pool.count -= 1;
+                    condvar.notify_all();
+                    return Err(err);
+                }
             }
         }
 
-        Ok(PooledConn {pool: self.clone(), conn: Some(conn)})
+        mysql_debug!("connection {} checked out of pool in {}", conn.connection_id, elapsed);
+        let (id, wait) = (conn.id(), since(checkout_start));
+        pool.event_handlers.emit(|handler| handler.checked_out(id, wait));
+        Ok(PooledConn {
+            pool: self.clone(),
+            conn: Some(conn),
+            generation: pool.generation,
+            invalid: false,
+        })
     }
 
-    /// Creates new pool with `min = 10` and `max = 100`.
+    /// Creates new pool with `min`, `max`, `overflow` and checkout timeout taken from
+    /// [`Opts::pool_opts`](../struct.Opts.html#structfield.pool_opts)
+    /// (`min = 10` and `max = 100` by default).
     pub fn new<T: Into<Opts>>(opts: T) -> MyResult<Pool> {
-        Pool::new_manual(10, 100, opts)
+        let opts = opts.into();
+        let (min, max) = (opts.pool_opts.min, opts.pool_opts.max);
+        Pool::new_manual(min, max, opts)
     }
 
-    /// Same as `new` but you can set `min` and `max`.
+    /// Same as `new` but you can set `min` and `max` (`Opts::pool_opts.min` and
+    /// `Opts::pool_opts.max` are ignored).
+    ///
+    /// Pool may create up to `Opts::pool_opts.overflow` connections above `max` while there is
+    /// no idle connection. Such connections are closed when returned to the pool.
     pub fn new_manual<T: Into<Opts>>(min: usize, max: usize, opts: T) -> MyResult<Pool> {
         let pool = try!(InnerPool::new(min, max, opts.into()));
-        Ok(Pool(Arc::new((Mutex::new(pool), Condvar::new()))))
+        let interval = millis(pool.opts.pool_opts.maintenance_interval_ms);
+        let pool = Pool(Arc::new((Mutex::new(pool), Condvar::new())));
+        if let Some(interval) = interval {
+            try!(pool.spawn_maintenance(interval));
+        }
+        Ok(pool)
+    }
+
+    /// Spawns thread which calls `Pool::maintain` every `interval` until the pool is dropped.
+    fn spawn_maintenance(&self, interval: StdDuration) -> MyResult<()> {
+        let inner = Arc::downgrade(&self.0);
+        let builder = thread::Builder::new().name("mysql-pool-maintenance".into());
+        try!(builder.spawn(move || {
+            loop {
+                thread::sleep(interval);
+                match inner.upgrade() {
+                    Some(inner) => Pool(inner).maintain(),
+                    None => break,
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    /// Resolves `Opts::ip_or_hostname` again if `PoolOpts::dns_ttl_ms` has passed since
+    /// the last resolution.
+    fn resolve(&self) {
+        let (host, port) = {
+            let mut pool = self.lock();
+            let ttl = match millis(pool.opts.pool_opts.dns_ttl_ms) {
+                Some(ttl) => ttl,
+                None => return,
+            };
+            if pool.resolved_at.map(|at| at.elapsed() < ttl).unwrap_or(false) {
+                return;
+            }
+            let host = match pool.opts.ip_or_hostname {
+                Some(ref host) if pool.opts.proxy.is_none() => host.clone(),
+                _ => return,
+            };
+            if host.parse::<IpAddr>().is_ok() {
+                return;
+            }
+            pool.resolved_at = Some(Instant::now());
+            (host, pool.opts.tcp_port)
+        };
+        // Resolution may block, so it is done outside of the lock.
+        match (&*host, port).to_socket_addrs() {
+            Ok(addrs) => {
+                let addrs: Vec<SocketAddr> = addrs.collect();
+                if !addrs.is_empty() {
+                    self.lock().resolved_addrs = Some(addrs);
+                }
+            },
+            Err(err) => mysql_debug!("could not resolve {}: {}", host, err),
+        }
+    }
+
+    /// Closes idle connections which exceeded idle timeout or max lifetime or are connected to
+    /// a stale address, pings the rest of idle connections and creates connections up to `min`.
+    fn maintain(&self) {
+        let elapsed = Elapsed::start();
+        self.resolve();
+        let (expired, idle_count, generation) = {
+            let mut pool = self.lock();
+            let conns: Vec<Conn> = pool.pool.drain(..).collect();
+            let (expired, idle): (Vec<Conn>, Vec<Conn>) = conns.into_iter().partition(|conn| {
+                pool.is_expired(conn) || pool.is_idle_for_too_long(conn) || pool.is_stale(conn)
+            });
+            pool.count -= expired.len();
+            for conn in expired.iter() {
+                pool.event_handlers.emit(|handler| handler.discarded(conn.id()));
+            }
+            let idle_count = idle.len();
+            pool.pool = idle;
+            (self.0).1.notify_all();
+            (expired, idle_count, pool.generation)
+        };
+        let expired_count = expired.len();
+        drop(expired);
+
+        // Idle connections are pinged one at a time outside of the lock, so the rest of them
+        // stay available for checkouts. Connections are checked out from the top of the stack,
+        // so the least recently used ones at the bottom keep their positions.
+        let mut broken = 0;
+        let mut position = 0;
+        for _ in 0..idle_count {
+            let mut conn = {
+                let mut pool = self.lock();
+                if pool.generation != generation || position >= pool.pool.len() {
+                    break;
+                }
+                pool.pool.remove(position)
+            };
+            let is_alive = conn.ping_err().is_ok() || conn.reset().is_ok();
+            let mut pool = self.lock();
+            if is_alive && pool.generation == generation {
+                let position_in_pool = cmp::min(position, pool.pool.len());
+                pool.pool.insert(position_in_pool, conn);
+                position += 1;
+            } else {
+                if !is_alive {
+                    broken += 1;
+                }
+                pool.count -= 1;
+                pool.event_handlers.emit(|handler| handler.discarded(conn.id()));
+            }
+            (self.0).1.notify_all();
+        }
+
+        let mut created = 0;
+        loop {
+            let (opts, stmts, generation) = {
+                let mut pool = self.lock();
+                if pool.count >= pool.min {
+                    break;
+                }
+                // Reserve a slot, so that checkouts do not exceed `max`.
+                pool.count += 1;
+                (pool.opts.clone(), pool.stmts.clone(), pool.generation)
+            };
+            let result = connect(opts, &*stmts);
+            let mut pool = self.lock();
+            let failed = match result {
+                Ok(conn) => {
+                    if pool.generation == generation {
+                        let id = conn.id();
+                        pool.pool.push(conn);
+                        pool.event_handlers.emit(|handler| handler.connection_created(id));
+                        created += 1;
+                    } else {
+                        pool.count -= 1;
+                    }
+                    false
+                },
+                Err(err) => {
+                    mysql_debug!("pool maintenance failed to create connection: {}", err);
+                    pool.count -= 1;
+                    true
+                },
+            };
+            (self.0).1.notify_all();
+            if failed {
+                break;
+            }
+        }
+        mysql_debug!("pool maintenance closed {} and created {} connections in {}",
+                     expired_count + broken,
+                     created,
+                     elapsed);
     }
 
     /// Gives you a [`PooledConn`](struct.PooledConn.html).
@@ -200,8 +565,44 @@ impl Pool {
     /// [`Conn::ping`](../struct.Conn.html#method.ping) and will
     /// call [`Conn::reset`](../struct.Conn.html#method.reset) if
     /// necessary.
+    ///
+    /// Failed checkout is retried according to `PoolOpts::retry_policy`.
     pub fn get_conn(&self) -> MyResult<PooledConn> {
-        self._get_conn(None::<String>, None, true)
+        self.with_retry(|| self._get_conn(None::<String>, None, true))
+    }
+
+    /// Calls `f` until it succeeds or `PoolOpts::retry_policy` gives up, sleeping between
+    /// attempts.
+    fn with_retry<T, F: FnMut() -> MyResult<T>>(&self, f: F) -> MyResult<T> {
+        retry(self.lock().opts.pool_opts.retry_policy, f)
+    }
+
+    /// Retries checkout and, if `RetryPolicy::retry_execution` is set, execution of `f`.
+    fn with_exec_retry<T, F>(&self, query: &str, mut f: F) -> MyResult<T>
+    where F: FnMut(PooledConn) -> MyResult<T> {
+        let policy = self.lock().opts.pool_opts.retry_policy;
+        if policy.retry_execution {
+            retry(policy, || f(try!(self._get_conn(Some(query), None, false))))
+        } else {
+            f(try!(retry(policy, || self._get_conn(Some(query), None, false))))
+        }
+    }
+
+    /// Takes a connection only if it is available without waiting, i.e. there are no waiters
+    /// and pool has an idle connection or is allowed to create a new one.
+    fn try_take(&self) -> Option<MyResult<PooledConn>> {
+        {
+            let pool = self.lock();
+            let available = !pool.pool.is_empty() || pool.count < pool.max + pool.overflow;
+            if !available || !pool.waiters.is_empty() {
+                return None;
+            }
+        }
+        match self._get_conn(None::<String>, Some(0), true) {
+            // Other caller was faster.
+            Err(Error::DriverError(DriverError::Timeout)) => None,
+            result => Some(result),
+        }
     }
 
     /// Will try to get connection for a duration of `timeout_ms` milliseconds.
@@ -210,11 +611,124 @@ impl Pool {
     /// This function will return `Error::DriverError(DriverError::Timeout)` if timeout was
     /// reached while waiting for new connection to become available.
     pub fn try_get_conn(&self, timeout_ms: u32) -> MyResult<PooledConn> {
-        self._get_conn(None::<String>, Some(timeout_ms), true)
+        self.with_retry(|| self._get_conn(None::<String>, Some(timeout_ms), true))
+    }
+
+    /// Checks out a connection, passes it to `f` and returns the connection to the pool
+    /// afterwards, so that a caller does not need to store a `PooledConn`.
+    ///
+    /// Connection is closed instead of being returned if `f` panics (the panic is resumed)
+    /// or returns an error for which
+    /// [`Error::is_connection_error`](../enum.Error.html#method.is_connection_error)
+    /// is `true`.
+    ///
+    /// ```ignore
+    /// let count: Option<u64> = try!(pool.scope(|conn| {
+    ///     conn.exec_first("SELECT COUNT(*) FROM users", ())
+    /// }));
+    /// ```
+    pub fn scope<T, F>(&self, f: F) -> MyResult<T>
+    where F: FnOnce(&mut PooledConn) -> MyResult<T> {
+        let conn = try!(self.get_conn());
+        run_scope(conn, f)
+    }
+
+    /// Same as [`Pool::scope`](#method.scope), but waits for a connection for at most
+    /// `timeout_ms` milliseconds (see [`Pool::try_get_conn`](#method.try_get_conn)).
+    pub fn try_scope<T, F>(&self, timeout_ms: u32, f: F) -> MyResult<T>
+    where F: FnOnce(&mut PooledConn) -> MyResult<T> {
+        let conn = try!(self.try_get_conn(timeout_ms));
+        run_scope(conn, f)
+    }
+
+    /// Makes pool clear session state of connections returned to it via
+    /// [`Conn::reset_connection`](../struct.Conn.html#method.reset_connection)
+    /// (defaults to `false`).
+    ///
+    /// Connection which failed to reset will be dropped. Note that statements prepared on
+    /// a connection will not survive the reset, so it makes statement caching useless.
+    pub fn set_reset_on_return(&self, reset_on_return: bool) -> MyResult<()> {
+        self.lock().reset_on_return = reset_on_return;
+        Ok(())
+    }
+
+    /// Sets default timeout in milliseconds for every method which takes connection from
+    /// the pool (defaults to `None`, i.e. wait forever).
+    ///
+    /// [`Pool::try_get_conn`](#method.try_get_conn) will use its own timeout.
+    pub fn set_checkout_timeout(&self, timeout_ms: Option<u32>) -> MyResult<()> {
+        self.lock().checkout_timeout = timeout_ms;
+        Ok(())
+    }
+
+    /// Closes every idle connection of a pool.
+    ///
+    /// Connections which are in use at the moment of the call will be closed when returned
+    /// to the pool, so every subsequent checkout gets a freshly established connection
+    /// (e.g. after a credentials rotation or DNS failover). New connections are created
+    /// lazily, so pool may hold less than `min` connections afterwards.
+    pub fn clear(&self) -> MyResult<()> {
+        let conns = {
+            let mut pool = self.lock();
+            let conns: Vec<Conn> = pool.pool.drain(..).collect();
+            pool.count -= conns.len();
+            for conn in conns.iter() {
+                pool.event_handlers.emit(|handler| handler.discarded(conn.id()));
+            }
+            pool.generation = pool.generation.wrapping_add(1);
+            (self.0).1.notify_all();
+            conns
+        };
+        mysql_debug!("pool cleared, {} idle connections dropped", conns.len());
+        // Connections are closed outside of the lock.
+        drop(conns);
+        Ok(())
+    }
+
+    /// Changes `min` and `max` of a running pool.
+    ///
+    /// Surplus idle connections are closed immediately and surplus connections which are in
+    /// use are closed when returned to the pool. New connections are not created eagerly,
+    /// but on checkout, so pool may hold less than `min` connections for a while.
+    ///
+    /// # Failure
+    /// Returns `Error::DriverError(DriverError::InvalidPoolConstraints)` if `min > max` or
+    /// `max == 0`.
+    pub fn set_constraints(&self, min: usize, max: usize) -> MyResult<()> {
+        if min > max || max == 0 {
+            return Err(Error::DriverError(DriverError::InvalidPoolConstraints));
+        }
+        let conns = {
+            let mut pool = self.lock();
+            pool.min = min;
+            pool.max = max;
+            let mut conns = Vec::new();
+            while pool.count > min && !pool.pool.is_empty() {
+                let conn = pool.pool.pop().unwrap();
+                pool.count -= 1;
+                pool.event_handlers.emit(|handler| handler.discarded(conn.id()));
+                conns.push(conn);
+            }
+            // Waiters may now be allowed to create a connection.
+            (self.0).1.notify_all();
+            conns
+        };
+        mysql_debug!("pool constraints set to min={} max={}, {} idle connections dropped",
+                     min,
+                     max,
+                     conns.len());
+        drop(conns);
+        Ok(())
+    }
+
+    /// Registers a listener of pool events.
+    pub fn add_event_handler<T: PoolEventHandler + 'static>(&self, handler: T) -> MyResult<()> {
+        self.lock().event_handlers.0.push(Arc::new(handler));
+        Ok(())
     }
 
     fn get_conn_by_stmt<T: AsRef<str>>(&self, query: T) -> MyResult<PooledConn> {
-        self._get_conn(Some(query), None, false)
+        self.with_retry(|| self._get_conn(Some(query.as_ref()), None, false))
     }
 
     /// Will prepare statement.
@@ -227,36 +741,317 @@ impl Pool {
         conn.pooled_prepare(query)
     }
 
+    /// Will prepare statement on every idle connection of a pool.
+    ///
+    /// Statement will also be prepared on every connection created by a pool afterwards, so
+    /// subsequent `Pool::prepare` and `Pool::prep_exec` calls with this query will hit the
+    /// statement cache on any connection taken from the pool. Connections which are in use
+    /// at the moment of the call will prepare statement lazily.
+    ///
+    /// Idle connections are taken out of the pool while statement is prepared, so the pool is
+    /// not locked during I/O. Returns ids of connections on which statement was not prepared
+    /// along with errors. Connections which failed due to a non-server error are closed.
+    /// Statement is not broadcasted to new connections if server rejected it.
+    ///
+    /// Will not check or fix connection health.
+    pub fn prepare_on_all<T: AsRef<str>>(&self, query: T) -> Result<(), Vec<(u32, Error)>> {
+        let query = query.as_ref();
+        let (conns, generation) = {
+            let mut pool = self.lock();
+            // Connections are still counted by the pool.
+            let conns: Vec<Conn> = pool.pool.drain(..).collect();
+            (conns, pool.generation)
+        };
+
+        let mut prepared = Vec::with_capacity(conns.len());
+        let mut errors = Vec::new();
+        for mut conn in conns.into_iter() {
+            match conn._prepare(query) {
+                Ok(_) => prepared.push(conn),
+                Err(err) => {
+                    let is_server_error = err.server_error().is_some();
+                    errors.push((conn.id(), err));
+                    if is_server_error {
+                        prepared.push(conn);
+                    } else {
+                        self.discard(conn);
+                    }
+                },
+            }
+        }
+
+        let mut pool = self.lock();
+        if pool.generation == generation {
+            // Connections checked in meanwhile are more recently used, so they stay on top.
+            let checked_in: Vec<Conn> = pool.pool.drain(..).collect();
+            pool.pool = prepared;
+            pool.pool.extend(checked_in);
+        } else {
+            pool.count -= prepared.len();
+            for conn in prepared.iter() {
+                pool.event_handlers.emit(|handler| handler.discarded(conn.id()));
+            }
+        }
+        let rejected = errors.iter().any(|&(_, ref err)| err.server_error().is_some());
+        if !rejected && !pool.stmts.iter().any(|stmt| stmt == query) {
+            pool.stmts.push(query.to_owned());
+        }
+        (self.0).1.notify_all();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Closes connection taken out of the pool and releases its slot.
+    fn discard(&self, conn: Conn) {
+        let id = conn.id();
+        drop(conn);
+        let mut pool = self.lock();
+        pool.count -= 1;
+        pool.event_handlers.emit(|handler| handler.discarded(id));
+        (self.0).1.notify_all();
+    }
+
     /// Shortcut for `try!(pool.get_conn()).prep_exec(..)`.
     ///
     /// It will try to find connection which has this statement cached.
     ///
     /// Will not check or fix connection health.
+    ///
+    /// Connection is returned to the pool only when `QueryResult` is dropped, so a slowly
+    /// consumed result keeps it out of the pool. Consider
+    /// [`Pool::prep_exec_buffered`](#method.prep_exec_buffered) or
+    /// [`Pool::exec`](#method.exec) instead.
+    ///
+    /// Failed checkout is retried according to `PoolOpts::retry_policy`. Failed execution is
+    /// retried only if `RetryPolicy::retry_execution` is set. Errors which occur while rows are
+    /// read are not retried.
     pub fn prep_exec<'a, A, T>(&'a self, query: A, params: T) -> MyResult<QueryResult<'a>>
     where A: AsRef<str>,
           T: Into<Params> {
-        let conn = try!(self.get_conn_by_stmt(query.as_ref()));
-        conn.pooled_prep_exec(query, params)
+        let query = query.as_ref();
+        let params = params.into();
+        self.with_exec_retry(query, |conn| conn.pooled_prep_exec(query, params.clone()))
+    }
+
+    /// Same as [`Pool::prep_exec`](#method.prep_exec) but reads the whole result set into
+    /// memory and returns connection to the pool before rows are returned.
+    ///
+    /// See [`Conn::prep_exec_buffered`](../struct.Conn.html#method.prep_exec_buffered).
+    pub fn prep_exec_buffered<A, T>(&self, query: A, params: T) -> MyResult<Vec<Row>>
+    where A: AsRef<str>,
+          T: Into<Params> {
+        let query = query.as_ref();
+        let params = params.into();
+        self.with_exec_retry(query, |mut conn| conn.prep_exec_buffered(query, params.clone()))
+    }
+
+    /// Shortcut for `try!(pool.get_conn()).exec(..)`.
+    ///
+    /// See [`Conn::exec`](../struct.Conn.html#method.exec).
+    pub fn exec<T, A, P>(&self, query: A, params: P) -> MyResult<Vec<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params)).collect_all_rows()
+    }
+
+    /// Shortcut for `try!(pool.get_conn()).exec_first(..)`.
+    ///
+    /// See [`Conn::exec_first`](../struct.Conn.html#method.exec_first).
+    pub fn exec_first<T, A, P>(&self, query: A, params: P) -> MyResult<Option<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params)).first_row_checked()
+    }
+
+    /// Shortcut for `try!(pool.get_conn()).exec_drop(..)`.
+    ///
+    /// See [`Conn::exec_drop`](../struct.Conn.html#method.exec_drop).
+    pub fn exec_drop<A, P>(&self, query: A, params: P) -> MyResult<()>
+    where A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params));
+        Ok(())
+    }
+
+    /// Shortcut for `try!(pool.get_conn()).prep_exec_map(..)`.
+    ///
+    /// See [`Conn::prep_exec_map`](../struct.Conn.html#method.prep_exec_map).
+    pub fn prep_exec_map<T, U, A, P, F>(&self, query: A, params: P, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params>,
+          F: FnMut(T) -> U {
+        try!(self.prep_exec(query, params)).map_all_rows(f)
+    }
+
+    /// Shortcut for `try!(pool.get_conn()).prep_exec_fold(..)`.
+    ///
+    /// See [`Conn::prep_exec_fold`](../struct.Conn.html#method.prep_exec_fold).
+    pub fn prep_exec_fold<T, U, A, P, F>(&self, query: A, params: P, init: U, f: F) -> MyResult<U>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params>,
+          F: FnMut(U, T) -> U {
+        try!(self.prep_exec(query, params)).fold_all_rows(init, f)
     }
 
     /// Shortcut for `try!(pool.get_conn()).start_transaction(..)`.
-    pub fn start_transaction(&self,
-                             consistent_snapshot: bool,
-                             isolation_level: Option<IsolationLevel>,
-                             readonly: Option<bool>) -> MyResult<Transaction> {
-        (try!(self.get_conn())).pooled_start_transaction(consistent_snapshot,
-                                                         isolation_level,
-                                                         readonly)
+    pub fn start_transaction(&self, tx_opts: TxOpts) -> MyResult<Transaction> {
+        (try!(self.get_conn())).pooled_start_transaction(tx_opts)
+    }
+}
+
+fn run_scope<T, F>(mut conn: PooledConn, f: F) -> MyResult<T>
+where F: FnOnce(&mut PooledConn) -> MyResult<T> {
+    match panic::catch_unwind(AssertUnwindSafe(|| f(&mut conn))) {
+        Ok(result) => {
+            if let Err(ref err) = result {
+                if err.is_connection_error() {
+                    conn.invalidate();
+                }
+            }
+            result
+        },
+        Err(payload) => {
+            // Connection may be left in the middle of a result set.
+            conn.invalidate();
+            drop(conn);
+            panic::resume_unwind(payload);
+        },
     }
 }
 
 impl fmt::Debug for Pool {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let pool = (self.0).0.lock().unwrap();
+        let pool = self.lock();
         write!(f, "Pool {{ min: {}, max: {} }}", pool.min, pool.max)
     }
 }
 
+/// Part of `total` which belongs to the shard number `i` of a `ShardedPool`.
+fn shard_size(total: usize, shards: usize, i: usize) -> usize {
+    total / shards + if i < total % shards { 1 } else { 0 }
+}
+
+/// Number of independent [`Pool`](struct.Pool.html)s which act as one pool.
+///
+/// Every checkout of a `Pool` takes its mutex, so under heavy multithreading all threads
+/// contend on a single lock. `ShardedPool` divides `min`, `max` and `overflow` between
+/// shards, each of them with its own lock. Thread takes connections from a shard selected
+/// by a hash of its id. If that shard has neither idle connections nor room for a new one,
+/// connection is stolen from other shards, and only if every shard is exhausted the thread
+/// waits on its own shard. Connection is always returned to the shard it was taken from.
+///
+/// ```ignore
+/// let pool = try!(ShardedPool::new("mysql://root@localhost/db?pool_max=256&pool_shards=16"));
+/// let count: Option<u64> = try!(pool.scope(|conn| {
+///     conn.exec_first("SELECT COUNT(*) FROM users", ())
+/// }));
+/// ```
+#[derive(Clone)]
+pub struct ShardedPool(Arc<Vec<Pool>>);
+
+impl ShardedPool {
+    /// Creates new pool with number of shards, `min`, `max` and `overflow` taken from
+    /// [`Opts::pool_opts`](../struct.Opts.html#structfield.pool_opts).
+    pub fn new<T: Into<Opts>>(opts: T) -> MyResult<ShardedPool> {
+        let opts = opts.into();
+        let (shards, min, max) = (opts.pool_opts.shards, opts.pool_opts.min, opts.pool_opts.max);
+        ShardedPool::new_manual(shards, min, max, opts)
+    }
+
+    /// Same as `new` but you can set number of shards, `min` and `max`.
+    ///
+    /// `max` should not be less than the number of shards, so that every shard is allowed
+    /// to hold at least one connection.
+    pub fn new_manual<T>(shards: usize, min: usize, max: usize, opts: T) -> MyResult<ShardedPool>
+    where T: Into<Opts> {
+        if shards == 0 || min > max || max < shards {
+            return Err(Error::DriverError(DriverError::InvalidPoolConstraints));
+        }
+        let opts = opts.into();
+        let mut pools = Vec::with_capacity(shards);
+        for i in 0..shards {
+            let mut opts = opts.clone();
+            opts.pool_opts.overflow = shard_size(opts.pool_opts.overflow, shards, i);
+            let (min, max) = (shard_size(min, shards, i), shard_size(max, shards, i));
+            pools.push(try!(Pool::new_manual(min, max, opts)));
+        }
+        Ok(ShardedPool(Arc::new(pools)))
+    }
+
+    /// Returns shards, e.g. to configure them or to add event handlers.
+    pub fn shards(&self) -> &[Pool] {
+        &*self.0
+    }
+
+    /// Index of a shard of the current thread.
+    fn home(&self) -> usize {
+        let mut hasher = DefaultHasher::new();
+        thread::current().id().hash(&mut hasher);
+        (hasher.finish() % self.0.len() as u64) as usize
+    }
+
+    fn _get_conn(&self, timeout_ms: Option<u32>) -> MyResult<PooledConn> {
+        let home = self.home();
+        let len = self.0.len();
+        for i in 0..len {
+            if let Some(result) = self.0[(home + i) % len].try_take() {
+                return result;
+            }
+        }
+        match timeout_ms {
+            Some(timeout_ms) => self.0[home].try_get_conn(timeout_ms),
+            None => self.0[home].get_conn(),
+        }
+    }
+
+    /// Same as [`Pool::get_conn`](struct.Pool.html#method.get_conn).
+    pub fn get_conn(&self) -> MyResult<PooledConn> {
+        self._get_conn(None)
+    }
+
+    /// Same as [`Pool::try_get_conn`](struct.Pool.html#method.try_get_conn).
+    ///
+    /// Timeout is applied to the waiting on the shard of the current thread.
+    pub fn try_get_conn(&self, timeout_ms: u32) -> MyResult<PooledConn> {
+        self._get_conn(Some(timeout_ms))
+    }
+
+    /// Same as [`Pool::scope`](struct.Pool.html#method.scope).
+    pub fn scope<T, F>(&self, f: F) -> MyResult<T>
+    where F: FnOnce(&mut PooledConn) -> MyResult<T> {
+        let conn = try!(self.get_conn());
+        run_scope(conn, f)
+    }
+
+    /// Same as [`Pool::try_scope`](struct.Pool.html#method.try_scope).
+    pub fn try_scope<T, F>(&self, timeout_ms: u32, f: F) -> MyResult<T>
+    where F: FnOnce(&mut PooledConn) -> MyResult<T> {
+        let conn = try!(self.try_get_conn(timeout_ms));
+        run_scope(conn, f)
+    }
+
+    /// Calls [`Pool::clear`](struct.Pool.html#method.clear) on every shard.
+    pub fn clear(&self) -> MyResult<()> {
+        for pool in self.0.iter() {
+            try!(pool.clear());
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ShardedPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ShardedPool {{ shards: {:?} }}", self.0)
+    }
+}
+
 /// Pooled mysql connection which will return to the pool on `drop`.
 ///
 /// You should prefer using `prepare` or `prep_exec` instead of `query` where possible, except
@@ -306,18 +1101,73 @@ impl fmt::Debug for Pool {
 #[derive(Debug)]
 pub struct PooledConn {
     pool: Pool,
-    conn: Option<Conn>
+    conn: Option<Conn>,
+    /// `InnerPool::generation` at checkout.
+    generation: u64,
+    /// Set by `PooledConn::invalidate`.
+    invalid: bool,
 }
 
 impl Drop for PooledConn {
     fn drop(&mut self) {
-        let mut pool = (self.pool.0).0.lock().unwrap();
-        if pool.count > pool.min || self.conn.is_none() {
+        // `None` if connection was unwrapped.
+        let id = self.conn.as_ref().map(|conn| conn.id());
+        let (reset_on_return, generation, expired) = {
+            let pool = self.pool.lock();
+            let expired = self.conn.as_ref().map(|conn| {
+                pool.is_expired(conn) || pool.is_stale(conn)
+            }).unwrap_or(false);
+            (pool.reset_on_return, pool.generation, expired)
+        };
+        if self.invalid || generation != self.generation || expired {
+            self.conn = None;
+        }
+        if reset_on_return && self.conn.is_some() {
+            if self.conn.as_mut().unwrap().reset_connection().is_err() {
+                self.conn = None;
+            }
+        }
+        let multi_statements_changed = self.conn.as_ref().map(|conn| {
+            conn.multi_statements != conn.opts.multi_statements
+        }).unwrap_or(false);
+        if multi_statements_changed {
+            // Next borrower should get connection configured according to `Opts`.
+            let result = {
+                let conn = self.conn.as_mut().unwrap();
+                let enabled = conn.opts.multi_statements;
+                conn.set_multi_statements(enabled)
+            };
+            if result.is_err() {
+                self.conn = None;
+            }
+        }
+        let mut pool = self.pool.lock();
+        if pool.count > pool.max && self.conn.is_some() {
+            mysql_debug!("overflow connection {} dropped on checkin",
+                         self.conn.as_ref().unwrap().connection_id);
             pool.count -= 1;
+            if let Some(id) = id {
+                pool.event_handlers.emit(|handler| handler.discarded(id));
+            }
+        } else if pool.count > pool.min || self.conn.is_none() {
+            mysql_debug!("connection {} dropped on checkin",
+                         self.conn.as_ref().map(|conn| conn.connection_id).unwrap_or(0));
+            pool.count -= 1;
+            if let Some(id) = id {
+                pool.event_handlers.emit(|handler| handler.discarded(id));
+            }
         } else {
-            pool.pool.push(self.conn.take().unwrap());
-            (self.pool.0).1.notify_one();
+            mysql_debug!("connection {} checked in to pool",
+                         self.conn.as_ref().unwrap().connection_id);
+            let mut conn = self.conn.take().unwrap();
+            conn.idle_since = Instant::now();
+            pool.pool.push(conn);
+            if let Some(id) = id {
+                pool.event_handlers.emit(|handler| handler.checked_in(id));
+            }
         }
+        // Waiters are served in FIFO order so all of them should check the pool.
+        (self.pool.0).1.notify_all();
     }
 }
 
@@ -328,6 +1178,20 @@ impl PooledConn {
         self.conn.as_mut().unwrap().query(query)
     }
 
+    /// Redirects to
+    /// [`Conn#query_multi`](../struct.Conn.html#method.query_multi).
+    pub fn query_multi<'a, T: AsRef<str>>(&'a mut self, query: T) -> MyResult<MultiResult<'a>> {
+        self.conn.as_mut().unwrap().query_multi(query)
+    }
+
+    /// Redirects to
+    /// [`Conn#call`](../struct.Conn.html#method.call).
+    pub fn call<T, P>(&mut self, proc_name: T, params: P) -> MyResult<ProcResult>
+    where T: AsRef<str>,
+          P: Into<Params> {
+        self.conn.as_mut().unwrap().call(proc_name, params)
+    }
+
     /// Redirects to
     /// [`Conn#prepare`](../struct.Conn.html#method.prepare).
     pub fn prepare<'a, T: AsRef<str> + 'a>(&'a mut self, query: T) -> MyResult<Stmt<'a>> {
@@ -340,15 +1204,118 @@ impl PooledConn {
         self.conn.as_mut().unwrap().prep_exec(query, params)
     }
 
+    /// Redirects to
+    /// [`Conn#prep_exec_buffered`](../struct.Conn.html#method.prep_exec_buffered).
+    pub fn prep_exec_buffered<A, T>(&mut self, query: A, params: T) -> MyResult<Vec<Row>>
+    where A: AsRef<str>,
+          T: Into<Params> {
+        self.conn.as_mut().unwrap().prep_exec_buffered(query, params)
+    }
+
+    /// Redirects to
+    /// [`Conn#exec`](../struct.Conn.html#method.exec).
+    pub fn exec<T, A, P>(&mut self, query: A, params: P) -> MyResult<Vec<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        self.conn.as_mut().unwrap().exec(query, params)
+    }
+
+    /// Redirects to
+    /// [`Conn#exec_first`](../struct.Conn.html#method.exec_first).
+    pub fn exec_first<T, A, P>(&mut self, query: A, params: P) -> MyResult<Option<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        self.conn.as_mut().unwrap().exec_first(query, params)
+    }
+
+    /// Redirects to
+    /// [`Conn#exec_drop`](../struct.Conn.html#method.exec_drop).
+    pub fn exec_drop<A, P>(&mut self, query: A, params: P) -> MyResult<()>
+    where A: AsRef<str>,
+          P: Into<Params> {
+        self.conn.as_mut().unwrap().exec_drop(query, params)
+    }
+
+    /// Redirects to
+    /// [`Conn#insert_many`](../struct.Conn.html#method.insert_many).
+    pub fn insert_many<T, C, I, P>(&mut self,
+                                   table: T,
+                                   columns: &[C],
+                                   rows: I,
+                                   chunk_size: usize) -> MyResult<u64>
+    where T: AsRef<str>,
+          C: AsRef<str>,
+          I: IntoIterator<Item=P>,
+          P: Into<Params> {
+        self.conn.as_mut().unwrap().insert_many(table, columns, rows, chunk_size)
+    }
+
+    /// Redirects to
+    /// [`Conn#query_map`](../struct.Conn.html#method.query_map).
+    pub fn query_map<T, U, Q, F>(&mut self, query: Q, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          Q: AsRef<str>,
+          F: FnMut(T) -> U {
+        self.conn.as_mut().unwrap().query_map(query, f)
+    }
+
+    /// Redirects to
+    /// [`Conn#query_fold`](../struct.Conn.html#method.query_fold).
+    pub fn query_fold<T, U, Q, F>(&mut self, query: Q, init: U, f: F) -> MyResult<U>
+    where T: FromRow,
+          Q: AsRef<str>,
+          F: FnMut(U, T) -> U {
+        self.conn.as_mut().unwrap().query_fold(query, init, f)
+    }
+
+    /// Redirects to
+    /// [`Conn#prep_exec_map`](../struct.Conn.html#method.prep_exec_map).
+    pub fn prep_exec_map<T, U, A, P, F>(&mut self, query: A, params: P, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params>,
+          F: FnMut(T) -> U {
+        self.conn.as_mut().unwrap().prep_exec_map(query, params, f)
+    }
+
+    /// Redirects to
+    /// [`Conn#prep_exec_fold`](../struct.Conn.html#method.prep_exec_fold).
+    pub fn prep_exec_fold<T, U, A, P, F>(&mut self,
+                                         query: A,
+                                         params: P,
+                                         init: U,
+                                         f: F) -> MyResult<U>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params>,
+          F: FnMut(U, T) -> U {
+        self.conn.as_mut().unwrap().prep_exec_fold(query, params, init, f)
+    }
+
     /// Redirects to
     /// [`Conn#start_transaction`](../struct.Conn.html#method.start_transaction)
-    pub fn start_transaction<'a>(&'a mut self,
-                                 consistent_snapshot: bool,
-                                 isolation_level: Option<IsolationLevel>,
-                                 readonly: Option<bool>) -> MyResult<Transaction<'a>> {
-        self.conn.as_mut().unwrap().start_transaction(consistent_snapshot,
-                                                      isolation_level,
-                                                      readonly)
+    pub fn start_transaction<'a>(&'a mut self, tx_opts: TxOpts) -> MyResult<Transaction<'a>> {
+        self.conn.as_mut().unwrap().start_transaction(tx_opts)
+    }
+
+    /// Redirects to
+    /// [`Conn#id`](../struct.Conn.html#method.id).
+    pub fn id(&self) -> u32 {
+        self.conn.as_ref().unwrap().id()
+    }
+
+    /// Redirects to
+    /// [`Conn#kill`](../struct.Conn.html#method.kill).
+    pub fn kill(&mut self, id: u32) -> MyResult<()> {
+        self.conn.as_mut().unwrap().kill(id)
+    }
+
+    /// Redirects to
+    /// [`Conn#reset_connection`](../struct.Conn.html#method.reset_connection).
+    pub fn reset_connection(&mut self) -> MyResult<()> {
+        self.conn.as_mut().unwrap().reset_connection()
     }
 
     /// Gives mutable reference to the wrapped
@@ -363,6 +1330,13 @@ impl PooledConn {
         self.conn.as_ref().unwrap()
     }
 
+    /// Marks connection as broken, so it will be closed instead of being returned to the pool.
+    ///
+    /// Pool will create a new connection in its place when needed.
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+    }
+
     /// Unwraps wrapped [`Conn`](../struct.Conn.html).
     pub fn unwrap(mut self) -> Conn {
         self.conn.take().unwrap()
@@ -384,13 +1358,8 @@ impl PooledConn {
         stmt.prep_exec(params)
     }
 
-    fn pooled_start_transaction<'a>(mut self,
-                                    consistent_snapshot: bool,
-                                    isolation_level: Option<IsolationLevel>,
-                                    readonly: Option<bool>) -> MyResult<Transaction<'a>> {
-        let _ = try!(self.as_mut()._start_transaction(consistent_snapshot,
-                                                      isolation_level,
-                                                      readonly));
+    fn pooled_start_transaction<'a>(mut self, tx_opts: TxOpts) -> MyResult<Transaction<'a>> {
+        let _ = try!(self.as_mut()._start_transaction(tx_opts));
         Ok(Transaction::new_pooled(self))
     }
 }
@@ -417,7 +1386,7 @@ mod test {
             pass: Some(pwd),
             ip_or_hostname: Some(ADDR.to_string()),
             tcp_port: port,
-            ssl_opts: Some((::std::convert::From::from("tests/ca-cert.pem"), None)),
+            ssl_opts: Some((::std::path::PathBuf::from("tests/ca-cert.pem"), None).into()),
             ..Default::default()
         }
     }
@@ -439,10 +1408,14 @@ mod test {
 
     mod pool {
         use super::get_opts;
+        use std::sync::{Arc, Mutex};
         use std::thread;
-        use super::super::Pool;
-        use super::super::super::super::value::from_value;
+        use std::time::{Duration as StdDuration, Instant};
+        use super::super::{shard_size, Pool, PoolEventHandler, ShardedPool};
+        use super::super::super::{Opts, PoolOpts, RetryPolicy, TxOpts};
+        use super::super::super::super::value::{from_value, Value};
         use super::super::super::super::error::{Error, DriverError};
+        use super::super::super::super::error::Result as MyResult;
         #[test]
         fn should_execute_queryes_on_PooledConn() {
             let pool = Pool::new(get_opts()).unwrap();
@@ -474,6 +1447,336 @@ mod test {
             assert!(pool.try_get_conn(357).is_ok());
         }
         #[test]
+        fn should_create_overflow_connections() {
+            let pool = Pool::new_manual(1, 1, Opts {
+                pool_opts: PoolOpts {
+                    overflow: 1,
+                    ..PoolOpts::default()
+                },
+                ..get_opts()
+            }).unwrap();
+            let conn1 = pool.try_get_conn(357).unwrap();
+            let conn2 = pool.try_get_conn(357).unwrap();
+            match pool.try_get_conn(357) {
+                Err(Error::DriverError(DriverError::Timeout)) => (),
+                _ => panic!("Expected Timeout error"),
+            }
+            drop(conn2);
+            assert_eq!((pool.0).0.lock().unwrap().count, 1);
+            drop(conn1);
+            let inner = (pool.0).0.lock().unwrap();
+            assert_eq!(inner.count, 1);
+            assert_eq!(inner.pool.len(), 1);
+        }
+        #[test]
+        fn should_emit_pool_events() {
+            struct Handler(Arc<Mutex<Vec<&'static str>>>);
+            impl PoolEventHandler for Handler {
+                fn connection_created(&self, _: u32) {
+                    self.0.lock().unwrap().push("created");
+                }
+                fn checked_out(&self, _: u32, _: StdDuration) {
+                    self.0.lock().unwrap().push("checked_out");
+                }
+                fn checked_in(&self, _: u32) {
+                    self.0.lock().unwrap().push("checked_in");
+                }
+                fn discarded(&self, _: u32) {
+                    self.0.lock().unwrap().push("discarded");
+                }
+                fn checkout_timed_out(&self, _: StdDuration) {
+                    self.0.lock().unwrap().push("timed_out");
+                }
+            }
+
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let pool = Pool::new_manual(1, 1, get_opts()).unwrap();
+            pool.add_event_handler(Handler(events.clone())).unwrap();
+            let conn = pool.get_conn().unwrap();
+            assert!(pool.try_get_conn(100).is_err());
+            drop(conn);
+            let mut conn = pool.get_conn().unwrap();
+            conn.invalidate();
+            drop(conn);
+            pool.get_conn().unwrap();
+            assert_eq!(*events.lock().unwrap(),
+                       vec!["checked_out", "timed_out", "checked_in", "checked_out", "discarded",
+                            "created", "checked_out", "checked_in"]);
+        }
+        #[test]
+        fn should_recover_from_poisoned_mutex() {
+            struct Panicky;
+            impl PoolEventHandler for Panicky {
+                fn checked_out(&self, _: u32, _: StdDuration) {
+                    panic!("handler panic");
+                }
+            }
+
+            let pool = Pool::new_manual(1, 1, get_opts()).unwrap();
+            let pool_clone = pool.clone();
+            let result = thread::spawn(move || {
+                let _guard = (pool_clone.0).0.lock().unwrap();
+                panic!("poison pool mutex");
+            }).join();
+            assert!(result.is_err());
+            assert!((pool.0).0.is_poisoned());
+            pool.add_event_handler(Panicky).unwrap();
+            let mut conn = pool.get_conn().unwrap();
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT 1", ()).unwrap(), Some(1));
+            drop(conn);
+            assert!(pool.get_conn().is_ok());
+        }
+        #[test]
+        fn should_resize_pool() {
+            let pool = Pool::new_manual(2, 2, get_opts()).unwrap();
+            assert!(pool.set_constraints(3, 1).is_err());
+            assert!(pool.set_constraints(0, 0).is_err());
+            let conn1 = pool.get_conn().unwrap();
+            let conn2 = pool.get_conn().unwrap();
+            assert!(pool.try_get_conn(357).is_err());
+            pool.set_constraints(3, 3).unwrap();
+            let conn3 = pool.try_get_conn(357).unwrap();
+            drop((conn1, conn2, conn3));
+            assert_eq!((pool.0).0.lock().unwrap().pool.len(), 3);
+            pool.set_constraints(1, 1).unwrap();
+            {
+                let inner = (pool.0).0.lock().unwrap();
+                assert_eq!(inner.count, 1);
+                assert_eq!(inner.pool.len(), 1);
+            }
+            let conn1 = pool.get_conn().unwrap();
+            assert!(pool.try_get_conn(357).is_err());
+            drop(conn1);
+        }
+        #[test]
+        fn should_maintain_pool() {
+            let pool = Pool::new_manual(2, 3, Opts {
+                pool_opts: PoolOpts {
+                    max_lifetime_ms: Some(100),
+                    ..PoolOpts::default()
+                },
+                ..get_opts()
+            }).unwrap();
+            let ids: Vec<u32> = (pool.0).0.lock().unwrap().pool.iter().map(|c| c.id()).collect();
+            // Pinged connections keep their positions.
+            pool.maintain();
+            {
+                let inner = (pool.0).0.lock().unwrap();
+                assert_eq!(inner.pool.iter().map(|c| c.id()).collect::<Vec<_>>(), ids);
+            }
+            thread::sleep(StdDuration::from_millis(150));
+            pool.maintain();
+            {
+                let inner = (pool.0).0.lock().unwrap();
+                assert_eq!(inner.count, 2);
+                assert_eq!(inner.pool.len(), 2);
+                assert!(inner.pool.iter().all(|conn| !ids.contains(&conn.id())));
+            }
+            // Expired connection is not returned to the pool.
+            let conn = pool.get_conn().unwrap();
+            thread::sleep(StdDuration::from_millis(150));
+            drop(conn);
+            assert_eq!((pool.0).0.lock().unwrap().count, 1);
+
+            let pool = Pool::new_manual(1, 1, Opts {
+                pool_opts: PoolOpts {
+                    maintenance_interval_ms: Some(10),
+                    ..PoolOpts::default()
+                },
+                ..get_opts()
+            }).unwrap();
+            let mut conn = pool.get_conn().unwrap();
+            conn.invalidate();
+            drop(conn);
+            assert_eq!((pool.0).0.lock().unwrap().count, 0);
+            thread::sleep(StdDuration::from_millis(200));
+            let inner = (pool.0).0.lock().unwrap();
+            assert_eq!(inner.count, 1);
+            assert_eq!(inner.pool.len(), 1);
+        }
+        #[test]
+        fn should_close_connections_to_stale_addresses() {
+            let pool = Pool::new_manual(1, 1, Opts {
+                pool_opts: PoolOpts {
+                    dns_ttl_ms: Some(60000),
+                    ..PoolOpts::default()
+                },
+                ..get_opts()
+            }).unwrap();
+            let id = (pool.0).0.lock().unwrap().pool[0].id();
+            pool.maintain();
+            assert_eq!((pool.0).0.lock().unwrap().pool[0].id(), id);
+            {
+                let mut inner = (pool.0).0.lock().unwrap();
+                inner.resolved_at = Some(Instant::now());
+                inner.resolved_addrs = Some(vec!["10.255.255.1:3307".parse().unwrap()]);
+            }
+            pool.maintain();
+            let inner = (pool.0).0.lock().unwrap();
+            assert_eq!(inner.count, 1);
+            assert!(inner.pool[0].id() != id);
+        }
+        #[test]
+        fn should_retry_failed_checkouts() {
+            let opts = Opts {
+                pool_opts: PoolOpts {
+                    retry_policy: RetryPolicy {
+                        max_attempts: 3,
+                        backoff_ms: 100,
+                        ..RetryPolicy::default()
+                    },
+                    ..PoolOpts::default()
+                },
+                ..get_opts()
+            };
+            let start = Instant::now();
+            assert!(Pool::new_manual(1, 1, Opts { tcp_port: 1, ..opts.clone() }).is_err());
+            assert!(start.elapsed() >= StdDuration::from_millis(150));
+            let pool = Pool::new_manual(0, 1, opts).unwrap();
+            assert_eq!(pool.exec_first::<u8, _, _>("SELECT 1", ()).unwrap(), Some(1));
+            (pool.0).0.lock().unwrap().opts.tcp_port = 1;
+            pool.clear().unwrap();
+            let start = Instant::now();
+            assert!(pool.get_conn().is_err());
+            // Two retries after at least 50ms and 100ms.
+            assert!(start.elapsed() >= StdDuration::from_millis(150));
+        }
+        #[test]
+        fn should_run_scope_and_discard_conn_on_panic() {
+            let pool = Pool::new_manual(1, 1, get_opts()).unwrap();
+            let id = pool.scope(|conn| Ok(conn.as_ref().id())).unwrap();
+            let value = pool.try_scope(1000, |conn| {
+                assert_eq!(conn.as_ref().id(), id);
+                conn.exec_first::<u8, _, _>("SELECT 1", ())
+            }).unwrap();
+            assert_eq!(value, Some(1));
+            assert_eq!((pool.0).0.lock().unwrap().pool.len(), 1);
+
+            let pool_clone = pool.clone();
+            let result = thread::spawn(move || {
+                pool_clone.scope(|conn| -> MyResult<()> {
+                    let mut result = conn.query("SELECT 1 UNION ALL SELECT 2").unwrap();
+                    result.next();
+                    panic!("panic in scope");
+                })
+            }).join();
+            assert!(result.is_err());
+            {
+                let inner = (pool.0).0.lock().unwrap();
+                assert_eq!(inner.count, 0);
+                assert_eq!(inner.pool.len(), 0);
+            }
+            let id2 = pool.scope(|conn| Ok(conn.as_ref().id())).unwrap();
+            assert!(id2 != id);
+
+            let _conn = pool.get_conn().unwrap();
+            match pool.try_scope(100, |_| Ok(())) {
+                Err(Error::DriverError(DriverError::Timeout)) => (),
+                _ => panic!("Expected Timeout error"),
+            }
+        }
+        #[test]
+        fn should_divide_limits_between_shards() {
+            assert_eq!((0..3).map(|i| shard_size(10, 3, i)).collect::<Vec<_>>(), vec![4, 3, 3]);
+            assert_eq!((0..4).map(|i| shard_size(2, 4, i)).collect::<Vec<_>>(), vec![1, 1, 0, 0]);
+            assert!(ShardedPool::new_manual(0, 0, 4, get_opts()).is_err());
+            assert!(ShardedPool::new_manual(4, 0, 2, get_opts()).is_err());
+        }
+        #[test]
+        fn should_steal_connections_from_other_shards() {
+            let pool = ShardedPool::new(Opts {
+                pool_opts: PoolOpts {
+                    min: 0,
+                    max: 4,
+                    shards: 2,
+                    ..PoolOpts::default()
+                },
+                ..get_opts()
+            }).unwrap();
+            assert_eq!(pool.shards().len(), 2);
+            // Every shard holds at most two connections.
+            let conns = (0..4).map(|_| pool.try_get_conn(357).unwrap()).collect::<Vec<_>>();
+            match pool.try_get_conn(357) {
+                Err(Error::DriverError(DriverError::Timeout)) => (),
+                _ => panic!("Expected Timeout error"),
+            }
+            drop(conns);
+            for shard in pool.shards() {
+                let _conn1 = shard.try_get_conn(357).unwrap();
+                let _conn2 = shard.try_get_conn(357).unwrap();
+            }
+            let value = pool.scope(|conn| conn.exec_first::<u8, _, _>("SELECT 1", ())).unwrap();
+            assert_eq!(value, Some(1));
+            pool.clear().unwrap();
+        }
+        #[test]
+        fn should_clear_pool_and_invalidate_conn() {
+            let pool = Pool::new_manual(2, 2, get_opts()).unwrap();
+            let mut conn = pool.get_conn().unwrap();
+            let id = conn.as_ref().id();
+            conn.invalidate();
+            drop(conn);
+            assert_eq!((pool.0).0.lock().unwrap().count, 1);
+            let conn = pool.get_conn().unwrap();
+            assert!(conn.as_ref().id() != id);
+            let id = conn.as_ref().id();
+            pool.clear().unwrap();
+            {
+                let inner = (pool.0).0.lock().unwrap();
+                assert_eq!(inner.count, 1);
+                assert_eq!(inner.pool.len(), 0);
+            }
+            // Connection checked out before `clear` is not returned to the pool.
+            drop(conn);
+            assert_eq!((pool.0).0.lock().unwrap().count, 0);
+            let conn = pool.get_conn().unwrap();
+            assert!(conn.as_ref().id() != id);
+        }
+        #[test]
+        fn should_exec_and_collect_typed_rows_on_Pool() {
+            let pool = Pool::new_manual(1, 1, get_opts()).unwrap();
+            assert_eq!(pool.exec::<(u8, String), _, _>("SELECT ?, ?", (1, "foo")).unwrap(),
+                       vec![(1, "foo".to_string())]);
+            assert_eq!(pool.exec_first::<u8, _, _>("SELECT 42", ()).unwrap(), Some(42));
+            pool.exec_drop("DO 1", ()).unwrap();
+            let mut conn = pool.get_conn().unwrap();
+            assert_eq!(conn.exec_first::<u8, _, _>("SELECT 42", ()).unwrap(), Some(42));
+        }
+        #[test]
+        fn should_use_pool_wide_checkout_timeout() {
+            let pool = Pool::new_manual(0, 1, get_opts()).unwrap();
+            pool.set_checkout_timeout(Some(357)).unwrap();
+            let conn1 = pool.get_conn().unwrap();
+            match pool.get_conn() {
+                Err(Error::DriverError(DriverError::Timeout)) => assert!(true),
+                _ => assert!(false),
+            }
+            drop(conn1);
+            assert!(pool.get_conn().is_ok());
+        }
+        #[test]
+        fn should_serve_waiters_in_fifo_order() {
+            let pool = Pool::new_manual(1, 1, get_opts()).unwrap();
+            let order = Arc::new(Mutex::new(Vec::new()));
+            let conn = pool.get_conn().unwrap();
+            let mut threads = Vec::new();
+            for i in 0..3 {
+                let pool = pool.clone();
+                let order = order.clone();
+                threads.push(thread::spawn(move || {
+                    let _conn = pool.get_conn().unwrap();
+                    order.lock().unwrap().push(i);
+                }));
+                // Make sure that threads are waiting in order of spawning.
+                thread::sleep(StdDuration::from_millis(100));
+            }
+            drop(conn);
+            for t in threads.into_iter() {
+                assert!(t.join().is_ok());
+            }
+            assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+        }
+        #[test]
         fn should_execute_statements_on_PooledConn() {
             let pool = Pool::new(get_opts()).unwrap();
             let mut threads = Vec::new();
@@ -530,12 +1833,72 @@ mod test {
             }
         }
         #[test]
+        fn should_return_conn_before_buffered_rows_are_processed() {
+            let pool = Pool::new_manual(1, 1, get_opts()).unwrap();
+            let rows = pool.prep_exec_buffered("SELECT ? UNION ALL SELECT ?", (1, 2)).unwrap();
+            let mut conn = pool.try_get_conn(100).unwrap();
+            let values = rows.into_iter().map(|row| from_value::<u8>(row.unwrap().remove(0)));
+            assert_eq!(values.collect::<Vec<_>>(), vec![1, 2]);
+            assert_eq!(conn.prep_exec_buffered("SELECT 1 FROM DUAL WHERE FALSE", ())
+                           .unwrap()
+                           .len(),
+                       0);
+        }
+        #[test]
+        fn should_prepare_statement_on_all_connections() {
+            let pool = Pool::new_manual(3, 3, get_opts()).unwrap();
+            assert!(pool.prepare_on_all("SELECT ?").is_ok());
+            assert!(pool.prepare_on_all("SELECT ?").is_ok());
+            match pool.prepare_on_all("SELEC ?") {
+                Err(ref errors) if errors.len() == 3 => {
+                    assert!(errors.iter().all(|&(_, ref err)| err.server_error().is_some()));
+                },
+                _ => panic!("statement should be rejected on every connection"),
+            }
+            {
+                let inner = (pool.0).0.lock().unwrap();
+                assert_eq!(inner.stmts, vec!["SELECT ?".to_owned()]);
+                assert!(inner.pool.iter().all(|conn| conn.has_stmt("SELECT ?")));
+            }
+            let conns = (0..3).map(|_| pool.get_conn().unwrap()).collect::<Vec<_>>();
+            assert!(conns.iter().all(|conn| conn.as_ref().has_stmt("SELECT ?")));
+        }
+        #[test]
+        fn should_reset_connection_on_return() {
+            let pool = Pool::new_manual(1, 1, get_opts()).unwrap();
+            pool.set_reset_on_return(true).unwrap();
+            let id = {
+                let mut conn = pool.get_conn().unwrap();
+                conn.query("SET @a = 42").unwrap();
+                conn.id()
+            };
+            let mut conn = pool.get_conn().unwrap();
+            assert_eq!(conn.id(), id);
+            let mut result = conn.query("SELECT @a").unwrap();
+            assert_eq!(result.next().unwrap().unwrap().unwrap(), vec![Value::NULL]);
+        }
+        #[test]
+        fn should_restore_multi_statements_on_return() {
+            let pool = Pool::new_manual(1, 1, Opts { multi_statements: false, ..get_opts() })
+                .unwrap();
+            let id = {
+                let mut conn = pool.get_conn().unwrap();
+                conn.as_mut().set_multi_statements(true).unwrap();
+                assert_eq!(conn.as_mut().query_multi("SELECT 1; SELECT 2").unwrap().count(), 2);
+                conn.id()
+            };
+            let mut conn = pool.get_conn().unwrap();
+            assert_eq!(conn.id(), id);
+            assert!(conn.as_mut().query_multi("SELECT 1; SELECT 2").is_err());
+            assert!(conn.query("SELECT 1; SELECT 2").is_err());
+        }
+        #[test]
         fn should_start_transaction_on_Pool() {
             let pool = Pool::new(get_opts()).unwrap();
             pool.prepare("CREATE TEMPORARY TABLE x.tbl(a INT)").ok().map(|mut stmt| {
                 assert!(stmt.execute(()).is_ok());
             });
-            assert!(pool.start_transaction(false, None, None).and_then(|mut t| {
+            assert!(pool.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(1)").is_ok());
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(2)").is_ok());
                 t.commit()
@@ -546,7 +1909,7 @@ mod test {
                     assert_eq!(from_value::<u8>(x.take(0).unwrap()), 2u8);
                 }
             });
-            assert!(pool.start_transaction(false, None, None).and_then(|mut t| {
+            assert!(pool.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(1)").is_ok());
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(2)").is_ok());
                 t.rollback()
@@ -557,7 +1920,7 @@ mod test {
                     assert_eq!(from_value::<u8>(x.take(0).unwrap()), 2u8);
                 }
             });
-            assert!(pool.start_transaction(false, None, None).and_then(|mut t| {
+            assert!(pool.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(1)").is_ok());
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(2)").is_ok());
                 Ok(())
@@ -574,7 +1937,7 @@ mod test {
             let pool = Pool::new(get_opts()).unwrap();
             let mut conn = pool.get_conn().unwrap();
             assert!(conn.query("CREATE TEMPORARY TABLE x.tbl(a INT)").is_ok());
-            assert!(conn.start_transaction(false, None, None).and_then(|mut t| {
+            assert!(conn.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(1)").is_ok());
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(2)").is_ok());
                 t.commit()
@@ -583,7 +1946,7 @@ mod test {
                 let mut x = x.unwrap();
                 assert_eq!(from_value::<u8>(x.take(0).unwrap()), 2u8);
             }
-            assert!(conn.start_transaction(false, None, None).and_then(|mut t| {
+            assert!(conn.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(1)").is_ok());
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(2)").is_ok());
                 t.rollback()
@@ -592,7 +1955,7 @@ mod test {
                 let mut x = x.unwrap();
                 assert_eq!(from_value::<u8>(x.take(0).unwrap()), 2u8);
             }
-            assert!(conn.start_transaction(false, None, None).and_then(|mut t| {
+            assert!(conn.start_transaction(TxOpts::new()).and_then(|mut t| {
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(1)").is_ok());
                 assert!(t.query("INSERT INTO x.tbl(a) VALUES(2)").is_ok());
                 Ok(())
@@ -0,0 +1,221 @@
+//! Establishes TCP connections through a SOCKS5 or HTTP `CONNECT` proxy.
+
+use std::io;
+use std::io::{Read, Write};
+use std::net;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use byteorder::BigEndian as BE;
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use super::opts::{Opts, ProxyOpts};
+
+fn proxy_error<T: Into<String>>(msg: T) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, msg.into())
+}
+
+/// Connects to `host:port` through `proxy`.
+///
+/// Socket related options of `opts` are applied to connection to the proxy itself.
+pub fn connect(proxy: &ProxyOpts,
+               host: &str,
+               port: u16,
+               opts: &Opts) -> io::Result<net::TcpStream> {
+    match *proxy {
+        ProxyOpts::Socks5 { host: ref proxy_host, port: proxy_port, ref auth } => {
+            let mut stream = try!(super::tcp_connect(proxy_host, proxy_port, opts));
+            let auth = auth.as_ref().map(|&(ref user, ref pass)| (&**user, &**pass));
+            try!(socks5_handshake(&mut stream, host, port, auth));
+            Ok(stream)
+        },
+        ProxyOpts::Http { host: ref proxy_host, port: proxy_port } => {
+            let mut stream = try!(super::tcp_connect(proxy_host, proxy_port, opts));
+            try!(http_connect(&mut stream, host, port));
+            Ok(stream)
+        },
+    }
+}
+
+/// Performs [SOCKS5](https://tools.ietf.org/html/rfc1928) `CONNECT` handshake.
+pub fn socks5_handshake<T>(stream: &mut T,
+                           host: &str,
+                           port: u16,
+                           auth: Option<(&str, &str)>) -> io::Result<()>
+where T: Read + Write {
+    if auth.is_some() {
+        try!(stream.write_all(&[5, 2, 0x00, 0x02]));
+    } else {
+        try!(stream.write_all(&[5, 1, 0x00]));
+    }
+    if try!(stream.read_u8()) != 5 {
+        return Err(proxy_error("Invalid SOCKS5 proxy response"));
+    }
+    match (try!(stream.read_u8()), auth) {
+        (0x00, _) => (),
+        (0x02, Some((user, pass))) => {
+            // https://tools.ietf.org/html/rfc1929
+            if user.len() > 255 || pass.len() > 255 {
+                return Err(proxy_error("SOCKS5 username or password is too long"));
+            }
+            let mut request = Vec::with_capacity(3 + user.len() + pass.len());
+            request.push(1);
+            request.push(user.len() as u8);
+            request.extend_from_slice(user.as_bytes());
+            request.push(pass.len() as u8);
+            request.extend_from_slice(pass.as_bytes());
+            try!(stream.write_all(&*request));
+            let _version = try!(stream.read_u8());
+            if try!(stream.read_u8()) != 0 {
+                return Err(proxy_error("SOCKS5 proxy authentication failed"));
+            }
+        },
+        _ => return Err(proxy_error("SOCKS5 proxy does not support offered authentication")),
+    }
+
+    let mut request = vec![5, 1, 0];
+    if let Ok(addr) = Ipv4Addr::from_str(host) {
+        request.push(1);
+        request.extend_from_slice(&addr.octets());
+    } else if let Ok(addr) = Ipv6Addr::from_str(host) {
+        request.push(4);
+        for segment in addr.segments().iter() {
+            try!(request.write_u16::<BE>(*segment));
+        }
+    } else {
+        if host.len() > 255 {
+            return Err(proxy_error("Hostname is too long for SOCKS5 proxy"));
+        }
+        request.push(3);
+        request.push(host.len() as u8);
+        request.extend_from_slice(host.as_bytes());
+    }
+    try!(request.write_u16::<BE>(port));
+    try!(stream.write_all(&*request));
+
+    if try!(stream.read_u8()) != 5 {
+        return Err(proxy_error("Invalid SOCKS5 proxy response"));
+    }
+    match try!(stream.read_u8()) {
+        0 => (),
+        code => return Err(proxy_error(format!("SOCKS5 proxy returned error code {}", code))),
+    }
+    let _reserved = try!(stream.read_u8());
+    let addr_len = match try!(stream.read_u8()) {
+        1 => 4,
+        3 => try!(stream.read_u8()) as usize,
+        4 => 16,
+        _ => return Err(proxy_error("Invalid SOCKS5 proxy response")),
+    };
+    // Skip bound address and port.
+    let mut buf = [0u8; 257];
+    try!(stream.read_exact(&mut buf[..addr_len + 2]));
+    Ok(())
+}
+
+/// Performs HTTP `CONNECT` request.
+pub fn http_connect<T>(stream: &mut T, host: &str, port: u16) -> io::Result<()>
+where T: Read + Write {
+    let authority = if Ipv6Addr::from_str(host).is_ok() {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    try!(write!(stream, "CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", authority));
+    try!(stream.flush());
+
+    // Read response byte by byte so that no byte of mysql protocol will be consumed.
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() > 8192 {
+            return Err(proxy_error("HTTP proxy response is too long"));
+        }
+        response.push(try!(stream.read_u8()));
+    }
+    let status_line = response.split(|x| *x == b'\n').next().unwrap_or(&[]);
+    let status = String::from_utf8_lossy(status_line);
+    let mut parts = status.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(version), Some("200")) if version.starts_with("HTTP/1.") => Ok(()),
+        _ => Err(proxy_error(format!("HTTP proxy refused connection: {}", status.trim()))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::io::{Read, Write};
+    use super::{http_connect, socks5_handshake};
+
+    /// In-memory stream with predefined input.
+    struct MockStream {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(input: &[u8]) -> MockStream {
+            MockStream {
+                input: io::Cursor::new(input.to_vec()),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_perform_socks5_handshake() {
+        let mut stream = MockStream::new(&[5, 0, 5, 0, 0, 1, 127, 0, 0, 1, 0x0c, 0xea]);
+        socks5_handshake(&mut stream, "db.local", 3306, None).unwrap();
+        let mut expected = vec![5, 1, 0, 5, 1, 0, 3, 8];
+        expected.extend_from_slice(b"db.local");
+        expected.extend_from_slice(&[0x0c, 0xea]);
+        assert_eq!(stream.output, expected);
+    }
+
+    #[test]
+    fn should_perform_socks5_handshake_with_auth() {
+        let mut stream = MockStream::new(&[5, 2, 1, 0, 5, 0, 0, 1, 127, 0, 0, 1, 0x0c, 0xea]);
+        socks5_handshake(&mut stream, "10.0.0.1", 3306, Some(("usr", "pw"))).unwrap();
+        assert_eq!(stream.output, vec![5, 2, 0, 2,
+                                       1, 3, b'u', b's', b'r', 2, b'p', b'w',
+                                       5, 1, 0, 1, 10, 0, 0, 1, 0x0c, 0xea]);
+    }
+
+    #[test]
+    fn should_return_socks5_error() {
+        let mut stream = MockStream::new(&[5, 0, 5, 5, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert!(socks5_handshake(&mut stream, "db.local", 3306, None).is_err());
+        let mut stream = MockStream::new(&[5, 0xff]);
+        assert!(socks5_handshake(&mut stream, "db.local", 3306, None).is_err());
+    }
+
+    #[test]
+    fn should_perform_http_connect() {
+        let mut stream = MockStream::new(b"HTTP/1.1 200 Connection established\r\n\r\n\x0a");
+        http_connect(&mut stream, "db.local", 3306).unwrap();
+        assert_eq!(stream.output,
+                   b"CONNECT db.local:3306 HTTP/1.1\r\nHost: db.local:3306\r\n\r\n".to_vec());
+        // Handshake packet should not be consumed.
+        assert_eq!(stream.input.position(), 39);
+
+        let mut stream = MockStream::new(b"HTTP/1.1 403 Forbidden\r\n\r\n");
+        assert!(http_connect(&mut stream, "::1", 3306).is_err());
+        assert_eq!(stream.output,
+                   b"CONNECT [::1]:3306 HTTP/1.1\r\nHost: [::1]:3306\r\n\r\n".to_vec());
+    }
+}
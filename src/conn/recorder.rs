@@ -0,0 +1,146 @@
+//! Recording of executed commands, e.g. for golden-file tests or audit trails.
+
+use std::fmt;
+use std::time::Duration;
+
+use super::interceptor::QueryKind;
+use super::sink::SharedSink;
+use super::super::value::Params;
+
+/// Command sent to the server and a summary of its result.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Record {
+    pub kind: QueryKind,
+    /// SQL text of a query or of a prepared statement after interceptors. Named parameters
+    /// are kept as is, while server receives `?` placeholders instead. `QueryKind::Prepare`
+    /// is recorded even if statement was taken from the statement cache.
+    pub query: String,
+    /// Parameters of `QueryKind::Execute` rendered as SQL literals. Named parameters are
+    /// rendered as `:name = value` in order of their occurrence.
+    pub params: Vec<String>,
+    pub duration: Duration,
+    /// Number of columns of a result set or of a prepared statement.
+    pub columns: usize,
+    pub affected_rows: u64,
+    pub last_insert_id: u64,
+    pub warnings: u16,
+    /// Error message if command failed.
+    pub error: Option<String>,
+}
+
+/// Renders parameters of an execution for `Record::params`.
+pub fn render_params(params: &Params, names: Option<&Vec<String>>) -> Vec<String> {
+    match *params {
+        Params::Empty => Vec::new(),
+        Params::Positional(ref values) => values.iter().map(|value| value.as_sql(false)).collect(),
+        Params::Named(ref map) => {
+            let render = |name: &String| {
+                match map.get(name) {
+                    Some(value) => format!(":{} = {}", name, value.as_sql(false)),
+                    None => format!(":{} = ?", name),
+                }
+            };
+            match names {
+                Some(names) => names.iter().map(render).collect(),
+                None => {
+                    let mut names = map.keys().collect::<Vec<_>>();
+                    names.sort();
+                    names.into_iter().map(render).collect()
+                },
+            }
+        },
+    }
+}
+
+/// Formats a record as a single line without duration, so output of a deterministic test
+/// could be compared with a golden file, e.g.
+/// `EXECUTE SELECT ?, ? -- params: 1, 'foo' -- columns: 2`.
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            QueryKind::Query => "QUERY",
+            QueryKind::Prepare => "PREPARE",
+            QueryKind::Execute => "EXECUTE",
+        };
+        try!(write!(f, "{} {}", kind, self.query.replace("\r", "\\r").replace("\n", "\\n")));
+        if !self.params.is_empty() {
+            try!(write!(f, " -- params: {}", self.params.join(", ")));
+        }
+        match self.error {
+            Some(ref error) => write!(f, " -- error: {}", error.replace("\n", "\\n")),
+            None if self.columns > 0 => write!(f, " -- columns: {}", self.columns),
+            None => {
+                write!(f,
+                       " -- affected_rows: {}, last_insert_id: {}, warnings: {}",
+                       self.affected_rows,
+                       self.last_insert_id,
+                       self.warnings)
+            },
+        }
+    }
+}
+
+/// Shareable sink of [`Record`](struct.Record.html)s which could be stored in
+/// [`Opts::recorder`](struct.Opts.html#structfield.recorder).
+///
+/// Sink is called synchronously after every text query, statement preparation and statement
+/// execution, so it should be fast.
+///
+/// ```ignore
+/// let opts = Opts {
+///     recorder: Some(Recorder::to_writer(File::create("queries.log").unwrap())),
+///     ..get_opts()
+/// };
+/// ```
+pub type Recorder = SharedSink<Record>;
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use super::{Record, render_params};
+    use super::super::interceptor::QueryKind;
+    use super::super::super::value::{Params, Value};
+
+    #[test]
+    fn should_format_record() {
+        let mut record = Record {
+            kind: QueryKind::Execute,
+            query: "SELECT ?,\n?".into(),
+            params: vec!["1".into(), "'foo'".into()],
+            duration: Duration::from_millis(3),
+            columns: 2,
+            affected_rows: 0,
+            last_insert_id: 0,
+            warnings: 0,
+            error: None,
+        };
+        assert_eq!(record.to_string(), "EXECUTE SELECT ?,\\n? -- params: 1, 'foo' -- columns: 2");
+        record.kind = QueryKind::Query;
+        record.query = "INSERT INTO t VALUES (1)".into();
+        record.params.clear();
+        record.columns = 0;
+        record.affected_rows = 1;
+        record.last_insert_id = 7;
+        assert_eq!(record.to_string(),
+                   "QUERY INSERT INTO t VALUES (1) -- affected_rows: 1, last_insert_id: 7, \
+                    warnings: 0");
+        record.error = Some("ERROR 1062 (23000): Duplicate entry".into());
+        assert_eq!(record.to_string(),
+                   "QUERY INSERT INTO t VALUES (1) -- error: ERROR 1062 (23000): Duplicate entry");
+    }
+
+    #[test]
+    fn should_render_params() {
+        assert!(render_params(&Params::Empty, None).is_empty());
+        let params = Params::Positional(vec![Value::from(1), Value::NULL, Value::from("a'b")]);
+        assert_eq!(render_params(&params, None), vec!["1", "NULL", "'a\\'b'"]);
+        let mut map = HashMap::default();
+        map.insert("b".to_string(), Value::from(2));
+        map.insert("a".to_string(), Value::from(1));
+        let params = Params::Named(map);
+        let names = vec!["b".to_string(), "a".to_string(), "c".to_string()];
+        assert_eq!(render_params(&params, Some(&names)), vec![":b = 2", ":a = 1", ":c = ?"]);
+        assert_eq!(render_params(&params, None), vec![":a = 1", ":b = 2"]);
+    }
+}
@@ -0,0 +1,362 @@
+//! Pool which routes read-only queries to replicas.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use time::{Duration, SteadyTime};
+
+use super::Transaction;
+use super::TxOpts;
+use super::pool::{Pool, PooledConn};
+use super::QueryResult;
+use super::super::error::{Error, DriverError};
+use super::super::error::Result as MyResult;
+use super::super::value::{FromRow, Params};
+
+/// Where [`ReplicatedPool`](struct.ReplicatedPool.html) should send a query.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Route {
+    /// Send query to the primary.
+    Primary,
+    /// Send query to a replica (or to the primary if no replica is available).
+    Replica,
+    /// Send query to a replica if it looks read-only (see
+    /// [`is_read_only_query`](fn.is_read_only_query.html)) and to the primary otherwise.
+    Auto,
+}
+
+/// Returns `true` if `query` is a `SELECT`, `SHOW`, `DESCRIBE` or `EXPLAIN` statement
+/// which does not lock rows (`FOR UPDATE`, `FOR SHARE`, `LOCK IN SHARE MODE`) and does not
+/// write into variables or files (`INTO`).
+///
+/// Keywords are matched as whole words, so a query which mentions them in a string literal
+/// is routed to the primary.
+pub fn is_read_only_query(query: &str) -> bool {
+    let upper = query.to_uppercase();
+    let words: Vec<&str> = upper.split(|c: char| !c.is_alphanumeric() && c != '_')
+                                .filter(|word| !word.is_empty())
+                                .collect();
+    let has = |sequence: &[&str]| words.windows(sequence.len()).any(|x| x == sequence);
+    match words.first() {
+        Some(&"SELECT") => {
+            !has(&["FOR", "UPDATE"]) &&
+            !has(&["FOR", "SHARE"]) &&
+            !has(&["LOCK", "IN", "SHARE", "MODE"]) &&
+            !has(&["INTO"])
+        },
+        Some(&"SHOW") | Some(&"DESCRIBE") | Some(&"DESC") | Some(&"EXPLAIN") => true,
+        _ => false,
+    }
+}
+
+/// Returns `true` if error means that a pool could not reach its server.
+fn is_connectivity_error(err: &Error) -> bool {
+    match *err {
+        Error::IoError(_) |
+        Error::DriverError(DriverError::CouldNotConnect(_)) |
+        Error::DriverError(DriverError::Timeout) => true,
+        _ => false,
+    }
+}
+
+#[derive(Debug)]
+struct Replica {
+    pool: Pool,
+    /// Replica won't be used until this moment after a connectivity error.
+    unhealthy_until: Mutex<Option<SteadyTime>>,
+}
+
+impl Replica {
+    fn is_healthy(&self) -> bool {
+        match self.unhealthy_until.lock() {
+            Ok(until) => until.map(|until| SteadyTime::now() >= until).unwrap_or(true),
+            _ => false,
+        }
+    }
+
+    fn mark_unhealthy(&self, retry_interval: Duration) {
+        if let Ok(mut until) = self.unhealthy_until.lock() {
+            *until = Some(SteadyTime::now() + retry_interval);
+        }
+    }
+}
+
+/// Wraps one primary [`Pool`](../pool/struct.Pool.html) and a number of replica pools.
+///
+/// Read-only queries are distributed among replicas in round-robin fashion and everything
+/// else goes to the primary. Replica which failed with a connectivity error will not be used
+/// for `replica_retry_interval` (5 seconds by default); if every replica is unavailable then
+/// query will be sent to the primary.
+///
+/// ```ignore
+/// let pool = ReplicatedPool::new(try!(Pool::new(primary_opts)),
+///                                vec![try!(Pool::new(replica1_opts)),
+///                                     try!(Pool::new(replica2_opts))]);
+/// // Goes to a replica
+/// let users: Vec<(u32, String)> = try!(pool.exec("SELECT id, name FROM users", ()));
+/// // Goes to the primary
+/// try!(pool.exec_drop("UPDATE users SET name = ? WHERE id = ?", ("foo", 1)));
+/// // Read your own writes
+/// let name: Option<String> = try!(pool.exec_first_on(Route::Primary,
+///                                                    "SELECT name FROM users WHERE id = ?",
+///                                                    (1,)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReplicatedPool {
+    primary: Pool,
+    replicas: Arc<Vec<Replica>>,
+    next_replica: Arc<AtomicUsize>,
+    retry_interval: Duration,
+}
+
+impl ReplicatedPool {
+    /// Creates new `ReplicatedPool`.
+    pub fn new(primary: Pool, replicas: Vec<Pool>) -> ReplicatedPool {
+        let replicas = replicas.into_iter().map(|pool| {
+            Replica {
+                pool: pool,
+                unhealthy_until: Mutex::new(None),
+            }
+        }).collect();
+        ReplicatedPool {
+            primary: primary,
+            replicas: Arc::new(replicas),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            retry_interval: Duration::seconds(5),
+        }
+    }
+
+    /// Sets for how long a replica which failed with a connectivity error will not be used.
+    pub fn with_replica_retry_interval(mut self, retry_interval_ms: u32) -> ReplicatedPool {
+        self.retry_interval = Duration::milliseconds(retry_interval_ms as i64);
+        self
+    }
+
+    /// Returns primary pool.
+    pub fn primary(&self) -> &Pool {
+        &self.primary
+    }
+
+    /// Returns replica pools.
+    pub fn replicas(&self) -> Vec<&Pool> {
+        self.replicas.iter().map(|replica| &replica.pool).collect()
+    }
+
+    /// Calls `f` with pools chosen according to `read_only` until it succeeds or fails with
+    /// an error which is not related to connectivity.
+    fn route<'a, T, F>(&'a self, read_only: bool, mut f: F) -> MyResult<T>
+    where F: FnMut(&'a Pool) -> MyResult<T> {
+        if read_only && !self.replicas.is_empty() {
+            let len = self.replicas.len();
+            let start = self.next_replica.fetch_add(1, Ordering::Relaxed);
+            for i in 0..len {
+                let replica = &self.replicas[(start + i) % len];
+                if !replica.is_healthy() {
+                    continue;
+                }
+                match f(&replica.pool) {
+                    Err(ref err) if is_connectivity_error(err) => {
+                        replica.mark_unhealthy(self.retry_interval);
+                    },
+                    result => return result,
+                }
+            }
+        }
+        f(&self.primary)
+    }
+
+    fn is_read_only(route: Route, query: &str) -> bool {
+        match route {
+            Route::Primary => false,
+            Route::Replica => true,
+            Route::Auto => is_read_only_query(query),
+        }
+    }
+
+    /// Gives you a [`PooledConn`](../pool/struct.PooledConn.html) from a replica if
+    /// `read_only` is `true` and from the primary otherwise.
+    ///
+    /// Connection is checked via [`Conn::ping`](../struct.Conn.html#method.ping), so
+    /// unavailable replica will be skipped.
+    pub fn get_conn(&self, read_only: bool) -> MyResult<PooledConn> {
+        self.route(read_only, |pool| pool.get_conn())
+    }
+
+    /// Same as [`Pool::prep_exec`](../pool/struct.Pool.html#method.prep_exec) but query
+    /// will be routed according to `Route::Auto`.
+    pub fn prep_exec<'a, A, T>(&'a self, query: A, params: T) -> MyResult<QueryResult<'a>>
+    where A: AsRef<str>,
+          T: Into<Params> {
+        self.prep_exec_on(Route::Auto, query, params)
+    }
+
+    /// Same as [`Pool::prep_exec`](../pool/struct.Pool.html#method.prep_exec) but query
+    /// will be routed according to `route`.
+    pub fn prep_exec_on<'a, A, T>(&'a self,
+                                  route: Route,
+                                  query: A,
+                                  params: T) -> MyResult<QueryResult<'a>>
+    where A: AsRef<str>,
+          T: Into<Params> {
+        let query = query.as_ref();
+        let params = params.into();
+        let read_only = ReplicatedPool::is_read_only(route, query);
+        self.route(read_only, |pool| pool.prep_exec(query, params.clone()))
+    }
+
+    /// Shortcut for `try!(pool.prep_exec(..)).collect_rows()`.
+    pub fn exec<T, A, P>(&self, query: A, params: P) -> MyResult<Vec<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params)).collect_all_rows()
+    }
+
+    /// Shortcut for `try!(pool.prep_exec_on(..)).collect_rows()`.
+    pub fn exec_on<T, A, P>(&self, route: Route, query: A, params: P) -> MyResult<Vec<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec_on(route, query, params)).collect_all_rows()
+    }
+
+    /// Shortcut for `try!(pool.prep_exec(..)).first_row()`.
+    pub fn exec_first<T, A, P>(&self, query: A, params: P) -> MyResult<Option<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params)).first_row_checked()
+    }
+
+    /// Shortcut for `try!(pool.prep_exec_on(..)).first_row()`.
+    pub fn exec_first_on<T, A, P>(&self, route: Route, query: A, params: P) -> MyResult<Option<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec_on(route, query, params)).first_row_checked()
+    }
+
+    /// Same as [`Conn::query_map`](../struct.Conn.html#method.query_map) but text query
+    /// will be routed according to `Route::Auto`.
+    pub fn query_map<T, U, Q, F>(&self, query: Q, f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          Q: AsRef<str>,
+          F: FnMut(T) -> U {
+        self.query_map_on(Route::Auto, query, f)
+    }
+
+    /// Same as [`Conn::query_map`](../struct.Conn.html#method.query_map) but text query
+    /// will be routed according to `route`.
+    pub fn query_map_on<T, U, Q, F>(&self, route: Route, query: Q, mut f: F) -> MyResult<Vec<U>>
+    where T: FromRow,
+          Q: AsRef<str>,
+          F: FnMut(T) -> U {
+        let query = query.as_ref();
+        let read_only = ReplicatedPool::is_read_only(route, query);
+        self.route(read_only, |pool| try!(pool.get_conn()).query_map(query, &mut f))
+    }
+
+    /// Executes statement and drops its result (routed according to `Route::Auto`).
+    pub fn exec_drop<A, P>(&self, query: A, params: P) -> MyResult<()>
+    where A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.prep_exec(query, params));
+        Ok(())
+    }
+
+    /// Starts transaction on the primary.
+    pub fn start_transaction(&self, tx_opts: TxOpts) -> MyResult<Transaction> {
+        self.primary.start_transaction(tx_opts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_read_only_query, ReplicatedPool, Route};
+    use conn::Opts;
+    use conn::pool::Pool;
+    use std::default::Default;
+
+    fn get_opts() -> Opts {
+        let pwd: String = ::std::env::var("MYSQL_SERVER_PASS").unwrap_or("password".to_string());
+        let port: u16 = ::std::env::var("MYSQL_SERVER_PORT").ok()
+                                   .map(|my_port| my_port.parse().ok().unwrap_or(3307))
+                                   .unwrap_or(3307);
+        Opts {
+            user: Some("root".to_string()),
+            pass: Some(pwd),
+            ip_or_hostname: Some("127.0.0.1".to_string()),
+            tcp_port: port,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_detect_read_only_queries() {
+        assert!(is_read_only_query("SELECT 1"));
+        assert!(is_read_only_query("  select * from t"));
+        assert!(is_read_only_query("(SELECT 1) UNION (SELECT 2)"));
+        assert!(is_read_only_query("SHOW TABLES"));
+        assert!(is_read_only_query("explain select 1"));
+        assert!(!is_read_only_query("SELECT * FROM t FOR UPDATE"));
+        assert!(!is_read_only_query("select * from t lock in share mode"));
+        assert!(!is_read_only_query("SELECT 1 INTO @a"));
+        assert!(!is_read_only_query("INSERT INTO t SELECT * FROM u"));
+        assert!(!is_read_only_query("UPDATE t SET a = 1"));
+        assert!(!is_read_only_query("SELECTX"));
+        assert!(!is_read_only_query("SELECT * FROM t FOR SHARE"));
+        assert!(!is_read_only_query("SELECT * FROM t FOR\n  UPDATE NOWAIT"));
+        assert!(!is_read_only_query("SELECT * FROM t FOR UPDATE\nSKIP LOCKED"));
+        assert!(!is_read_only_query("SELECT * FROM t LOCK\tIN SHARE\nMODE"));
+        assert!(!is_read_only_query("SELECT 1 INTO\n@a"));
+        assert!(!is_read_only_query("SELECT * FROM t INTO\tOUTFILE '/tmp/t'"));
+        assert!(is_read_only_query("SELECT for_update, into_x FROM t"));
+    }
+
+    fn role_pool(role: &str) -> Pool {
+        Pool::new_manual(1, 2, Opts {
+            init: vec![format!("SET @role = '{}'", role)],
+            ..get_opts()
+        }).unwrap()
+    }
+
+    #[test]
+    fn should_route_queries() {
+        let pool = ReplicatedPool::new(role_pool("primary"),
+                                       vec![role_pool("replica1"), role_pool("replica2")]);
+        let mut roles = Vec::new();
+        for _ in 0..4 {
+            roles.push(pool.exec_first::<String, _, _>("SELECT @role", ()).unwrap().unwrap());
+        }
+        roles.sort();
+        assert_eq!(roles, vec!["replica1", "replica1", "replica2", "replica2"]);
+        let role: Option<String> = pool.exec_first_on(Route::Primary, "SELECT @role", ())
+            .unwrap();
+        assert_eq!(role, Some("primary".to_string()));
+        let roles = pool.query_map("SELECT @role", |role: String| role).unwrap();
+        assert!(roles[0].starts_with("replica"));
+        let roles = pool.query_map_on(Route::Primary, "SELECT @role", |role: String| role)
+            .unwrap();
+        assert_eq!(roles, vec!["primary"]);
+        let mut conn = pool.get_conn(false).unwrap();
+        let role: Option<String> = conn.exec_first("SELECT @role", ()).unwrap();
+        assert_eq!(role, Some("primary".to_string()));
+        // Query errors should not cause fallback.
+        assert!(pool.exec_drop("SELECT * FROM non_existent_table", ()).is_err());
+        assert!(pool.replicas.iter().all(|replica| replica.is_healthy()));
+    }
+
+    #[test]
+    fn should_fall_back_to_primary() {
+        let dead_replica = Pool::new_manual(0, 1, Opts {
+            tcp_port: 1,
+            ..get_opts()
+        }).unwrap();
+        let pool = ReplicatedPool::new(role_pool("primary"), vec![dead_replica]);
+        for _ in 0..2 {
+            let role: Option<String> = pool.exec_first("SELECT @role", ()).unwrap();
+            assert_eq!(role, Some("primary".to_string()));
+        }
+        assert!(!pool.replicas[0].is_healthy());
+    }
+}
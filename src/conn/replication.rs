@@ -0,0 +1,391 @@
+//! Typed results of replication related `SHOW` statements.
+//!
+//! Column names differ across server versions (e.g. `Master_Host` was renamed to `Source_Host`
+//! in MySQL 8.0.22), so columns are looked up by every known name.
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::{Conn, Row};
+use super::super::error::Error;
+use super::super::error::Result as MyResult;
+use super::super::error::ServerError;
+use super::super::value::{FromValue, from_value_opt};
+
+/// Result of `SHOW MASTER STATUS` (`SHOW BINARY LOG STATUS` since MySQL 8.2).
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct MasterStatus {
+    /// Current binary log file.
+    pub file: String,
+    /// Position in the current binary log file.
+    pub position: u64,
+    pub binlog_do_db: String,
+    pub binlog_ignore_db: String,
+    /// `None` if server does not support GTIDs.
+    pub executed_gtid_set: Option<String>,
+}
+
+/// Row of `SHOW SLAVE STATUS` (`SHOW REPLICA STATUS` since MySQL 8.0.22).
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ReplicaStatus {
+    pub source_host: String,
+    pub source_port: u16,
+    pub source_user: String,
+    /// Source binary log file which I/O thread is reading.
+    pub source_log_file: String,
+    pub read_source_log_pos: u64,
+    /// Source binary log file containing the last event executed by SQL thread.
+    pub relay_source_log_file: String,
+    pub exec_source_log_pos: u64,
+    /// `true` if I/O thread is running and connected (`Yes`).
+    pub io_running: bool,
+    /// `true` if SQL thread is running (`Yes`).
+    pub sql_running: bool,
+    /// `None` if replication is not running.
+    pub seconds_behind_source: Option<u64>,
+    pub last_io_errno: u32,
+    pub last_io_error: String,
+    pub last_sql_errno: u32,
+    pub last_sql_error: String,
+    /// `None` if server does not support GTIDs.
+    pub retrieved_gtid_set: Option<String>,
+    /// `None` if server does not support GTIDs.
+    pub executed_gtid_set: Option<String>,
+    /// `None` if server does not support GTIDs.
+    pub auto_position: Option<bool>,
+    /// `None` if server does not support replication channels.
+    pub channel_name: Option<String>,
+}
+
+/// Error returned by `GtidSet::from_str`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ParseGtidSetError(String);
+
+impl fmt::Display for ParseGtidSetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid GTID set: {}", self.0)
+    }
+}
+
+impl error::Error for ParseGtidSetError {
+    fn description(&self) -> &str {
+        "Invalid GTID set"
+    }
+}
+
+/// MySQL GTID set, e.g. `3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7`.
+///
+/// Tagged GTIDs of MySQL 8.4 (`3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:tag:1-3`) are
+/// supported. Transactions of a tag are identified by `uuid:tag` source id.
+///
+/// Intervals of every source are kept sorted and merged, so textual representation is
+/// the same as the one produced by server.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct GtidSet {
+    intervals: BTreeMap<String, Vec<(u64, u64)>>,
+}
+
+impl GtidSet {
+    pub fn new() -> GtidSet {
+        GtidSet::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Returns inclusive intervals of transaction numbers of a source (`uuid` or `uuid:tag`).
+    pub fn intervals<T: AsRef<str>>(&self, source_id: T) -> &[(u64, u64)] {
+        self.intervals.get(&source_id.as_ref().to_lowercase()).map(|x| &**x).unwrap_or(&[])
+    }
+
+    /// Adds inclusive interval of transaction numbers of a source.
+    pub fn add_interval<T: AsRef<str>>(&mut self, source_id: T, start: u64, end: u64) {
+        let intervals = self.intervals
+            .entry(source_id.as_ref().to_lowercase())
+            .or_insert_with(Vec::new);
+        intervals.push((start, end));
+        intervals.sort();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(intervals.len());
+        for &(start, end) in intervals.iter() {
+            let adjacent = match merged.last() {
+                Some(last) => start <= last.1.saturating_add(1),
+                None => false,
+            };
+            if adjacent {
+                let last = merged.last_mut().unwrap();
+                if end > last.1 {
+                    last.1 = end;
+                }
+            } else {
+                merged.push((start, end));
+            }
+        }
+        *intervals = merged;
+    }
+
+    /// Returns `true` if transaction `source_id:gno` belongs to this set.
+    pub fn contains<T: AsRef<str>>(&self, source_id: T, gno: u64) -> bool {
+        self.intervals(source_id).iter().any(|&(start, end)| start <= gno && gno <= end)
+    }
+
+    /// Returns `true` if every transaction of this set belongs to `other`
+    /// (e.g. to check that a replica caught up with a source).
+    pub fn is_subset_of(&self, other: &GtidSet) -> bool {
+        self.intervals.iter().all(|(source_id, intervals)| {
+            let other = other.intervals(source_id);
+            intervals.iter().all(|&(start, end)| {
+                other.iter().any(|&(o_start, o_end)| o_start <= start && end <= o_end)
+            })
+        })
+    }
+}
+
+impl FromStr for GtidSet {
+    type Err = ParseGtidSetError;
+
+    /// Parses GTID set as returned by server (whitespace is ignored).
+    fn from_str(s: &str) -> Result<GtidSet, ParseGtidSetError> {
+        let err = || ParseGtidSetError(s.to_string());
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut set = GtidSet::new();
+        for source in s.split(',').filter(|x| !x.is_empty()) {
+            let mut parts = source.split(':');
+            let uuid = parts.next().unwrap_or("");
+            if uuid.len() != 36 {
+                return Err(err());
+            }
+            let mut source_id = uuid.to_string();
+            let mut has_intervals = false;
+            for interval in parts {
+                if !interval.starts_with(|c: char| c.is_digit(10)) {
+                    // Tag applies to intervals which follow it.
+                    if !has_intervals && source_id != uuid || !is_valid_tag(interval) {
+                        return Err(err());
+                    }
+                    source_id = format!("{}:{}", uuid, interval);
+                    has_intervals = false;
+                    continue;
+                }
+                let mut bounds = interval.splitn(2, '-');
+                let start = try!(bounds.next().unwrap_or("").parse::<u64>().map_err(|_| err()));
+                let end = match bounds.next() {
+                    Some(end) => try!(end.parse::<u64>().map_err(|_| err())),
+                    None => start,
+                };
+                if start == 0 || end < start {
+                    return Err(err());
+                }
+                set.add_interval(&*source_id, start, end);
+                has_intervals = true;
+            }
+            if !has_intervals {
+                return Err(err());
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Tag is a letter or `_` followed by up to 31 letters, digits or `_`.
+fn is_valid_tag(tag: &str) -> bool {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    tag.len() <= 32 &&
+    tag.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') &&
+    tag.chars().all(valid_char)
+}
+
+impl fmt::Display for GtidSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut last_uuid = None;
+        // Untagged source id sorts before tagged ones of the same uuid.
+        for (source_id, intervals) in self.intervals.iter() {
+            let (uuid, tag) = match source_id.find(':') {
+                Some(i) => (&source_id[..i], Some(&source_id[i..])),
+                None => (&**source_id, None),
+            };
+            if last_uuid != Some(uuid) {
+                if last_uuid.is_some() {
+                    try!(f.write_str(","));
+                }
+                try!(f.write_str(uuid));
+                last_uuid = Some(uuid);
+            }
+            if let Some(tag) = tag {
+                try!(f.write_str(tag));
+            }
+            for &(start, end) in intervals.iter() {
+                if start == end {
+                    try!(write!(f, ":{}", start));
+                } else {
+                    try!(write!(f, ":{}-{}", start, end));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs `query` falling back to `legacy_query` if server does not know the statement.
+fn show(conn: &mut Conn, query: &str, legacy_query: &str) -> MyResult<Vec<Row>> {
+    let rows = conn.query(query).and_then(|result| result.collect::<MyResult<Vec<Row>>>());
+    match rows {
+        Err(Error::MySqlError(ref err)) if err.code == ServerError::ER_PARSE_ERROR as u16 => {
+            try!(conn.query(legacy_query)).collect()
+        },
+        rows => rows,
+    }
+}
+
+/// Takes value of the first present column of `names`.
+///
+/// Returns `Ok(None)` if there is no such column or if it is `NULL`.
+fn take<T: FromValue>(row: &mut Row, names: &[&str]) -> MyResult<Option<T>> {
+    for name in names {
+        if let Some(index) = row.column_index(name) {
+            return match row.take(index) {
+                Some(value) => from_value_opt::<Option<T>>(value),
+                None => Ok(None),
+            };
+        }
+    }
+    Ok(None)
+}
+
+fn take_or_default<T: FromValue + Default>(row: &mut Row, names: &[&str]) -> MyResult<T> {
+    take(row, names).map(|x| x.unwrap_or_default())
+}
+
+fn take_yes(row: &mut Row, names: &[&str]) -> MyResult<bool> {
+    take::<String>(row, names).map(|x| x.map(|x| x == "Yes").unwrap_or(false))
+}
+
+pub fn master_status(conn: &mut Conn) -> MyResult<Option<MasterStatus>> {
+    let rows = try!(show(conn, "SHOW BINARY LOG STATUS", "SHOW MASTER STATUS"));
+    match rows.into_iter().next() {
+        Some(mut row) => Ok(Some(MasterStatus {
+            file: try!(take_or_default(&mut row, &["File"])),
+            position: try!(take_or_default(&mut row, &["Position"])),
+            binlog_do_db: try!(take_or_default(&mut row, &["Binlog_Do_DB"])),
+            binlog_ignore_db: try!(take_or_default(&mut row, &["Binlog_Ignore_DB"])),
+            executed_gtid_set: try!(take(&mut row, &["Executed_Gtid_Set"])),
+        })),
+        None => Ok(None),
+    }
+}
+
+pub fn replica_status(conn: &mut Conn) -> MyResult<Vec<ReplicaStatus>> {
+    let rows = try!(show(conn, "SHOW REPLICA STATUS", "SHOW SLAVE STATUS"));
+    let mut output = Vec::with_capacity(rows.len());
+    for mut row in rows {
+        let row = &mut row;
+        output.push(ReplicaStatus {
+            source_host: try!(take_or_default(row, &["Source_Host", "Master_Host"])),
+            source_port: try!(take_or_default(row, &["Source_Port", "Master_Port"])),
+            source_user: try!(take_or_default(row, &["Source_User", "Master_User"])),
+            source_log_file: try!(take_or_default(row, &["Source_Log_File",
+                                                         "Master_Log_File"])),
+            read_source_log_pos: try!(take_or_default(row, &["Read_Source_Log_Pos",
+                                                             "Read_Master_Log_Pos"])),
+            relay_source_log_file: try!(take_or_default(row, &["Relay_Source_Log_File",
+                                                               "Relay_Master_Log_File"])),
+            exec_source_log_pos: try!(take_or_default(row, &["Exec_Source_Log_Pos",
+                                                             "Exec_Master_Log_Pos"])),
+            io_running: try!(take_yes(row, &["Replica_IO_Running", "Slave_IO_Running"])),
+            sql_running: try!(take_yes(row, &["Replica_SQL_Running", "Slave_SQL_Running"])),
+            seconds_behind_source: try!(take(row, &["Seconds_Behind_Source",
+                                                    "Seconds_Behind_Master"])),
+            last_io_errno: try!(take_or_default(row, &["Last_IO_Errno"])),
+            last_io_error: try!(take_or_default(row, &["Last_IO_Error"])),
+            last_sql_errno: try!(take_or_default(row, &["Last_SQL_Errno"])),
+            last_sql_error: try!(take_or_default(row, &["Last_SQL_Error"])),
+            retrieved_gtid_set: try!(take(row, &["Retrieved_Gtid_Set"])),
+            executed_gtid_set: try!(take(row, &["Executed_Gtid_Set"])),
+            auto_position: try!(take::<u8>(row, &["Auto_Position"])).map(|x| x == 1),
+            channel_name: try!(take(row, &["Channel_Name"])),
+        });
+    }
+    Ok(output)
+}
+
+pub fn gtid_executed(conn: &mut Conn) -> MyResult<GtidSet> {
+    let rows = try!(try!(conn.query("SELECT @@GLOBAL.gtid_executed")).collect_rows::<String>());
+    let gtid_executed = rows.into_iter().next().unwrap_or(String::new());
+    match gtid_executed.parse() {
+        Ok(set) => Ok(set),
+        Err(_) => Err(Error::FromValueError(gtid_executed.into())),
+    }
+}
+
+pub fn wait_for_gtid(conn: &mut Conn,
+                     gtid_set: &GtidSet,
+                     timeout: Option<Duration>) -> MyResult<bool> {
+    // Function returns 0 if every transaction was executed and 1 on timeout.
+    let result: Option<u8> = match timeout {
+        Some(timeout) => {
+            let secs = timeout.as_secs() as f64 + timeout.subsec_nanos() as f64 / 1e9;
+            try!(conn.exec_first("SELECT WAIT_FOR_EXECUTED_GTID_SET(?, ?)",
+                                 (gtid_set.to_string(), secs)))
+        },
+        None => {
+            try!(conn.exec_first("SELECT WAIT_FOR_EXECUTED_GTID_SET(?)", (gtid_set.to_string(),)))
+        },
+    };
+    Ok(result == Some(0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::GtidSet;
+
+    #[test]
+    fn should_parse_gtid_set() {
+        let set: GtidSet = "3E11FA47-71CA-11E1-9E33-C80AA9429562:7:1-5,\n\
+                            4e11fa47-71ca-11e1-9e33-c80aa9429562:1-3:4-10"
+            .parse()
+            .unwrap();
+        assert_eq!(set.intervals("3e11fa47-71ca-11e1-9e33-c80aa9429562"), &[(1, 5), (7, 7)]);
+        assert_eq!(set.intervals("4e11fa47-71ca-11e1-9e33-c80aa9429562"), &[(1, 10)]);
+        assert_eq!(set.to_string(),
+                   "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7,\
+                    4e11fa47-71ca-11e1-9e33-c80aa9429562:1-10");
+        assert!(set.contains("3e11fa47-71ca-11e1-9e33-c80aa9429562", 7));
+        assert!(!set.contains("3e11fa47-71ca-11e1-9e33-c80aa9429562", 6));
+        assert!("".parse::<GtidSet>().unwrap().is_empty());
+        assert!("3e11fa47-71ca-11e1-9e33-c80aa9429562".parse::<GtidSet>().is_err());
+        assert!("3e11fa47-71ca-11e1-9e33-c80aa9429562:5-1".parse::<GtidSet>().is_err());
+        assert!("0-1-100".parse::<GtidSet>().is_err());
+    }
+
+    #[test]
+    fn should_parse_tagged_gtid_set() {
+        let set: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:Tag_1:1-3:7,\
+                            3e11fa47-71ca-11e1-9e33-c80aa9429562:other:2,\
+                            4e11fa47-71ca-11e1-9e33-c80aa9429562:tag_1:4"
+            .parse()
+            .unwrap();
+        assert_eq!(set.intervals("3e11fa47-71ca-11e1-9e33-c80aa9429562"), &[(1, 5)]);
+        assert_eq!(set.intervals("3e11fa47-71ca-11e1-9e33-c80aa9429562:tag_1"), &[(1, 3), (7, 7)]);
+        assert!(set.contains("3e11fa47-71ca-11e1-9e33-c80aa9429562:other", 2));
+        assert!(!set.contains("3e11fa47-71ca-11e1-9e33-c80aa9429562", 7));
+        assert_eq!(set.to_string(),
+                   "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:other:2:tag_1:1-3:7,\
+                    4e11fa47-71ca-11e1-9e33-c80aa9429562:tag_1:4");
+        assert_eq!(set.to_string().parse::<GtidSet>().unwrap(), set);
+        assert!("3e11fa47-71ca-11e1-9e33-c80aa9429562:tag".parse::<GtidSet>().is_err());
+        assert!("3e11fa47-71ca-11e1-9e33-c80aa9429562:a:b:1".parse::<GtidSet>().is_err());
+        assert!("3e11fa47-71ca-11e1-9e33-c80aa9429562:t-g:1".parse::<GtidSet>().is_err());
+    }
+
+    #[test]
+    fn should_check_gtid_subset() {
+        let source: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-10".parse().unwrap();
+        let replica: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7".parse().unwrap();
+        assert!(replica.is_subset_of(&source));
+        assert!(!source.is_subset_of(&replica));
+        assert!(GtidSet::new().is_subset_of(&replica));
+    }
+}
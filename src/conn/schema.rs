@@ -0,0 +1,226 @@
+//! Introspection of tables and columns via `information_schema`.
+
+use super::{Conn, Row, take_nullable};
+use super::super::escape::quote_identifier;
+use super::super::error::Error;
+use super::super::error::Result as MyResult;
+
+/// Column of a table or a view as described by `information_schema.COLUMNS`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ColumnInfo {
+    pub name: String,
+    /// Position of a column in a table starting from 1.
+    pub ordinal_position: u64,
+    /// Full column type, e.g. `varchar(255)` or `int(10) unsigned`.
+    pub column_type: String,
+    /// Type name without length and attributes, e.g. `varchar`.
+    pub data_type: String,
+    pub nullable: bool,
+    /// Default value as text (`None` if there is no default or default is `NULL`).
+    pub default: Option<String>,
+    /// Character set of a string column.
+    pub character_set: Option<String>,
+    /// Collation of a string column.
+    pub collation: Option<String>,
+    /// Additional information, e.g. `auto_increment`.
+    pub extra: String,
+    pub comment: String,
+    /// Whether a column is indexed: `PRI`, `UNI`, `MUL` or empty string.
+    pub key: String,
+    /// Expression of a generated column (`None` for other columns and for servers older
+    /// than MySQL 5.7 and MariaDB 10.2).
+    pub generation_expression: Option<String>,
+}
+
+impl ColumnInfo {
+    /// Returns `true` if column is a part of the primary key.
+    pub fn is_primary_key(&self) -> bool {
+        self.key == "PRI"
+    }
+
+    /// Returns `true` if column is of an unsigned numeric type.
+    pub fn is_unsigned(&self) -> bool {
+        self.column_type.contains(" unsigned")
+    }
+
+    /// Returns `true` if column has `AUTO_INCREMENT` attribute.
+    pub fn is_auto_increment(&self) -> bool {
+        self.extra.to_lowercase().contains("auto_increment")
+    }
+
+    /// Returns `true` if column is a generated (`VIRTUAL` or `STORED`) column. Values could not
+    /// be given for such columns, so they should be skipped by `INSERT` and `UPDATE`.
+    pub fn is_generated(&self) -> bool {
+        let extra = self.extra.to_uppercase();
+        self.generation_expression.is_some() ||
+        extra.contains("VIRTUAL GENERATED") ||
+        extra.contains("STORED GENERATED") ||
+        extra.contains("PERSISTENT")
+    }
+
+    /// Returns `true` if column is `INVISIBLE` (MySQL 8.0.23), i.e. it is not returned by
+    /// `SELECT *` and should be named explicitly.
+    pub fn is_invisible(&self) -> bool {
+        self.extra.to_uppercase().contains("INVISIBLE")
+    }
+
+    /// Returns `true` if default of a column is an expression (e.g. `DEFAULT (UUID())` or
+    /// `DEFAULT CURRENT_TIMESTAMP`) rather than a literal. `default` holds the expression then.
+    ///
+    /// Relies on `DEFAULT_GENERATED` in `EXTRA` which is reported by MySQL 8.0.13+ only, so it
+    /// always returns `false` on older MySQL versions and on MariaDB.
+    pub fn has_default_expression(&self) -> bool {
+        self.extra.to_uppercase().contains("DEFAULT_GENERATED")
+    }
+
+    /// Returns `true` if value of a column could be given by `INSERT`.
+    pub fn is_insertable(&self) -> bool {
+        !self.is_generated()
+    }
+}
+
+/// `ER_BAD_FIELD_ERROR` is returned by servers without `GENERATION_EXPRESSION` column.
+const ER_BAD_FIELD_ERROR: u16 = 1054;
+
+const TABLE_NAMES: &'static str = "SELECT TABLE_NAME FROM information_schema.TABLES \
+                                   WHERE TABLE_SCHEMA = ? ORDER BY TABLE_NAME";
+
+/// Query of `information_schema.COLUMNS` with optional trailing columns. Columns are read
+/// by index, so both variants share the same leading list.
+macro_rules! columns_query {
+    ($($column:expr),*) => (concat!(
+        "SELECT COLUMN_NAME, ORDINAL_POSITION, COLUMN_TYPE, DATA_TYPE, IS_NULLABLE, \
+         COLUMN_DEFAULT, CHARACTER_SET_NAME, COLLATION_NAME, EXTRA, COLUMN_COMMENT, COLUMN_KEY",
+        $(", ", $column,)*
+        " FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? \
+         ORDER BY ORDINAL_POSITION"
+    ));
+}
+
+const COLUMNS: &'static str = columns_query!("GENERATION_EXPRESSION");
+
+const COLUMNS_WITHOUT_GENERATION: &'static str = columns_query!();
+
+const PRIMARY_KEY: &'static str = "SELECT COLUMN_NAME FROM information_schema.KEY_COLUMN_USAGE \
+                                   WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? \
+                                   AND CONSTRAINT_NAME = 'PRIMARY' ORDER BY ORDINAL_POSITION";
+
+fn names(conn: &mut Conn, query: &str, db: &str, table: Option<&str>) -> MyResult<Vec<String>> {
+    let rows: Vec<Row> = match table {
+        Some(table) => try!(try!(conn.prep_exec(query, (db, table))).collect()),
+        None => try!(try!(conn.prep_exec(query, (db,))).collect()),
+    };
+    let mut output = Vec::with_capacity(rows.len());
+    for mut row in rows {
+        output.push(try!(take_nullable(&mut row, 0)).unwrap_or_default());
+    }
+    Ok(output)
+}
+
+pub fn table_names(conn: &mut Conn, db: &str) -> MyResult<Vec<String>> {
+    names(conn, TABLE_NAMES, db, None)
+}
+
+pub fn primary_key(conn: &mut Conn, db: &str, table: &str) -> MyResult<Vec<String>> {
+    names(conn, PRIMARY_KEY, db, Some(table))
+}
+
+pub fn columns(conn: &mut Conn, db: &str, table: &str) -> MyResult<Vec<ColumnInfo>> {
+    let rows: Vec<Row> = match conn.prep_exec(COLUMNS, (db, table)) {
+        Ok(result) => try!(result.collect()),
+        Err(Error::MySqlError(ref err)) if err.code == ER_BAD_FIELD_ERROR => {
+            try!(try!(conn.prep_exec(COLUMNS_WITHOUT_GENERATION, (db, table))).collect())
+        },
+        Err(err) => return Err(err),
+    };
+    let mut output = Vec::with_capacity(rows.len());
+    // Columns are taken by index because MySQL 8.0 changed case of `information_schema`
+    // column names.
+    for mut row in rows {
+        let row = &mut row;
+        let nullable: Option<String> = try!(take_nullable(row, 4));
+        output.push(ColumnInfo {
+            name: try!(take_nullable(row, 0)).unwrap_or_default(),
+            ordinal_position: try!(take_nullable(row, 1)).unwrap_or(0),
+            column_type: try!(take_nullable(row, 2)).unwrap_or_default(),
+            data_type: try!(take_nullable(row, 3)).unwrap_or_default(),
+            nullable: nullable.map(|x| x == "YES").unwrap_or(false),
+            default: try!(take_nullable(row, 5)),
+            character_set: try!(take_nullable(row, 6)),
+            collation: try!(take_nullable(row, 7)),
+            extra: try!(take_nullable(row, 8)).unwrap_or_default(),
+            comment: try!(take_nullable(row, 9)).unwrap_or_default(),
+            key: try!(take_nullable(row, 10)).unwrap_or_default(),
+            // Empty for other columns.
+            generation_expression: try!(take::<String>(row, 11)).and_then(|expr| {
+                if expr.is_empty() { None } else { Some(expr) }
+            }),
+        });
+    }
+    Ok(output)
+}
+
+/// Splits optionally qualified and optionally quoted table name (e.g. `` `my.db`.users ``)
+/// into unquoted database and table names.
+///
+/// Only the first `.` outside of backticks separates database name.
+fn split_table_name(name: &str) -> (Option<String>, String) {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '`' if quoted && chars.peek() == Some(&'`') => {
+                chars.next();
+                parts.last_mut().unwrap().push('`');
+            },
+            '`' => quoted = !quoted,
+            '.' if !quoted && parts.len() == 1 => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    let table = parts.pop().unwrap();
+    (parts.pop(), table)
+}
+
+pub fn describe(conn: &mut Conn, table: &str) -> MyResult<Vec<ColumnInfo>> {
+    let (db, table) = split_table_name(table);
+    let table = &*table;
+    let db = match db {
+        Some(db) => Some(db),
+        None => {
+            let db: Option<Option<String>> = try!(try!(conn.query("SELECT DATABASE()"))
+                                                       .first_row());
+            db.and_then(|db| db)
+        },
+    };
+    let output = match db {
+        Some(ref db) => try!(columns(conn, db, table)),
+        None => Vec::new(),
+    };
+    if output.is_empty() {
+        // Server reports `ER_NO_SUCH_TABLE` or `ER_NO_DB_ERROR`.
+        let name = match db {
+            Some(ref db) => format!("{}.{}", quote_identifier(db), quote_identifier(table)),
+            None => quote_identifier(table),
+        };
+        try!(conn.query(format!("SHOW COLUMNS FROM {}", name)));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::split_table_name;
+
+    #[test]
+    fn should_split_table_name() {
+        assert_eq!(split_table_name("users"), (None, "users".to_string()));
+        assert_eq!(split_table_name("app.users"),
+                   (Some("app".to_string()), "users".to_string()));
+        assert_eq!(split_table_name("`my.db`.`a.b`"),
+                   (Some("my.db".to_string()), "a.b".to_string()));
+        assert_eq!(split_table_name("`we``ird`"), (None, "we`ird".to_string()));
+        assert_eq!(split_table_name("`a.b`"), (None, "a.b".to_string()));
+    }
+}
@@ -0,0 +1,261 @@
+//! Routing of queries to shards of a horizontally partitioned database.
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::pool::{Pool, PooledConn};
+use super::{QueryResult, Row};
+use super::super::error::{Error, DriverError};
+use super::super::error::Result as MyResult;
+use super::super::value::{from_row_opt, FromRow, Params, Value};
+
+/// Maps a sharding key (e.g. tenant id) to an index of a shard of a
+/// [`ShardRouter`](struct.ShardRouter.html). Implemented for closures.
+pub trait ShardResolver: Send + Sync {
+    fn resolve(&self, key: &Value) -> usize;
+}
+
+impl<F: Fn(&Value) -> usize + Send + Sync> ShardResolver for F {
+    fn resolve(&self, key: &Value) -> usize {
+        self(key)
+    }
+}
+
+/// Number of [`Pool`](../pool/struct.Pool.html)s, one per shard, and a
+/// [`ShardResolver`](trait.ShardResolver.html) which selects a shard for a key.
+///
+/// Queries which take a key are sent to a single shard. `*_all` methods fan out a query
+/// to every shard one by one and merge their results.
+///
+/// ```ignore
+/// let router = ShardRouter::new(vec![try!(Pool::new(shard0_opts)),
+///                                    try!(Pool::new(shard1_opts))],
+///                               |tenant: &Value| from_value::<u64>(tenant.clone()) as usize % 2);
+/// let users: Vec<(u64, String)> = try!(router.exec(tenant_id,
+///                                                  "SELECT id, name FROM users",
+///                                                  ()));
+/// let counts: Vec<u64> = try!(router.exec_all("SELECT COUNT(*) FROM users", ()));
+/// ```
+#[derive(Clone)]
+pub struct ShardRouter {
+    shards: Arc<Vec<Pool>>,
+    resolver: Arc<ShardResolver>,
+}
+
+impl ShardRouter {
+    /// Creates new router. Index of a pool in `shards` is an id of a shard.
+    pub fn new<R: ShardResolver + 'static>(shards: Vec<Pool>, resolver: R) -> ShardRouter {
+        ShardRouter {
+            shards: Arc::new(shards),
+            resolver: Arc::new(resolver),
+        }
+    }
+
+    /// Returns pools of shards.
+    pub fn shards(&self) -> &[Pool] {
+        &*self.shards
+    }
+
+    /// Returns id of a shard for `key`.
+    ///
+    /// # Failure
+    /// Returns `DriverError::UnknownShard` if resolver returned an id without a pool.
+    pub fn shard_id<K: Into<Value>>(&self, key: K) -> MyResult<usize> {
+        let id = self.resolver.resolve(&key.into());
+        if id < self.shards.len() {
+            Ok(id)
+        } else {
+            Err(Error::DriverError(DriverError::UnknownShard(id)))
+        }
+    }
+
+    /// Returns pool of a shard for `key`.
+    pub fn pool<K: Into<Value>>(&self, key: K) -> MyResult<&Pool> {
+        let id = try!(self.shard_id(key));
+        Ok(&self.shards[id])
+    }
+
+    /// Gives you a [`PooledConn`](../pool/struct.PooledConn.html) of a shard for `key`.
+    pub fn get_conn<K: Into<Value>>(&self, key: K) -> MyResult<PooledConn> {
+        try!(self.pool(key)).get_conn()
+    }
+
+    /// Same as [`Pool::prep_exec`](../pool/struct.Pool.html#method.prep_exec) on a shard
+    /// for `key`.
+    pub fn prep_exec<'a, K, A, T>(&'a self,
+                                  key: K,
+                                  query: A,
+                                  params: T) -> MyResult<QueryResult<'a>>
+    where K: Into<Value>,
+          A: AsRef<str>,
+          T: Into<Params> {
+        try!(self.pool(key)).prep_exec(query, params)
+    }
+
+    /// Shortcut for `try!(router.prep_exec(..)).collect_rows()`.
+    pub fn exec<T, K, A, P>(&self, key: K, query: A, params: P) -> MyResult<Vec<T>>
+    where T: FromRow,
+          K: Into<Value>,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.pool(key)).exec(query, params)
+    }
+
+    /// Shortcut for `try!(router.prep_exec(..)).first_row()`.
+    pub fn exec_first<T, K, A, P>(&self, key: K, query: A, params: P) -> MyResult<Option<T>>
+    where T: FromRow,
+          K: Into<Value>,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.pool(key)).exec_first(query, params)
+    }
+
+    /// Executes statement on a shard for `key` and drops its result.
+    pub fn exec_drop<K, A, P>(&self, key: K, query: A, params: P) -> MyResult<()>
+    where K: Into<Value>,
+          A: AsRef<str>,
+          P: Into<Params> {
+        try!(self.pool(key)).exec_drop(query, params)
+    }
+
+    /// Executes statement on every shard and returns rows of all shards in order of shards.
+    ///
+    /// Statement is executed on every shard before the first row is returned, so
+    /// a connection of every shard is held until `MergedResult` is dropped.
+    pub fn prep_exec_all<'a, A, T>(&'a self, query: A, params: T) -> MyResult<MergedResult<'a>>
+    where A: AsRef<str>,
+          T: Into<Params> {
+        let params = params.into();
+        let mut results = Vec::with_capacity(self.shards.len());
+        for pool in self.shards.iter() {
+            results.push(try!(pool.prep_exec(query.as_ref(), params.clone())));
+        }
+        Ok(MergedResult {
+            results: results,
+            current: 0,
+        })
+    }
+
+    /// Shortcut for `try!(router.prep_exec_all(..)).collect_rows()`.
+    pub fn exec_all<T, A, P>(&self, query: A, params: P) -> MyResult<Vec<T>>
+    where T: FromRow,
+          A: AsRef<str>,
+          P: Into<Params> {
+        let mut result = try!(self.prep_exec_all(query, params));
+        let rows = try!(result.collect_rows());
+        for shard_result in result.results() {
+            try!(shard_result.check_row_limit());
+        }
+        Ok(rows)
+    }
+
+    /// Executes statement on every shard and drops its results, e.g. to apply a migration.
+    pub fn exec_drop_all<A, P>(&self, query: A, params: P) -> MyResult<()>
+    where A: AsRef<str>,
+          P: Into<Params> {
+        let params = params.into();
+        for pool in self.shards.iter() {
+            try!(pool.exec_drop(query.as_ref(), params.clone()));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ShardRouter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ShardRouter {{ shards: {:?} }}", self.shards)
+    }
+}
+
+/// Rows of results of a statement executed on every shard of a
+/// [`ShardRouter`](struct.ShardRouter.html).
+#[derive(Debug)]
+pub struct MergedResult<'a> {
+    results: Vec<QueryResult<'a>>,
+    /// Index of a result which is being read.
+    current: usize,
+}
+
+impl<'a> MergedResult<'a> {
+    /// Returns results of shards.
+    pub fn results(&self) -> &[QueryResult<'a>] {
+        &*self.results
+    }
+
+    /// Returns sum of affected rows of all shards.
+    pub fn affected_rows(&self) -> u64 {
+        self.results.iter().map(|result| result.affected_rows()).sum()
+    }
+
+    /// Converts every row to `T`.
+    pub fn collect_rows<T: FromRow>(&mut self) -> MyResult<Vec<T>> {
+        let mut output = Vec::new();
+        for row in self {
+            output.push(try!(from_row_opt(try!(row))));
+        }
+        Ok(output)
+    }
+}
+
+impl<'a> Iterator for MergedResult<'a> {
+    type Item = MyResult<Row>;
+
+    fn next(&mut self) -> Option<MyResult<Row>> {
+        while self.current < self.results.len() {
+            if let Some(row) = self.results[self.current].next() {
+                return Some(row);
+            }
+            self.current += 1;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ShardRouter;
+    use conn::Opts;
+    use conn::pool::Pool;
+    use error::{Error, DriverError};
+    use std::default::Default;
+    use value::{from_value, Value};
+
+    fn get_opts() -> Opts {
+        let pwd: String = ::std::env::var("MYSQL_SERVER_PASS").unwrap_or("password".to_string());
+        let port: u16 = ::std::env::var("MYSQL_SERVER_PORT").ok()
+                                   .map(|my_port| my_port.parse().ok().unwrap_or(3307))
+                                   .unwrap_or(3307);
+        Opts {
+            user: Some("root".to_string()),
+            pass: Some(pwd),
+            ip_or_hostname: Some("127.0.0.1".to_string()),
+            tcp_port: port,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_route_and_fan_out_queries() {
+        let shards = vec![Pool::new_manual(1, 1, get_opts()).unwrap(),
+                          Pool::new_manual(1, 1, get_opts()).unwrap()];
+        let router = ShardRouter::new(shards,
+                                      |key: &Value| from_value::<u64>(key.clone()) as usize % 3);
+        assert_eq!(router.shard_id(4).unwrap(), 1);
+        let id0: u32 = router.exec_first(0, "SELECT CONNECTION_ID()", ()).unwrap().unwrap();
+        let id1: u32 = router.exec_first(1, "SELECT CONNECTION_ID()", ()).unwrap().unwrap();
+        assert!(id0 != id1);
+        match router.get_conn(2) {
+            Err(Error::DriverError(DriverError::UnknownShard(2))) => (),
+            _ => panic!("Expected UnknownShard error"),
+        }
+
+        let ids: Vec<u32> = router.exec_all("SELECT CONNECTION_ID()", ()).unwrap();
+        assert_eq!(ids, vec![id0, id1]);
+        let values: Vec<(u8, u32)> = router.exec_all("SELECT ?, CONNECTION_ID() UNION ALL \
+                                                      SELECT ? + 1, CONNECTION_ID()",
+                                                     (1, 1))
+                                           .unwrap();
+        assert_eq!(values, vec![(1, id0), (2, id0), (1, id1), (2, id1)]);
+        router.exec_drop_all("DO 1", ()).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+//! Shared receivers of diagnostic events, e.g. [`Record`s](struct.Record.html) of
+//! [`Recorder`](type.Recorder.html) and [`Packet`s](struct.Packet.html) of
+//! [`PacketTracer`](struct.PacketTracer.html).
+
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Receiver of events of type `T`. Implemented for closures.
+pub trait Sink<T>: Send + Sync {
+    fn send(&self, event: T);
+}
+
+impl<T, F: Fn(T) + Send + Sync> Sink<T> for F {
+    fn send(&self, event: T) {
+        self(event)
+    }
+}
+
+struct WriterSink<W>(Mutex<W>);
+
+impl<T: fmt::Display, W: Write + Send> Sink<T> for WriterSink<W> {
+    fn send(&self, event: T) {
+        if let Ok(mut writer) = self.0.lock() {
+            let _ = writeln!(writer, "{}", event);
+        }
+    }
+}
+
+/// Shareable [`Sink`](trait.Sink.html) which could be stored in `Opts`.
+///
+/// Sink is called synchronously by a connection, so it should be fast.
+pub struct SharedSink<T>(Arc<Sink<T>>);
+
+impl<T: 'static> SharedSink<T> {
+    pub fn new<S: Sink<T> + 'static>(sink: S) -> SharedSink<T> {
+        SharedSink(Arc::new(sink))
+    }
+
+    /// Writes every event as a line of text using its `Display` implementation.
+    /// Write errors are ignored.
+    pub fn to_writer<W: Write + Send + 'static>(writer: W) -> SharedSink<T>
+    where T: fmt::Display {
+        SharedSink::new(WriterSink(Mutex::new(writer)))
+    }
+
+    #[doc(hidden)]
+    pub fn send(&self, event: T) {
+        self.0.send(event)
+    }
+}
+
+impl<T> Clone for SharedSink<T> {
+    fn clone(&self) -> SharedSink<T> {
+        SharedSink(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for SharedSink<T> {
+    fn eq(&self, other: &SharedSink<T>) -> bool {
+        &*self.0 as *const _ as *const () == &*other.0 as *const _ as *const ()
+    }
+}
+
+impl<T> Eq for SharedSink<T> {}
+
+impl<T> fmt::Debug for SharedSink<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedSink")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use super::SharedSink;
+
+    #[test]
+    fn should_write_events_as_lines() {
+        let buf = Arc::new(Mutex::new(Vec::new()));
+        let sink = SharedSink::to_writer(SharedBuf(buf.clone()));
+        sink.send(1);
+        sink.send(2);
+        assert_eq!(&*buf.lock().unwrap(), b"1\n2\n");
+        assert!(sink == sink.clone());
+        assert!(sink != SharedSink::new(|_: i32| ()));
+    }
+
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl ::std::io::Write for SharedBuf {
+        fn write(&mut self, data: &[u8]) -> ::std::io::Result<usize> {
+            self.0.lock().unwrap().write(data)
+        }
+        fn flush(&mut self) -> ::std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
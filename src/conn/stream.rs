@@ -0,0 +1,41 @@
+//! Custom transports for [`Conn::new_with_stream`](struct.Conn.html#method.new_with_stream).
+
+use std::io;
+use std::net;
+
+#[cfg(feature = "socket")]
+use unix_socket as us;
+
+/// Transport which could be used to talk to a server, e.g. a channel of an SSH tunnel or an
+/// in-memory stream in tests.
+///
+/// Driver wraps a stream in a buffer, so implementation does not need to buffer reads or writes.
+pub trait Stream: io::Read + io::Write + Send {
+    /// Called when connection is dropped, right after `COM_QUIT` is sent. Errors are ignored.
+    fn shutdown(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Returns `true` if transport is encrypted or local, so password could be sent in clear text
+    /// (defaults to `false`). TLS is never negotiated over a custom stream.
+    fn is_secure(&self) -> bool {
+        false
+    }
+}
+
+impl Stream for net::TcpStream {
+    fn shutdown(&mut self) -> io::Result<()> {
+        net::TcpStream::shutdown(self, net::Shutdown::Both)
+    }
+}
+
+#[cfg(feature = "socket")]
+impl Stream for us::UnixStream {
+    fn shutdown(&mut self) -> io::Result<()> {
+        us::UnixStream::shutdown(self, net::Shutdown::Both)
+    }
+
+    fn is_secure(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,219 @@
+//! Wire-level tracing of MySql protocol packets.
+
+use std::fmt;
+use std::io::Write;
+
+use super::sink::{SharedSink, Sink};
+
+static COMMAND_NAMES: [&'static str; 32] = [
+    "COM_SLEEP", "COM_QUIT", "COM_INIT_DB", "COM_QUERY", "COM_FIELD_LIST", "COM_CREATE_DB",
+    "COM_DROP_DB", "COM_REFRESH", "COM_SHUTDOWN", "COM_STATISTICS", "COM_PROCESS_INFO",
+    "COM_CONNECT", "COM_PROCESS_KILL", "COM_DEBUG", "COM_PING", "COM_TIME", "COM_DELAYED_INSERT",
+    "COM_CHANGE_USER", "COM_BINLOG_DUMP", "COM_TABLE_DUMP", "COM_CONNECT_OUT",
+    "COM_REGISTER_SLAVE", "COM_STMT_PREPARE", "COM_STMT_EXECUTE", "COM_STMT_SEND_LONG_DATA",
+    "COM_STMT_CLOSE", "COM_STMT_RESET", "COM_SET_OPTION", "COM_STMT_FETCH", "COM_DAEMON",
+    "COM_BINLOG_DUMP_GTID", "COM_RESET_CONNECTION",
+];
+
+/// Default number of payload bytes kept in a [`Packet`](struct.Packet.html).
+pub const DEFAULT_TRACE_LIMIT: usize = 256;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum PacketDirection {
+    Sent,
+    Received,
+}
+
+/// Packet sent to or received from the server.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Packet {
+    pub direction: PacketDirection,
+    /// Sequence id of the (first) packet. Payloads larger than 16MB are traced once.
+    pub seq_id: u8,
+    /// Length of the whole payload.
+    pub len: usize,
+    /// Payload truncated to the limit of a tracer.
+    pub payload: Vec<u8>,
+}
+
+impl Packet {
+    /// Best-effort description of a packet based on its first byte, e.g. `COM_QUERY` or `ERR`.
+    ///
+    /// Sent packet with sequence id `0` is a command. Received packet is described as
+    /// `OK`, `ERR`, `EOF`, `LOCAL_INFILE` or `DATA`. Description of a row which starts with
+    /// the marker byte could be wrong, since tracer does not know protocol state.
+    pub fn description(&self) -> &'static str {
+        let first = self.payload.first().cloned();
+        match self.direction {
+            PacketDirection::Sent => {
+                match first {
+                    Some(x) if self.seq_id == 0 => {
+                        COMMAND_NAMES.get(x as usize).cloned().unwrap_or("UNKNOWN_COMMAND")
+                    },
+                    _ => "DATA",
+                }
+            },
+            PacketDirection::Received => {
+                match first {
+                    Some(0x00) => "OK",
+                    Some(0xFF) => "ERR",
+                    Some(0xFE) if self.len < 9 => "EOF",
+                    Some(0xFB) => "LOCAL_INFILE",
+                    _ => "DATA",
+                }
+            },
+        }
+    }
+}
+
+/// Formats a packet as a header line followed by a hex dump of 16 bytes per line, e.g.
+///
+/// ```text
+/// >> #0 COM_QUERY (9 bytes)
+/// 0000  03 53 45 4c 45 43 54 20  31                       .SELECT 1
+/// ```
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let arrow = match self.direction {
+            PacketDirection::Sent => ">>",
+            PacketDirection::Received => "<<",
+        };
+        try!(write!(f, "{} #{} {} ({} bytes)", arrow, self.seq_id, self.description(), self.len));
+        for (i, chunk) in self.payload.chunks(16).enumerate() {
+            try!(write!(f, "\n{:04x} ", i * 16));
+            for j in 0..16 {
+                if j == 8 {
+                    try!(write!(f, " "));
+                }
+                match chunk.get(j) {
+                    Some(x) => try!(write!(f, " {:02x}", x)),
+                    None => try!(write!(f, "   ")),
+                }
+            }
+            try!(write!(f, "  "));
+            for &x in chunk {
+                let c = if x >= 0x20 && x < 0x7F { x as char } else { '.' };
+                try!(write!(f, "{}", c));
+            }
+        }
+        if self.payload.len() < self.len {
+            try!(write!(f, "\n... {} more bytes", self.len - self.payload.len()));
+        }
+        Ok(())
+    }
+}
+
+/// Shareable sink of [`Packet`](struct.Packet.html)s which could be stored in
+/// [`Opts::packet_tracer`](struct.Opts.html#structfield.packet_tracer).
+///
+/// Every packet including handshake is traced, so a trace may contain sensitive data such as
+/// parameters of queries or a password sent in clear text by `mysql_clear_password` plugin.
+///
+/// ```ignore
+/// let opts = Opts {
+///     packet_tracer: Some(PacketTracer::to_writer(io::stderr()).limit(64)),
+///     ..get_opts()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct PacketTracer {
+    sink: SharedSink<Packet>,
+    limit: usize,
+}
+
+impl PacketTracer {
+    /// Creates tracer which keeps `DEFAULT_TRACE_LIMIT` bytes of every payload.
+    pub fn new<T: Sink<Packet> + 'static>(sink: T) -> PacketTracer {
+        PacketTracer {
+            sink: SharedSink::new(sink),
+            limit: DEFAULT_TRACE_LIMIT,
+        }
+    }
+
+    /// Writes every packet as text (see `Display` implementation of `Packet`).
+    /// Write errors are ignored.
+    pub fn to_writer<W: Write + Send + 'static>(writer: W) -> PacketTracer {
+        PacketTracer {
+            sink: SharedSink::to_writer(writer),
+            limit: DEFAULT_TRACE_LIMIT,
+        }
+    }
+
+    /// Sets number of payload bytes kept in a packet.
+    pub fn limit(mut self, limit: usize) -> PacketTracer {
+        self.limit = limit;
+        self
+    }
+
+    #[doc(hidden)]
+    pub fn trace(&self, direction: PacketDirection, seq_id: u8, payload: &[u8]) {
+        let end = ::std::cmp::min(payload.len(), self.limit);
+        self.sink.send(Packet {
+            direction: direction,
+            seq_id: seq_id,
+            len: payload.len(),
+            payload: payload[..end].to_vec(),
+        });
+    }
+}
+
+impl PartialEq for PacketTracer {
+    fn eq(&self, other: &PacketTracer) -> bool {
+        self.sink == other.sink && self.limit == other.limit
+    }
+}
+
+impl Eq for PacketTracer {}
+
+impl fmt::Debug for PacketTracer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PacketTracer {{ limit: {} }}", self.limit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use super::{Packet, PacketDirection, PacketTracer};
+
+    #[test]
+    fn should_describe_packets() {
+        let packet = |direction, seq_id, payload: &[u8]| {
+            Packet {
+                direction: direction,
+                seq_id: seq_id,
+                len: payload.len(),
+                payload: payload.to_vec(),
+            }
+        };
+        assert_eq!(packet(PacketDirection::Sent, 0, b"\x03SELECT 1").description(), "COM_QUERY");
+        assert_eq!(packet(PacketDirection::Sent, 0, b"\x17").description(), "COM_STMT_EXECUTE");
+        assert_eq!(packet(PacketDirection::Sent, 0, b"\xAA").description(), "UNKNOWN_COMMAND");
+        assert_eq!(packet(PacketDirection::Sent, 1, b"\x03").description(), "DATA");
+        assert_eq!(packet(PacketDirection::Received, 1, b"\x00\x00\x00").description(), "OK");
+        assert_eq!(packet(PacketDirection::Received, 1, b"\xFF\x15\x04").description(), "ERR");
+        assert_eq!(packet(PacketDirection::Received, 5, b"\xFE\x00\x00\x02\x00").description(),
+                   "EOF");
+        assert_eq!(packet(PacketDirection::Received, 2, b"\x01").description(), "DATA");
+    }
+
+    #[test]
+    fn should_format_and_truncate_packets() {
+        let packets = Arc::new(Mutex::new(Vec::new()));
+        let sink = packets.clone();
+        let tracer = PacketTracer::new(move |packet: Packet| sink.lock().unwrap().push(packet)).limit(20);
+        tracer.trace(PacketDirection::Sent, 0, b"\x03SELECT 1");
+        tracer.trace(PacketDirection::Received, 1, b"\x01\x00\x02\x03\x04\x05\x06\x07\x08\x09\
+                                                     abcdefghijklmnopqrstuvwxyz");
+        let packets = packets.lock().unwrap();
+        assert_eq!(packets[0].to_string(),
+                   ">> #0 COM_QUERY (9 bytes)\n\
+                    0000  03 53 45 4c 45 43 54 20  31                       .SELECT 1");
+        assert_eq!(packets[1].payload.len(), 20);
+        assert_eq!(packets[1].to_string(),
+                   "<< #1 DATA (36 bytes)\n\
+                    0000  01 00 02 03 04 05 06 07  08 09 61 62 63 64 65 66  ..........abcdef\n\
+                    0010  67 68 69 6a                                       ghij\n\
+                    ... 16 more bytes");
+    }
+}
@@ -19,6 +19,7 @@ bitflags! {
         const SERVER_STATUS_METADATA_CHANGED     = 0x0400u16,
         const SERVER_QUERY_WAS_SLOW              = 0x0800u16,
         const SERVER_PT_OUT_PARAMS               = 0x1000u16,
+        const SERVER_SESSION_STATE_CHANGED       = 0x4000u16,
     }
 }
 
@@ -47,6 +48,21 @@ bitflags! {
         const CLIENT_PLUGIN_AUTH                    = 0x00080000u32,
         const CLIENT_CONNECT_ATTRS                  = 0x00100000u32,
         const CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA = 0x00200000u32,
+        const CLIENT_SESSION_TRACK                  = 0x00800000u32,
+    }
+}
+
+bitflags! {
+    /// MariaDB extended capabilities (upper 32 bits of 64-bit capabilities).
+    ///
+    /// Announced in reserved bytes of the initial handshake if server does not set
+    /// `CLIENT_LONG_PASSWORD` (`CLIENT_MYSQL` in MariaDB terms).
+    flags MariadbCapabilityFlags: u32 {
+        const MARIADB_CLIENT_PROGRESS               = 0x00000001u32,
+        const MARIADB_CLIENT_COM_MULTI              = 0x00000002u32,
+        const MARIADB_CLIENT_STMT_BULK_OPERATIONS   = 0x00000004u32,
+        const MARIADB_CLIENT_EXTENDED_TYPE_INFO     = 0x00000008u32,
+        const MARIADB_CLIENT_CACHE_METADATA         = 0x00000010u32,
     }
 }
 
@@ -177,3 +193,136 @@ bitflags! {
         const PART_KEY_FLAG         = 16384u16,
     }
 }
+
+bitflags! {
+    /// SQL modes of a session (see [`Conn::sql_mode`](../struct.Conn.html#method.sql_mode)).
+    ///
+    /// Bits are the ones used by MySQL server. Combination modes (`ANSI`, `TRADITIONAL`, ...)
+    /// are reported by server together with modes they imply.
+    flags SqlMode: u64 {
+        const MODE_REAL_AS_FLOAT              = 0x0000_0001u64,
+        const MODE_PIPES_AS_CONCAT            = 0x0000_0002u64,
+        const MODE_ANSI_QUOTES                = 0x0000_0004u64,
+        const MODE_IGNORE_SPACE               = 0x0000_0008u64,
+        const MODE_ONLY_FULL_GROUP_BY         = 0x0000_0020u64,
+        const MODE_NO_UNSIGNED_SUBTRACTION    = 0x0000_0040u64,
+        const MODE_NO_DIR_IN_CREATE           = 0x0000_0080u64,
+        const MODE_POSTGRESQL                 = 0x0000_0100u64,
+        const MODE_ORACLE                     = 0x0000_0200u64,
+        const MODE_MSSQL                      = 0x0000_0400u64,
+        const MODE_DB2                        = 0x0000_0800u64,
+        const MODE_MAXDB                      = 0x0000_1000u64,
+        const MODE_NO_KEY_OPTIONS             = 0x0000_2000u64,
+        const MODE_NO_TABLE_OPTIONS           = 0x0000_4000u64,
+        const MODE_NO_FIELD_OPTIONS           = 0x0000_8000u64,
+        const MODE_MYSQL323                   = 0x0001_0000u64,
+        const MODE_MYSQL40                    = 0x0002_0000u64,
+        const MODE_ANSI                       = 0x0004_0000u64,
+        const MODE_NO_AUTO_VALUE_ON_ZERO      = 0x0008_0000u64,
+        const MODE_NO_BACKSLASH_ESCAPES       = 0x0010_0000u64,
+        const MODE_STRICT_TRANS_TABLES        = 0x0020_0000u64,
+        const MODE_STRICT_ALL_TABLES          = 0x0040_0000u64,
+        const MODE_NO_ZERO_IN_DATE            = 0x0080_0000u64,
+        const MODE_NO_ZERO_DATE               = 0x0100_0000u64,
+        const MODE_INVALID_DATES              = 0x0200_0000u64,
+        const MODE_ERROR_FOR_DIVISION_BY_ZERO = 0x0400_0000u64,
+        const MODE_TRADITIONAL                = 0x0800_0000u64,
+        const MODE_NO_AUTO_CREATE_USER        = 0x1000_0000u64,
+        const MODE_HIGH_NOT_PRECEDENCE        = 0x2000_0000u64,
+        const MODE_NO_ENGINE_SUBSTITUTION     = 0x4000_0000u64,
+        const MODE_PAD_CHAR_TO_FULL_LENGTH    = 0x8000_0000u64,
+        const MODE_TIME_TRUNCATE_FRACTIONAL   = 0x1_0000_0000u64,
+    }
+}
+
+static SQL_MODE_NAMES: [(&'static str, SqlMode); 32] = [
+    ("REAL_AS_FLOAT", MODE_REAL_AS_FLOAT),
+    ("PIPES_AS_CONCAT", MODE_PIPES_AS_CONCAT),
+    ("ANSI_QUOTES", MODE_ANSI_QUOTES),
+    ("IGNORE_SPACE", MODE_IGNORE_SPACE),
+    ("ONLY_FULL_GROUP_BY", MODE_ONLY_FULL_GROUP_BY),
+    ("NO_UNSIGNED_SUBTRACTION", MODE_NO_UNSIGNED_SUBTRACTION),
+    ("NO_DIR_IN_CREATE", MODE_NO_DIR_IN_CREATE),
+    ("POSTGRESQL", MODE_POSTGRESQL),
+    ("ORACLE", MODE_ORACLE),
+    ("MSSQL", MODE_MSSQL),
+    ("DB2", MODE_DB2),
+    ("MAXDB", MODE_MAXDB),
+    ("NO_KEY_OPTIONS", MODE_NO_KEY_OPTIONS),
+    ("NO_TABLE_OPTIONS", MODE_NO_TABLE_OPTIONS),
+    ("NO_FIELD_OPTIONS", MODE_NO_FIELD_OPTIONS),
+    ("MYSQL323", MODE_MYSQL323),
+    ("MYSQL40", MODE_MYSQL40),
+    ("ANSI", MODE_ANSI),
+    ("NO_AUTO_VALUE_ON_ZERO", MODE_NO_AUTO_VALUE_ON_ZERO),
+    ("NO_BACKSLASH_ESCAPES", MODE_NO_BACKSLASH_ESCAPES),
+    ("STRICT_TRANS_TABLES", MODE_STRICT_TRANS_TABLES),
+    ("STRICT_ALL_TABLES", MODE_STRICT_ALL_TABLES),
+    ("NO_ZERO_IN_DATE", MODE_NO_ZERO_IN_DATE),
+    ("NO_ZERO_DATE", MODE_NO_ZERO_DATE),
+    ("ALLOW_INVALID_DATES", MODE_INVALID_DATES),
+    ("ERROR_FOR_DIVISION_BY_ZERO", MODE_ERROR_FOR_DIVISION_BY_ZERO),
+    ("TRADITIONAL", MODE_TRADITIONAL),
+    ("NO_AUTO_CREATE_USER", MODE_NO_AUTO_CREATE_USER),
+    ("HIGH_NOT_PRECEDENCE", MODE_HIGH_NOT_PRECEDENCE),
+    ("NO_ENGINE_SUBSTITUTION", MODE_NO_ENGINE_SUBSTITUTION),
+    ("PAD_CHAR_TO_FULL_LENGTH", MODE_PAD_CHAR_TO_FULL_LENGTH),
+    ("TIME_TRUNCATE_FRACTIONAL", MODE_TIME_TRUNCATE_FRACTIONAL),
+];
+
+impl SqlMode {
+    /// Parses comma separated mode names as in `@@sql_mode`. Names are case insensitive and
+    /// unknown names are ignored.
+    pub fn from_names(names: &str) -> SqlMode {
+        let mut mode = SqlMode::empty();
+        for name in names.split(',').map(str::trim) {
+            for &(known, flag) in SQL_MODE_NAMES.iter() {
+                if known.eq_ignore_ascii_case(name) {
+                    mode.insert(flag);
+                }
+            }
+        }
+        mode
+    }
+
+    /// Returns comma separated mode names suitable for `SET sql_mode = '...'`.
+    pub fn names(&self) -> String {
+        SQL_MODE_NAMES.iter()
+            .filter(|&&(_, flag)| self.contains(flag))
+            .map(|&(name, _)| name)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Returns names from comma separated `names` which are ignored by
+    /// [`SqlMode::from_names`](#method.from_names).
+    #[doc(hidden)]
+    pub fn unknown_names(names: &str) -> Vec<&str> {
+        names.split(',')
+            .map(str::trim)
+            .filter(|name| {
+                !name.is_empty() &&
+                !SQL_MODE_NAMES.iter().any(|&(known, _)| known.eq_ignore_ascii_case(name))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MODE_ANSI_QUOTES, MODE_NO_BACKSLASH_ESCAPES, MODE_STRICT_TRANS_TABLES, SqlMode};
+
+    #[test]
+    fn should_parse_and_format_sql_mode() {
+        let mode = SqlMode::from_names("STRICT_TRANS_TABLES, no_backslash_escapes,UNKNOWN_MODE");
+        assert_eq!(mode, MODE_STRICT_TRANS_TABLES | MODE_NO_BACKSLASH_ESCAPES);
+        assert_eq!(mode.names(), "NO_BACKSLASH_ESCAPES,STRICT_TRANS_TABLES");
+        assert_eq!(SqlMode::from_names(""), SqlMode::empty());
+        assert_eq!(SqlMode::empty().names(), "");
+        assert_eq!(SqlMode::from_names(&*MODE_ANSI_QUOTES.names()), MODE_ANSI_QUOTES);
+        let names = "STRICT_TRANS_TABLES, UNKNOWN_MODE,,EMPTY_STRING_IS_NULL";
+        assert_eq!(SqlMode::unknown_names(names), vec!["UNKNOWN_MODE", "EMPTY_STRING_IS_NULL"]);
+        assert_eq!(SqlMode::from_names("ALLOW_INVALID_DATES"), MODE_INVALID_DATES);
+        assert!(SqlMode::unknown_names("").is_empty());
+    }
+}
@@ -3,20 +3,32 @@ use std::fmt;
 use std::fmt::Display;
 use std::io;
 use std::result;
+use std::string::FromUtf8Error;
 
 use byteorder::Error as BoError;
 #[cfg(feature = "openssl")]
-use openssl::ssl::error::{SslError};
+use openssl::error::ErrorStack;
+#[cfg(feature = "openssl")]
+use openssl::ssl::Error as SslError;
+#[cfg(feature = "openssl")]
+use openssl::ssl::HandshakeError;
 
 use super::conn::Row;
+use super::consts::{CapabilityFlags, ColumnType};
 use super::value::Value;
 
 use url::ParseError;
 
+/// Error returned by a server.
+///
+/// Use `code` to match on specific server errors (see [`ServerError`](enum.ServerError.html)).
 #[derive(Eq, PartialEq, Clone)]
 pub struct MySqlError {
+    /// SQLSTATE value.
     pub state: String,
+    /// Human readable error message.
     pub message: String,
+    /// Server error code.
     pub code: u16,
 }
 
@@ -46,7 +58,121 @@ pub enum Error {
     #[cfg(feature = "openssl")]
     SslError(SslError),
     FromValueError(Value),
+    /// Text value is not valid UTF-8. Offending bytes are available via
+    /// `FromUtf8Error::as_bytes` or `FromUtf8Error::into_bytes` and could be converted via
+    /// [`Utf8Policy::decode`](enum.Utf8Policy.html#method.decode).
+    Utf8Error(FromUtf8Error),
     FromRowError(Row),
+    FromColumnError(FromColumnError),
+}
+
+impl Error {
+    /// Returns reference to the [`MySqlError`](struct.MySqlError.html) if this error was
+    /// returned by a server.
+    ///
+    /// Server errors of a handshake are wrapped into `DriverError::AuthFailed`,
+    /// `DriverError::AuthPluginMismatch`, `DriverError::SecureTransportRequired` and
+    /// `DriverError::HandshakeRejected`, so they are not matched by `Error::MySqlError(..)`
+    /// patterns. This method returns the wrapped server error as well.
+    ///
+    /// ```ignore
+    /// match pool.prep_exec("INSERT INTO tbl (id) VALUES (?)", (1,)) {
+    ///     Err(ref err) if err.server_error().map(|e| e.code) == Some(1062) => {
+    ///         // ER_DUP_ENTRY
+    ///     },
+    ///     _ => (),
+    /// }
+    /// ```
+    pub fn server_error(&self) -> Option<&MySqlError> {
+        match *self {
+            Error::MySqlError(ref err) => Some(err),
+            Error::DriverError(DriverError::AuthFailed(ref err, _)) => Some(err),
+            Error::DriverError(DriverError::AuthPluginMismatch(ref err, _, _)) => Some(err),
+            Error::DriverError(DriverError::SecureTransportRequired(ref err)) => Some(err),
+            Error::DriverError(DriverError::HandshakeRejected(ref err, _)) => Some(err),
+            _ => None,
+        }
+    }
+
+    fn server_code(&self) -> Option<u16> {
+        self.server_error().map(|err| err.code)
+    }
+
+    /// Returns `true` if transaction was rolled back by a server due to a deadlock
+    /// (`ER_LOCK_DEADLOCK`).
+    pub fn is_deadlock(&self) -> bool {
+        self.server_code() == Some(1213) // ER_LOCK_DEADLOCK
+    }
+
+    /// Returns `true` if connection is broken or could not be established, i.e. this is an I/O
+    /// error, `DriverError::CouldNotConnect`, or server refused or dropped connection
+    /// (e.g. `ER_CON_COUNT_ERROR`, `ER_SERVER_SHUTDOWN` or `ER_NET_READ_ERROR`).
+    ///
+    /// Connection which returned such an error should not be used anymore.
+    pub fn is_connection_error(&self) -> bool {
+        match *self {
+            Error::IoError(_) => true,
+            Error::DriverError(DriverError::CouldNotConnect(_)) => true,
+            Error::DriverError(DriverError::PacketOutOfSync) => true,
+            Error::MySqlError(ref err) => {
+                match err.code {
+                    1040 | // ER_CON_COUNT_ERROR
+                    1053 | // ER_SERVER_SHUTDOWN
+                    1152 | // ER_ABORTING_CONNECTION
+                    1154...1161 | // ER_NET_READ_ERROR_FROM_PIPE..ER_NET_WRITE_INTERRUPTED
+                    1203 | // ER_TOO_MANY_USER_CONNECTIONS
+                    1927 => true, // ER_CONNECTION_KILLED
+                    _ => false,
+                }
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the same operation is likely to succeed if retried (possibly on
+    /// a new connection), i.e. this is a deadlock, a lock wait timeout, a connection error
+    /// (see [`is_connection_error`](#method.is_connection_error)), or pool checkout timeout.
+    ///
+    /// Note that whole transaction should be retried after a deadlock since it was rolled back.
+    pub fn is_transient(&self) -> bool {
+        if self.is_deadlock() || self.is_connection_error() {
+            return true;
+        }
+        match *self {
+            Error::DriverError(DriverError::Timeout) => true,
+            Error::MySqlError(ref err) => {
+                match err.code {
+                    1205 | // ER_LOCK_WAIT_TIMEOUT
+                    1689 => true, // ER_LOCK_ABORTED
+                    _ => false,
+                }
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if statement violated an integrity constraint, i.e. duplicate key,
+    /// foreign key, `NOT NULL` or `CHECK` constraint (SQLSTATE class `23`).
+    pub fn is_constraint_violation(&self) -> bool {
+        match self.server_error() {
+            Some(err) => {
+                err.state.starts_with("23") || match err.code {
+                    1022 | // ER_DUP_KEY
+                    1048 | // ER_BAD_NULL_ERROR
+                    1062 | // ER_DUP_ENTRY
+                    1169 | // ER_DUP_UNIQUE
+                    1216 | // ER_NO_REFERENCED_ROW
+                    1217 | // ER_ROW_IS_REFERENCED
+                    1451 | // ER_ROW_IS_REFERENCED_2
+                    1452 | // ER_NO_REFERENCED_ROW_2
+                    1586 | // ER_DUP_ENTRY_WITH_KEY_NAME
+                    3819 => true, // ER_CHECK_CONSTRAINT_VIOLATED
+                    _ => false,
+                }
+            },
+            None => false,
+        }
+    }
 }
 
 impl error::Error for Error {
@@ -60,6 +186,8 @@ impl error::Error for Error {
             Error::SslError(_) => "ssl error",
             Error::FromRowError(_) => "from row conversion error",
             Error::FromValueError(_) => "from value conversion error",
+            Error::Utf8Error(_) => "invalid UTF-8 in text value",
+            Error::FromColumnError(_) => "from column conversion error",
         }
     }
 
@@ -72,6 +200,8 @@ impl error::Error for Error {
             Error::UrlError(_) => "url error",
             Error::FromRowError(_) => "from row conversion error",
             Error::FromValueError(_) => "from value conversion error",
+            Error::Utf8Error(_) => "invalid UTF-8 in text value",
+            Error::FromColumnError(_) => "from column conversion error",
         }
     }
 
@@ -83,6 +213,8 @@ impl error::Error for Error {
             Error::MySqlError(ref err) => Some(err),
             Error::UrlError(ref err) => Some(err),
             Error::SslError(ref err) => Some(err),
+            Error::FromColumnError(ref err) => Some(err),
+            Error::Utf8Error(ref err) => Some(err),
             _ => None
         }
     }
@@ -94,11 +226,50 @@ impl error::Error for Error {
             Error::DriverError(ref err) => Some(err),
             Error::MySqlError(ref err) => Some(err),
             Error::UrlError(ref err) => Some(err),
+            Error::FromColumnError(ref err) => Some(err),
+            Error::Utf8Error(ref err) => Some(err),
             _ => None
         }
     }
 }
 
+/// Failed conversion of a value taken from a row via
+/// [`Row::get_opt`](struct.Row.html#method.get_opt) or
+/// [`Row::take_opt`](struct.Row.html#method.take_opt).
+#[derive(Clone, PartialEq)]
+pub struct FromColumnError {
+    /// Index of a column.
+    pub index: usize,
+    /// Name of a column (`None` if row was not produced by `QueryResult`).
+    pub name: Option<String>,
+    /// Value which could not be converted.
+    pub value: Value,
+    /// Name of a type the value was converted to (as given by `std::any::type_name`).
+    pub type_name: &'static str,
+}
+
+impl fmt::Display for FromColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(ref name) => try!(write!(f, "column `{}' (index {})", name, self.index)),
+            None => try!(write!(f, "column with index {}", self.index)),
+        }
+        write!(f, ": cannot convert {} to `{}'", self.value.as_sql(false), self.type_name)
+    }
+}
+
+impl fmt::Debug for FromColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl error::Error for FromColumnError {
+    fn description(&self) -> &str {
+        "from column conversion error"
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
         Error::IoError(err)
@@ -131,6 +302,24 @@ impl From<SslError> for Error {
     }
 }
 
+#[cfg(feature = "openssl")]
+impl From<ErrorStack> for Error {
+    fn from(err: ErrorStack) -> Error {
+        Error::SslError(SslError::Ssl(err))
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl<S: fmt::Debug> From<HandshakeError<S>> for Error {
+    fn from(err: HandshakeError<S>) -> Error {
+        match err {
+            HandshakeError::SetupFailure(err) => err.into(),
+            HandshakeError::Failure(stream) => Error::SslError(stream.into_error()),
+            HandshakeError::Interrupted(stream) => Error::SslError(stream.into_error()),
+        }
+    }
+}
+
 impl From<UrlError> for Error {
     fn from(err: UrlError) -> Error {
         Error::UrlError(err)
@@ -146,8 +335,13 @@ impl fmt::Display for Error {
             Error::DriverError(ref err) => write!(f, "DriverError {{ {} }}", err),
             Error::UrlError(ref err) => write!(f, "UrlError {{ {} }}", err),
             Error::SslError(ref err) => write!(f, "SslError {{ {} }}", err),
-            Error::FromRowError(_) => "from row conversion error".fmt(f),
+            Error::FromRowError(ref row) => match row.conversion_error() {
+                Some(err) => write!(f, "from row conversion error: {}", err),
+                None => "from row conversion error".fmt(f),
+            },
             Error::FromValueError(_) => "from value conversion error".fmt(f),
+            Error::Utf8Error(ref err) => write!(f, "Utf8Error {{ {} }}", err),
+            Error::FromColumnError(ref err) => write!(f, "FromColumnError {{ {} }}", err),
         }
     }
 }
@@ -160,8 +354,13 @@ impl fmt::Display for Error {
             Error::MySqlError(ref err) => write!(f, "MySqlError {{ {} }}", err),
             Error::DriverError(ref err) => write!(f, "DriverError {{ {} }}", err),
             Error::UrlError(ref err) => write!(f, "UrlError {{ {} }}", err),
-            Error::FromRowError(_) => "from row conversion error".fmt(f),
+            Error::FromRowError(ref row) => match row.conversion_error() {
+                Some(err) => write!(f, "from row conversion error: {}", err),
+                None => "from row conversion error".fmt(f),
+            },
             Error::FromValueError(_) => "from value conversion error".fmt(f),
+            Error::Utf8Error(ref err) => write!(f, "Utf8Error {{ {} }}", err),
+            Error::FromColumnError(ref err) => write!(f, "FromColumnError {{ {} }}", err),
         }
     }
 }
@@ -177,7 +376,8 @@ pub enum DriverError {
     CouldNotConnect(Option<String>),
     UnsupportedProtocol(u8),
     PacketOutOfSync,
-    PacketTooLarge,
+    /// Payload length and `max_allowed_packet`.
+    PacketTooLarge(usize, usize),
     Protocol41NotSet,
     UnexpectedPacket,
     MismatchedStmtParams(u16, usize),
@@ -188,12 +388,62 @@ pub enum DriverError {
     ReadOnlyTransNotSupported,
     PoisonedPoolMutex,
     Timeout,
+    MissingNamedParameter(String),
+    NamedParamsForPositionalQuery,
+    UnsupportedAuthPlugin(String),
+    CleartextPasswordNotAllowed,
+    ResultNotConsumed,
+    MultiStatementsDisabled,
+    /// `ResultBudget::max_bytes`.
+    ResultBudgetExceeded(u64),
+    /// `Opts::max_rows` (or `QueryResult::limit`) exceeded by a shortcut such as `Conn::exec`
+    /// which drops its `QueryResult`.
+    RowLimitExceeded(u64),
+    InvalidCharset(String),
+    /// Statement was killed via [`CancellationToken`](../conn/struct.CancellationToken.html).
+    QueryCancelled,
+    /// [`ShardResolver`](../conn/sharded/trait.ShardResolver.html) returned id of a shard
+    /// without a pool.
+    UnknownShard(usize),
+    /// Index of a statement parameter and its type reported by server.
+    InvalidStmtParam(usize, ColumnType),
+    /// Server rejected credentials (e.g. `ER_ACCESS_DENIED_ERROR`). Server error and name of
+    /// an auth plugin used by the client.
+    ///
+    /// Breaking change: this error was previously returned as `Error::MySqlError`. Use
+    /// [`Error::server_error`](enum.Error.html#method.server_error) to match on error code.
+    AuthFailed(MySqlError, String),
+    /// Server does not support auth plugin used by the client (`ER_NOT_SUPPORTED_AUTH_MODE`).
+    /// Server error, plugin used by the client and default plugin of the server.
+    AuthPluginMismatch(MySqlError, String, String),
+    /// Server requires SSL for the account or globally (`ER_SECURE_TRANSPORT_REQUIRED`), but
+    /// connection is not secure.
+    SecureTransportRequired(MySqlError),
+    /// Server could not handle a handshake response (`ER_HANDSHAKE_ERROR`). Server error and
+    /// capabilities sent by the client.
+    HandshakeRejected(MySqlError, CapabilityFlags),
+    /// SSL was requested via `Opts::ssl_opts` for a connection created by
+    /// [`Conn::new_with_stream`](../conn/struct.Conn.html#method.new_with_stream).
+    SslOverCustomStream,
+    /// Name of an `Opts` field which is set but is not supported by a connection type (e.g.
+    /// `ssl_opts` of an X Protocol connection).
+    UnsupportedOption(&'static str),
 }
 
 impl error::Error for DriverError {
     fn description(&self) -> &str {
         "MySql driver error"
     }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            DriverError::AuthFailed(ref err, _) |
+            DriverError::AuthPluginMismatch(ref err, _, _) |
+            DriverError::SecureTransportRequired(ref err) |
+            DriverError::HandshakeRejected(ref err, _) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for DriverError {
@@ -211,8 +461,9 @@ impl fmt::Display for DriverError {
             DriverError::PacketOutOfSync => {
                 write!(f, "Packet out of sync")
             }
-            DriverError::PacketTooLarge => {
-                write!(f, "Packet too large")
+            DriverError::PacketTooLarge(len, max_allowed_packet) => {
+                write!(f, "Packet of {} bytes exceeds max_allowed_packet ({} bytes)",
+                       len, max_allowed_packet)
             }
             DriverError::Protocol41NotSet => {
                 write!(f, "Server must set CLIENT_PROTOCOL_41 flag")
@@ -244,7 +495,66 @@ impl fmt::Display for DriverError {
             },
             DriverError::Timeout => {
                 write!(f, "Operation timed out")
-            }
+            },
+            DriverError::MissingNamedParameter(ref name) => {
+                write!(f, "Missing named parameter `{}` for statement", name)
+            },
+            DriverError::NamedParamsForPositionalQuery => {
+                write!(f, "Can not pass named parameters to statement with positional parameters")
+            },
+            DriverError::UnsupportedAuthPlugin(ref name) => {
+                write!(f, "Unsupported authentication plugin `{}`", name)
+            },
+            DriverError::CleartextPasswordNotAllowed => {
+                write!(f, "mysql_clear_password authentication plugin requires SSL, \
+                           unix socket or named pipe connection")
+            },
+            DriverError::ResultNotConsumed => {
+                write!(f, "Can not send a command while result set of previous command is not \
+                           consumed")
+            },
+            DriverError::ResultBudgetExceeded(max_bytes) => {
+                write!(f, "Rows of a query exceed memory budget of {} bytes", max_bytes)
+            },
+            DriverError::RowLimitExceeded(max_rows) => {
+                write!(f, "Query returned more than {} rows", max_rows)
+            },
+            DriverError::MultiStatementsDisabled => {
+                write!(f, "Multi-statement queries are disabled by Opts::multi_statements")
+            },
+            DriverError::InvalidCharset(ref name) => {
+                write!(f, "Invalid character set or collation name `{}'", name)
+            },
+            DriverError::QueryCancelled => {
+                write!(f, "Query was cancelled")
+            },
+            DriverError::UnknownShard(id) => {
+                write!(f, "Shard resolver returned unknown shard id {}", id)
+            },
+            DriverError::InvalidStmtParam(index, column_type) => {
+                write!(f, "Value of statement parameter {} is not convertible to {:?}",
+                       index, column_type)
+            },
+            DriverError::AuthFailed(ref err, ref plugin) => {
+                write!(f, "Authentication via `{}` failed: {}", plugin, err)
+            },
+            DriverError::AuthPluginMismatch(ref err, ref plugin, ref server_plugin) => {
+                write!(f, "Server does not support authentication plugin `{}` (server default \
+                           is `{}`): {}",
+                       plugin, server_plugin, err)
+            },
+            DriverError::SecureTransportRequired(ref err) => {
+                write!(f, "Server requires secure connection, enable SSL: {}", err)
+            },
+            DriverError::HandshakeRejected(ref err, flags) => {
+                write!(f, "Server rejected handshake with client capabilities {:?}: {}", flags, err)
+            },
+            DriverError::SslOverCustomStream => {
+                write!(f, "SSL is not supported over a custom stream")
+            },
+            DriverError::UnsupportedOption(name) => {
+                write!(f, "Option `{}' is not supported by this connection", name)
+            },
         }
     }
 }
@@ -1038,3 +1348,61 @@ pub enum ServerError {
     ER_UNSUPPORTED_ENGINE = 1726u16,
     ER_BINLOG_UNSAFE_AUTOINC_NOT_FIRST = 1727u16,
 }
+
+#[cfg(test)]
+mod test {
+    use std::error;
+    use std::io;
+    use super::{DriverError, Error, MySqlError};
+
+    fn server_error(code: u16, state: &str) -> Error {
+        Error::MySqlError(MySqlError {
+            state: state.into(),
+            message: String::new(),
+            code: code,
+        })
+    }
+
+    #[test]
+    fn should_classify_errors() {
+        let deadlock = server_error(1213, "40001");
+        assert!(deadlock.is_deadlock());
+        assert!(deadlock.is_transient());
+        assert!(!deadlock.is_connection_error());
+
+        let lock_wait_timeout = server_error(1205, "HY000");
+        assert!(!lock_wait_timeout.is_deadlock());
+        assert!(lock_wait_timeout.is_transient());
+
+        let io_error = Error::IoError(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"));
+        assert!(io_error.is_connection_error());
+        assert!(io_error.is_transient());
+        assert!(server_error(1040, "08004").is_connection_error());
+        assert!(server_error(1158, "08S01").is_transient());
+        assert!(Error::DriverError(DriverError::Timeout).is_transient());
+        assert!(!Error::DriverError(DriverError::Timeout).is_connection_error());
+
+        assert!(server_error(1062, "23000").is_constraint_violation());
+        assert!(server_error(1452, "23000").is_constraint_violation());
+        assert!(server_error(3819, "HY000").is_constraint_violation());
+        assert!(!server_error(1062, "23000").is_transient());
+
+        let access_denied = MySqlError {
+            state: "28000".into(),
+            message: String::new(),
+            code: 1045,
+        };
+        let auth_failed = Error::DriverError(DriverError::AuthFailed(access_denied.clone(),
+                                                                      "foo".into()));
+        assert_eq!(auth_failed.server_error(), Some(&access_denied));
+        assert!(!auth_failed.is_transient());
+        let cause = error::Error::cause(&auth_failed).and_then(|err| err.cause());
+        assert_eq!(cause.map(ToString::to_string), Some(access_denied.to_string()));
+
+        let syntax_error = server_error(1064, "42000");
+        assert!(!syntax_error.is_deadlock());
+        assert!(!syntax_error.is_transient());
+        assert!(!syntax_error.is_connection_error());
+        assert!(!syntax_error.is_constraint_violation());
+    }
+}
@@ -0,0 +1,103 @@
+//! Escaping of dynamic parts of SQL text which could not be passed as statement parameters.
+//!
+//! Prefer placeholders whenever possible.
+
+/// Escapes `value` to be placed between single quotes of a string literal.
+///
+/// Special characters are escaped with backslash unless `no_backslash_escapes` is `true`
+/// (i.e. `NO_BACKSLASH_ESCAPES` SQL mode is enabled), in which case only single quotes are
+/// doubled. [`Conn::escape_string`](struct.Conn.html#method.escape_string) takes the mode
+/// from the connection's status.
+///
+/// ```
+/// # use mysql::escape_string;
+/// assert_eq!(escape_string("it's\n", false), "it\\'s\\n");
+/// assert_eq!(escape_string("it's\n", true), "it''s\n");
+/// ```
+pub fn escape_string(value: &str, no_backslash_escapes: bool) -> String {
+    let mut output = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        if no_backslash_escapes {
+            if c == '\'' {
+                output.push('\'');
+            }
+            output.push(c);
+        } else {
+            match c {
+                '\x00' => output.push_str("\\0"),
+                '\n' => output.push_str("\\n"),
+                '\r' => output.push_str("\\r"),
+                '\x1a' => output.push_str("\\Z"),
+                '\\' | '\'' | '"' => {
+                    output.push('\\');
+                    output.push(c);
+                },
+                c => output.push(c),
+            }
+        }
+    }
+    output
+}
+
+/// Escapes `%` and `_` wildcards (and the escape character itself) of a `LIKE` pattern with
+/// backslash, so that `value` is matched literally.
+///
+/// Backslash is the default escape character of `LIKE` unless `NO_BACKSLASH_ESCAPES` SQL mode
+/// is enabled, so add `ESCAPE '\'` clause in that mode. The result is still a string which
+/// should be passed as a parameter or escaped via `escape_string`.
+///
+/// ```
+/// # use mysql::escape_like;
+/// assert_eq!(escape_like("100%_sure"), "100\\%\\_sure");
+/// ```
+pub fn escape_like(value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        if c == '%' || c == '_' || c == '\\' {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output
+}
+
+/// Quotes identifier (e.g. table or column name) with backticks.
+///
+/// Backticks within the identifier are doubled. Qualified names (`db.table`) should be quoted
+/// part by part.
+///
+/// ```
+/// # use mysql::quote_identifier;
+/// assert_eq!(quote_identifier("order"), "`order`");
+/// assert_eq!(quote_identifier("we`ird"), "`we``ird`");
+/// ```
+pub fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace("`", "``"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape_like, escape_string, quote_identifier};
+
+    #[test]
+    fn should_escape_string() {
+        assert_eq!(escape_string("plain", false), "plain");
+        assert_eq!(escape_string("a'b\"c\\d\x00e\r\n\x1a", false),
+                   "a\\'b\\\"c\\\\d\\0e\\r\\n\\Z");
+        assert_eq!(escape_string("a'b\"c\\d", true), "a''b\"c\\d");
+        assert_eq!(escape_string("ü'", false), "ü\\'");
+    }
+
+    #[test]
+    fn should_escape_like() {
+        assert_eq!(escape_like("a%b_c\\d"), "a\\%b\\_c\\\\d");
+        assert_eq!(escape_like("plain"), "plain");
+    }
+
+    #[test]
+    fn should_quote_identifier() {
+        assert_eq!(quote_identifier("tbl"), "`tbl`");
+        assert_eq!(quote_identifier("a`b"), "`a``b`");
+        assert_eq!(quote_identifier(""), "``");
+    }
+}
@@ -0,0 +1,458 @@
+//! Spatial types support.
+//!
+//! MySql represents `GEOMETRY` values as 4-byte SRID followed by
+//! [WKB](http://dev.mysql.com/doc/refman/5.7/en/gis-data-formats.html#gis-wkb-format)
+//! representation of a geometry. Such values are received as `Value::Bytes` and could be
+//! converted to [`Geometry`](enum.Geometry.html), [`Point`](struct.Point.html) or
+//! [`Spatial<T>`](struct.Spatial.html) (if you need SRID) via `FromValue`. Conversions back to
+//! `Value` are also implemented, so spatial values could be used as statement parameters.
+//!
+//! ```ignore
+//! use mysql::geometry::{Geometry, Point, Spatial};
+//!
+//! let p: Spatial<Point> = from_value(row.take(0).unwrap());
+//! conn.prep_exec("INSERT INTO places (location) VALUES (?)", (Point { x: 1.0, y: 2.0 },));
+//! ```
+
+use std::io;
+
+use byteorder::{BigEndian as BE, LittleEndian as LE};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use super::error::{Error, Result as MyResult};
+use super::value::{ConvIr, FromValue, Value};
+
+const WKB_POINT: u32 = 1;
+const WKB_LINE_STRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTI_POINT: u32 = 4;
+const WKB_MULTI_LINE_STRING: u32 = 5;
+const WKB_MULTI_POLYGON: u32 = 6;
+const WKB_GEOMETRY_COLLECTION: u32 = 7;
+
+/// Two-dimensional point.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Geometry value.
+///
+/// Polygons are represented as a list of rings where the first ring is exterior.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Geometry {
+    Point(Point),
+    LineString(Vec<Point>),
+    Polygon(Vec<Vec<Point>>),
+    MultiPoint(Vec<Point>),
+    MultiLineString(Vec<Vec<Point>>),
+    MultiPolygon(Vec<Vec<Vec<Point>>>),
+    GeometryCollection(Vec<Geometry>),
+}
+
+/// Geometry along with its spatial reference system identifier.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Spatial<T> {
+    pub srid: u32,
+    pub geometry: T,
+}
+
+fn invalid_wkb() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Invalid WKB representation of a geometry")
+}
+
+struct WkbReader<'a> {
+    reader: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> WkbReader<'a> {
+    fn read_u32(&mut self) -> io::Result<u32> {
+        if self.little_endian {
+            self.reader.read_u32::<LE>()
+        } else {
+            self.reader.read_u32::<BE>()
+        }
+    }
+
+    fn read_f64(&mut self) -> io::Result<f64> {
+        if self.little_endian {
+            self.reader.read_f64::<LE>()
+        } else {
+            self.reader.read_f64::<BE>()
+        }
+    }
+
+    fn read_point(&mut self) -> io::Result<Point> {
+        let x = try!(self.read_f64());
+        let y = try!(self.read_f64());
+        Ok(Point { x: x, y: y })
+    }
+
+    fn read_points(&mut self) -> io::Result<Vec<Point>> {
+        let count = try!(self.read_u32()) as usize;
+        // Every point takes 16 bytes so it's safe to check length upfront.
+        if count > self.reader.len() / 16 {
+            return Err(invalid_wkb());
+        }
+        let mut points = Vec::with_capacity(count);
+        for _ in 0..count {
+            points.push(try!(self.read_point()));
+        }
+        Ok(points)
+    }
+
+    fn read_rings(&mut self) -> io::Result<Vec<Vec<Point>>> {
+        let count = try!(self.read_u32());
+        let mut rings = Vec::new();
+        for _ in 0..count {
+            rings.push(try!(self.read_points()));
+        }
+        Ok(rings)
+    }
+
+    /// Reads byte order and type of a nested geometry.
+    fn read_header(&mut self) -> io::Result<u32> {
+        self.little_endian = match try!(self.reader.read_u8()) {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid_wkb()),
+        };
+        self.read_u32()
+    }
+
+    fn read_geometry(&mut self) -> io::Result<Geometry> {
+        let geometry = match try!(self.read_header()) {
+            WKB_POINT => Geometry::Point(try!(self.read_point())),
+            WKB_LINE_STRING => Geometry::LineString(try!(self.read_points())),
+            WKB_POLYGON => Geometry::Polygon(try!(self.read_rings())),
+            WKB_MULTI_POINT => {
+                let count = try!(self.read_u32());
+                let mut points = Vec::new();
+                for _ in 0..count {
+                    match try!(self.read_geometry()) {
+                        Geometry::Point(p) => points.push(p),
+                        _ => return Err(invalid_wkb()),
+                    }
+                }
+                Geometry::MultiPoint(points)
+            },
+            WKB_MULTI_LINE_STRING => {
+                let count = try!(self.read_u32());
+                let mut lines = Vec::new();
+                for _ in 0..count {
+                    match try!(self.read_geometry()) {
+                        Geometry::LineString(l) => lines.push(l),
+                        _ => return Err(invalid_wkb()),
+                    }
+                }
+                Geometry::MultiLineString(lines)
+            },
+            WKB_MULTI_POLYGON => {
+                let count = try!(self.read_u32());
+                let mut polygons = Vec::new();
+                for _ in 0..count {
+                    match try!(self.read_geometry()) {
+                        Geometry::Polygon(p) => polygons.push(p),
+                        _ => return Err(invalid_wkb()),
+                    }
+                }
+                Geometry::MultiPolygon(polygons)
+            },
+            WKB_GEOMETRY_COLLECTION => {
+                let count = try!(self.read_u32());
+                let mut geometries = Vec::new();
+                for _ in 0..count {
+                    geometries.push(try!(self.read_geometry()));
+                }
+                Geometry::GeometryCollection(geometries)
+            },
+            _ => return Err(invalid_wkb()),
+        };
+        Ok(geometry)
+    }
+}
+
+fn write_points(writer: &mut Vec<u8>, points: &[Point]) -> io::Result<()> {
+    try!(writer.write_u32::<LE>(points.len() as u32));
+    for p in points {
+        try!(writer.write_f64::<LE>(p.x));
+        try!(writer.write_f64::<LE>(p.y));
+    }
+    Ok(())
+}
+
+fn write_rings(writer: &mut Vec<u8>, rings: &[Vec<Point>]) -> io::Result<()> {
+    try!(writer.write_u32::<LE>(rings.len() as u32));
+    for ring in rings {
+        try!(write_points(writer, ring));
+    }
+    Ok(())
+}
+
+fn write_header(writer: &mut Vec<u8>, wkb_type: u32) -> io::Result<()> {
+    try!(writer.write_u8(1));
+    writer.write_u32::<LE>(wkb_type)
+}
+
+impl Geometry {
+    /// Parses geometry from WKB representation.
+    pub fn from_wkb(wkb: &[u8]) -> io::Result<Geometry> {
+        let mut reader = WkbReader {
+            reader: wkb,
+            little_endian: true,
+        };
+        let geometry = try!(reader.read_geometry());
+        if reader.reader.len() > 0 {
+            return Err(invalid_wkb());
+        }
+        Ok(geometry)
+    }
+
+    /// Writes little endian WKB representation of a geometry.
+    pub fn write_wkb(&self, writer: &mut Vec<u8>) -> io::Result<()> {
+        match *self {
+            Geometry::Point(ref p) => {
+                try!(write_header(writer, WKB_POINT));
+                try!(writer.write_f64::<LE>(p.x));
+                writer.write_f64::<LE>(p.y)
+            },
+            Geometry::LineString(ref points) => {
+                try!(write_header(writer, WKB_LINE_STRING));
+                write_points(writer, points)
+            },
+            Geometry::Polygon(ref rings) => {
+                try!(write_header(writer, WKB_POLYGON));
+                write_rings(writer, rings)
+            },
+            Geometry::MultiPoint(ref points) => {
+                try!(write_header(writer, WKB_MULTI_POINT));
+                try!(writer.write_u32::<LE>(points.len() as u32));
+                for p in points {
+                    try!(Geometry::Point(*p).write_wkb(writer));
+                }
+                Ok(())
+            },
+            Geometry::MultiLineString(ref lines) => {
+                try!(write_header(writer, WKB_MULTI_LINE_STRING));
+                try!(writer.write_u32::<LE>(lines.len() as u32));
+                for line in lines {
+                    try!(write_header(writer, WKB_LINE_STRING));
+                    try!(write_points(writer, line));
+                }
+                Ok(())
+            },
+            Geometry::MultiPolygon(ref polygons) => {
+                try!(write_header(writer, WKB_MULTI_POLYGON));
+                try!(writer.write_u32::<LE>(polygons.len() as u32));
+                for polygon in polygons {
+                    try!(write_header(writer, WKB_POLYGON));
+                    try!(write_rings(writer, polygon));
+                }
+                Ok(())
+            },
+            Geometry::GeometryCollection(ref geometries) => {
+                try!(write_header(writer, WKB_GEOMETRY_COLLECTION));
+                try!(writer.write_u32::<LE>(geometries.len() as u32));
+                for geometry in geometries {
+                    try!(geometry.write_wkb(writer));
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Spatial<Geometry> {
+    /// Parses MySql internal geometry representation (SRID followed by WKB).
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Spatial<Geometry>> {
+        let mut reader = bytes;
+        let srid = try!(reader.read_u32::<LE>());
+        let geometry = try!(Geometry::from_wkb(reader));
+        Ok(Spatial {
+            srid: srid,
+            geometry: geometry,
+        })
+    }
+}
+
+impl<T: Into<Geometry>> Spatial<T> {
+    /// Returns MySql internal geometry representation (SRID followed by WKB).
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut writer = Vec::new();
+        writer.write_u32::<LE>(self.srid).unwrap();
+        self.geometry.into().write_wkb(&mut writer).unwrap();
+        writer
+    }
+}
+
+impl From<Point> for Geometry {
+    fn from(x: Point) -> Geometry {
+        Geometry::Point(x)
+    }
+}
+
+impl<T: Into<Geometry>> From<Spatial<T>> for Value {
+    fn from(x: Spatial<T>) -> Value {
+        Value::Bytes(x.into_bytes())
+    }
+}
+
+impl From<Geometry> for Value {
+    fn from(x: Geometry) -> Value {
+        Spatial { srid: 0, geometry: x }.into()
+    }
+}
+
+impl From<Point> for Value {
+    fn from(x: Point) -> Value {
+        Spatial { srid: 0, geometry: x }.into()
+    }
+}
+
+/// Intermediate result of a `Value` to spatial type conversion.
+#[derive(Debug)]
+pub struct GeometryIr<T> {
+    bytes: Vec<u8>,
+    output: T,
+}
+
+impl<T> GeometryIr<T> {
+    fn parse<F>(v: Value, f: F) -> MyResult<GeometryIr<T>>
+    where F: FnOnce(Spatial<Geometry>) -> Option<T> {
+        match v {
+            Value::Bytes(bytes) => {
+                let output = Spatial::<Geometry>::from_bytes(&*bytes).ok().and_then(f);
+                match output {
+                    Some(output) => Ok(GeometryIr {
+                        bytes: bytes,
+                        output: output,
+                    }),
+                    None => Err(Error::FromValueError(Value::Bytes(bytes))),
+                }
+            },
+            v => Err(Error::FromValueError(v)),
+        }
+    }
+}
+
+impl ConvIr<Spatial<Geometry>> for GeometryIr<Spatial<Geometry>> {
+    fn new(v: Value) -> MyResult<GeometryIr<Spatial<Geometry>>> {
+        GeometryIr::parse(v, Some)
+    }
+    fn commit(self) -> Spatial<Geometry> {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        Value::Bytes(self.bytes)
+    }
+}
+
+impl ConvIr<Spatial<Point>> for GeometryIr<Spatial<Point>> {
+    fn new(v: Value) -> MyResult<GeometryIr<Spatial<Point>>> {
+        GeometryIr::parse(v, |x| match x.geometry {
+            Geometry::Point(p) => Some(Spatial { srid: x.srid, geometry: p }),
+            _ => None,
+        })
+    }
+    fn commit(self) -> Spatial<Point> {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        Value::Bytes(self.bytes)
+    }
+}
+
+impl ConvIr<Geometry> for GeometryIr<Geometry> {
+    fn new(v: Value) -> MyResult<GeometryIr<Geometry>> {
+        GeometryIr::parse(v, |x| Some(x.geometry))
+    }
+    fn commit(self) -> Geometry {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        Value::Bytes(self.bytes)
+    }
+}
+
+impl ConvIr<Point> for GeometryIr<Point> {
+    fn new(v: Value) -> MyResult<GeometryIr<Point>> {
+        GeometryIr::parse(v, |x| match x.geometry {
+            Geometry::Point(p) => Some(p),
+            _ => None,
+        })
+    }
+    fn commit(self) -> Point {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        Value::Bytes(self.bytes)
+    }
+}
+
+impl FromValue for Spatial<Geometry> {
+    type Intermediate = GeometryIr<Spatial<Geometry>>;
+}
+
+impl FromValue for Spatial<Point> {
+    type Intermediate = GeometryIr<Spatial<Point>>;
+}
+
+impl FromValue for Geometry {
+    type Intermediate = GeometryIr<Geometry>;
+}
+
+impl FromValue for Point {
+    type Intermediate = GeometryIr<Point>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Geometry, Point, Spatial};
+    use super::super::value::{from_value, from_value_opt, Value};
+
+    #[test]
+    fn should_parse_point() {
+        // SELECT ST_GeomFromText('POINT(1 -1)', 4326)
+        let bytes = b"\xe6\x10\x00\x00\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\xf0\x3f\
+                      \x00\x00\x00\x00\x00\x00\xf0\xbf".to_vec();
+        let p = from_value::<Spatial<Point>>(Value::Bytes(bytes.clone()));
+        assert_eq!(p, Spatial { srid: 4326, geometry: Point { x: 1.0, y: -1.0 } });
+        assert_eq!(Value::from(p), Value::Bytes(bytes));
+    }
+
+    #[test]
+    fn should_parse_big_endian_wkb() {
+        let wkb = b"\x00\x00\x00\x00\x02\x00\x00\x00\x01\
+                    \x3f\xf0\x00\x00\x00\x00\x00\x00\x40\x00\x00\x00\x00\x00\x00\x00";
+        assert_eq!(Geometry::from_wkb(&wkb[..]).unwrap(),
+                   Geometry::LineString(vec![Point { x: 1.0, y: 2.0 }]));
+    }
+
+    #[test]
+    fn should_roundtrip_geometries() {
+        let square = vec![Point { x: 0.0, y: 0.0 }, Point { x: 1.0, y: 0.0 },
+                          Point { x: 1.0, y: 1.0 }, Point { x: 0.0, y: 0.0 }];
+        let geometries = vec![
+            Geometry::Polygon(vec![square.clone()]),
+            Geometry::MultiPoint(square.clone()),
+            Geometry::MultiLineString(vec![square.clone(), square.clone()]),
+            Geometry::MultiPolygon(vec![vec![square.clone()], vec![square.clone()]]),
+            Geometry::GeometryCollection(vec![Geometry::Point(square[1]),
+                                              Geometry::LineString(square.clone())]),
+        ];
+        for geometry in geometries {
+            assert_eq!(from_value::<Geometry>(geometry.clone().into()), geometry);
+        }
+    }
+
+    #[test]
+    fn should_not_convert_invalid_values() {
+        assert!(from_value_opt::<Geometry>(Value::Int(1)).is_err());
+        assert!(from_value_opt::<Geometry>(Value::Bytes(b"\x00\x00\x00\x00\x01".to_vec())).is_err());
+        let line: Value = Geometry::LineString(vec![]).into();
+        assert!(from_value_opt::<Point>(line.clone()).is_err());
+        assert!(from_value_opt::<Geometry>(line).is_ok());
+    }
+}
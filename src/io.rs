@@ -15,7 +15,9 @@ use super::error::DriverError::PacketOutOfSync;
 use super::error::Result as MyResult;
 
 #[cfg(feature = "openssl")]
-use openssl::{ssl, x509};
+use openssl::{pkcs12, pkey, ssl, x509};
+#[cfg(feature = "openssl")]
+use super::conn::{Certificate, Identity, Opts, SslOpts, TlsVersion};
 use bufstream::BufStream;
 use byteorder::ByteOrder;
 use byteorder::ReadBytesExt;
@@ -235,12 +237,12 @@ pub trait Write: WriteBytesExt {
     }
 
     fn write_packet(&mut self, data: &[u8], mut seq_id: u8, max_allowed_packet: usize) -> MyResult<u8> {
-        if data.len() > max_allowed_packet && max_allowed_packet < consts::MAX_PAYLOAD_LEN {
-            return Err(DriverError(PacketTooLarge));
+        if data.len() > max_allowed_packet {
+            return Err(DriverError(PacketTooLarge(data.len(), max_allowed_packet)));
         }
         if data.len() == 0 {
             try!(self.write_all(&[0, 0, 0, seq_id]));
-            seq_id += 1;
+            seq_id = seq_id.wrapping_add(1);
         } else {
             let mut last_was_max = false;
             for chunk in data.chunks(consts::MAX_PAYLOAD_LEN) {
@@ -249,11 +251,11 @@ pub trait Write: WriteBytesExt {
                 try!(self.write_u8(seq_id));
                 try!(self.write_all(chunk));
                 last_was_max = chunk_len == consts::MAX_PAYLOAD_LEN;
-                seq_id += 1;
+                seq_id = seq_id.wrapping_add(1);
             }
             if last_was_max {
                 try!(self.write_all(&[0u8, 0u8, 0u8, seq_id]));
-                seq_id += 1;
+                seq_id = seq_id.wrapping_add(1);
             }
         }
         try!(self.flush());
@@ -270,6 +272,16 @@ pub enum Stream {
     #[cfg(feature = "pipe")]
     PipeStream(BufStream<np::PipeClient>),
     TcpStream(Option<TcpStream>),
+    Custom(BufStream<CustomStream>),
+}
+
+impl Stream {
+    pub fn is_custom(&self) -> bool {
+        match *self {
+            Stream::Custom(_) => true,
+            _ => false,
+        }
+    }
 }
 
 #[cfg(feature = "openssl")]
@@ -280,35 +292,82 @@ impl Stream {
             _ => false,
         }
     }
-    pub fn make_secure(mut self,
-                       verify_peer: bool,
-                       ssl_opts: &Option<(::std::path::PathBuf,
-                                          Option<(::std::path::PathBuf, ::std::path::PathBuf)>)>)
-    -> MyResult<Stream>
-    {
-        if self.is_insecure() {
-            let mut ctx = try!(ssl::SslContext::new(ssl::SslMethod::Tlsv1));
-            let mode = if verify_peer {
-                ssl::SSL_VERIFY_PEER
+    fn ssl_connector(ssl_opts: &SslOpts) -> MyResult<ssl::SslConnector> {
+        let mut builder = try!(ssl::SslConnectorBuilder::new(ssl::SslMethod::tls()));
+        {
+            let ctx = builder.builder_mut();
+            if ssl_opts.danger_accept_invalid_certs {
+                ctx.set_verify(ssl::SSL_VERIFY_NONE);
             } else {
-                ssl::SSL_VERIFY_NONE
-            };
-            ctx.set_verify(mode, None);
-            match *ssl_opts {
-                Some((ref ca_cert, None)) => try!(ctx.set_CA_file(&ca_cert)),
-                Some((ref ca_cert, Some((ref client_cert, ref client_key)))) => {
-                    try!(ctx.set_CA_file(&ca_cert));
-                    try!(ctx.set_certificate_file(&client_cert, x509::X509FileType::PEM));
-                    try!(ctx.set_private_key_file(&client_key, x509::X509FileType::PEM));
+                ctx.set_verify(ssl::SSL_VERIFY_PEER);
+            }
+            if let Some(ref ca_cert) = ssl_opts.root_cert_path {
+                try!(ctx.set_ca_file(ca_cert));
+            }
+            if let Some(ref root_cert) = ssl_opts.root_cert {
+                try!(ctx.cert_store_mut().add_cert(try!(load_certificate(root_cert))));
+            }
+            if let (&Some((ref client_cert, ref client_key)), None) =
+                (&ssl_opts.client_identity_path, ssl_opts.client_identity.as_ref())
+            {
+                try!(ctx.set_certificate_file(client_cert, x509::X509_FILETYPE_PEM));
+                try!(ctx.set_private_key_file(client_key, x509::X509_FILETYPE_PEM));
+            }
+            if !ssl_opts.tls_versions.is_empty() {
+                let versions = [(TlsVersion::Tlsv1_0, ssl::SSL_OP_NO_TLSV1),
+                                (TlsVersion::Tlsv1_1, ssl::SSL_OP_NO_TLSV1_1),
+                                (TlsVersion::Tlsv1_2, ssl::SSL_OP_NO_TLSV1_2)];
+                for &(version, option) in versions.iter() {
+                    if !ssl_opts.tls_versions.contains(&version) {
+                        ctx.set_options(option);
+                    }
+                }
+            }
+            if let Some(ref cipher_list) = ssl_opts.cipher_list {
+                try!(ctx.set_cipher_list(cipher_list));
+            }
+            match ssl_opts.client_identity {
+                Some(Identity::CertKey(ref cert, ref key)) => {
+                    try!(ctx.set_certificate(&try!(load_certificate(cert))));
+                    try!(ctx.set_private_key(&try!(pkey::PKey::private_key_from_pem(key))));
                 },
-                _ => unreachable!(),
+                Some(Identity::Pkcs12(ref der, ref password)) => {
+                    let identity = try!(try!(pkcs12::Pkcs12::from_der(der)).parse(password));
+                    try!(ctx.set_certificate(&identity.cert));
+                    try!(ctx.set_private_key(&identity.pkey));
+                    for cert in identity.chain {
+                        try!(ctx.add_extra_chain_cert(cert));
+                    }
+                },
+                None => (),
             }
+        }
+        Ok(builder.build())
+    }
+
+    pub fn make_secure(mut self, opts: &Opts) -> MyResult<Stream> {
+        let ssl_opts = match opts.ssl_opts {
+            Some(ref ssl_opts) => ssl_opts,
+            None => return Ok(self),
+        };
+        if self.is_insecure() {
+            let connector = try!(Stream::ssl_connector(ssl_opts));
             match self {
                 Stream::TcpStream(ref mut opt_stream) if opt_stream.is_some() => {
                     let stream = opt_stream.take().unwrap();
                     match stream {
                         TcpStream::Insecure(stream) => {
-                            let sstream = try!(ssl::SslStream::connect(&ctx, stream.into_inner().unwrap()));
+                            let stream = stream.into_inner().unwrap();
+                            let sstream = if !ssl_opts.danger_accept_invalid_certs &&
+                                             !ssl_opts.danger_skip_domain_validation {
+                                let domain = opts.ip_or_hostname.as_ref()
+                                    .map(|x| &**x)
+                                    .unwrap_or("127.0.0.1");
+                                try!(connector.connect(domain, stream))
+                            } else {
+                                try!(connector.danger_connect_without_providing_domain_for_certificate_verification_and_server_name_indication(
+                                    stream))
+                            };
                             Ok(Stream::TcpStream(Some(TcpStream::Secure(BufStream::new(sstream)))))
                         },
                         _ => unreachable!(),
@@ -323,6 +382,15 @@ impl Stream {
     }
 }
 
+#[cfg(feature = "openssl")]
+fn load_certificate(cert: &Certificate) -> MyResult<x509::X509> {
+    let cert = match *cert {
+        Certificate::Pem(ref pem) => try!(x509::X509::from_pem(pem)),
+        Certificate::Der(ref der) => try!(x509::X509::from_der(der)),
+    };
+    Ok(cert)
+}
+
 impl Drop for Stream {
     fn drop(&mut self) {
         if let &mut Stream::TcpStream(None) = self {
@@ -330,6 +398,9 @@ impl Drop for Stream {
         }
         let _ = self.write_packet(&[Command::COM_QUIT as u8], 0, consts::MAX_PAYLOAD_LEN);
         let _ = self.flush();
+        if let Stream::Custom(ref mut s) = *self {
+            let _ = s.get_mut().0.shutdown();
+        }
     }
 }
 
@@ -339,6 +410,7 @@ impl io::Read for Stream {
         match *self {
             Stream::UnixStream(ref mut s) => s.read(buf),
             Stream::TcpStream(Some(ref mut s)) => s.read(buf),
+            Stream::Custom(ref mut s) => s.read(buf),
             _ => panic!("Incomplete stream"),
         }
     }
@@ -348,6 +420,7 @@ impl io::Read for Stream {
         match *self {
             Stream::PipeStream(ref mut s) => s.read(buf),
             Stream::TcpStream(Some(ref mut s)) => s.read(buf),
+            Stream::Custom(ref mut s) => s.read(buf),
             _ => panic!("Incomplete stream"),
         }
     }
@@ -356,6 +429,7 @@ impl io::Read for Stream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match *self {
             Stream::TcpStream(Some(ref mut s)) => s.read(buf),
+            Stream::Custom(ref mut s) => s.read(buf),
             _ => panic!("Incomplete stream"),
         }
     }
@@ -367,6 +441,7 @@ impl io::Write for Stream {
         match *self {
             Stream::UnixStream(ref mut s) => s.write(buf),
             Stream::TcpStream(Some(ref mut s)) => s.write(buf),
+            Stream::Custom(ref mut s) => s.write(buf),
             _ => panic!("Incomplete stream"),
         }
     }
@@ -376,6 +451,7 @@ impl io::Write for Stream {
         match *self {
             Stream::UnixStream(ref mut s) => s.flush(),
             Stream::TcpStream(Some(ref mut s)) => s.flush(),
+            Stream::Custom(ref mut s) => s.flush(),
             _ => panic!("Incomplete stream"),
         }
     }
@@ -385,6 +461,7 @@ impl io::Write for Stream {
         match *self {
             Stream::PipeStream(ref mut s) => s.write(buf),
             Stream::TcpStream(Some(ref mut s)) => s.write(buf),
+            Stream::Custom(ref mut s) => s.write(buf),
             _ => panic!("Incomplete stream"),
         }
     }
@@ -394,6 +471,7 @@ impl io::Write for Stream {
         match *self {
             Stream::PipeStream(ref mut s) => s.flush(),
             Stream::TcpStream(Some(ref mut s)) => s.flush(),
+            Stream::Custom(ref mut s) => s.flush(),
             _ => panic!("Incomplete stream"),
         }
     }
@@ -402,6 +480,7 @@ impl io::Write for Stream {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match *self {
             Stream::TcpStream(Some(ref mut s)) => s.write(buf),
+            Stream::Custom(ref mut s) => s.write(buf),
             _ => panic!("Incomplete stream"),
         }
     }
@@ -410,11 +489,37 @@ impl io::Write for Stream {
     fn flush(&mut self) -> io::Result<()> {
         match *self {
             Stream::TcpStream(Some(ref mut s)) => s.flush(),
+            Stream::Custom(ref mut s) => s.flush(),
             _ => panic!("Incomplete stream"),
         }
     }
 }
 
+/// Stream passed to `Conn::new_with_stream`.
+pub struct CustomStream(pub Box<super::conn::Stream>);
+
+impl io::Read for CustomStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl io::Write for CustomStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl fmt::Debug for CustomStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Custom stream")
+    }
+}
+
 pub enum TcpStream {
     #[cfg(feature = "openssl")]
     Secure(BufStream<ssl::SslStream<net::TcpStream>>),
@@ -488,3 +593,40 @@ impl io::Write for TcpStream {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Write;
+    use consts;
+    use error::Error::DriverError;
+    use error::DriverError::PacketTooLarge;
+
+    #[test]
+    fn should_split_packets() {
+        let mut output = Vec::new();
+        let data = vec![0u8; consts::MAX_PAYLOAD_LEN + 10];
+        let seq_id = output.write_packet(&*data, 0, usize::max_value()).unwrap();
+        assert_eq!(seq_id, 2);
+        assert_eq!(&output[..4], &[0xff, 0xff, 0xff, 0]);
+        let second = 4 + consts::MAX_PAYLOAD_LEN;
+        assert_eq!(&output[second..second + 4], &[10, 0, 0, 1]);
+        assert_eq!(output.len(), second + 4 + 10);
+
+        // Payload of exactly MAX_PAYLOAD_LEN bytes is followed by an empty packet.
+        let mut output = Vec::new();
+        let data = vec![0u8; consts::MAX_PAYLOAD_LEN];
+        let seq_id = output.write_packet(&*data, 5, usize::max_value()).unwrap();
+        assert_eq!(seq_id, 7);
+        assert_eq!(&output[output.len() - 4..], &[0, 0, 0, 6]);
+    }
+
+    #[test]
+    fn should_not_write_packet_larger_than_max_allowed_packet() {
+        let mut output = Vec::new();
+        match output.write_packet(&[0u8; 10], 0, 9) {
+            Err(DriverError(PacketTooLarge(10, 9))) => (),
+            _ => panic!("Expected PacketTooLarge error"),
+        }
+        assert!(output.is_empty());
+    }
+}
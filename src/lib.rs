@@ -20,6 +20,31 @@
 //! features = ["socket"]
 //! ```
 //!
+//! `#[derive(FromRow, IntoParams)]` for structs with named fields and `#[derive(IntoValue)]`
+//! for newtypes are available via `derive` cargo feature:
+//!
+//! ```toml
+//! [dependencies.mysql]
+//! mysql = "*"
+//! features = ["derive"]
+//! ```
+//!
+//! With `log` cargo feature driver will write `debug` records with `mysql` target for
+//! connection establishment, queries, statement preparation and execution and pool
+//! checkouts/checkins (see `Opts::log_sql_max_len`).
+//!
+//! `DECIMAL` values could be converted to and from `rust_decimal::Decimal` or
+//! `bigdecimal::BigDecimal` via `rust_decimal` or `bigdecimal` cargo features. Without them use
+//! [`Decimal`](value/struct.Decimal.html).
+//!
+//! `metrics` cargo feature enables export of pool and query metrics (checkouts, checkout wait
+//! time, connection errors, query latency) to a recorder registered via
+//! [`set_metrics_recorder`](conn/metrics/fn.set_metrics_recorder.html).
+//!
+//! `uuid` cargo feature enables conversions of `uuid::Uuid` values. `Uuid` is sent to the server
+//! as 16 bytes, so it matches a `BINARY(16)` column; use `uuid.hyphenated().to_string()` to
+//! store it in a `CHAR(36)` column. Both representations could be read back into `Uuid`.
+//!
 //! #### Windows support (since 0.18.0)
 //!
 //! Currently rust-mysql-simple has no support for SSL on Windows.
@@ -150,40 +175,208 @@ extern crate url;
 extern crate bufstream;
 extern crate fnv;
 extern crate chrono;
+extern crate socket2;
+#[cfg(feature = "rust_decimal")]
+extern crate rust_decimal;
+#[cfg(feature = "bigdecimal")]
+extern crate bigdecimal;
+#[cfg(feature = "uuid")]
+extern crate uuid;
+#[cfg(feature = "derive")]
+#[allow(unused_imports)]
+#[macro_use]
+extern crate mysql_derive;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+
+/// Writes `debug` record with `mysql` target if `log` feature is enabled.
+#[cfg(feature = "log")]
+macro_rules! mysql_debug {
+    ($($arg:tt)+) => (debug!(target: "mysql", $($arg)+));
+}
+
+/// Writes `debug` record with `mysql` target if `log` feature is enabled.
+#[cfg(not(feature = "log"))]
+macro_rules! mysql_debug {
+    ($($arg:tt)+) => ({
+        if false {
+            let _ = format!($($arg)+);
+        }
+    });
+}
+
+/// Writes `warn` record with `mysql` target if `log` feature is enabled.
+#[cfg(feature = "log")]
+macro_rules! mysql_warn {
+    ($($arg:tt)+) => (warn!(target: "mysql", $($arg)+));
+}
+
+/// Writes `warn` record with `mysql` target if `log` feature is enabled.
+#[cfg(not(feature = "log"))]
+macro_rules! mysql_warn {
+    ($($arg:tt)+) => ({
+        if false {
+            let _ = format!($($arg)+);
+        }
+    });
+}
 
 mod scramble;
 pub mod consts;
 pub mod error;
+mod escape;
 mod packet;
 mod io;
 pub mod value;
+pub mod geometry;
+pub mod qb;
 pub mod conn;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+#[cfg(feature = "mysqlx")]
+pub mod mysqlx;
 
+#[cfg(feature = "ssl")]
+#[doc(inline)]
+pub use conn::Certificate;
+#[doc(inline)]
+pub use conn::CancellationToken;
 #[doc(inline)]
 pub use conn::Column;
 #[doc(inline)]
+pub use conn::ColumnIndex;
+#[doc(inline)]
 pub use conn::Conn;
 #[doc(inline)]
+pub use conn::ColumnInfo;
+#[doc(inline)]
+pub use conn::CsvOptions;
+#[doc(inline)]
+pub use conn::DropBehavior;
+#[doc(inline)]
+pub use conn::GtidSet;
+#[cfg(feature = "ssl")]
+#[doc(inline)]
+pub use conn::Identity;
+#[doc(inline)]
+pub use conn::InitFn;
+#[doc(inline)]
+pub use conn::in_list;
+#[doc(inline)]
+pub use conn::AccessMode;
+#[doc(inline)]
+pub use conn::Interceptor;
+#[doc(inline)]
 pub use conn::IsolationLevel;
 #[doc(inline)]
+pub use conn::MasterStatus;
+#[cfg(feature = "metrics")]
+#[doc(inline)]
+pub use conn::metrics::{clear_metrics_recorder, set_metrics_recorder, MetricsRecorder};
+#[doc(inline)]
+pub use conn::OnBudgetExceeded;
+#[doc(inline)]
 pub use conn::Opts;
 #[doc(inline)]
+pub use conn::ParseGtidSetError;
+#[doc(inline)]
+pub use conn::ProcessInfo;
+#[doc(inline)]
+pub use conn::ProcResult;
+#[doc(inline)]
+pub use conn::QueryInterceptor;
+#[doc(inline)]
+pub use conn::QueryKind;
+#[doc(inline)]
+pub use conn::MultiResult;
+#[doc(inline)]
+pub use conn::Packet;
+#[doc(inline)]
+pub use conn::PacketDirection;
+#[doc(inline)]
+pub use conn::PacketTracer;
+#[doc(inline)]
 pub use conn::QueryResult;
 #[doc(inline)]
+pub use conn::RawResponse;
+#[doc(inline)]
+pub use conn::Record;
+#[doc(inline)]
+pub use conn::Recorder;
+#[doc(inline)]
+pub use conn::ReplicaStatus;
+#[doc(inline)]
+pub use conn::ResolveStrategy;
+#[doc(inline)]
+pub use conn::ResultBudget;
+#[doc(inline)]
+pub use conn::ResultSet;
+#[doc(inline)]
+pub use conn::RetryOn;
+#[doc(inline)]
+pub use conn::RetryPolicy;
+#[doc(inline)]
 pub use conn::Row;
 #[doc(inline)]
+pub use conn::ServerStatistics;
+#[doc(inline)]
+pub use conn::SharedSink;
+#[doc(inline)]
+pub use conn::Sink;
+#[doc(inline)]
+pub use conn::RowRef;
+#[doc(inline)]
+pub use conn::RowsRef;
+#[cfg(feature = "ssl")]
+#[doc(inline)]
+pub use conn::SslOpts;
+#[doc(inline)]
 pub use conn::Stmt;
 #[doc(inline)]
+pub use conn::Stream;
+#[doc(inline)]
+pub use conn::TextCoercion;
+#[cfg(feature = "ssl")]
+#[doc(inline)]
+pub use conn::TlsVersion;
+#[doc(inline)]
 pub use conn::Transaction;
 #[doc(inline)]
+pub use conn::TxOpts;
+#[doc(inline)]
+pub use conn::Warning;
+#[doc(inline)]
+pub use conn::ProxyOpts;
+#[doc(inline)]
+pub use conn::PoolOpts;
+#[doc(inline)]
 pub use conn::pool::Pool;
 #[doc(inline)]
+pub use conn::pool::PoolEventHandler;
+#[doc(inline)]
 pub use conn::pool::PooledConn;
 #[doc(inline)]
+pub use conn::replicated::ReplicatedPool;
+#[doc(inline)]
+pub use conn::replicated::Route;
+#[doc(inline)]
+pub use conn::replicated::is_read_only_query;
+#[doc(inline)]
+pub use conn::pool::ShardedPool;
+#[doc(inline)]
+pub use conn::sharded::MergedResult;
+#[doc(inline)]
+pub use conn::sharded::ShardResolver;
+#[doc(inline)]
+pub use conn::sharded::ShardRouter;
+#[doc(inline)]
 pub use error::DriverError;
 #[doc(inline)]
 pub use error::Error;
 #[doc(inline)]
+pub use error::FromColumnError;
+#[doc(inline)]
 pub use error::MySqlError;
 #[doc(inline)]
 pub use error::Result;
@@ -192,7 +385,22 @@ pub use error::ServerError;
 #[doc(inline)]
 pub use error::UrlError;
 #[doc(inline)]
+pub use escape::escape_like;
+#[doc(inline)]
+pub use escape::escape_string;
+#[doc(inline)]
+pub use escape::quote_identifier;
+#[doc(inline)]
+pub use packet::OkPacket;
+#[doc(inline)]
+pub use value::Bit;
+#[doc(inline)]
 pub use value::ConvIr;
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub use mysql_derive::{FromRow, IntoParams, IntoValue};
+#[doc(inline)]
+pub use value::Decimal;
 #[doc(inline)]
 pub use value::FromRow;
 #[doc(inline)]
@@ -200,10 +408,16 @@ pub use value::FromValue;
 #[doc(inline)]
 pub use value::Params;
 #[doc(inline)]
+pub use value::Set;
+#[doc(inline)]
 pub use value::ToValue;
 #[doc(inline)]
+pub use value::Utf8Policy;
+#[doc(inline)]
 pub use value::Value;
 #[doc(inline)]
+pub use value::ValueRef;
+#[doc(inline)]
 pub use value::from_row;
 #[doc(inline)]
 pub use value::from_row_opt;
@@ -0,0 +1,798 @@
+//! #### Only available if `mysqlx` feature enabled.
+//!
+//! Experimental implementation of a SQL-only subset of the
+//! [X Protocol](https://dev.mysql.com/doc/internals/en/x-protocol.html).
+//!
+//! X Protocol is served on a separate port (`33060` by default), so `Opts::tcp_port` should
+//! point to it. Only `MYSQL41` authentication over plain TCP is supported for now, so
+//! `DriverError::UnsupportedOption` is returned if `Opts::ssl_opts` is set.
+//!
+//! ```ignore
+//! let opts = Opts {
+//!     user: Some("root".to_string()),
+//!     pass: Some("password".to_string()),
+//!     tcp_port: 33060,
+//!     ..Default::default()
+//! };
+//! let mut conn = XConn::new(opts).unwrap();
+//! for row in conn.prep_exec("SELECT ?, ?", (42, "foo")).unwrap() {
+//!     let (a, b): (i64, String) = from_row(row.unwrap());
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::io;
+use std::io::Read as StdRead;
+use std::io::Write as StdWrite;
+use std::net;
+
+use bufstream::BufStream;
+use byteorder::LittleEndian as LE;
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use super::consts;
+use super::consts::ColumnType;
+use super::conn::{count_placeholders, parse_named_params, Column, Opts, Row};
+use super::error::Error::{DriverError, MySqlError};
+use super::error::DriverError::{CouldNotConnect, MismatchedStmtParams, MissingNamedParameter};
+use super::error::DriverError::{NamedParamsForPositionalQuery, UnexpectedPacket};
+#[cfg(feature = "ssl")]
+use super::error::DriverError::UnsupportedOption;
+use super::error::{self, Result as MyResult};
+use super::scramble::scramble;
+use super::value::{Params, Value};
+
+mod proto;
+
+use self::proto::{Field, Fields};
+
+/// Client message types.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[repr(u8)]
+enum ClientMessage {
+    CON_CLOSE                  = 3,
+    SESS_AUTHENTICATE_START    = 4,
+    SESS_AUTHENTICATE_CONTINUE = 5,
+    SQL_STMT_EXECUTE           = 12,
+}
+
+// Server message types.
+const SRV_OK: u8 = 0;
+const SRV_ERROR: u8 = 1;
+const SRV_SESS_AUTHENTICATE_CONTINUE: u8 = 3;
+const SRV_SESS_AUTHENTICATE_OK: u8 = 4;
+const SRV_NOTICE: u8 = 11;
+const SRV_RESULTSET_COLUMN_META_DATA: u8 = 12;
+const SRV_RESULTSET_ROW: u8 = 13;
+const SRV_RESULTSET_FETCH_DONE: u8 = 14;
+const SRV_RESULTSET_FETCH_DONE_MORE_RESULTSETS: u8 = 16;
+const SRV_SQL_STMT_EXECUTE_OK: u8 = 17;
+const SRV_RESULTSET_FETCH_DONE_MORE_OUT_PARAMS: u8 = 18;
+
+// Mysqlx.Datatypes.Scalar.Type
+const SCALAR_V_SINT: u64 = 1;
+const SCALAR_V_UINT: u64 = 2;
+const SCALAR_V_NULL: u64 = 3;
+const SCALAR_V_OCTETS: u64 = 4;
+const SCALAR_V_DOUBLE: u64 = 5;
+const SCALAR_V_STRING: u64 = 8;
+
+// Mysqlx.Resultset.ColumnMetaData.FieldType
+const FIELD_SINT: u64 = 1;
+const FIELD_UINT: u64 = 2;
+const FIELD_DOUBLE: u64 = 5;
+const FIELD_FLOAT: u64 = 6;
+const FIELD_BYTES: u64 = 7;
+const FIELD_TIME: u64 = 10;
+const FIELD_DATETIME: u64 = 12;
+const FIELD_SET: u64 = 15;
+const FIELD_ENUM: u64 = 16;
+const FIELD_BIT: u64 = 17;
+const FIELD_DECIMAL: u64 = 18;
+
+// Mysqlx.Notice.Frame.Type::SESSION_STATE_CHANGED and its parameters
+const NOTICE_SESSION_STATE_CHANGED: u64 = 3;
+const STATE_GENERATED_INSERT_ID: u64 = 3;
+const STATE_ROWS_AFFECTED: u64 = 4;
+
+fn malformed() -> error::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Malformed X Protocol message").into()
+}
+
+fn parse_error(pld: &[u8]) -> MyResult<error::MySqlError> {
+    let mut code = 0u16;
+    let mut state = String::new();
+    let mut message = String::new();
+    for field in Fields::new(pld) {
+        match try!(field) {
+            (2, Field::Varint(x)) => code = x as u16,
+            (3, Field::Bytes(x)) => message = String::from_utf8_lossy(x).into_owned(),
+            (4, Field::Bytes(x)) => state = String::from_utf8_lossy(x).into_owned(),
+            _ => (),
+        }
+    }
+    Ok(error::MySqlError {
+        state: state,
+        message: message,
+        code: code,
+    })
+}
+
+/// Replaces `:name` placeholders of `query` with `?` and converts named `params` into
+/// positional ones, since X Protocol only supports positional placeholders.
+fn positional_params(query: &str, params: Params) -> MyResult<(Cow<str>, Params)> {
+    let (named_params, real_query) = parse_named_params(query);
+    let params = match (params, named_params) {
+        (Params::Named(map), Some(names)) => {
+            let mut values = Vec::with_capacity(names.len());
+            for name in names {
+                match map.get(&name) {
+                    Some(value) => values.push(value.clone()),
+                    None => return Err(DriverError(MissingNamedParameter(name))),
+                }
+            }
+            Params::Positional(values)
+        },
+        (Params::Named(_), None) => return Err(DriverError(NamedParamsForPositionalQuery)),
+        (params, _) => params,
+    };
+    Ok((real_query, params))
+}
+
+/// Encodes `value` as `Mysqlx.Datatypes.Any` message.
+fn encode_any(value: &Value) -> Vec<u8> {
+    let mut scalar = Vec::new();
+    match *value {
+        Value::NULL => proto::write_uint(&mut scalar, 1, SCALAR_V_NULL),
+        Value::Int(x) => {
+            proto::write_uint(&mut scalar, 1, SCALAR_V_SINT);
+            proto::write_sint(&mut scalar, 2, x);
+        },
+        Value::UInt(x) => {
+            proto::write_uint(&mut scalar, 1, SCALAR_V_UINT);
+            proto::write_uint(&mut scalar, 3, x);
+        },
+        Value::Float(x) => {
+            proto::write_uint(&mut scalar, 1, SCALAR_V_DOUBLE);
+            proto::write_double(&mut scalar, 6, x);
+        },
+        Value::Bytes(ref x) => {
+            let mut octets = Vec::with_capacity(x.len() + 4);
+            proto::write_bytes(&mut octets, 1, &x[..]);
+            proto::write_uint(&mut scalar, 1, SCALAR_V_OCTETS);
+            proto::write_bytes(&mut scalar, 5, &octets[..]);
+        },
+        Value::Date(..) | Value::Time(..) => {
+            // There is no temporal scalar type so temporal values are sent as strings.
+            let string = value.as_sql(false);
+            let mut string_msg = Vec::with_capacity(string.len() + 2);
+            proto::write_bytes(&mut string_msg, 1, string.trim_matches('\'').as_bytes());
+            proto::write_uint(&mut scalar, 1, SCALAR_V_STRING);
+            proto::write_bytes(&mut scalar, 9, &string_msg[..]);
+        },
+    }
+    let mut any = Vec::with_capacity(scalar.len() + 4);
+    proto::write_uint(&mut any, 1, 1);
+    proto::write_bytes(&mut any, 2, &scalar[..]);
+    any
+}
+
+fn parse_column(pld: &[u8]) -> MyResult<Column> {
+    let mut field_type = 0u64;
+    let mut column = Column {
+        schema: Vec::new(),
+        table: Vec::new(),
+        org_table: Vec::new(),
+        name: Vec::new(),
+        org_name: Vec::new(),
+        default_values: Vec::new(),
+        column_length: 0,
+        character_set: 0,
+        flags: consts::ColumnFlags::empty(),
+        column_type: ColumnType::MYSQL_TYPE_NULL,
+        decimals: 0,
+    };
+    for field in Fields::new(pld) {
+        match try!(field) {
+            (1, Field::Varint(x)) => field_type = x,
+            (2, Field::Bytes(x)) => column.name = x.to_vec(),
+            (3, Field::Bytes(x)) => column.org_name = x.to_vec(),
+            (4, Field::Bytes(x)) => column.table = x.to_vec(),
+            (5, Field::Bytes(x)) => column.org_table = x.to_vec(),
+            (6, Field::Bytes(x)) => column.schema = x.to_vec(),
+            (8, Field::Varint(x)) => column.character_set = x as u16,
+            (9, Field::Varint(x)) => column.decimals = x as u8,
+            (10, Field::Varint(x)) => column.column_length = x as u32,
+            _ => (),
+        }
+    }
+    column.column_type = match field_type {
+        FIELD_SINT => ColumnType::MYSQL_TYPE_LONGLONG,
+        FIELD_UINT => {
+            column.flags.insert(consts::UNSIGNED_FLAG);
+            ColumnType::MYSQL_TYPE_LONGLONG
+        },
+        FIELD_DOUBLE => ColumnType::MYSQL_TYPE_DOUBLE,
+        FIELD_FLOAT => ColumnType::MYSQL_TYPE_FLOAT,
+        FIELD_BYTES => ColumnType::MYSQL_TYPE_VAR_STRING,
+        FIELD_TIME => ColumnType::MYSQL_TYPE_TIME,
+        FIELD_DATETIME => ColumnType::MYSQL_TYPE_DATETIME,
+        FIELD_SET => ColumnType::MYSQL_TYPE_SET,
+        FIELD_ENUM => ColumnType::MYSQL_TYPE_ENUM,
+        FIELD_BIT => ColumnType::MYSQL_TYPE_BIT,
+        FIELD_DECIMAL => ColumnType::MYSQL_TYPE_NEWDECIMAL,
+        _ => return Err(malformed()),
+    };
+    Ok(column)
+}
+
+/// Decodes X Protocol `DECIMAL` representation (scale byte followed by packed BCD).
+fn decode_decimal(bytes: &[u8]) -> MyResult<Vec<u8>> {
+    if bytes.is_empty() {
+        return Err(malformed());
+    }
+    let scale = bytes[0] as usize;
+    let mut digits = Vec::with_capacity(bytes.len() * 2);
+    let mut negative = false;
+    'outer: for byte in &bytes[1..] {
+        for &nibble in &[byte >> 4, byte & 0x0f] {
+            match nibble {
+                0...9 => digits.push(b'0' + nibble),
+                0x0c => break 'outer,
+                0x0d => {
+                    negative = true;
+                    break 'outer;
+                },
+                _ => return Err(malformed()),
+            }
+        }
+    }
+    while digits.len() <= scale {
+        digits.insert(0, b'0');
+    }
+    let mut out = Vec::with_capacity(digits.len() + 2);
+    if negative {
+        out.push(b'-');
+    }
+    let int_len = digits.len() - scale;
+    out.extend_from_slice(&digits[..int_len]);
+    if scale > 0 {
+        out.push(b'.');
+        out.extend_from_slice(&digits[int_len..]);
+    }
+    Ok(out)
+}
+
+fn decode_value(column: &Column, mut bytes: &[u8]) -> MyResult<Value> {
+    if bytes.is_empty() {
+        return Ok(Value::NULL);
+    }
+    let reader = &mut bytes;
+    let value = match column.column_type {
+        ColumnType::MYSQL_TYPE_LONGLONG => {
+            let x = try!(proto::read_varint(reader));
+            if column.flags.contains(consts::UNSIGNED_FLAG) {
+                Value::UInt(x)
+            } else {
+                Value::Int(proto::unzigzag(x))
+            }
+        },
+        ColumnType::MYSQL_TYPE_BIT => Value::UInt(try!(proto::read_varint(reader))),
+        ColumnType::MYSQL_TYPE_DOUBLE => Value::Float(try!(reader.read_f64::<LE>())),
+        ColumnType::MYSQL_TYPE_FLOAT => Value::Float(try!(reader.read_f32::<LE>()) as f64),
+        ColumnType::MYSQL_TYPE_DATETIME => {
+            let mut parts = [0u64; 7];
+            let mut i = 0;
+            while !reader.is_empty() && i < parts.len() {
+                parts[i] = try!(proto::read_varint(reader));
+                i += 1;
+            }
+            Value::Date(parts[0] as u16, parts[1] as u8, parts[2] as u8, parts[3] as u8,
+                        parts[4] as u8, parts[5] as u8, parts[6] as u32)
+        },
+        ColumnType::MYSQL_TYPE_TIME => {
+            let is_negative = try!(reader.read_u8()) == 1u8;
+            let mut parts = [0u64; 4];
+            let mut i = 0;
+            while !reader.is_empty() && i < parts.len() {
+                parts[i] = try!(proto::read_varint(reader));
+                i += 1;
+            }
+            Value::Time(is_negative, (parts[0] / 24) as u32, (parts[0] % 24) as u8,
+                        parts[1] as u8, parts[2] as u8, parts[3] as u32)
+        },
+        ColumnType::MYSQL_TYPE_NEWDECIMAL => Value::Bytes(try!(decode_decimal(reader))),
+        ColumnType::MYSQL_TYPE_SET => Value::Bytes(reader.to_vec()),
+        _ => {
+            // Strings, blobs and enums are sent with a trailing `\0`.
+            let len = reader.len();
+            Value::Bytes(reader[..len - 1].to_vec())
+        },
+    };
+    Ok(value)
+}
+
+/// Connection speaking the X Protocol.
+#[derive(Debug)]
+pub struct XConn {
+    opts: Opts,
+    stream: Option<BufStream<net::TcpStream>>,
+    affected_rows: u64,
+    last_insert_id: u64,
+    has_results: bool,
+    /// Message that was read ahead while collecting column metadata.
+    pending: Option<(u8, Vec<u8>)>,
+}
+
+impl XConn {
+    /// Connects to the X Protocol port and authenticates using `MYSQL41` mechanism.
+    pub fn new<T: Into<Opts>>(opts: T) -> MyResult<XConn> {
+        let opts = opts.into();
+        // Credentials should not be sent in the clear if user asked for TLS.
+        #[cfg(feature = "ssl")]
+        {
+            if opts.ssl_enabled() {
+                return Err(DriverError(UnsupportedOption("ssl_opts")));
+            }
+        }
+        let stream = {
+            let addr = match opts.ip_or_hostname {
+                Some(ref addr) => addr,
+                None => return Err(DriverError(CouldNotConnect(None))),
+            };
+            match net::TcpStream::connect(&(&**addr, opts.tcp_port)) {
+                Ok(stream) => stream,
+                Err(_) => return Err(DriverError(CouldNotConnect(opts.ip_or_hostname.clone()))),
+            }
+        };
+        let mut conn = XConn {
+            opts: opts,
+            stream: Some(BufStream::new(stream)),
+            affected_rows: 0,
+            last_insert_id: 0,
+            has_results: false,
+            pending: None,
+        };
+        try!(conn.authenticate());
+        Ok(conn)
+    }
+
+    fn get_mut_stream(&mut self) -> &mut BufStream<net::TcpStream> {
+        self.stream.as_mut().unwrap()
+    }
+
+    fn write_message(&mut self, msg_type: ClientMessage, pld: &[u8]) -> MyResult<()> {
+        let stream = self.get_mut_stream();
+        try!(stream.write_u32::<LE>(pld.len() as u32 + 1));
+        try!(stream.write_u8(msg_type as u8));
+        try!(stream.write_all(pld));
+        try!(stream.flush());
+        Ok(())
+    }
+
+    fn read_message(&mut self) -> MyResult<(u8, Vec<u8>)> {
+        let stream = self.get_mut_stream();
+        let len = try!(stream.read_u32::<LE>()) as usize;
+        if len == 0 {
+            return Err(malformed());
+        }
+        let msg_type = try!(stream.read_u8());
+        let mut pld = Vec::with_capacity(len - 1);
+        let count = try!(stream.take((len - 1) as u64).read_to_end(&mut pld));
+        if count != len - 1 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                      "Unexpected EOF while reading X Protocol message").into());
+        }
+        Ok((msg_type, pld))
+    }
+
+    /// Reads next message skipping notices but handling session state changes.
+    fn read_non_notice(&mut self) -> MyResult<(u8, Vec<u8>)> {
+        if let Some(msg) = self.pending.take() {
+            return Ok(msg);
+        }
+        loop {
+            let (msg_type, pld) = try!(self.read_message());
+            match msg_type {
+                SRV_NOTICE => try!(self.handle_notice(&pld[..])),
+                SRV_ERROR => return Err(MySqlError(try!(parse_error(&pld[..])))),
+                _ => return Ok((msg_type, pld)),
+            }
+        }
+    }
+
+    fn handle_notice(&mut self, pld: &[u8]) -> MyResult<()> {
+        let mut notice_type = 0;
+        let mut payload: &[u8] = &[];
+        for field in Fields::new(pld) {
+            match try!(field) {
+                (1, Field::Varint(x)) => notice_type = x,
+                (3, Field::Bytes(x)) => payload = x,
+                _ => (),
+            }
+        }
+        if notice_type != NOTICE_SESSION_STATE_CHANGED {
+            return Ok(());
+        }
+        let mut param = 0;
+        let mut value = None;
+        for field in Fields::new(payload) {
+            match try!(field) {
+                (1, Field::Varint(x)) => param = x,
+                (2, Field::Bytes(scalar)) => {
+                    for field in Fields::new(scalar) {
+                        match try!(field) {
+                            (3, Field::Varint(x)) => value = Some(x),
+                            _ => (),
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+        match (param, value) {
+            (STATE_ROWS_AFFECTED, Some(x)) => self.affected_rows = x,
+            (STATE_GENERATED_INSERT_ID, Some(x)) => self.last_insert_id = x,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn authenticate(&mut self) -> MyResult<()> {
+        let mut start = Vec::new();
+        proto::write_bytes(&mut start, 1, b"MYSQL41");
+        try!(self.write_message(ClientMessage::SESS_AUTHENTICATE_START, &start[..]));
+
+        let (msg_type, pld) = try!(self.read_non_notice());
+        if msg_type != SRV_SESS_AUTHENTICATE_CONTINUE {
+            return Err(DriverError(UnexpectedPacket));
+        }
+        let mut salt: &[u8] = &[];
+        for field in Fields::new(&pld[..]) {
+            match try!(field) {
+                (1, Field::Bytes(x)) => salt = x,
+                _ => (),
+            }
+        }
+
+        let mut auth_data = Vec::new();
+        if let Some(ref db_name) = self.opts.db_name {
+            auth_data.extend_from_slice(db_name.as_bytes());
+        }
+        auth_data.push(0u8);
+        if let Some(ref user) = self.opts.user {
+            auth_data.extend_from_slice(user.as_bytes());
+        }
+        auth_data.push(0u8);
+        let scrambled = self.opts.pass.as_ref().and_then(|pass| scramble(salt, pass.as_bytes()));
+        if let Some(scrambled) = scrambled {
+            auth_data.push(b'*');
+            for byte in scrambled {
+                auth_data.extend(format!("{:02X}", byte).bytes());
+            }
+        }
+        let mut cont = Vec::new();
+        proto::write_bytes(&mut cont, 1, &auth_data[..]);
+        try!(self.write_message(ClientMessage::SESS_AUTHENTICATE_CONTINUE, &cont[..]));
+
+        let (msg_type, _) = try!(self.read_non_notice());
+        if msg_type != SRV_SESS_AUTHENTICATE_OK {
+            return Err(DriverError(UnexpectedPacket));
+        }
+        Ok(())
+    }
+
+    fn stmt_execute(&mut self, query: &str, params: Params) -> MyResult<Vec<Column>> {
+        let (query, params) = try!(positional_params(query, params));
+        let query = &*query;
+        let mut pld = Vec::with_capacity(query.len() + 8);
+        proto::write_bytes(&mut pld, 1, query.as_bytes());
+        match params {
+            Params::Empty => (),
+            Params::Positional(params) => {
+                let placeholders = count_placeholders(query);
+                if placeholders != params.len() {
+                    return Err(DriverError(MismatchedStmtParams(placeholders as u16,
+                                                                params.len())));
+                }
+                for param in params.iter() {
+                    proto::write_bytes(&mut pld, 2, &encode_any(param)[..]);
+                }
+            },
+            Params::Named(_) => unreachable!(),
+        }
+        proto::write_bytes(&mut pld, 3, b"sql");
+        self.affected_rows = 0;
+        self.last_insert_id = 0;
+        self.pending = None;
+        try!(self.write_message(ClientMessage::SQL_STMT_EXECUTE, &pld[..]));
+        self.has_results = true;
+        self.read_columns()
+    }
+
+    fn read_columns(&mut self) -> MyResult<Vec<Column>> {
+        let mut columns = Vec::new();
+        loop {
+            let (msg_type, pld) = match self.read_non_notice() {
+                Ok(x) => x,
+                Err(err) => {
+                    self.has_results = false;
+                    return Err(err);
+                }
+            };
+            match msg_type {
+                SRV_RESULTSET_COLUMN_META_DATA => columns.push(try!(parse_column(&pld[..]))),
+                SRV_RESULTSET_ROW => {
+                    // First row of a result set. Keep it for the iterator.
+                    self.pending = Some((msg_type, pld));
+                    return Ok(columns);
+                },
+                SRV_RESULTSET_FETCH_DONE => {
+                    if columns.is_empty() {
+                        continue;
+                    }
+                    return Ok(columns);
+                },
+                SRV_RESULTSET_FETCH_DONE_MORE_RESULTSETS |
+                SRV_RESULTSET_FETCH_DONE_MORE_OUT_PARAMS => {
+                    if columns.is_empty() {
+                        continue;
+                    }
+                    // Result set has no rows. Keep the message, so that the iterator resets
+                    // columns before reading metadata of the next result set.
+                    self.pending = Some((msg_type, pld));
+                    return Ok(columns);
+                },
+                SRV_SQL_STMT_EXECUTE_OK => {
+                    self.has_results = false;
+                    return Ok(columns);
+                },
+                SRV_OK => (),
+                _ => {
+                    self.has_results = false;
+                    return Err(DriverError(UnexpectedPacket));
+                },
+            }
+        }
+    }
+
+    /// Executes `query` using text statement execution.
+    pub fn query<'a, T: AsRef<str> + 'a>(&'a mut self, query: T) -> MyResult<XQueryResult<'a>> {
+        self.prep_exec(query, ())
+    }
+
+    /// Executes `query` with `params` bound to its `?` or `:name` placeholders.
+    pub fn prep_exec<'a, A, T>(&'a mut self, query: A, params: T) -> MyResult<XQueryResult<'a>>
+    where A: AsRef<str> + 'a,
+          T: Into<Params> {
+        let columns = try!(self.stmt_execute(query.as_ref(), params.into()));
+        Ok(XQueryResult {
+            conn: self,
+            columns: columns,
+        })
+    }
+
+    /// Returns number of rows affected by the last statement.
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    /// Returns last generated insert id.
+    pub fn last_insert_id(&self) -> u64 {
+        self.last_insert_id
+    }
+
+    /// Reads next row. `columns` is replaced with columns of the next result set, if any.
+    fn next_row(&mut self, columns: &mut Vec<Column>) -> MyResult<Option<Row>> {
+        if !self.has_results {
+            return Ok(None);
+        }
+        loop {
+            let (msg_type, pld) = match self.read_non_notice() {
+                Ok(x) => x,
+                Err(err) => {
+                    self.has_results = false;
+                    return Err(err);
+                }
+            };
+            match msg_type {
+                SRV_RESULTSET_ROW => {
+                    let mut values = Vec::with_capacity(columns.len());
+                    for (i, field) in Fields::new(&pld[..]).enumerate() {
+                        let bytes = match try!(field) {
+                            (1, Field::Bytes(x)) => x,
+                            _ => return Err(malformed()),
+                        };
+                        match columns.get(i) {
+                            Some(column) => values.push(try!(decode_value(column, bytes))),
+                            None => return Err(malformed()),
+                        }
+                    }
+                    return Ok(Some(Row::new(values)));
+                },
+                SRV_RESULTSET_COLUMN_META_DATA => columns.push(try!(parse_column(&pld[..]))),
+                // Columns of the next result set follow.
+                SRV_RESULTSET_FETCH_DONE_MORE_RESULTSETS |
+                SRV_RESULTSET_FETCH_DONE_MORE_OUT_PARAMS => columns.clear(),
+                SRV_RESULTSET_FETCH_DONE => (),
+                SRV_SQL_STMT_EXECUTE_OK => {
+                    self.has_results = false;
+                    return Ok(None);
+                },
+                _ => {
+                    self.has_results = false;
+                    return Err(DriverError(UnexpectedPacket));
+                },
+            }
+        }
+    }
+}
+
+impl Drop for XConn {
+    fn drop(&mut self) {
+        let _ = self.write_message(ClientMessage::CON_CLOSE, &[]);
+    }
+}
+
+/// Result of a statement executed via X Protocol.
+///
+/// Iterates over rows of all result sets returned by the statement.
+#[derive(Debug)]
+pub struct XQueryResult<'a> {
+    conn: &'a mut XConn,
+    columns: Vec<Column>,
+}
+
+impl<'a> XQueryResult<'a> {
+    /// Returns a slice of a [`Column`s](../conn/struct.Column.html) of the result set which is
+    /// being read.
+    pub fn columns_ref(&self) -> &[Column] {
+        self.columns.as_ref()
+    }
+
+    /// Returns number of rows affected by the statement. Only meaningful after all rows
+    /// were consumed.
+    pub fn affected_rows(&self) -> u64 {
+        self.conn.affected_rows
+    }
+
+    /// Returns last generated insert id. Only meaningful after all rows were consumed.
+    pub fn last_insert_id(&self) -> u64 {
+        self.conn.last_insert_id
+    }
+}
+
+impl<'a> Iterator for XQueryResult<'a> {
+    type Item = MyResult<Row>;
+
+    fn next(&mut self) -> Option<MyResult<Row>> {
+        match self.conn.next_row(&mut self.columns) {
+            Ok(Some(row)) => Some(Ok(row)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl<'a> Drop for XQueryResult<'a> {
+    fn drop(&mut self) {
+        while let Some(_) = self.next() {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_decimal, decode_value, encode_any, parse_column, parse_error, proto};
+    use std::collections::HashMap;
+    use super::positional_params;
+    use super::super::consts::{self, ColumnType};
+    use super::super::error::{DriverError, Error};
+    use super::super::value::{Params, Value};
+
+    #[test]
+    fn should_parse_error() {
+        let mut pld = Vec::new();
+        proto::write_uint(&mut pld, 1, 1);
+        proto::write_uint(&mut pld, 2, 1062);
+        proto::write_bytes(&mut pld, 3, b"Duplicate entry");
+        proto::write_bytes(&mut pld, 4, b"23000");
+        let err = parse_error(&pld[..]).unwrap();
+        assert_eq!(err.code, 1062);
+        assert_eq!(err.state, "23000");
+        assert_eq!(err.message, "Duplicate entry");
+    }
+
+    #[test]
+    fn should_parse_column_meta_data() {
+        let mut pld = Vec::new();
+        proto::write_uint(&mut pld, 1, 2);
+        proto::write_bytes(&mut pld, 2, b"id");
+        proto::write_bytes(&mut pld, 4, b"tbl");
+        let column = parse_column(&pld[..]).unwrap();
+        assert_eq!(column.name, b"id".to_vec());
+        assert_eq!(column.table, b"tbl".to_vec());
+        assert_eq!(column.column_type, ColumnType::MYSQL_TYPE_LONGLONG);
+        assert!(column.flags.contains(consts::UNSIGNED_FLAG));
+    }
+
+    #[test]
+    fn should_decode_values() {
+        let mut pld = Vec::new();
+        proto::write_uint(&mut pld, 1, 1);
+        let sint = parse_column(&pld[..]).unwrap();
+        let mut bytes = Vec::new();
+        proto::write_varint(&mut bytes, proto::zigzag(-42));
+        assert_eq!(decode_value(&sint, &bytes[..]).unwrap(), Value::Int(-42));
+        assert_eq!(decode_value(&sint, &[]).unwrap(), Value::NULL);
+
+        let mut pld = Vec::new();
+        proto::write_uint(&mut pld, 1, 7);
+        let string = parse_column(&pld[..]).unwrap();
+        assert_eq!(decode_value(&string, b"foo\0").unwrap(), Value::Bytes(b"foo".to_vec()));
+
+        let mut pld = Vec::new();
+        proto::write_uint(&mut pld, 1, 12);
+        let datetime = parse_column(&pld[..]).unwrap();
+        assert_eq!(decode_value(&datetime, &[0xde, 0x0f, 11, 1]).unwrap(),
+                   Value::Date(2014, 11, 1, 0, 0, 0, 0));
+    }
+
+    #[test]
+    fn should_decode_decimal() {
+        assert_eq!(decode_decimal(&[0x02, 0x12, 0x34, 0x5c]).unwrap(), b"123.45".to_vec());
+        assert_eq!(decode_decimal(&[0x01, 0x12, 0x34, 0xd0]).unwrap(), b"-123.4".to_vec());
+        assert_eq!(decode_decimal(&[0x03, 0x5c]).unwrap(), b"0.005".to_vec());
+    }
+
+    #[test]
+    fn should_convert_named_params() {
+        let mut named = HashMap::default();
+        named.insert("a".to_string(), Value::from(1));
+        match positional_params("SELECT :a, :b", Params::Named(named.clone())) {
+            Err(Error::DriverError(DriverError::MissingNamedParameter(ref name))) => {
+                assert_eq!(name, "b");
+            },
+            _ => panic!("expected MissingNamedParameter"),
+        }
+        match positional_params("SELECT ?", Params::Named(named.clone())) {
+            Err(Error::DriverError(DriverError::NamedParamsForPositionalQuery)) => (),
+            _ => panic!("expected NamedParamsForPositionalQuery"),
+        }
+        named.insert("b".to_string(), Value::from("foo"));
+        let (query, params) = positional_params("SELECT :a, :b, :a", Params::Named(named))
+                                  .unwrap();
+        assert_eq!(query, "SELECT ?, ?, ?");
+        assert_eq!(params, Params::Positional(vec![Value::Int(1),
+                                                   Value::Bytes(b"foo".to_vec()),
+                                                   Value::Int(1)]));
+        let (query, params) = positional_params("SELECT ?", (1,).into()).unwrap();
+        assert_eq!(query, "SELECT ?");
+        assert_eq!(params, Params::Positional(vec![Value::Int(1)]));
+    }
+
+    #[test]
+    #[cfg(feature = "ssl")]
+    fn should_reject_ssl_opts() {
+        use super::XConn;
+        use super::super::conn::{Opts, SslOpts};
+
+        let opts = Opts { ssl_opts: Some(SslOpts::default()), ..Default::default() };
+        match XConn::new(opts) {
+            Err(Error::DriverError(DriverError::UnsupportedOption("ssl_opts"))) => (),
+            _ => panic!("expected UnsupportedOption"),
+        }
+    }
+
+    #[test]
+    fn should_encode_params() {
+        let any = encode_any(&Value::Int(-1));
+        let mut expected = Vec::new();
+        let mut scalar = Vec::new();
+        proto::write_uint(&mut scalar, 1, 1);
+        proto::write_sint(&mut scalar, 2, -1);
+        proto::write_uint(&mut expected, 1, 1);
+        proto::write_bytes(&mut expected, 2, &scalar[..]);
+        assert_eq!(any, expected);
+    }
+}
@@ -0,0 +1,190 @@
+//! Minimal protobuf wire format codec used by the X Protocol implementation.
+//!
+//! Only the subset needed to build and parse X Protocol messages is implemented.
+
+use std::io;
+
+pub const WIRE_VARINT: u8 = 0;
+pub const WIRE_FIXED64: u8 = 1;
+pub const WIRE_BYTES: u8 = 2;
+pub const WIRE_FIXED32: u8 = 5;
+
+/// Value of a single protobuf field.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+pub fn zigzag(x: i64) -> u64 {
+    ((x << 1) ^ (x >> 63)) as u64
+}
+
+pub fn unzigzag(x: u64) -> i64 {
+    ((x >> 1) as i64) ^ (-((x & 1) as i64))
+}
+
+pub fn write_varint(buf: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        buf.push((x as u8) | 0x80);
+        x >>= 7;
+    }
+    buf.push(x as u8);
+}
+
+pub fn write_key(buf: &mut Vec<u8>, field: u32, wire_type: u8) {
+    write_varint(buf, ((field as u64) << 3) | wire_type as u64);
+}
+
+pub fn write_uint(buf: &mut Vec<u8>, field: u32, x: u64) {
+    write_key(buf, field, WIRE_VARINT);
+    write_varint(buf, x);
+}
+
+pub fn write_sint(buf: &mut Vec<u8>, field: u32, x: i64) {
+    write_uint(buf, field, zigzag(x));
+}
+
+pub fn write_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    write_key(buf, field, WIRE_BYTES);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+pub fn write_double(buf: &mut Vec<u8>, field: u32, x: f64) {
+    write_key(buf, field, WIRE_FIXED64);
+    let bits = x.to_bits();
+    for i in 0..8 {
+        buf.push((bits >> (i * 8)) as u8);
+    }
+}
+
+pub fn read_varint(reader: &mut &[u8]) -> io::Result<u64> {
+    let mut out = 0u64;
+    let mut shift = 0;
+    loop {
+        if reader.is_empty() || shift > 63 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Malformed protobuf varint"));
+        }
+        let byte = reader[0];
+        *reader = &reader[1..];
+        out |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(out);
+        }
+        shift += 7;
+    }
+}
+
+fn read_fixed(reader: &mut &[u8], len: usize) -> io::Result<u64> {
+    if reader.len() < len {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Unexpected end of protobuf field"));
+    }
+    let mut out = 0u64;
+    for i in 0..len {
+        out |= (reader[i] as u64) << (i * 8);
+    }
+    *reader = &reader[len..];
+    Ok(out)
+}
+
+/// Iterator over `(field_number, value)` pairs of an encoded protobuf message.
+pub struct Fields<'a> {
+    reader: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    pub fn new(pld: &'a [u8]) -> Fields<'a> {
+        Fields { reader: pld }
+    }
+
+    fn read_field(&mut self) -> io::Result<(u32, Field<'a>)> {
+        let key = try!(read_varint(&mut self.reader));
+        let field = (key >> 3) as u32;
+        let value = match (key & 0x07) as u8 {
+            WIRE_VARINT => Field::Varint(try!(read_varint(&mut self.reader))),
+            WIRE_FIXED64 => Field::Fixed64(try!(read_fixed(&mut self.reader, 8))),
+            WIRE_FIXED32 => Field::Fixed32(try!(read_fixed(&mut self.reader, 4)) as u32),
+            WIRE_BYTES => {
+                let len = try!(read_varint(&mut self.reader)) as usize;
+                if self.reader.len() < len {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                              "Unexpected end of protobuf field"));
+                }
+                let reader = self.reader;
+                self.reader = &reader[len..];
+                Field::Bytes(&reader[..len])
+            },
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                           "Unsupported protobuf wire type")),
+        };
+        Ok((field, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = io::Result<(u32, Field<'a>)>;
+
+    fn next(&mut self) -> Option<io::Result<(u32, Field<'a>)>> {
+        if self.reader.is_empty() {
+            None
+        } else {
+            let result = self.read_field();
+            if result.is_err() {
+                self.reader = &[];
+            }
+            Some(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Field, Fields, read_varint, unzigzag, write_bytes, write_uint, write_sint,
+                write_varint, zigzag};
+
+    #[test]
+    fn should_encode_and_decode_varint() {
+        for &x in &[0u64, 1, 127, 128, 300, 16_384, ::std::u64::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, x);
+            let mut reader = &buf[..];
+            assert_eq!(read_varint(&mut reader).unwrap(), x);
+            assert!(reader.is_empty());
+        }
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 300);
+        assert_eq!(buf, vec![0xac, 0x02]);
+    }
+
+    #[test]
+    fn should_zigzag() {
+        for &x in &[0i64, -1, 1, -64, 64, ::std::i64::MIN, ::std::i64::MAX] {
+            assert_eq!(unzigzag(zigzag(x)), x);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+
+    #[test]
+    fn should_iterate_over_fields() {
+        let mut buf = Vec::new();
+        write_uint(&mut buf, 1, 150);
+        write_bytes(&mut buf, 2, b"testing");
+        write_sint(&mut buf, 3, -2);
+        let fields = Fields::new(&buf[..]).map(|x| x.unwrap()).collect::<Vec<_>>();
+        assert_eq!(fields, vec![(1, Field::Varint(150)),
+                                (2, Field::Bytes(b"testing")),
+                                (3, Field::Varint(3))]);
+    }
+
+    #[test]
+    fn should_fail_on_truncated_message() {
+        let buf = [0x12u8, 0x05, b'a'];
+        let mut fields = Fields::new(&buf[..]);
+        assert!(fields.next().unwrap().is_err());
+        assert!(fields.next().is_none());
+    }
+}
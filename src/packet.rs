@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::io;
 use std::io::Read as StdRead;
 
@@ -8,6 +9,7 @@ use byteorder::{ByteOrder, ReadBytesExt};
 use super::consts;
 use super::consts::StatusFlags;
 use super::consts::CapabilityFlags;
+use super::consts::MariadbCapabilityFlags;
 use super::error;
 use super::error::DriverError;
 use super::io::Read;
@@ -18,30 +20,111 @@ lazy_static! {
     };
 }
 
+/// [`OK_Packet`](http://dev.mysql.com/doc/internals/en/packet-OK_Packet.html) which terminates
+/// a statement without a result set.
+///
+/// Returned by [`QueryResult::ok_packet`](struct.QueryResult.html#method.ok_packet).
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct OkPacket {
-    pub affected_rows: u64,
-    pub last_insert_id: u64,
-    pub status_flags: StatusFlags,
-    pub warnings: u16,
-    pub info: Vec<u8>
+    affected_rows: u64,
+    last_insert_id: u64,
+    status_flags: StatusFlags,
+    warnings: u16,
+    info: Vec<u8>,
+    session_state_changes: Vec<u8>,
 }
 
+/// Type of a session state change which carries GTIDs reported via `session_track_gtids`.
+const SESSION_TRACK_GTIDS: u8 = 3;
+
 impl OkPacket {
-    pub fn from_payload(pld: &[u8]) -> io::Result<OkPacket> {
+    #[doc(hidden)]
+    pub fn from_payload(pld: &[u8], c_flags: CapabilityFlags) -> io::Result<OkPacket> {
         let mut reader = &pld[1..];
+        let affected_rows = try!(reader.read_lenenc_int());
+        let last_insert_id = try!(reader.read_lenenc_int());
+        let status_flags = StatusFlags::from_bits_truncate(try!(reader.read_u16::<LE>()));
+        let warnings = try!(reader.read_u16::<LE>());
+        let mut info = Vec::new();
+        let mut session_state_changes = Vec::new();
+        if c_flags.contains(consts::CLIENT_SESSION_TRACK) {
+            if reader.len() > 0 {
+                info = try!(reader.read_lenenc_bytes());
+            }
+            if status_flags.contains(consts::SERVER_SESSION_STATE_CHANGED) {
+                session_state_changes = try!(reader.read_lenenc_bytes());
+            }
+        } else {
+            try!(reader.read_to_end(&mut info));
+        }
         Ok(OkPacket{
-            affected_rows: try!(reader.read_lenenc_int()),
-            last_insert_id: try!(reader.read_lenenc_int()),
-            status_flags: StatusFlags::from_bits_truncate(try!(reader.read_u16::<LE>())),
-            warnings: try!(reader.read_u16::<LE>()),
-            info: {
-                let mut info = Vec::with_capacity(reader.len());
-                try!(reader.read_to_end(&mut info));
-                info
-            },
+            affected_rows: affected_rows,
+            last_insert_id: last_insert_id,
+            status_flags: status_flags,
+            warnings: warnings,
+            info: info,
+            session_state_changes: session_state_changes,
         })
     }
+
+    /// Returns number of rows affected by a statement.
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    /// Returns last insert id of a statement.
+    pub fn last_insert_id(&self) -> u64 {
+        self.last_insert_id
+    }
+
+    /// Returns server status flags.
+    pub fn status_flags(&self) -> StatusFlags {
+        self.status_flags
+    }
+
+    /// Returns number of warnings of a statement.
+    pub fn warnings(&self) -> u16 {
+        self.warnings
+    }
+
+    /// Returns human readable information about a statement, e.g. `Rows matched: 3  Changed: 1
+    /// Warnings: 0` for an `UPDATE`.
+    pub fn info(&self) -> &[u8] {
+        &*self.info
+    }
+
+    /// Returns raw session state changes (empty unless `CLIENT_SESSION_TRACK` is negotiated).
+    ///
+    /// See [`OkPacket::gtids`](#method.gtids) for parsed GTIDs.
+    pub fn session_state_changes(&self) -> &[u8] {
+        &*self.session_state_changes
+    }
+
+    /// Returns `info` as a string (invalid UTF-8 sequences are replaced).
+    pub fn info_str(&self) -> Cow<str> {
+        String::from_utf8_lossy(&*self.info)
+    }
+
+    /// Returns GTIDs reported in session state changes if `session_track_gtids` is enabled.
+    pub fn gtids(&self) -> Option<String> {
+        let mut reader = &self.session_state_changes[..];
+        while reader.len() > 0 {
+            let change_type = match reader.read_u8() {
+                Ok(change_type) => change_type,
+                Err(_) => return None,
+            };
+            let data = match reader.read_lenenc_bytes() {
+                Ok(data) => data,
+                Err(_) => return None,
+            };
+            if change_type == SESSION_TRACK_GTIDS && data.len() > 0 {
+                // First byte is an encoding specification.
+                let mut data = &data[1..];
+                return data.read_lenenc_bytes().ok().and_then(|x| String::from_utf8(x).ok());
+            }
+        }
+        None
+    }
 }
 
 #[derive(Clone, Eq, PartialEq)]
@@ -106,8 +189,14 @@ impl EOFPacket {
 /// (major, minor, micro) mysql server version.
 pub type ServerVersion = (u16, u16, u16);
 
+/// MariaDB >= 10.0 prefixes its version with `5.5.5-` to not confuse old replication clients.
+const MARIADB_VERSION_PREFIX: &'static str = "5.5.5-";
+
 fn parse_version(bytes: &[u8]) -> error::Result<ServerVersion> {
-    let ver_str = String::from_utf8_lossy(bytes).into_owned();
+    let mut ver_str = String::from_utf8_lossy(bytes).into_owned();
+    if ver_str.starts_with(MARIADB_VERSION_PREFIX) && ver_str.contains("MariaDB") {
+        ver_str = ver_str[MARIADB_VERSION_PREFIX.len()..].to_string();
+    }
     VERSION_RE.captures(&ver_str[..])
     .and_then(|capts| {
         Some((
@@ -129,8 +218,10 @@ pub struct HandshakePacket {
     pub auth_plugin_data: Vec<u8>,
     pub auth_plugin_name: Vec<u8>,
     pub server_version: ServerVersion,
+    pub server_version_str: String,
     pub connection_id: u32,
     pub capability_flags: CapabilityFlags,
+    pub mariadb_capability_flags: MariadbCapabilityFlags,
     pub status_flags: StatusFlags,
     pub protocol_version: u8,
     pub character_set: u8,
@@ -143,11 +234,13 @@ impl HandshakePacket {
         let mut auth_plugin_name: Vec<u8> = Vec::with_capacity(32);
         let mut character_set = 0u8;
         let mut status_flags = StatusFlags::empty();
+        let mut mariadb_capability_flags = MariadbCapabilityFlags::empty();
         let payload_len = pld.len();
         let mut reader = io::Cursor::new(pld);
         let protocol_version = try!(reader.read_u8());
         let version_bytes = try!(reader.read_to_null());
         let server_version = try!(parse_version(&version_bytes[..]));
+        let server_version_str = String::from_utf8_lossy(&*version_bytes).into_owned();
         let connection_id = try!(reader.read_u32::<LE>());
         try!(reader.by_ref().take(8).read_to_end(&mut auth_plugin_data));
         // skip filler
@@ -165,7 +258,12 @@ impl HandshakePacket {
                 let pos = reader.position();
                 reader.set_position(pos + 1);
             }
-            { let pos = reader.position(); reader.set_position(pos + 10); }
+            // Reserved (MariaDB puts its extended capabilities into the last four bytes).
+            { let pos = reader.position(); reader.set_position(pos + 6); }
+            let extended_cf = try!(reader.read_u32::<LE>());
+            if !capability_flags.contains(consts::CLIENT_LONG_PASSWORD) {
+                mariadb_capability_flags = MariadbCapabilityFlags::from_bits_truncate(extended_cf);
+            }
             if capability_flags.contains(consts::CLIENT_SECURE_CONNECTION) {
                 let mut len = length_of_auth_plugin_data - 8i16;
                 len = if len > 13i16 { len } else { 13i16 };
@@ -183,7 +281,9 @@ impl HandshakePacket {
         }
         Ok(HandshakePacket{protocol_version: protocol_version, connection_id: connection_id,
                          auth_plugin_data: auth_plugin_data, server_version: server_version,
+                         server_version_str: server_version_str,
                          capability_flags: capability_flags, character_set: character_set,
+                         mariadb_capability_flags: mariadb_capability_flags,
                          status_flags: status_flags, auth_plugin_name: auth_plugin_name})
     }
 }
@@ -198,12 +298,28 @@ mod test {
     #[test]
     fn should_parse_OK_packet() {
         let payload = [0u8, 1u8, 2u8, 8u8, 0u8, 4u8, 0u8, 32u8];
-        let ok_packet = OkPacket::from_payload(&payload).unwrap();
+        let ok_packet = OkPacket::from_payload(&payload, consts::CLIENT_PROTOCOL_41).unwrap();
         assert_eq!(ok_packet.affected_rows, 1);
         assert_eq!(ok_packet.last_insert_id, 2);
         assert_eq!(ok_packet.status_flags, consts::SERVER_MORE_RESULTS_EXISTS);
         assert_eq!(ok_packet.warnings, 4);
         assert_eq!(ok_packet.info, vec!(32u8));
+        assert_eq!(ok_packet.gtids(), None);
+    }
+    #[test]
+    fn should_parse_session_state_changes_of_OK_packet() {
+        let gtid = b"3e11fa47-71ca-11e1-9e33-c80aa9429562:23";
+        // Empty info followed by schema change and GTIDs change.
+        let mut payload = vec![0u8, 1u8, 0u8, 0x02, 0x40, 0u8, 0u8, 0u8, gtid.len() as u8 + 8];
+        payload.extend_from_slice(&[1u8, 2u8, 1u8, b'x']);
+        payload.extend_from_slice(&[3u8, gtid.len() as u8 + 2, 0u8, gtid.len() as u8]);
+        payload.extend_from_slice(gtid);
+        let flags = consts::CLIENT_PROTOCOL_41 | consts::CLIENT_SESSION_TRACK;
+        let ok_packet = OkPacket::from_payload(&payload, flags).unwrap();
+        assert_eq!(ok_packet.affected_rows, 1);
+        assert!(ok_packet.status_flags.contains(consts::SERVER_SESSION_STATE_CHANGED));
+        assert_eq!(ok_packet.info, Vec::<u8>::new());
+        assert_eq!(ok_packet.gtids(), Some(String::from_utf8(gtid.to_vec()).unwrap()));
     }
     #[test]
     fn should_parse_Error_packet() {
@@ -228,6 +344,7 @@ mod test {
         let handshake_packet = HandshakePacket::from_payload(payload).unwrap();
         assert_eq!(handshake_packet.protocol_version, 0x0a);
         assert_eq!(handshake_packet.server_version, (5, 6, 4));
+        assert_eq!(handshake_packet.server_version_str, "5.6.4");
         assert_eq!(handshake_packet.connection_id, 1);
         assert_eq!(handshake_packet.auth_plugin_data,
                    vec!(1u8, 2u8, 3u8, 4u8, 5u8, 6u8, 7u8, 8u8));
@@ -262,5 +379,30 @@ mod test {
                    consts::SERVER_MORE_RESULTS_EXISTS);
         assert_eq!(handshake_packet.auth_plugin_name,
                    vec!(1u8, 2u8, 3u8, 4u8, 5u8));
+        assert_eq!(handshake_packet.mariadb_capability_flags,
+                   consts::MariadbCapabilityFlags::empty());
+    }
+    #[test]
+    fn should_parse_mariadb_handshake_packet() {
+        let mut payload = b"\x0a5.5.5-10.4.12-MariaDB-log\x00\x01\x00\x00\x00\x01\x02\x03\x04\
+                            \x05\x06\x07\x08\x00\x04\x80".to_vec();
+        payload.push(33u8);
+        payload.extend(vec!(8u8, 0u8).into_iter());
+        payload.extend(vec!(0x08_u8, 0u8).into_iter());
+        payload.extend(vec!(0x15_u8).into_iter());
+        payload.extend(iter::repeat(0u8).take(6));
+        payload.extend(vec!(0x1d_u8, 0u8, 0u8, 0u8).into_iter());
+        payload.extend(iter::repeat(0x61_u8).take(12));
+        payload.push(0u8);
+        payload.extend(b"client_ed25519\x00".iter().cloned());
+        let handshake_packet = HandshakePacket::from_payload(&payload[..]).unwrap();
+        assert_eq!(handshake_packet.server_version, (10, 4, 12));
+        assert_eq!(handshake_packet.server_version_str, "5.5.5-10.4.12-MariaDB-log");
+        assert_eq!(handshake_packet.mariadb_capability_flags,
+                   consts::MARIADB_CLIENT_PROGRESS |
+                   consts::MARIADB_CLIENT_STMT_BULK_OPERATIONS |
+                   consts::MARIADB_CLIENT_EXTENDED_TYPE_INFO |
+                   consts::MARIADB_CLIENT_CACHE_METADATA);
+        assert_eq!(handshake_packet.auth_plugin_name, b"client_ed25519".to_vec());
     }
 }
@@ -0,0 +1,286 @@
+//! Minimal builder of parameterized `SELECT` and `INSERT` statements.
+//!
+//! It is meant for dynamic filters and column lists where SQL would otherwise be assembled via
+//! `format!`. Identifiers are quoted and every value becomes a `?` placeholder, so the result
+//! could be passed as is to `Conn::prep_exec` and friends. It is not an ORM — use plain SQL
+//! for anything beyond simple statements.
+//!
+//! ```
+//! use mysql::qb::{Op, Order, Select};
+//! use mysql::{Params, Value};
+//!
+//! let (sql, params) = Select::from("app.users")
+//!     .columns(&["id", "name"])
+//!     .filter("age", Op::Ge, 18)
+//!     .filter_in("status", vec!["active", "trial"])
+//!     .order_by("name", Order::Asc)
+//!     .limit(10)
+//!     .build();
+//! assert_eq!(sql, "SELECT `id`, `name` FROM `app`.`users` \
+//!                  WHERE `age` >= ? AND `status` IN (?, ?) ORDER BY `name` ASC LIMIT 10");
+//! assert_eq!(params, Params::Positional(vec![Value::from(18),
+//!                                            Value::from("active"),
+//!                                            Value::from("trial")]));
+//! ```
+
+use super::escape::quote_identifier;
+use super::value::{Params, Value};
+
+/// Comparison operator of a filter.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Like,
+    NotLike,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "<>",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Like => "LIKE",
+            Op::NotLike => "NOT LIKE",
+        }
+    }
+}
+
+/// Sort order of `ORDER BY` clause.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Builder of `SELECT` statement over a single table.
+///
+/// Filters are joined with `AND`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Select {
+    table: String,
+    columns: Vec<String>,
+    filters: Vec<String>,
+    params: Vec<Value>,
+    order_by: Vec<String>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl Select {
+    /// Selects from `table`, which may be qualified with a database name (`db.table`).
+    pub fn from<T: AsRef<str>>(table: T) -> Select {
+        Select {
+            table: quote_name(table.as_ref()),
+            columns: Vec::new(),
+            filters: Vec::new(),
+            params: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        }
+    }
+
+    /// Adds a column to select. All columns (`*`) are selected if none is given.
+    pub fn column<T: AsRef<str>>(mut self, column: T) -> Select {
+        self.columns.push(quote_name(column.as_ref()));
+        self
+    }
+
+    /// Adds columns to select.
+    pub fn columns<T: AsRef<str>>(mut self, columns: &[T]) -> Select {
+        for column in columns {
+            self.columns.push(quote_name(column.as_ref()));
+        }
+        self
+    }
+
+    /// Adds `column <op> ?` filter.
+    pub fn filter<T, V>(mut self, column: T, op: Op, value: V) -> Select
+    where T: AsRef<str>,
+          V: Into<Value>,
+    {
+        self.filters.push(format!("{} {} ?", quote_name(column.as_ref()), op.as_str()));
+        self.params.push(value.into());
+        self
+    }
+
+    /// Adds `column IN (?, ...)` filter. Empty `values` match no rows.
+    pub fn filter_in<T, I>(mut self, column: T, values: I) -> Select
+    where T: AsRef<str>,
+          I: IntoIterator,
+          I::Item: Into<Value>,
+    {
+        let len = self.params.len();
+        self.params.extend(values.into_iter().map(Into::into));
+        let count = self.params.len() - len;
+        if count == 0 {
+            self.filters.push("FALSE".into());
+        } else {
+            let placeholders = vec!["?"; count].join(", ");
+            self.filters.push(format!("{} IN ({})", quote_name(column.as_ref()), placeholders));
+        }
+        self
+    }
+
+    /// Adds `column IS NULL` filter.
+    pub fn filter_null<T: AsRef<str>>(mut self, column: T) -> Select {
+        self.filters.push(format!("{} IS NULL", quote_name(column.as_ref())));
+        self
+    }
+
+    /// Adds `column IS NOT NULL` filter.
+    pub fn filter_not_null<T: AsRef<str>>(mut self, column: T) -> Select {
+        self.filters.push(format!("{} IS NOT NULL", quote_name(column.as_ref())));
+        self
+    }
+
+    /// Adds a column to `ORDER BY` clause.
+    pub fn order_by<T: AsRef<str>>(mut self, column: T, order: Order) -> Select {
+        let order = match order {
+            Order::Asc => "ASC",
+            Order::Desc => "DESC",
+        };
+        self.order_by.push(format!("{} {}", quote_name(column.as_ref()), order));
+        self
+    }
+
+    /// Returns at most `limit` rows.
+    pub fn limit(mut self, limit: u64) -> Select {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skips `offset` rows. Requires `limit`, otherwise it is ignored.
+    pub fn offset(mut self, offset: u64) -> Select {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Returns SQL text and its parameters.
+    pub fn build(self) -> (String, Params) {
+        let mut sql = String::from("SELECT ");
+        if self.columns.is_empty() {
+            sql.push('*');
+        } else {
+            sql.push_str(&*self.columns.join(", "));
+        }
+        sql.push_str(" FROM ");
+        sql.push_str(&*self.table);
+        if !self.filters.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&*self.filters.join(" AND "));
+        }
+        if !self.order_by.is_empty() {
+            sql.push_str(" ORDER BY ");
+            sql.push_str(&*self.order_by.join(", "));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&*format!(" LIMIT {}", limit));
+            if let Some(offset) = self.offset {
+                sql.push_str(&*format!(" OFFSET {}", offset));
+            }
+        }
+        (sql, into_params(self.params))
+    }
+}
+
+/// Builder of single-row `INSERT` statement.
+///
+/// Use [`Conn::insert_many`](../struct.Conn.html#method.insert_many) for multiple rows.
+///
+/// ```
+/// use mysql::qb::Insert;
+///
+/// let (sql, _) = Insert::into("users").value("name", "foo").value("age", 42).build();
+/// assert_eq!(sql, "INSERT INTO `users` (`name`, `age`) VALUES (?, ?)");
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct Insert {
+    table: String,
+    columns: Vec<String>,
+    params: Vec<Value>,
+}
+
+impl Insert {
+    /// Inserts into `table`, which may be qualified with a database name (`db.table`).
+    pub fn into<T: AsRef<str>>(table: T) -> Insert {
+        Insert {
+            table: quote_name(table.as_ref()),
+            columns: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Sets value of a column.
+    pub fn value<T: AsRef<str>, V: Into<Value>>(mut self, column: T, value: V) -> Insert {
+        self.columns.push(quote_name(column.as_ref()));
+        self.params.push(value.into());
+        self
+    }
+
+    /// Returns SQL text and its parameters. Row of default values is inserted if no value is
+    /// given.
+    pub fn build(self) -> (String, Params) {
+        let sql = format!("INSERT INTO {} ({}) VALUES ({})",
+                          self.table,
+                          self.columns.join(", "),
+                          vec!["?"; self.params.len()].join(", "));
+        (sql, into_params(self.params))
+    }
+}
+
+/// Quotes possibly qualified name part by part.
+fn quote_name(name: &str) -> String {
+    name.split('.').map(quote_identifier).collect::<Vec<_>>().join(".")
+}
+
+fn into_params(params: Vec<Value>) -> Params {
+    if params.is_empty() {
+        Params::Empty
+    } else {
+        Params::Positional(params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Insert, Op, Order, Select};
+    use super::super::value::{Params, Value};
+
+    #[test]
+    fn should_build_select() {
+        assert_eq!(Select::from("tbl").build(), ("SELECT * FROM `tbl`".to_string(), Params::Empty));
+        let (sql, params) = Select::from("tbl")
+            .column("a`b")
+            .filter("name", Op::Like, "foo%")
+            .filter_null("deleted_at")
+            .filter_not_null("x.y")
+            .filter_in("id", Vec::<u32>::new())
+            .order_by("id", Order::Desc)
+            .offset(5)
+            .build();
+        assert_eq!(sql, "SELECT `a``b` FROM `tbl` WHERE `name` LIKE ? AND `deleted_at` IS NULL \
+                         AND `x`.`y` IS NOT NULL AND FALSE ORDER BY `id` DESC");
+        assert_eq!(params, Params::Positional(vec![Value::from("foo%")]));
+        let (sql, _) = Select::from("tbl").limit(10).offset(20).build();
+        assert_eq!(sql, "SELECT * FROM `tbl` LIMIT 10 OFFSET 20");
+    }
+
+    #[test]
+    fn should_build_insert() {
+        let (sql, params) = Insert::into("db.tbl").value("a", 1).value("b", "x").build();
+        assert_eq!(sql, "INSERT INTO `db`.`tbl` (`a`, `b`) VALUES (?, ?)");
+        assert_eq!(params, Params::Positional(vec![Value::from(1), Value::from("x")]));
+        assert_eq!(Insert::into("tbl").build(),
+                   ("INSERT INTO `tbl` () VALUES ()".to_string(), Params::Empty));
+    }
+}
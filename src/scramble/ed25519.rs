@@ -0,0 +1,258 @@
+//! Ed25519 signatures used by MariaDB `client_ed25519` auth plugin.
+//!
+//! Port of the signing part of [TweetNaCl](https://tweetnacl.cr.yp.to/). Field elements are
+//! sixteen 16-bit limbs stored in `i64`.
+
+use super::sha512::sha512;
+
+type Gf = [i64; 16];
+
+const GF0: Gf = [0; 16];
+const GF1: Gf = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+const D2: Gf = [0xf159, 0x26b2, 0x9b94, 0xebd6, 0xb156, 0x8283, 0x149a, 0x00e0,
+                0xd130, 0xeef3, 0x80f2, 0x198e, 0xfce7, 0x56df, 0xd9dc, 0x2406];
+const X: Gf = [0xd51a, 0x8f25, 0x2d60, 0xc956, 0xa7b2, 0x9525, 0xc760, 0x692c,
+               0xdc5c, 0xfdd6, 0xe231, 0xc0a4, 0x53fe, 0xcd6e, 0x36d3, 0x2169];
+const Y: Gf = [0x6658, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+               0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666];
+/// Order of the base point.
+const L: [i64; 32] = [0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+                      0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+                      0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10];
+
+fn car25519(o: &mut Gf) {
+    for i in 0..16 {
+        o[i] += 1 << 16;
+        let c = o[i] >> 16;
+        if i < 15 {
+            o[i + 1] += c - 1;
+        } else {
+            o[0] += 38 * (c - 1);
+        }
+        o[i] -= c << 16;
+    }
+}
+
+/// Swaps `p` and `q` if `b` is 1 in constant time.
+fn sel25519(p: &mut Gf, q: &mut Gf, b: i64) {
+    let c = !(b - 1);
+    for i in 0..16 {
+        let t = c & (p[i] ^ q[i]);
+        p[i] ^= t;
+        q[i] ^= t;
+    }
+}
+
+fn pack25519(n: &Gf) -> [u8; 32] {
+    let mut t = *n;
+    car25519(&mut t);
+    car25519(&mut t);
+    car25519(&mut t);
+    let mut m = GF0;
+    for _ in 0..2 {
+        m[0] = t[0] - 0xffed;
+        for i in 1..15 {
+            m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+            m[i - 1] &= 0xffff;
+        }
+        m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+        let b = (m[15] >> 16) & 1;
+        m[14] &= 0xffff;
+        sel25519(&mut t, &mut m, 1 - b);
+    }
+    let mut o = [0u8; 32];
+    for i in 0..16 {
+        o[2 * i] = t[i] as u8;
+        o[2 * i + 1] = (t[i] >> 8) as u8;
+    }
+    o
+}
+
+fn add25519(a: &Gf, b: &Gf) -> Gf {
+    let mut o = GF0;
+    for i in 0..16 {
+        o[i] = a[i] + b[i];
+    }
+    o
+}
+
+fn sub25519(a: &Gf, b: &Gf) -> Gf {
+    let mut o = GF0;
+    for i in 0..16 {
+        o[i] = a[i] - b[i];
+    }
+    o
+}
+
+fn mul25519(a: &Gf, b: &Gf) -> Gf {
+    let mut t = [0i64; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            t[i + j] += a[i] * b[j];
+        }
+    }
+    for i in 0..15 {
+        t[i] += 38 * t[i + 16];
+    }
+    let mut o = GF0;
+    o.copy_from_slice(&t[..16]);
+    car25519(&mut o);
+    car25519(&mut o);
+    o
+}
+
+fn inv25519(i: &Gf) -> Gf {
+    let mut c = *i;
+    for a in (0..254).rev() {
+        c = mul25519(&c, &c);
+        if a != 2 && a != 4 {
+            c = mul25519(&c, i);
+        }
+    }
+    c
+}
+
+/// Point in extended coordinates.
+type Point = [Gf; 4];
+
+fn add(p: &mut Point, q: &Point) {
+    let a = mul25519(&sub25519(&p[1], &p[0]), &sub25519(&q[1], &q[0]));
+    let b = mul25519(&add25519(&p[0], &p[1]), &add25519(&q[0], &q[1]));
+    let c = mul25519(&mul25519(&p[3], &q[3]), &D2);
+    let d = mul25519(&p[2], &q[2]);
+    let d = add25519(&d, &d);
+    let e = sub25519(&b, &a);
+    let f = sub25519(&d, &c);
+    let g = add25519(&d, &c);
+    let h = add25519(&b, &a);
+    p[0] = mul25519(&e, &f);
+    p[1] = mul25519(&h, &g);
+    p[2] = mul25519(&g, &f);
+    p[3] = mul25519(&e, &h);
+}
+
+fn cswap(p: &mut Point, q: &mut Point, b: i64) {
+    for i in 0..4 {
+        sel25519(&mut p[i], &mut q[i], b);
+    }
+}
+
+fn pack(p: &Point) -> [u8; 32] {
+    let zi = inv25519(&p[2]);
+    let tx = mul25519(&p[0], &zi);
+    let ty = mul25519(&p[1], &zi);
+    let mut r = pack25519(&ty);
+    r[31] ^= (pack25519(&tx)[0] & 1) << 7;
+    r
+}
+
+/// Multiplies the base point by scalar `s`.
+fn scalarbase(s: &[u8]) -> Point {
+    let mut p = [GF0, GF1, GF1, GF0];
+    let mut q = [X, Y, GF1, mul25519(&X, &Y)];
+    for i in (0..256).rev() {
+        let b = ((s[i / 8] >> (i & 7)) & 1) as i64;
+        cswap(&mut p, &mut q, b);
+        let p_copy = p;
+        add(&mut q, &p_copy);
+        add(&mut p, &p_copy);
+        cswap(&mut p, &mut q, b);
+    }
+    p
+}
+
+/// Reduces 64-byte little-endian number `x` modulo `L`.
+fn mod_l(x: &mut [i64; 64]) -> [u8; 32] {
+    for i in (32..64).rev() {
+        let mut carry = 0;
+        let mut j = i - 32;
+        while j < i - 12 {
+            x[j] += carry - 16 * x[i] * L[j - (i - 32)];
+            carry = (x[j] + 128) >> 8;
+            x[j] -= carry << 8;
+            j += 1;
+        }
+        x[j] += carry;
+        x[i] = 0;
+    }
+    let mut carry = 0;
+    for j in 0..32 {
+        x[j] += carry - (x[31] >> 4) * L[j];
+        carry = x[j] >> 8;
+        x[j] &= 255;
+    }
+    for j in 0..32 {
+        x[j] -= carry * L[j];
+    }
+    let mut r = [0u8; 32];
+    for i in 0..32 {
+        x[i + 1] += x[i] >> 8;
+        r[i] = (x[i] & 255) as u8;
+    }
+    r
+}
+
+fn reduce(bytes: &[u8]) -> [u8; 32] {
+    let mut x = [0i64; 64];
+    for i in 0..64 {
+        x[i] = bytes[i] as i64;
+    }
+    mod_l(&mut x)
+}
+
+/// Signs `message` with a key derived from `password` as MariaDB does, i.e. SHA-512 of the
+/// password is used as an expanded secret key.
+pub fn sign(message: &[u8], password: &[u8]) -> Vec<u8> {
+    let mut d = sha512(password);
+    d[0] &= 248;
+    d[31] &= 127;
+    d[31] |= 64;
+    let public_key = pack(&scalarbase(&d[..32]));
+
+    let mut buf = d[32..].to_vec();
+    buf.extend_from_slice(message);
+    let r = reduce(&*sha512(&*buf));
+    let big_r = pack(&scalarbase(&r));
+
+    let mut buf = big_r.to_vec();
+    buf.extend_from_slice(&public_key);
+    buf.extend_from_slice(message);
+    let h = reduce(&*sha512(&*buf));
+
+    let mut x = [0i64; 64];
+    for i in 0..32 {
+        x[i] = r[i] as i64;
+    }
+    for i in 0..32 {
+        for j in 0..32 {
+            x[i + j] += h[i] as i64 * d[j] as i64;
+        }
+    }
+    let s = mod_l(&mut x);
+
+    let mut signature = big_r.to_vec();
+    signature.extend_from_slice(&s);
+    signature
+}
+
+#[cfg(test)]
+mod test {
+    use super::sign;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len() / 2).map(|i| u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn should_sign_message() {
+        // RFC 8032, section 7.1, tests 1 and 2 (32-byte password is the same as a seed).
+        let secret = unhex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60");
+        assert_eq!(sign(b"", &*secret),
+                   unhex("e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+                          5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"));
+        let secret = unhex("4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb");
+        assert_eq!(sign(&[0x72], &*secret),
+                   unhex("92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+                          085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"));
+    }
+}
@@ -1,4 +1,7 @@
+mod ed25519;
 mod sha1;
+mod sha256;
+mod sha512;
 
 pub fn scramble(scr: &[u8], password: &[u8]) -> Option<Vec<u8>> {
     if password.len() == 0 {
@@ -21,9 +24,72 @@ pub fn scramble(scr: &[u8], password: &[u8]) -> Option<Vec<u8>> {
     Some(output.to_vec())
 }
 
+/// Scrambles password for `caching_sha2_password` auth plugin:
+/// `SHA256(password) XOR SHA256(SHA256(SHA256(password)), nonce)`.
+pub fn scramble_sha256(nonce: &[u8], password: &[u8]) -> Option<Vec<u8>> {
+    if password.len() == 0 {
+        return None;
+    }
+
+    let sha_pass = sha256::sha256(password);
+    let mut message = sha256::sha256(&*sha_pass);
+    message.extend_from_slice(nonce);
+    let hash = sha256::sha256(&*message);
+
+    Some(sha_pass.iter().zip(hash.iter()).map(|(x, y)| x ^ y).collect())
+}
+
+/// Signs `nonce` for MariaDB `client_ed25519` auth plugin with a key derived from `password`.
+pub fn scramble_ed25519(nonce: &[u8], password: &[u8]) -> Vec<u8> {
+    ed25519::sign(nonce, password)
+}
+
+/// Pre-4.1 password hash (`OLD_PASSWORD()`). Spaces and tabs are ignored.
+fn hash_password_323(password: &[u8]) -> (u32, u32) {
+    let mut nr: u32 = 1345345333;
+    let mut add: u32 = 7;
+    let mut nr2: u32 = 0x12345671;
+    for &c in password.iter().filter(|&&c| c != b' ' && c != b'\t') {
+        let tmp = c as u32;
+        nr ^= ((nr & 63).wrapping_add(add)).wrapping_mul(tmp).wrapping_add(nr << 8);
+        nr2 = nr2.wrapping_add((nr2 << 8) ^ nr);
+        add = add.wrapping_add(tmp);
+    }
+    (nr & 0x7FFFFFFF, nr2 & 0x7FFFFFFF)
+}
+
+/// Scrambles password for `mysql_old_password` auth plugin using first 8 bytes of `scr`.
+pub fn scramble_323(scr: &[u8], password: &[u8]) -> Option<Vec<u8>> {
+    if password.len() == 0 {
+        return None;
+    }
+
+    const MAX_VALUE: u64 = 0x3FFFFFFF;
+    let hash_pass = hash_password_323(password);
+    let hash_message = hash_password_323(&scr[..::std::cmp::min(8, scr.len())]);
+    let mut seed1 = ((hash_pass.0 ^ hash_message.0) as u64) % MAX_VALUE;
+    let mut seed2 = ((hash_pass.1 ^ hash_message.1) as u64) % MAX_VALUE;
+    let mut rnd = || {
+        seed1 = (seed1 * 3 + seed2) % MAX_VALUE;
+        seed2 = (seed1 + seed2 + 33) % MAX_VALUE;
+        seed1 as f64 / MAX_VALUE as f64
+    };
+
+    let mut output = Vec::with_capacity(8);
+    for _ in 0..8 {
+        output.push((rnd() * 31.0).floor() as u8 + 64);
+    }
+    let extra = (rnd() * 31.0).floor() as u8;
+    for x in output.iter_mut() {
+        *x ^= extra;
+    }
+
+    Some(output)
+}
+
 #[cfg(test)]
 mod test {
-    use super::scramble;
+    use super::{hash_password_323, scramble, scramble_323, scramble_sha256};
 
     #[test]
     fn should_compute_scrambled_password() {
@@ -41,4 +107,29 @@ mod test {
                         0x22_u8, 0x70_u8, 0xd8_u8, 0xfb_u8, 0x9f_u8,
                         0xad_u8, 0xba_u8, 0x90_u8, 0x6b_u8, 0x70_u8]);
     }
+
+    #[test]
+    fn should_compute_sha256_scrambled_password() {
+        assert_eq!(scramble_sha256(b"12345678901234567890", b"password").unwrap(),
+                   vec![0x71, 0x8d, 0x80, 0xfb, 0x92, 0xb6, 0x93, 0xf7, 0xee, 0x1f, 0xca, 0xd9,
+                        0x5b, 0xfe, 0x7a, 0x2b, 0xe1, 0xf3, 0x32, 0xac, 0x2c, 0x3e, 0xc6, 0xcd,
+                        0xc8, 0x58, 0x93, 0xb6, 0x5b, 0x24, 0x06, 0x50]);
+        assert!(scramble_sha256(b"12345678901234567890", b"").is_none());
+    }
+
+    #[test]
+    fn should_compute_old_password_hash() {
+        // SELECT OLD_PASSWORD('password')
+        assert_eq!(hash_password_323(b"password"), (0x5d2e1939, 0x3cc5ef67));
+        assert_eq!(hash_password_323(b"pass word"), hash_password_323(b"password"));
+    }
+
+    #[test]
+    fn should_compute_old_scrambled_password() {
+        assert_eq!(scramble_323(b"12345678", b"password").unwrap(),
+                   vec![0x46, 0x4b, 0x4d, 0x50, 0x5c, 0x50, 0x48, 0x4e]);
+        assert_eq!(scramble_323(b"12345678901234567890", b"password"),
+                   scramble_323(b"12345678", b"password"));
+        assert!(scramble_323(b"12345678", b"").is_none());
+    }
 }
@@ -0,0 +1,109 @@
+static K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// SHA-512 hash of `message` (FIPS 180-4).
+pub fn sha512(message: &[u8]) -> Vec<u8> {
+    let mut hash: [u64; 8] = [0x6a09e667f3bcc908,
+                              0xbb67ae8584caa73b,
+                              0x3c6ef372fe94f82b,
+                              0xa54ff53a5f1d36f1,
+                              0x510e527fade682d1,
+                              0x9b05688c2b3e6c1f,
+                              0x1f83d9abfb41bd6b,
+                              0x5be0cd19137e2179];
+    let mut msg = message.to_vec();
+    let msg_bit_len = (message.len() as u64).wrapping_mul(8);
+    msg.push(0x80);
+    while msg.len() % 128 != 112 {
+        msg.push(0);
+    }
+    // Length is 128-bit, but messages longer than 2^64 bits are not supported.
+    msg.extend_from_slice(&[0u8; 8]);
+    for i in 0..8 {
+        msg.push((msg_bit_len >> (56 - i * 8)) as u8);
+    }
+
+    for block in msg.chunks(128) {
+        let mut w = [0u64; 80];
+        for j in 0..16 {
+            for k in 0..8 {
+                w[j] = (w[j] << 8) | block[j * 8 + k] as u64;
+            }
+        }
+        for j in 16..80 {
+            let s0 = w[j - 15].rotate_right(1) ^ w[j - 15].rotate_right(8) ^ (w[j - 15] >> 7);
+            let s1 = w[j - 2].rotate_right(19) ^ w[j - 2].rotate_right(61) ^ (w[j - 2] >> 6);
+            w[j] = w[j - 16].wrapping_add(s0).wrapping_add(w[j - 7]).wrapping_add(s1);
+        }
+        let mut v = hash;
+        for j in 0..80 {
+            let s1 = v[4].rotate_right(14) ^ v[4].rotate_right(18) ^ v[4].rotate_right(41);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(K[j]).wrapping_add(w[j]);
+            let s0 = v[0].rotate_right(28) ^ v[0].rotate_right(34) ^ v[0].rotate_right(39);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+            v[7] = v[6];
+            v[6] = v[5];
+            v[5] = v[4];
+            v[4] = v[3].wrapping_add(t1);
+            v[3] = v[2];
+            v[2] = v[1];
+            v[1] = v[0];
+            v[0] = t1.wrapping_add(t2);
+        }
+        for j in 0..8 {
+            hash[j] = hash[j].wrapping_add(v[j]);
+        }
+    }
+
+    let mut output = Vec::with_capacity(64);
+    for word in hash.iter() {
+        for i in 0..8 {
+            output.push((word >> (56 - i * 8)) as u8);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::sha512;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn should_compute_sha512_hash() {
+        assert_eq!(hex(&*sha512(b"")),
+                   "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+                    47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e");
+        assert_eq!(hex(&*sha512(b"abc")),
+                   "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                    2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f");
+        assert_eq!(hex(&*sha512(&[b'a'; 112])),
+                   "c01d080efd492776a1c43bd23dd99d0a2e626d481e16782e75d54c2503b5dc32\
+                    bd05f0f1ba33e568b88fd2d970929b719ecbb152f58f130a407c8830604b70ca");
+    }
+}
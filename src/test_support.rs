@@ -0,0 +1,688 @@
+//! Scripted in-memory server which allows to unit-test code built on top of this crate
+//! without a running MySql server.
+//!
+//! #### Only available if `test-support` feature enabled.
+//! It is meant for `[dev-dependencies]`:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! mysql = { version = "*", features = ["test-support"] }
+//! ```
+//!
+//! [`MockServer`](struct.MockServer.html) answers the handshake (any credentials are accepted),
+//! `COM_QUERY`, `COM_STMT_*`, `COM_PING` and a few other commands with canned packets.
+//! Connections talk to it through an in-memory [`Stream`](../trait.Stream.html), so no threads
+//! or sockets are involved.
+//!
+//! ```
+//! use mysql::Value;
+//! use mysql::test_support::{MockServer, Request, Response};
+//!
+//! let server = MockServer::new()
+//!     .on_query("SELECT id, name FROM users WHERE id = ?",
+//!               Response::rows(vec!["id", "name"],
+//!                              vec![vec![Value::from(1), Value::from("foo")]]))
+//!     .on_query("DELETE FROM users", Response::ok(3, 0));
+//! let mut conn = server.conn().unwrap();
+//!
+//! let users: Vec<(u32, String)> =
+//!     conn.exec("SELECT id, name FROM users WHERE id = ?", (1,)).unwrap();
+//! assert_eq!(users, vec![(1, "foo".to_string())]);
+//! assert_eq!(conn.query("DELETE FROM users").unwrap().affected_rows(), 3);
+//! assert!(conn.query("DROP TABLE users").is_err());
+//!
+//! assert_eq!(server.requests()[1],
+//!            Request::Execute("SELECT id, name FROM users WHERE id = ?".into(),
+//!                             vec![Value::Int(1)]));
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::io::Write as StdWrite;
+use std::sync::{Arc, Mutex};
+
+use super::conn::{Conn, Opts, Stream};
+use super::consts;
+use super::consts::{ColumnType, Command};
+use super::error::Result as MyResult;
+use super::io::Read as MyRead;
+use super::io::Write;
+use super::value::Value;
+
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
+use byteorder::LittleEndian as LE;
+
+/// `ER_UNKNOWN_ERROR` is returned for a query without a response.
+pub const ER_UNKNOWN_ERROR: u16 = 1105;
+/// `ER_UNKNOWN_STMT_HANDLER` is returned for an execution of an unknown statement.
+pub const ER_UNKNOWN_STMT_HANDLER: u16 = 1243;
+
+const MAX_ALLOWED_PACKET: usize = 16 * 1024 * 1024;
+
+/// Canned response of a [`MockServer`](struct.MockServer.html) to a query.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Response {
+    Ok {
+        affected_rows: u64,
+        last_insert_id: u64,
+    },
+    Err {
+        code: u16,
+        state: String,
+        message: String,
+    },
+    /// Type of a column is derived from its first non-`NULL` value, so every value of a column
+    /// should be of the same variant.
+    ResultSet {
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    },
+}
+
+impl Response {
+    pub fn ok(affected_rows: u64, last_insert_id: u64) -> Response {
+        Response::Ok {
+            affected_rows: affected_rows,
+            last_insert_id: last_insert_id,
+        }
+    }
+
+    /// Error with `HY000` SQL state.
+    pub fn error<T: Into<String>>(code: u16, message: T) -> Response {
+        Response::Err {
+            code: code,
+            state: "HY000".into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn rows<T: Into<String>>(columns: Vec<T>, rows: Vec<Vec<Value>>) -> Response {
+        Response::ResultSet {
+            columns: columns.into_iter().map(Into::into).collect(),
+            rows: rows,
+        }
+    }
+
+    fn columns(&self) -> &[String] {
+        match *self {
+            Response::ResultSet { ref columns, .. } => &*columns,
+            _ => &[],
+        }
+    }
+}
+
+/// Command received by a [`MockServer`](struct.MockServer.html).
+#[derive(Clone, PartialEq, Debug)]
+pub enum Request {
+    Query(String),
+    Prepare(String),
+    /// Query of an executed statement and its parameters.
+    Execute(String, Vec<Value>),
+}
+
+#[derive(Debug)]
+struct Script {
+    version: String,
+    responses: HashMap<String, VecDeque<Response>>,
+    requests: Vec<Request>,
+    connections: u32,
+}
+
+impl Script {
+    /// Returns response to `query` without consuming it.
+    fn peek(&self, query: &str) -> Option<Response> {
+        match self.responses.get(normalize(query)) {
+            Some(responses) => responses.front().cloned(),
+            None => builtin(query),
+        }
+    }
+
+    /// Returns response to `query`. The last scripted response is repeated.
+    fn next(&mut self, request: Request, query: &str) -> Response {
+        if let Some(responses) = self.responses.get_mut(normalize(query)) {
+            self.requests.push(request);
+            return if responses.len() > 1 {
+                responses.pop_front().unwrap()
+            } else {
+                responses.front().cloned().unwrap()
+            };
+        }
+        match builtin(query) {
+            Some(response) => response,
+            None => {
+                self.requests.push(request);
+                unexpected(query)
+            },
+        }
+    }
+}
+
+/// Ignores surrounding whitespace and trailing semicolon of a query.
+fn normalize(query: &str) -> &str {
+    query.trim().trim_right_matches(';').trim_right()
+}
+
+/// Responses to queries executed by `Conn` itself.
+fn builtin(query: &str) -> Option<Response> {
+    match normalize(query) {
+        "SELECT @@max_allowed_packet" => {
+            Some(Response::rows(vec!["@@max_allowed_packet"],
+                                vec![vec![Value::Int(MAX_ALLOWED_PACKET as i64)]]))
+        },
+        _ => None,
+    }
+}
+
+fn unexpected(query: &str) -> Response {
+    Response::error(ER_UNKNOWN_ERROR, format!("MockServer: no response for query: {}", query))
+}
+
+/// Scripted server. Cloned server shares the script and the log of requests.
+///
+/// Queries are matched exactly, ignoring surrounding whitespace and trailing semicolon.
+/// A query without a response fails with `ER_UNKNOWN_ERROR`.
+#[derive(Clone, Debug)]
+pub struct MockServer(Arc<Mutex<Script>>);
+
+impl MockServer {
+    /// Creates server which reports version `5.7.30-mock`.
+    pub fn new() -> MockServer {
+        MockServer(Arc::new(Mutex::new(Script {
+            version: "5.7.30-mock".into(),
+            responses: HashMap::new(),
+            requests: Vec::new(),
+            connections: 0,
+        })))
+    }
+
+    /// Sets version reported in the handshake of subsequent connections.
+    pub fn version<T: Into<String>>(self, version: T) -> MockServer {
+        self.0.lock().unwrap().version = version.into();
+        self
+    }
+
+    /// Adds response to `query` either executed as a text query or as a prepared statement.
+    ///
+    /// Responses added for the same query are returned in order and the last one is repeated.
+    pub fn on_query<T: Into<String>>(self, query: T, response: Response) -> MockServer {
+        {
+            let mut script = self.0.lock().unwrap();
+            let query = normalize(&*query.into()).to_string();
+            script.responses.entry(query).or_insert_with(VecDeque::new).push_back(response);
+        }
+        self
+    }
+
+    /// Returns commands received by all connections to this server except of queries which
+    /// `Conn` executes on its own (e.g. `SELECT @@max_allowed_packet`).
+    pub fn requests(&self) -> Vec<Request> {
+        self.0.lock().unwrap().requests.clone()
+    }
+
+    /// Returns transport of a new connection to this server.
+    pub fn stream(&self) -> MockStream {
+        let mut script = self.0.lock().unwrap();
+        script.connections += 1;
+        let mut stream = MockStream {
+            server: self.clone(),
+            input: Vec::new(),
+            output: Vec::new(),
+            seq_id: 0,
+            authenticated: false,
+            stmts: HashMap::new(),
+            next_stmt_id: 1,
+        };
+        stream.send(&*handshake(&*script.version, script.connections)).unwrap();
+        stream
+    }
+
+    /// Connects to this server with default options.
+    pub fn conn(&self) -> MyResult<Conn> {
+        self.conn_with_opts(Opts::default())
+    }
+
+    /// Connects to this server. Address, socket, proxy and SSL options are ignored.
+    pub fn conn_with_opts<T: Into<Opts>>(&self, opts: T) -> MyResult<Conn> {
+        Conn::new_with_stream(self.stream(), opts)
+    }
+}
+
+fn handshake(version: &str, connection_id: u32) -> Vec<u8> {
+    let capabilities = consts::CLIENT_LONG_PASSWORD |
+                       consts::CLIENT_LONG_FLAG |
+                       consts::CLIENT_CONNECT_WITH_DB |
+                       consts::CLIENT_PROTOCOL_41 |
+                       consts::CLIENT_TRANSACTIONS |
+                       consts::CLIENT_SECURE_CONNECTION |
+                       consts::CLIENT_MULTI_STATEMENTS |
+                       consts::CLIENT_MULTI_RESULTS |
+                       consts::CLIENT_PS_MULTI_RESULTS |
+                       consts::CLIENT_PLUGIN_AUTH;
+    let mut buf = vec![10u8];
+    buf.extend_from_slice(version.as_bytes());
+    buf.push(0);
+    buf.write_u32::<LE>(connection_id).unwrap();
+    buf.extend_from_slice(b"abcdefgh\0");
+    buf.write_u16::<LE>(capabilities.bits() as u16).unwrap();
+    // utf8_general_ci
+    buf.push(33);
+    buf.write_u16::<LE>(consts::SERVER_STATUS_AUTOCOMMIT.bits()).unwrap();
+    buf.write_u16::<LE>((capabilities.bits() >> 16) as u16).unwrap();
+    buf.push(21);
+    buf.extend_from_slice(&[0u8; 10]);
+    buf.extend_from_slice(b"ijklmnopqrst\0");
+    buf.extend_from_slice(b"mysql_native_password\0");
+    buf
+}
+
+/// Type, flags and character set of a column of a result set.
+fn column_type(rows: &[Vec<Value>], index: usize) -> (ColumnType, consts::ColumnFlags, u16) {
+    let value = rows.iter().filter_map(|row| row.get(index)).find(|value| **value != Value::NULL);
+    let empty = consts::ColumnFlags::empty();
+    match value {
+        Some(&Value::Bytes(..)) => (ColumnType::MYSQL_TYPE_VAR_STRING, empty, 33),
+        Some(&Value::Int(..)) => (ColumnType::MYSQL_TYPE_LONGLONG, empty, 63),
+        Some(&Value::UInt(..)) => (ColumnType::MYSQL_TYPE_LONGLONG, consts::UNSIGNED_FLAG, 63),
+        Some(&Value::Float(..)) => (ColumnType::MYSQL_TYPE_DOUBLE, empty, 63),
+        Some(&Value::Date(..)) => (ColumnType::MYSQL_TYPE_DATETIME, empty, 63),
+        Some(&Value::Time(..)) => (ColumnType::MYSQL_TYPE_TIME, empty, 63),
+        Some(&Value::NULL) | None => (ColumnType::MYSQL_TYPE_NULL, empty, 63),
+    }
+}
+
+fn column_def(name: &str,
+              column_type: ColumnType,
+              flags: consts::ColumnFlags,
+              character_set: u16) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    // Catalog, schema, table, original table, name and original name.
+    let parts: [&[u8]; 6] = [b"def", b"", b"", b"", name.as_bytes(), name.as_bytes()];
+    for part in parts.iter() {
+        try!(buf.write_lenenc_bytes(part));
+    }
+    try!(buf.write_lenenc_int(0x0c));
+    try!(buf.write_u16::<LE>(character_set));
+    try!(buf.write_u32::<LE>(255));
+    try!(buf.write_u8(column_type as u8));
+    try!(buf.write_u16::<LE>(flags.bits()));
+    try!(buf.write_u8(0));
+    try!(buf.write_u16::<LE>(0));
+    Ok(buf)
+}
+
+/// Text protocol representation of a non-`NULL` value.
+fn text(value: &Value) -> Vec<u8> {
+    match *value {
+        Value::Bytes(ref bytes) => bytes.clone(),
+        Value::Int(x) => x.to_string().into_bytes(),
+        Value::UInt(x) => x.to_string().into_bytes(),
+        Value::Float(x) => x.to_string().into_bytes(),
+        _ => {
+            // Dates and times are rendered as quoted literals.
+            let literal = value.as_sql(true);
+            literal.trim_matches('\'').as_bytes().to_vec()
+        },
+    }
+}
+
+/// Counts `?` placeholders outside of quotes.
+fn count_params(query: &str) -> u16 {
+    let mut count = 0;
+    let mut quote = None;
+    for c in query.chars() {
+        match (quote, c) {
+            (None, '\'') | (None, '"') | (None, '`') => quote = Some(c),
+            (None, '?') => count += 1,
+            (Some(q), c) if q == c => quote = None,
+            _ => (),
+        }
+    }
+    count
+}
+
+/// In-memory transport of a connection to a [`MockServer`](struct.MockServer.html).
+///
+/// Every packet written by a client is answered right away. Reading with no pending response
+/// fails with `UnexpectedEof` instead of blocking.
+#[derive(Debug)]
+pub struct MockStream {
+    server: MockServer,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    seq_id: u8,
+    authenticated: bool,
+    /// Query and number of parameters of prepared statements.
+    stmts: HashMap<u32, (String, u16)>,
+    next_stmt_id: u32,
+}
+
+impl MockStream {
+    fn send(&mut self, payload: &[u8]) -> io::Result<()> {
+        try!(self.output.write_le_uint_n(payload.len() as u64, 3));
+        try!(self.output.write_u8(self.seq_id));
+        try!(self.output.write_all(payload));
+        self.seq_id = self.seq_id.wrapping_add(1);
+        Ok(())
+    }
+
+    fn send_ok(&mut self, affected_rows: u64, last_insert_id: u64) -> io::Result<()> {
+        let mut buf = vec![0u8];
+        try!(buf.write_lenenc_int(affected_rows));
+        try!(buf.write_lenenc_int(last_insert_id));
+        try!(buf.write_u16::<LE>(consts::SERVER_STATUS_AUTOCOMMIT.bits()));
+        try!(buf.write_u16::<LE>(0));
+        self.send(&*buf)
+    }
+
+    fn send_err(&mut self, code: u16, state: &str, message: &str) -> io::Result<()> {
+        let mut buf = vec![0xFFu8];
+        try!(buf.write_u16::<LE>(code));
+        buf.push(b'#');
+        buf.extend_from_slice(state.as_bytes());
+        buf.extend_from_slice(message.as_bytes());
+        self.send(&*buf)
+    }
+
+    fn send_eof(&mut self) -> io::Result<()> {
+        let mut buf = vec![0xFEu8];
+        try!(buf.write_u16::<LE>(0));
+        try!(buf.write_u16::<LE>(consts::SERVER_STATUS_AUTOCOMMIT.bits()));
+        self.send(&*buf)
+    }
+
+    fn send_columns(&mut self, columns: &[String], rows: &[Vec<Value>]) -> io::Result<()> {
+        for (i, name) in columns.iter().enumerate() {
+            let (column_type, flags, character_set) = column_type(rows, i);
+            try!(self.send(&*try!(column_def(name, column_type, flags, character_set))));
+        }
+        self.send_eof()
+    }
+
+    fn send_response(&mut self, response: Response, is_bin: bool) -> io::Result<()> {
+        match response {
+            Response::Ok { affected_rows, last_insert_id } => {
+                self.send_ok(affected_rows, last_insert_id)
+            },
+            Response::Err { code, ref state, ref message } => self.send_err(code, state, message),
+            Response::ResultSet { ref columns, ref rows } => {
+                let mut buf = Vec::new();
+                try!(buf.write_lenenc_int(columns.len() as u64));
+                try!(self.send(&*buf));
+                try!(self.send_columns(columns, rows));
+                for row in rows.iter() {
+                    let mut buf = Vec::new();
+                    if is_bin {
+                        // http://dev.mysql.com/doc/internals/en/null-bitmap.html
+                        let bit_offset = 2;
+                        buf.push(0u8);
+                        buf.extend(::std::iter::repeat(0u8).take((columns.len() + 9) / 8));
+                        for (i, value) in row.iter().enumerate() {
+                            if *value == Value::NULL {
+                                buf[1 + (i + bit_offset) / 8] |= 1 << ((i + bit_offset) % 8);
+                            } else {
+                                try!(value.write_bin(&mut buf));
+                            }
+                        }
+                    } else {
+                        for value in row.iter() {
+                            match *value {
+                                Value::NULL => buf.push(0xFB),
+                                _ => try!(buf.write_lenenc_bytes(&*text(value))),
+                            }
+                        }
+                    }
+                    try!(self.send(&*buf));
+                }
+                self.send_eof()
+            },
+        }
+    }
+
+    fn prepare(&mut self, query: String) -> io::Result<()> {
+        let response = {
+            let mut script = self.server.0.lock().unwrap();
+            let response = script.peek(&*query);
+            if script.responses.contains_key(normalize(&*query)) || builtin(&*query).is_none() {
+                script.requests.push(Request::Prepare(query.clone()));
+            }
+            response.unwrap_or_else(|| unexpected(&*query))
+        };
+        if let Response::Err { code, ref state, ref message } = response {
+            return self.send_err(code, state, message);
+        }
+        let id = self.next_stmt_id;
+        self.next_stmt_id += 1;
+        let num_params = count_params(&*query);
+        let columns = response.columns().to_vec();
+        self.stmts.insert(id, (query, num_params));
+
+        let mut buf = vec![0u8];
+        try!(buf.write_u32::<LE>(id));
+        try!(buf.write_u16::<LE>(columns.len() as u16));
+        try!(buf.write_u16::<LE>(num_params));
+        try!(buf.write_u8(0));
+        try!(buf.write_u16::<LE>(0));
+        try!(self.send(&*buf));
+        if num_params > 0 {
+            for _ in 0..num_params {
+                let def = try!(column_def("?",
+                                          ColumnType::MYSQL_TYPE_VAR_STRING,
+                                          consts::ColumnFlags::empty(),
+                                          63));
+                try!(self.send(&*def));
+            }
+            try!(self.send_eof());
+        }
+        if !columns.is_empty() {
+            let rows = match response {
+                Response::ResultSet { rows, .. } => rows,
+                _ => Vec::new(),
+            };
+            try!(self.send_columns(&*columns, &*rows));
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, mut data: &[u8]) -> io::Result<()> {
+        let id = try!(data.read_u32::<LE>());
+        let (query, num_params) = match self.stmts.get(&id) {
+            Some(&(ref query, num_params)) => (query.clone(), num_params as usize),
+            None => {
+                let message = format!("Unknown prepared statement handler ({}) given to \
+                                       mysqld_stmt_execute", id);
+                return self.send_err(ER_UNKNOWN_STMT_HANDLER, "HY000", &*message);
+            },
+        };
+        // Skip flags and iteration count.
+        if data.len() < 5 {
+            return Err(malformed_packet());
+        }
+        let mut data = &data[5..];
+        let mut params = Vec::with_capacity(num_params);
+        if num_params > 0 {
+            let bitmap_len = (num_params + 7) / 8;
+            if data.len() < bitmap_len {
+                return Err(malformed_packet());
+            }
+            let bitmap = data[..bitmap_len].to_vec();
+            data = &data[bitmap_len..];
+            let mut types = Vec::with_capacity(num_params);
+            if try!(data.read_u8()) == 1 {
+                for _ in 0..num_params {
+                    types.push((try!(data.read_u8()), try!(data.read_u8())));
+                }
+            } else {
+                types.resize(num_params, (ColumnType::MYSQL_TYPE_VAR_STRING as u8, 0));
+            }
+            for (i, &(column_type, flags)) in types.iter().enumerate() {
+                if bitmap[i / 8] & (1 << (i % 8)) == 0 {
+                    params.push(try!(data.read_bin_value(column_type.into(), flags & 0x80 != 0)));
+                } else {
+                    params.push(Value::NULL);
+                }
+            }
+        }
+        let response = {
+            let mut script = self.server.0.lock().unwrap();
+            script.next(Request::Execute(query.clone(), params), &*query)
+        };
+        self.send_response(response, true)
+    }
+
+    fn handle(&mut self, payload: &[u8]) -> io::Result<()> {
+        if !self.authenticated {
+            // Any credentials are accepted.
+            self.authenticated = true;
+            return self.send_ok(0, 0);
+        }
+        let (command, data) = match payload.split_first() {
+            Some((command, data)) => (*command, data),
+            None => return Ok(()),
+        };
+        if command == Command::COM_QUERY as u8 {
+            let query = String::from_utf8_lossy(data).into_owned();
+            let response = {
+                let mut script = self.server.0.lock().unwrap();
+                script.next(Request::Query(query.clone()), &*query)
+            };
+            self.send_response(response, false)
+        } else if command == Command::COM_STMT_PREPARE as u8 {
+            self.prepare(String::from_utf8_lossy(data).into_owned())
+        } else if command == Command::COM_STMT_EXECUTE as u8 {
+            self.execute(data)
+        } else if command == Command::COM_STMT_CLOSE as u8 {
+            if data.len() >= 4 {
+                self.stmts.remove(&LE::read_u32(data));
+            }
+            Ok(())
+        } else if command == Command::COM_QUIT as u8 ||
+                  command == Command::COM_STMT_SEND_LONG_DATA as u8 {
+            Ok(())
+        } else if command == Command::COM_PING as u8 ||
+                  command == Command::COM_INIT_DB as u8 ||
+                  command == Command::COM_STMT_RESET as u8 ||
+                  command == Command::COM_RESET_CONNECTION as u8 {
+            if command == Command::COM_RESET_CONNECTION as u8 {
+                self.stmts.clear();
+            }
+            self.send_ok(0, 0)
+        } else {
+            let message = format!("MockServer: unsupported command 0x{:02x}", command);
+            self.send_err(ER_UNKNOWN_ERROR, "HY000", &*message)
+        }
+    }
+}
+
+impl io::Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.output.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                      "MockServer has nothing to send"));
+        }
+        let count = ::std::cmp::min(buf.len(), self.output.len());
+        buf[..count].copy_from_slice(&self.output[..count]);
+        self.output.drain(..count);
+        Ok(count)
+    }
+}
+
+impl io::Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.input.extend_from_slice(buf);
+        while self.input.len() >= 4 {
+            let len = try!((&self.input[..3]).read_uint::<LE>(3)) as usize;
+            if self.input.len() < 4 + len {
+                break;
+            }
+            self.seq_id = self.input[3].wrapping_add(1);
+            let payload: Vec<u8> = self.input.drain(..4 + len).skip(4).collect();
+            try!(self.handle(&*payload));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Stream for MockStream {}
+
+fn malformed_packet() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "MockServer received malformed packet")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MockServer, Request, Response, ER_UNKNOWN_ERROR};
+    use conn::Opts;
+    use error::Error::MySqlError;
+    use value::Value;
+
+    #[test]
+    fn should_answer_scripted_queries() {
+        let server = MockServer::new()
+            .version("8.0.30-mock")
+            .on_query("SELECT a, b FROM t",
+                      Response::rows(vec!["a", "b"],
+                                     vec![vec![Value::Int(-1), Value::from("foo")],
+                                          vec![Value::NULL, Value::Date(2020, 1, 2, 3, 4, 5, 0)]]))
+            .on_query("INSERT INTO t (a) VALUES (?)", Response::ok(1, 10))
+            .on_query("INSERT INTO t (a) VALUES (?)", Response::error(1062, "Duplicate entry"));
+        let mut conn = server.conn_with_opts(Opts {
+            user: Some("root".into()),
+            pass: Some("password".into()),
+            ..Opts::default()
+        }).unwrap();
+        assert_eq!(conn.id(), 1);
+        assert!(conn.ping());
+
+        let text: Vec<(Option<i64>, Value)> = conn.query("SELECT a, b FROM t;").unwrap()
+                                                  .map(|row| ::from_row(row.unwrap()))
+                                                  .collect();
+        let bin: Vec<(Option<i64>, Value)> = conn.exec("SELECT a, b FROM t", ()).unwrap();
+        assert_eq!(text, vec![(Some(-1), Value::from("foo")),
+                              (None, Value::from("2020-01-02 03:04:05"))]);
+        assert_eq!(bin, vec![(Some(-1), Value::from("foo")),
+                             (None, Value::Date(2020, 1, 2, 3, 4, 5, 0))]);
+
+        let result = conn.prep_exec("INSERT INTO t (a) VALUES (?)", (Value::NULL,)).unwrap();
+        assert_eq!(result.last_insert_id(), 10);
+        drop(result);
+        for _ in 0..2 {
+            match conn.prep_exec("INSERT INTO t (a) VALUES (?)", ("x",)) {
+                Err(MySqlError(ref err)) if err.code == 1062 => (),
+                result => panic!("Expected duplicate entry error, got {:?}", result.is_ok()),
+            }
+        }
+        match conn.query("SELECT c FROM t") {
+            Err(MySqlError(ref err)) if err.code == ER_UNKNOWN_ERROR => (),
+            _ => panic!("Expected error for unscripted query"),
+        }
+
+        assert_eq!(server.conn().unwrap().id(), 2);
+        assert_eq!(server.requests(),
+                   vec![Request::Query("SELECT a, b FROM t;".into()),
+                        Request::Prepare("SELECT a, b FROM t".into()),
+                        Request::Execute("SELECT a, b FROM t".into(), vec![]),
+                        Request::Prepare("INSERT INTO t (a) VALUES (?)".into()),
+                        Request::Execute("INSERT INTO t (a) VALUES (?)".into(), vec![Value::NULL]),
+                        Request::Execute("INSERT INTO t (a) VALUES (?)".into(),
+                                         vec![Value::from("x")]),
+                        Request::Execute("INSERT INTO t (a) VALUES (?)".into(),
+                                         vec![Value::from("x")]),
+                        Request::Query("SELECT c FROM t".into())]);
+    }
+
+    #[test]
+    fn should_reject_truncated_execute_packet() {
+        let server = MockServer::new().on_query("SELECT ?", Response::ok(0, 0));
+        let mut stream = server.stream();
+        stream.authenticated = true;
+        stream.prepare("SELECT ?".into()).unwrap();
+        // Statement id without flags and iteration count.
+        assert!(stream.execute(&[1, 0, 0, 0]).is_err());
+        // Flags and iteration count without null bitmap.
+        assert!(stream.execute(&[1, 0, 0, 0, 0, 1, 0, 0, 0]).is_err());
+    }
+}
@@ -1,21 +1,28 @@
+use std::cmp;
 use std::str::FromStr;
 use std::str::from_utf8;
 use std::borrow::ToOwned;
 use std::collections::HashMap;
+use std::error;
+use std::fmt;
 use std::hash::BuildHasherDefault as BldHshrDflt;
 use std::io;
 use std::io::Write as stdWrite;
-use std::time::Duration;
+use std::iter;
+use std::iter::FromIterator;
+use std::num::{NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroIsize};
+use std::num::{NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128, NonZeroUsize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use time::{
     Timespec,
     Tm,
     at,
     now,
     self,
-    strptime,
 };
 
 use super::consts;
+use super::escape::escape_string;
 use super::conn::{Column};
 use super::error::{
     Error,
@@ -121,6 +128,11 @@ pub enum Value {
 
 impl Value {
     /// Get correct string representation of a mysql value
+    ///
+    /// Kept for compatibility: backslash escaping differs from `escape_string`, and floats
+    /// and invalid UTF-8 are rendered in a way which is not always parsed back as the same
+    /// value. Use [`Value::as_sql`](#method.as_sql) instead.
+    #[deprecated(note = "use `Value::as_sql` instead")]
     pub fn into_str(&self) -> String {
         match *self {
             Value::NULL => "NULL".to_owned(),
@@ -167,9 +179,73 @@ impl Value {
         }
     }
 
+    /// Renders value as an SQL literal, e.g. for logging or for a debug representation of
+    /// a statement with its parameters.
+    ///
+    /// Strings are quoted and escaped as by [`escape_string`](fn.escape_string.html)
+    /// (`no_backslash_escapes` should match `NO_BACKSLASH_ESCAPES` SQL mode), bytes which are
+    /// not valid UTF-8 are rendered as a hex literal and floats use exponent notation so that
+    /// they are parsed as `DOUBLE`. MySql has no literals for NaN and infinities, so they
+    /// are rendered as `NULL`.
+    ///
+    /// ```
+    /// # use mysql::Value;
+    /// assert_eq!(Value::from("it's").as_sql(false), "'it\\'s'");
+    /// assert_eq!(Value::from("it's").as_sql(true), "'it''s'");
+    /// assert_eq!(Value::Bytes(vec![0xFF, 0x00]).as_sql(false), "X'FF00'");
+    /// assert_eq!(Value::Float(1.5).as_sql(false), "1.5e0");
+    /// assert_eq!(Value::Time(true, 1, 2, 3, 4, 0).as_sql(false), "'-26:03:04'");
+    /// ```
+    pub fn as_sql(&self, no_backslash_escapes: bool) -> String {
+        match *self {
+            Value::NULL => "NULL".into(),
+            Value::Bytes(ref x) => {
+                match from_utf8(&*x) {
+                    Ok(s) => format!("'{}'", escape_string(s, no_backslash_escapes)),
+                    Err(_) => {
+                        let mut s = String::with_capacity(x.len() * 2 + 3);
+                        s.push_str("X'");
+                        for c in x.iter() {
+                            s.push_str(&*format!("{:02X}", c));
+                        }
+                        s.push('\'');
+                        s
+                    },
+                }
+            },
+            Value::Int(x) => x.to_string(),
+            Value::UInt(x) => x.to_string(),
+            Value::Float(x) if x.is_finite() => format!("{:e}", x),
+            Value::Float(_) => "NULL".into(),
+            Value::Date(y, m, d, 0, 0, 0, 0) => format!("'{:04}-{:02}-{:02}'", y, m, d),
+            Value::Date(y, m, d, h, i, s, 0) => {
+                format!("'{:04}-{:02}-{:02} {:02}:{:02}:{:02}'", y, m, d, h, i, s)
+            },
+            Value::Date(y, m, d, h, i, s, u) => {
+                format!("'{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}'", y, m, d, h, i, s, u)
+            },
+            Value::Time(neg, d, h, i, s, u) => {
+                let sign = if neg { "-" } else { "" };
+                let hours = d * 24 + h as u32;
+                if u == 0 {
+                    format!("'{}{:02}:{:02}:{:02}'", sign, hours, i, s)
+                } else {
+                    format!("'{}{:02}:{:02}:{:02}.{:06}'", sign, hours, i, s, u)
+                }
+            },
+        }
+    }
+
     #[doc(hidden)]
     pub fn to_bin(&self) -> io::Result<Vec<u8>> {
         let mut writer = Vec::with_capacity(256);
+        try!(self.write_bin(&mut writer));
+        Ok(writer)
+    }
+
+    /// Writes binary protocol representation of a value (nothing for `NULL`).
+    #[doc(hidden)]
+    pub fn write_bin<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match *self {
             Value::NULL => (),
             Value::Bytes(ref x) => {
@@ -231,7 +307,7 @@ impl Value {
                 try!(writer.write_u32::<LE>(u));
             }
         };
-        Ok(writer)
+        Ok(())
     }
 
     #[doc(hidden)]
@@ -252,6 +328,108 @@ impl Value {
         Ok(output)
     }
 
+    /// Parses text protocol value of a column into a value of the type which binary protocol
+    /// returns for this column (e.g. `Value::Int` for `INT` column). Value which could not be
+    /// parsed is returned as is.
+    #[doc(hidden)]
+    pub fn coerce_text(self, column: &Column) -> Value {
+        match self {
+            Value::Bytes(bytes) => {
+                let coerced = coerce_text_bytes(&*bytes, column);
+                coerced.unwrap_or(Value::Bytes(bytes))
+            },
+            value => value,
+        }
+    }
+
+    /// Converts value of a statement parameter into a value of the type which server reported
+    /// for this parameter (e.g. text into `Value::Date` for `DATETIME` parameter). Value which
+    /// could not be represented by this type is returned as `Err`.
+    ///
+    /// Values of string, blob and unknown parameters are passed as is.
+    #[doc(hidden)]
+    pub fn coerce_param(self, param: &Column) -> Result<Value, Value> {
+        match param.column_type {
+            consts::ColumnType::MYSQL_TYPE_TINY |
+            consts::ColumnType::MYSQL_TYPE_SHORT |
+            consts::ColumnType::MYSQL_TYPE_YEAR |
+            consts::ColumnType::MYSQL_TYPE_LONG |
+            consts::ColumnType::MYSQL_TYPE_INT24 |
+            consts::ColumnType::MYSQL_TYPE_LONGLONG => {
+                let x = match self {
+                    Value::Int(x) => Some(x as i128),
+                    Value::UInt(x) => Some(x as i128),
+                    Value::Bytes(ref bytes) => {
+                        from_utf8(bytes).ok().and_then(|text| text.trim().parse::<i128>().ok())
+                    },
+                    // Server rounds fractional numbers itself.
+                    Value::NULL | Value::Float(_) => return Ok(self),
+                    _ => None,
+                };
+                let (min, max) = int_param_range(param);
+                match x {
+                    Some(x) if x >= min && x <= max => {
+                        if x > i64::max_value() as i128 {
+                            Ok(Value::UInt(x as u64))
+                        } else {
+                            Ok(Value::Int(x as i64))
+                        }
+                    },
+                    _ => Err(self),
+                }
+            },
+            consts::ColumnType::MYSQL_TYPE_FLOAT |
+            consts::ColumnType::MYSQL_TYPE_DOUBLE => match self {
+                Value::Bytes(bytes) => {
+                    match from_utf8(&*bytes).ok().and_then(|x| x.trim().parse().ok()) {
+                        Some(x) => Ok(Value::Float(x)),
+                        None => Err(Value::Bytes(bytes)),
+                    }
+                },
+                Value::Date(..) | Value::Time(..) => Err(self),
+                value => Ok(value),
+            },
+            consts::ColumnType::MYSQL_TYPE_DECIMAL |
+            consts::ColumnType::MYSQL_TYPE_NEWDECIMAL => match self {
+                Value::Bytes(bytes) => {
+                    let is_decimal = from_utf8(&*bytes).ok()
+                                                       .map(|x| x.trim().parse::<Decimal>().is_ok())
+                                                       .unwrap_or(false);
+                    if is_decimal {
+                        Ok(Value::Bytes(bytes))
+                    } else {
+                        Err(Value::Bytes(bytes))
+                    }
+                },
+                Value::Date(..) | Value::Time(..) => Err(self),
+                value => Ok(value),
+            },
+            consts::ColumnType::MYSQL_TYPE_TIMESTAMP |
+            consts::ColumnType::MYSQL_TYPE_DATE |
+            consts::ColumnType::MYSQL_TYPE_DATETIME => match self {
+                Value::Bytes(bytes) => match parse_param_datetime_string(&*bytes) {
+                    Some((y, m, d, h, i, s, u)) => {
+                        Ok(Value::Date(y as u16, m as u8, d as u8, h as u8, i as u8, s as u8, u))
+                    },
+                    None => Err(Value::Bytes(bytes)),
+                },
+                value @ Value::NULL | value @ Value::Date(..) => Ok(value),
+                value => Err(value),
+            },
+            consts::ColumnType::MYSQL_TYPE_TIME => match self {
+                Value::Bytes(bytes) => match parse_mysql_time_string(&*bytes) {
+                    Some((neg, h, i, s, u)) => {
+                        Ok(Value::Time(neg, h / 24, (h % 24) as u8, i as u8, s as u8, u))
+                    },
+                    None => Err(Value::Bytes(bytes)),
+                },
+                value @ Value::NULL | value @ Value::Time(..) => Ok(value),
+                value => Err(value),
+            },
+            _ => Ok(self),
+        }
+    }
+
     #[doc(hidden)]
     pub fn from_bin_payload(pld: &[u8], columns: &[Column]) -> io::Result<Vec<Value>> {
         let bit_offset = 2; // http://dev.mysql.com/doc/internals/en/null-bitmap.html
@@ -274,60 +452,312 @@ impl Value {
         Ok(values)
     }
 
-    // (NULL-bitmap, values, ids of fields to send throwgh send_long_data)
+    /// Writes NULL-bitmap, types and values of `COM_STMT_EXECUTE` parameters into `buf`.
+    ///
+    /// Returns ids of values to send through `COM_STMT_SEND_LONG_DATA`.
     #[doc(hidden)]
-    pub fn to_bin_payload(params: &[Column], values: &[Value], max_allowed_packet: usize) -> io::Result<(Vec<u8>, Vec<u8>, Option<Vec<u16>>)> {
+    pub fn write_bin_payload(params: &[Column],
+                             values: &[Value],
+                             max_allowed_packet: usize,
+                             buf: &mut Vec<u8>) -> io::Result<Option<Vec<u16>>> {
         let bitmap_len = (params.len() + 7) / 8;
+        let bitmap_offset = buf.len();
         let mut large_ids = Vec::new();
-        let mut writer = Vec::new();
-        let mut bitmap = vec![0u8; bitmap_len];
-        let mut i = 0u16;
-        let mut written = 0;
-        let cap = max_allowed_packet - bitmap_len - values.len() * 8;
-        for value in values.iter() {
+        let cap = max_allowed_packet.saturating_sub(bitmap_len + values.len() * 8);
+        buf.extend(iter::repeat(0u8).take(bitmap_len));
+        buf.push(1u8);
+        for (value, param) in values.iter().zip(params) {
+            let (column_type, flags) = match *value {
+                Value::NULL => (param.column_type, 0u8),
+                Value::Bytes(..) => (consts::ColumnType::MYSQL_TYPE_VAR_STRING, 0u8),
+                Value::Int(..) => (consts::ColumnType::MYSQL_TYPE_LONGLONG, 0u8),
+                Value::UInt(..) => (consts::ColumnType::MYSQL_TYPE_LONGLONG, 128u8),
+                Value::Float(..) => (consts::ColumnType::MYSQL_TYPE_DOUBLE, 0u8),
+                Value::Date(..) => (consts::ColumnType::MYSQL_TYPE_DATETIME, 0u8),
+                Value::Time(..) => (consts::ColumnType::MYSQL_TYPE_TIME, 0u8),
+            };
+            buf.push(column_type as u8);
+            buf.push(flags);
+        }
+        let values_offset = buf.len();
+        for (i, value) in values.iter().enumerate() {
             match *value {
-                Value::NULL => bitmap[i as usize / 8] |= 1 << ((i % 8u16) as usize),
-                _ => {
-                    let val = try!(value.to_bin());
-                    if val.len() < cap - written {
-                        written += val.len();
-                        try!(writer.write_all(&val[..]));
+                Value::NULL => buf[bitmap_offset + i / 8] |= 1 << (i % 8),
+                // Only strings and blobs could be sent via COM_STMT_SEND_LONG_DATA.
+                Value::Bytes(ref x) => {
+                    let written = buf.len() - values_offset;
+                    if lenenc_len(x.len()) + x.len() < cap.saturating_sub(written) {
+                        try!(buf.write_lenenc_bytes(&x[..]));
                     } else {
-                        large_ids.push(i);
+                        large_ids.push(i as u16);
                     }
-                }
+                },
+                _ => try!(value.write_bin(buf)),
             }
-            i += 1;
         }
         if large_ids.len() == 0 {
-            Ok((bitmap, writer, None))
+            Ok(None)
         } else {
-            Ok((bitmap, writer, Some(large_ids)))
+            Ok(Some(large_ids))
+        }
+    }
+}
+
+/// Length of length-encoded integer `x`.
+fn lenenc_len(x: usize) -> usize {
+    if x < 251 {
+        1
+    } else if x < 65_536 {
+        3
+    } else if x < 16_777_216 {
+        4
+    } else {
+        9
+    }
+}
+
+/// Borrowed counterpart of a [`Value`](enum.Value.html).
+///
+/// `ValueRef::Bytes` borrows its content from the network buffer of a row, so reading wide
+/// text result sets via [`QueryResult::by_ref_rows`](../conn/struct.QueryResult.html#method.by_ref_rows)
+/// won't allocate a `Vec` per cell.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum ValueRef<'a> {
+    NULL,
+    Bytes(&'a [u8]),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    /// year, month, day, hour, minutes, seconds, micro seconds
+    Date(u16, u8, u8, u8, u8, u8, u32),
+    /// is negative, days, hours, minutes, seconds, micro seconds
+    Time(bool, u32, u8, u8, u8, u32)
+}
+
+impl<'a> ValueRef<'a> {
+    /// Returns borrowed bytes if this value is `ValueRef::Bytes`.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            ValueRef::Bytes(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Returns borrowed `str` if this value is `ValueRef::Bytes` containing valid utf8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.as_bytes().and_then(|x| from_utf8(x).ok())
+    }
+
+    /// Converts this value into an owned [`Value`](enum.Value.html).
+    pub fn to_value(&self) -> Value {
+        match *self {
+            ValueRef::NULL => Value::NULL,
+            ValueRef::Bytes(x) => Value::Bytes(x.to_vec()),
+            ValueRef::Int(x) => Value::Int(x),
+            ValueRef::UInt(x) => Value::UInt(x),
+            ValueRef::Float(x) => Value::Float(x),
+            ValueRef::Date(y, m, d, h, i, s, u) => Value::Date(y, m, d, h, i, s, u),
+            ValueRef::Time(n, d, h, i, s, u) => Value::Time(n, d, h, i, s, u),
+        }
+    }
+
+    fn read_lenenc_bytes(reader: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+        let len = try!(reader.read_lenenc_int()) as usize;
+        if reader.len() < len {
+            return Err(io::Error::new(io::ErrorKind::Other,
+                                      "Unexpected EOF while reading length encoded string"));
+        }
+        let (head, tail) = reader.split_at(len);
+        *reader = tail;
+        Ok(head)
+    }
+
+    #[doc(hidden)]
+    pub fn from_payload(pld: &'a [u8], columns_count: usize) -> io::Result<Vec<ValueRef<'a>>> {
+        let mut output = Vec::with_capacity(columns_count);
+        let mut reader = pld;
+        while !reader.is_empty() {
+            if reader[0] == 0xfb {
+                reader = &reader[1..];
+                output.push(ValueRef::NULL);
+            } else {
+                output.push(ValueRef::Bytes(try!(ValueRef::read_lenenc_bytes(&mut reader))));
+            }
+        }
+        Ok(output)
+    }
+
+    /// See `Value::coerce_text`.
+    #[doc(hidden)]
+    pub fn coerce_text(self, column: &Column) -> ValueRef<'a> {
+        match self {
+            ValueRef::Bytes(bytes) => {
+                match coerce_text_bytes(bytes, column) {
+                    Some(Value::Int(x)) => ValueRef::Int(x),
+                    Some(Value::UInt(x)) => ValueRef::UInt(x),
+                    Some(Value::Float(x)) => ValueRef::Float(x),
+                    Some(Value::Date(y, m, d, h, i, s, u)) => ValueRef::Date(y, m, d, h, i, s, u),
+                    Some(Value::Time(neg, d, h, i, s, u)) => ValueRef::Time(neg, d, h, i, s, u),
+                    _ => ValueRef::Bytes(bytes),
+                }
+            },
+            value => value,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn from_bin_payload(pld: &'a [u8], columns: &[Column]) -> io::Result<Vec<ValueRef<'a>>> {
+        let bit_offset = 2; // http://dev.mysql.com/doc/internals/en/null-bitmap.html
+        let bitmap_len = (columns.len() + 7 + bit_offset) / 8;
+        if pld.len() < 1 + bitmap_len {
+            return Err(io::Error::new(io::ErrorKind::Other,
+                                      "Unexpected EOF while reading binary row"));
+        }
+        let bitmap = &pld[1..1 + bitmap_len];
+        let mut values = Vec::with_capacity(columns.len());
+        let mut reader = &pld[1 + bitmap_len..];
+        for (i, c) in columns.iter().enumerate() {
+            if bitmap[(i + bit_offset) / 8] & (1 << ((i + bit_offset) % 8)) != 0 {
+                values.push(ValueRef::NULL);
+                continue;
+            }
+            let value = match c.column_type {
+                consts::ColumnType::MYSQL_TYPE_STRING |
+                consts::ColumnType::MYSQL_TYPE_VAR_STRING |
+                consts::ColumnType::MYSQL_TYPE_BLOB |
+                consts::ColumnType::MYSQL_TYPE_TINY_BLOB |
+                consts::ColumnType::MYSQL_TYPE_MEDIUM_BLOB |
+                consts::ColumnType::MYSQL_TYPE_LONG_BLOB |
+                consts::ColumnType::MYSQL_TYPE_SET |
+                consts::ColumnType::MYSQL_TYPE_ENUM |
+                consts::ColumnType::MYSQL_TYPE_DECIMAL |
+                consts::ColumnType::MYSQL_TYPE_VARCHAR |
+                consts::ColumnType::MYSQL_TYPE_BIT |
+                consts::ColumnType::MYSQL_TYPE_NEWDECIMAL |
+                consts::ColumnType::MYSQL_TYPE_GEOMETRY => {
+                    ValueRef::Bytes(try!(ValueRef::read_lenenc_bytes(&mut reader)))
+                },
+                _ => {
+                    let unsigned = c.flags.contains(consts::UNSIGNED_FLAG);
+                    match try!(reader.read_bin_value(c.column_type, unsigned)) {
+                        Value::NULL => ValueRef::NULL,
+                        Value::Int(x) => ValueRef::Int(x),
+                        Value::UInt(x) => ValueRef::UInt(x),
+                        Value::Float(x) => ValueRef::Float(x),
+                        Value::Date(y, m, d, h, i, s, u) => ValueRef::Date(y, m, d, h, i, s, u),
+                        Value::Time(n, d, h, i, s, u) => ValueRef::Time(n, d, h, i, s, u),
+                        Value::Bytes(_) => unreachable!(),
+                    }
+                },
+            };
+            values.push(value);
         }
+        Ok(values)
+    }
+}
+
+impl<'a> From<ValueRef<'a>> for Value {
+    fn from(x: ValueRef<'a>) -> Value {
+        x.to_value()
     }
 }
 
 macro_rules! rollback {
     ($x:ident) => (match $x {
         Ok(x) => x.rollback(),
-        Err(Error::FromValueError(x)) => x,
-        _ => unreachable!(),
+        Err(err) => rejected_value(err),
     });
 }
 
+/// Returns value which was rejected by `ConvIr::new`.
+#[doc(hidden)]
+pub fn rejected_value(err: Error) -> Value {
+    match err {
+        Error::FromValueError(value) => value,
+        Error::Utf8Error(err) => Value::Bytes(err.into_bytes()),
+        _ => unreachable!(),
+    }
+}
+
 /// Will *panic* if could not convert `row` to `T`.
+///
+/// Panic message contains name, index and target type of a column which could not be converted.
 #[inline]
 pub fn from_row<T: FromRow>(row: Row) -> T {
     FromRow::from_row(row)
 }
 
 /// Will return `Err(row)` if could not convert `row` to `T`
+///
+/// Column which could not be converted is available via
+/// [`Row::conversion_error`](../struct.Row.html#method.conversion_error) of the returned row.
 #[inline]
 pub fn from_row_opt<T: FromRow>(row: Row) -> MyResult<T> {
     FromRow::from_row_opt(row)
 }
 
+/// Unwraps result of `FromRow::from_row_opt`. Panic message contains the conversion error.
+///
+/// Used by `#[derive(FromRow)]`.
+#[doc(hidden)]
+pub fn unwrap_row<T>(result: MyResult<T>, target: &str) -> T {
+    match result {
+        Ok(x) => x,
+        Err(err) => panic!("Could not convert row to {}: {}", target, err),
+    }
+}
+
+/// Takes value of a column named `name` and converts it into `T`'s intermediate
+/// representation. Value is placed back into the row if conversion fails.
+///
+/// Used by `#[derive(FromRow)]`.
+#[doc(hidden)]
+pub fn take_by_name<T: FromValue>(row: &mut Row, name: &str) -> Option<T::Intermediate> {
+    let index = match row.column_index(name) {
+        Some(index) => index,
+        None => return None,
+    };
+    match row.take(index) {
+        Some(value) => match T::get_intermediate(value) {
+            Ok(ir) => Some(ir),
+            Err(err) => {
+                row.place_rejected::<T>(index, err);
+                None
+            },
+        },
+        None => None,
+    }
+}
+
+/// Rolls back value taken by `take_by_name`.
+///
+/// Used by `#[derive(FromRow)]`.
+#[doc(hidden)]
+pub fn place_by_name<T, Ir: ConvIr<T>>(row: &mut Row, name: &str, ir: Ir) {
+    if let Some(index) = row.column_index(name) {
+        row.place(index, ir.rollback());
+    }
+}
+
 /// Trait to convert `Row` into tuple of `FromValue` implementors up to arity 12.
+///
+/// With `derive` feature it could be derived for structs with named fields. Values will be
+/// taken from columns with the same names as fields:
+///
+/// ```ignore
+/// #[macro_use]
+/// extern crate mysql;
+///
+/// #[derive(FromRow)]
+/// struct User {
+///     id: u64,
+///     name: String,
+/// }
+///
+/// let users: Vec<User> = pool.prep_exec("SELECT id, name FROM users", ())
+///     .and_then(|mut result| result.collect_rows())
+///     .unwrap();
+/// ```
 pub trait FromRow {
     fn from_row(row: Row) -> Self;
     fn from_row_opt(row: Row) -> MyResult<Self> where Self: Sized;
@@ -339,11 +769,10 @@ macro_rules! take_or_place {
             Some(value) => {
                 match $t::get_intermediate(value) {
                     Ok(ir) => ir,
-                    Err(Error::FromValueError(value)) => {
-                        $row.place($index, value);
+                    Err(err) => {
+                        $row.place_rejected::<$t>($index, err);
                         return Err(Error::FromRowError($row));
                     },
-                    _ => unreachable!(),
                 }
             },
             None => return Err(Error::FromRowError($row)),
@@ -354,12 +783,11 @@ macro_rules! take_or_place {
             Some(value) => {
                 match $t::get_intermediate(value) {
                     Ok(ir) => ir,
-                    Err(Error::FromValueError(value)) => {
+                    Err(err) => {
                         $($row.place($idx, $ir.rollback());)*
-                        $row.place($index, value);
+                        $row.place_rejected::<$t>($index, err);
                         return Err(Error::FromRowError($row));
                     },
-                    _ => unreachable!(),
                 }
             },
             None => return Err(Error::FromRowError($row)),
@@ -372,7 +800,7 @@ where Ir: ConvIr<T>,
       T: FromValue<Intermediate=Ir> {
     #[inline]
     fn from_row(row: Row) -> T {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to T")
+        unwrap_row(FromRow::from_row_opt(row), "T")
     }
     fn from_row_opt(mut row: Row) -> MyResult<T> {
         if row.len() == 1 {
@@ -388,7 +816,7 @@ where Ir1: ConvIr<T1>,
       T1: FromValue<Intermediate=Ir1> {
     #[inline]
     fn from_row(row: Row) -> (T1,) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1,)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1,)")
     }
     fn from_row_opt(row: Row) -> MyResult<(T1,)> {
         T1::from_row_opt(row).map(|t| (t,))
@@ -401,7 +829,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir2: ConvIr<T2>, T2: FromValue<Intermediate=Ir2> {
     #[inline]
     fn from_row(row: Row) -> (T1, T2) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1,T2)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1,T2)")
     }
     fn from_row_opt(mut row: Row) -> MyResult<(T1, T2)> {
         if row.len() != 2 {
@@ -421,7 +849,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir3: ConvIr<T3>, T3: FromValue<Intermediate=Ir3>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1,T2,T3)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1,T2,T3)")
     }
     fn from_row_opt(mut row: Row) -> MyResult<(T1, T2, T3)> {
         if row.len() != 3 {
@@ -448,7 +876,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir4: ConvIr<T4>, T4: FromValue<Intermediate=Ir4>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T4)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T4)")
     }
     fn from_row_opt(mut row: Row) -> MyResult<(T1, T2, T3, T4)> {
         if row.len() != 4 {
@@ -479,7 +907,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir5: ConvIr<T5>, T5: FromValue<Intermediate=Ir5>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4, T5) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T5)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T5)")
     }
     fn from_row_opt(mut row: Row) -> MyResult<(T1, T2, T3, T4, T5)> {
         if row.len() != 5 {
@@ -514,7 +942,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir6: ConvIr<T6>, T6: FromValue<Intermediate=Ir6>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4, T5, T6) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T6)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T6)")
     }
     fn from_row_opt(mut row: Row) ->
         MyResult<(T1, T2, T3, T4, T5, T6)>
@@ -555,7 +983,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir7: ConvIr<T7>, T7: FromValue<Intermediate=Ir7>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4, T5, T6, T7) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T7)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T7)")
     }
     fn from_row_opt(mut row: Row) ->
         MyResult<(T1, T2, T3, T4, T5, T6, T7)>
@@ -601,7 +1029,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir8: ConvIr<T8>, T8: FromValue<Intermediate=Ir8>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4, T5, T6, T7, T8) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T8)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T8)")
     }
     fn from_row_opt(mut row: Row) ->
         MyResult<(T1, T2, T3, T4, T5, T6, T7, T8)>
@@ -656,7 +1084,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir9: ConvIr<T9>, T9: FromValue<Intermediate=Ir9>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4, T5, T6, T7, T8, T9) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T9)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T9)")
     }
     fn from_row_opt(mut row: Row) ->
         MyResult<(T1, T2, T3, T4, T5, T6, T7, T8, T9)>
@@ -719,7 +1147,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir10: ConvIr<T10>, T10: FromValue<Intermediate=Ir10>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4, T5, T6, T7, T8, T9, T10) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T10)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T10)")
     }
     fn from_row_opt(mut row: Row) ->
         MyResult<(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10)>
@@ -790,7 +1218,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir11: ConvIr<T11>, T11: FromValue<Intermediate=Ir11>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T11)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T11)")
     }
     fn from_row_opt(mut row: Row) ->
         MyResult<(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11)>
@@ -869,7 +1297,7 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
       Ir12: ConvIr<T12>, T12: FromValue<Intermediate=Ir12>, {
     #[inline]
     fn from_row(row: Row) -> (T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12) {
-        FromRow::from_row_opt(row).ok().expect("Could not convert row to (T1 .. T12)")
+        unwrap_row(FromRow::from_row_opt(row), "(T1 .. T12)")
     }
     fn from_row_opt(mut row: Row) ->
         MyResult<(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12)>
@@ -928,6 +1356,25 @@ where Ir1: ConvIr<T1>, T1: FromValue<Intermediate=Ir1>,
     }
 }
 
+/// Parameters of a prepared statement.
+///
+/// `Named` parameters are only allowed for statements with `:name` placeholders, e.g.
+/// `SELECT * FROM users WHERE id = :id`. Name could be used more than once, e.g.
+/// `WHERE a = :x OR b = :x`, and a single value will be bound to every occurrence.
+/// With `derive` feature `#[derive(IntoParams)]` converts a struct with named fields into
+/// `Params::Named`.
+///
+/// `Positional` parameters could be converted from tuples, arrays, `Vec`s and slices of
+/// values or collected from an iterator, which is handy if number of placeholders is not
+/// known at compile time:
+///
+/// ```ignore
+/// let placeholders = vec!["?"; ids.len()].join(", ");
+/// let query = format!("SELECT * FROM users WHERE id IN ({})", placeholders);
+/// let params: Params = ids.iter().cloned().collect();
+/// let users: Vec<(u64, String)> = try!(conn.exec(query, params));
+/// ```
+#[derive(Clone, PartialEq, Debug)]
 pub enum Params {
     Empty,
     Named(HashMap<String, Value, BldHshrDflt<FnvHasher>>),
@@ -954,6 +1401,23 @@ impl<T: Into<Value>> From<Vec<T>> for Params {
     }
 }
 
+impl<'a> From<&'a [Value]> for Params {
+    fn from(x: &'a [Value]) -> Params {
+        x.iter().cloned().collect()
+    }
+}
+
+impl<T: Into<Value>> FromIterator<T> for Params {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Params {
+        let raw_params: Vec<Value> = iter.into_iter().map(Into::into).collect();
+        if raw_params.len() == 0 {
+            Params::Empty
+        } else {
+            Params::Positional(raw_params)
+        }
+    }
+}
+
 impl<'a> From<&'a [&'a ToValue]> for Params {
     fn from(x: &'a [&'a ToValue]) -> Params {
         let mut raw_params = Vec::with_capacity(x.len());
@@ -998,6 +1462,24 @@ into_params_impl!([A,a],[B,b],[C,c],[D,d],[E,e],[F,f],[G,g],[H,h],[I,i],[J,j]);
 into_params_impl!([A,a],[B,b],[C,c],[D,d],[E,e],[F,f],[G,g],[H,h],[I,i],[J,j],[K,k]);
 into_params_impl!([A,a],[B,b],[C,c],[D,d],[E,e],[F,f],[G,g],[H,h],[I,i],[J,j],[K,k],[L,l]);
 
+macro_rules! into_params_array_impl {
+    ($($n:expr)*) => ($(
+        impl<T: Into<Value> + Clone> From<[T; $n]> for Params {
+            fn from(x: [T; $n]) -> Params {
+                x.iter().cloned().collect()
+            }
+        }
+    )*);
+}
+
+into_params_array_impl!(1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+                        17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32);
+
+/// Conversion of a borrowed value into `Value`, e.g. to pass parameters as `&[&ToValue]`.
+///
+/// Implemented for every `T: Into<Value> + Clone`, so it is enough to implement
+/// `From<T> for Value` (or to use `#[derive(IntoValue)]` on a newtype with `derive` feature)
+/// to pass `T` or `Option<T>` (`None` is `NULL`) as a parameter.
 pub trait ToValue {
     fn to_value(&self) -> Value;
 }
@@ -1065,6 +1547,46 @@ impl From<usize> for Value {
     }
 }
 
+/// Values out of `i64` and `u64` ranges are sent as decimal strings, so they could be
+/// stored into `DECIMAL(39)` columns.
+impl From<i128> for Value {
+    fn from(x: i128) -> Value {
+        if x >= ::std::i64::MIN as i128 && x <= ::std::i64::MAX as i128 {
+            Value::Int(x as i64)
+        } else if x >= 0 && x <= ::std::u64::MAX as i128 {
+            Value::UInt(x as u64)
+        } else {
+            Value::Bytes(x.to_string().into_bytes())
+        }
+    }
+}
+
+/// Values out of `u64` range are sent as decimal strings, so they could be stored into
+/// `DECIMAL(39)` columns.
+impl From<u128> for Value {
+    fn from(x: u128) -> Value {
+        if x <= ::std::u64::MAX as u128 {
+            Value::UInt(x as u64)
+        } else {
+            Value::Bytes(x.to_string().into_bytes())
+        }
+    }
+}
+
+macro_rules! into_value_non_zero_impl {
+    ($($t:ty),+) => ($(
+        impl From<$t> for Value {
+            fn from(x: $t) -> Value {
+                x.get().into()
+            }
+        }
+    )+);
+}
+
+into_value_non_zero_impl!(NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128,
+                          NonZeroIsize, NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64,
+                          NonZeroU128, NonZeroUsize);
+
 impl From<f32> for Value {
     fn from(x: f32) -> Value {
         Value::Float(x as f64)
@@ -1120,7 +1642,8 @@ impl From<NaiveDateTime> for Value {
             x.hour() as u8,
             x.minute() as u8,
             x.second() as u8,
-            x.nanosecond() / 1000,
+            // Leap second is represented by nanoseconds overflow.
+            cmp::min(x.nanosecond() / 1000, 999_999),
         )
     }
 }
@@ -1150,7 +1673,8 @@ impl From<NaiveTime> for Value {
             x.hour() as u8,
             x.minute() as u8,
             x.second() as u8,
-            x.nanosecond() / 1000,
+            // Leap second is represented by nanoseconds overflow.
+            cmp::min(x.nanosecond() / 1000, 999_999),
         )
     }
 }
@@ -1172,7 +1696,7 @@ impl From<Timespec> for Value {
 impl From<Duration> for Value {
     fn from(x: Duration) -> Value {
         let mut secs_total = x.as_secs();
-        let micros = (x.subsec_nanos() as f64 / 1000_f64).round() as u32;
+        let micros = x.subsec_nanos() / 1000;
         let seconds = (secs_total % 60) as u8;
         secs_total -= seconds as u64;
         let minutes = ((secs_total % (60 * 60)) / 60) as u8;
@@ -1183,6 +1707,28 @@ impl From<Duration> for Value {
     }
 }
 
+/// `SystemTime` is converted to a UTC date and time (unlike `Timespec`, which is converted to
+/// a local one), so the stored value does not depend on the client's time zone. `SystemTime`
+/// is also read back from a date and time as UTC.
+///
+/// Note that server converts `TIMESTAMP` values from and to the session `time_zone`, so it
+/// should be `'+00:00'` for `TIMESTAMP` columns.
+impl From<SystemTime> for Value {
+    fn from(x: SystemTime) -> Value {
+        let (secs, nanos) = match x.duration_since(UNIX_EPOCH) {
+            Ok(after) => (after.as_secs() as i64, after.subsec_nanos()),
+            Err(err) => {
+                let before = err.duration();
+                match before.subsec_nanos() {
+                    0 => (-(before.as_secs() as i64), 0),
+                    n => (-(before.as_secs() as i64) - 1, 1_000_000_000 - n),
+                }
+            },
+        };
+        NaiveDateTime::from_timestamp(secs, nanos).into()
+    }
+}
+
 impl From<time::Duration> for Value {
     fn from(mut x: time::Duration) -> Value {
         let negative = x < time::Duration::zero();
@@ -1216,6 +1762,11 @@ pub trait ConvIr<T>: Sized {
 /// `FromRow` requires ability to cheaply rollback `FromValue` conversion. This ability is
 /// provided via `Intermediate` associated type.
 ///
+/// `Value::NULL` is only convertible to `Value` and `Option<T>`, which is implemented for
+/// every `T: FromValue`. Other conversions of `NULL` return `Err(Error::FromValueError(NULL))`
+/// via `from_value_opt` (or `Error::FromRowError` via `from_row_opt`), so use `Option<T>`
+/// for nullable columns.
+///
 /// Example implementation:
 ///
 /// ```ignore
@@ -1254,13 +1805,15 @@ pub trait FromValue: Sized {
         Self::from_value_opt(v).ok().expect("Could not retrieve Self from Value")
     }
 
-    /// Will return `Err(Error::FromValueError(v))` if could not convert `v` to `Self`.
+    /// Will return `Err(Error::FromValueError(v))` if could not convert `v` to `Self`
+    /// (`Err(Error::Utf8Error(..))` if text is not valid UTF-8).
     fn from_value_opt(v: Value) -> MyResult<Self> {
         let ir = try!(Self::Intermediate::new(v));
         Ok(ir.commit())
     }
 
-    /// Will return `Err(Error::FromValueError(v))` if `v` is not convertible to `Self`.
+    /// Will return `Err(Error::FromValueError(v))` if `v` is not convertible to `Self`
+    /// (`Err(Error::Utf8Error(..))` if text is not valid UTF-8).
     fn get_intermediate(v: Value) -> MyResult<Self::Intermediate> {
         Self::Intermediate::new(v)
     }
@@ -1288,6 +1841,51 @@ pub struct BytesIr {
     bytes: Vec<u8>,
 }
 
+/// Policy of conversion of `Value::Bytes` which is not valid UTF-8 to `String`.
+///
+/// Text of columns with non UTF-8 character set (e.g. latin1) or binary data could not be
+/// converted to `String` as is, so `from_value_opt::<String>` (as well as `Option<String>`,
+/// `from_row_opt` and `Row::get_opt`) returns `Error::Utf8Error` which carries offending
+/// bytes. Policy is passed explicitly to [`Utf8Policy::decode`](#method.decode):
+///
+/// ```ignore
+/// let name = try!(Utf8Policy::Lossy.decode(row.take("name").unwrap()));
+/// ```
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Utf8Policy {
+    /// Conversion fails with `Error::Utf8Error` (default).
+    Strict,
+    /// Invalid sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    Lossy,
+    /// Every byte is converted to a `char` with the same code point (i.e. bytes are decoded as
+    /// latin1), so original bytes could be restored via `s.chars().map(|c| c as u8)`.
+    Passthrough,
+}
+
+impl Utf8Policy {
+    /// Converts `value` to `String` according to this policy.
+    ///
+    /// Valid UTF-8 is converted as is. Values which are not `Value::Bytes` are converted the
+    /// same way as by `from_value_opt::<String>`.
+    pub fn decode(self, value: Value) -> MyResult<String> {
+        match (from_value_opt::<String>(value), self) {
+            (Err(Error::Utf8Error(err)), Utf8Policy::Lossy) => {
+                Ok(String::from_utf8_lossy(err.as_bytes()).into_owned())
+            },
+            (Err(Error::Utf8Error(err)), Utf8Policy::Passthrough) => {
+                Ok(err.as_bytes().iter().map(|&byte| byte as char).collect())
+            },
+            (result, _) => result,
+        }
+    }
+}
+
+impl Default for Utf8Policy {
+    fn default() -> Utf8Policy {
+        Utf8Policy::Strict
+    }
+}
+
 #[derive(Debug)]
 pub struct StringIr {
     bytes: Vec<u8>,
@@ -1365,10 +1963,7 @@ where T: FromValue<Intermediate=Ir>,
                         value: None,
                         ir: Some(ir),
                     }),
-                    Err(Error::FromValueError(v)) => {
-                        Err(Error::FromValueError(v))
-                    },
-                    _ => unreachable!(),
+                    Err(err) => Err(err),
                 }
             }
         }
@@ -1391,6 +1986,7 @@ where T: FromValue<Intermediate=Ir>,
     }
 }
 
+/// `NULL` is converted to `None`, any other value is converted to `Some(T)`.
 impl<T> FromValue for Option<T>
 where T: FromValue {
     type Intermediate = OptionIr<T::Intermediate>;
@@ -1430,9 +2026,9 @@ impl FromValue for Value {
 impl ConvIr<String> for StringIr {
     fn new(v: Value) -> MyResult<StringIr> {
         match v {
-            Value::Bytes(bytes) => match from_utf8(&*bytes) {
-                Ok(_) => Ok(StringIr { bytes: bytes }),
-                Err(_) => Err(Error::FromValueError(Value::Bytes(bytes))),
+            Value::Bytes(bytes) => match String::from_utf8(bytes) {
+                Ok(string) => Ok(StringIr { bytes: string.into_bytes() }),
+                Err(err) => Err(Error::Utf8Error(err)),
             },
             v => Err(Error::FromValueError(v)),
         }
@@ -1509,17 +2105,20 @@ impl ConvIr<u64> for ParseIr<u64> {
     }
 }
 
-impl ConvIr<f32> for ParseIr<f32> {
-    fn new(v: Value) -> MyResult<ParseIr<f32>> {
+/// `DECIMAL` values are parsed from text, so `DECIMAL(38, 0)` columns fit into `i128`.
+impl ConvIr<i128> for ParseIr<i128> {
+    fn new(v: Value) -> MyResult<ParseIr<i128>> {
         match v {
-            Value::Float(x) if x >= ::std::f32::MIN as f64 && x <= ::std::f32::MAX as f64 => {
-                Ok(ParseIr {
-                    value: Value::Float(x),
-                    output: x as f32,
-                })
-            },
+            Value::Int(x) => Ok(ParseIr {
+                value: Value::Int(x),
+                output: x as i128,
+            }),
+            Value::UInt(x) => Ok(ParseIr {
+                value: Value::UInt(x),
+                output: x as i128,
+            }),
             Value::Bytes(bytes) => {
-                let val = from_utf8(&*bytes).ok().and_then(|x| f32::from_str(x).ok());
+                let val = from_utf8(&*bytes).ok().and_then(|x| i128::from_str(x).ok());
                 match val {
                     Some(x) => Ok(ParseIr {
                         value: Value::Bytes(bytes),
@@ -1531,7 +2130,7 @@ impl ConvIr<f32> for ParseIr<f32> {
             v => Err(Error::FromValueError(v)),
         }
     }
-    fn commit(self) -> f32 {
+    fn commit(self) -> i128 {
         self.output
     }
     fn rollback(self) -> Value {
@@ -1539,27 +2138,31 @@ impl ConvIr<f32> for ParseIr<f32> {
     }
 }
 
-impl ConvIr<f64> for ParseIr<f64> {
-    fn new(v: Value) -> MyResult<ParseIr<f64>> {
+impl ConvIr<u128> for ParseIr<u128> {
+    fn new(v: Value) -> MyResult<ParseIr<u128>> {
         match v {
-            Value::Float(x) => Ok(ParseIr {
-                value: Value::Float(x),
-                output: x,
+            Value::Int(x) if x >= 0 => Ok(ParseIr {
+                value: Value::Int(x),
+                output: x as u128,
+            }),
+            Value::UInt(x) => Ok(ParseIr {
+                value: Value::UInt(x),
+                output: x as u128,
             }),
             Value::Bytes(bytes) => {
-                let val = from_utf8(&*bytes).ok().and_then(|x| f64::from_str(x).ok());
+                let val = from_utf8(&*bytes).ok().and_then(|x| u128::from_str(x).ok());
                 match val {
                     Some(x) => Ok(ParseIr {
                         value: Value::Bytes(bytes),
                         output: x,
                     }),
-                    _ => Err(Error::FromValueError(Value::Bytes(bytes))),
+                    None => Err(Error::FromValueError(Value::Bytes(bytes))),
                 }
             },
             v => Err(Error::FromValueError(v)),
         }
     }
-    fn commit(self) -> f64 {
+    fn commit(self) -> u128 {
         self.output
     }
     fn rollback(self) -> Value {
@@ -1567,21 +2170,117 @@ impl ConvIr<f64> for ParseIr<f64> {
     }
 }
 
-impl ConvIr<bool> for ParseIr<bool> {
-    fn new(v: Value) -> MyResult<ParseIr<bool>> {
-        match v {
-            Value::Int(0) => Ok(ParseIr {
-                value: Value::Int(0),
-                output: false,
-            }),
-            Value::Int(1) => Ok(ParseIr {
-                value: Value::Int(1),
-                output: true,
-            }),
-            Value::Bytes(bytes) => {
-                if bytes.len() == 1 {
-                    match bytes[0] {
-                        0x30 => Ok(ParseIr {
+/// Conversion to `NonZero*` types is the one of the underlying integer type, which fails
+/// if a value is zero.
+macro_rules! impl_from_value_non_zero {
+    ($t:ty, $inner:ty, $msg:expr) => (
+        impl ConvIr<$t> for ParseIr<$t> {
+            fn new(v: Value) -> MyResult<ParseIr<$t>> {
+                let ir = try!(<ParseIr<$inner> as ConvIr<$inner>>::new(v));
+                match <$t>::new(ir.output) {
+                    Some(output) => Ok(ParseIr {
+                        value: ir.value,
+                        output: output,
+                    }),
+                    None => Err(Error::FromValueError(ir.value)),
+                }
+            }
+            fn commit(self) -> $t {
+                self.output
+            }
+            fn rollback(self) -> Value {
+                self.value
+            }
+        }
+
+        impl_from_value!($t, ParseIr<$t>, $msg);
+    );
+}
+
+impl ConvIr<f32> for ParseIr<f32> {
+    fn new(v: Value) -> MyResult<ParseIr<f32>> {
+        match v {
+            Value::Float(x) if x >= ::std::f32::MIN as f64 && x <= ::std::f32::MAX as f64 => {
+                Ok(ParseIr {
+                    value: Value::Float(x),
+                    output: x as f32,
+                })
+            },
+            Value::Bytes(bytes) => {
+                let val = from_utf8(&*bytes).ok().and_then(|x| f32::from_str(x).ok());
+                match val {
+                    Some(x) => Ok(ParseIr {
+                        value: Value::Bytes(bytes),
+                        output: x,
+                    }),
+                    None => Err(Error::FromValueError(Value::Bytes(bytes))),
+                }
+            },
+            v => Err(Error::FromValueError(v)),
+        }
+    }
+    fn commit(self) -> f32 {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl ConvIr<f64> for ParseIr<f64> {
+    fn new(v: Value) -> MyResult<ParseIr<f64>> {
+        match v {
+            Value::Float(x) => Ok(ParseIr {
+                value: Value::Float(x),
+                output: x,
+            }),
+            Value::Bytes(bytes) => {
+                let val = from_utf8(&*bytes).ok().and_then(|x| f64::from_str(x).ok());
+                match val {
+                    Some(x) => Ok(ParseIr {
+                        value: Value::Bytes(bytes),
+                        output: x,
+                    }),
+                    _ => Err(Error::FromValueError(Value::Bytes(bytes))),
+                }
+            },
+            v => Err(Error::FromValueError(v)),
+        }
+    }
+    fn commit(self) -> f64 {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+/// `bool` is converted from `0` and `1` integers (e.g. `TINYINT(1)` or `BOOLEAN` columns
+/// of binary protocol), from `"0"` and `"1"` strings (text protocol) and from `BIT(1)` values
+/// (`b"\x00"` and `b"\x01"`). Other values, e.g. `2`, are not converted.
+impl ConvIr<bool> for ParseIr<bool> {
+    fn new(v: Value) -> MyResult<ParseIr<bool>> {
+        match v {
+            Value::Int(0) => Ok(ParseIr {
+                value: Value::Int(0),
+                output: false,
+            }),
+            Value::Int(1) => Ok(ParseIr {
+                value: Value::Int(1),
+                output: true,
+            }),
+            Value::UInt(0) => Ok(ParseIr {
+                value: Value::UInt(0),
+                output: false,
+            }),
+            Value::UInt(1) => Ok(ParseIr {
+                value: Value::UInt(1),
+                output: true,
+            }),
+            Value::Bytes(bytes) => {
+                if bytes.len() == 1 {
+                    match bytes[0] {
+                        0x30 => Ok(ParseIr {
                             value: Value::Bytes(bytes),
                             output: false,
                         }),
@@ -1589,6 +2288,15 @@ impl ConvIr<bool> for ParseIr<bool> {
                             value: Value::Bytes(bytes),
                             output: true,
                         }),
+                        // `BIT(1)` value
+                        0x00 => Ok(ParseIr {
+                            value: Value::Bytes(bytes),
+                            output: false,
+                        }),
+                        0x01 => Ok(ParseIr {
+                            value: Value::Bytes(bytes),
+                            output: true,
+                        }),
                         _ => Err(Error::FromValueError(Value::Bytes(bytes))),
                     }
                 } else {
@@ -1623,37 +2331,303 @@ impl ConvIr<Vec<u8>> for BytesIr {
     }
 }
 
+/// Value of a `BIT(M)` column.
+///
+/// Server sends `BIT` values as big-endian byte strings, so plain `u64` conversion
+/// will not work for them.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub struct Bit(pub u64);
+
+impl ConvIr<Bit> for ParseIr<Bit> {
+    fn new(v: Value) -> MyResult<ParseIr<Bit>> {
+        match v {
+            Value::Int(x) if x >= 0 => Ok(ParseIr {
+                value: Value::Int(x),
+                output: Bit(x as u64),
+            }),
+            Value::UInt(x) => Ok(ParseIr {
+                value: Value::UInt(x),
+                output: Bit(x),
+            }),
+            Value::Bytes(bytes) => {
+                if bytes.len() <= 8 {
+                    let x = bytes.iter().fold(0u64, |acc, &byte| (acc << 8) | byte as u64);
+                    Ok(ParseIr {
+                        value: Value::Bytes(bytes),
+                        output: Bit(x),
+                    })
+                } else {
+                    Err(Error::FromValueError(Value::Bytes(bytes)))
+                }
+            },
+            v => Err(Error::FromValueError(v)),
+        }
+    }
+    fn commit(self) -> Bit {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl From<Bit> for Value {
+    fn from(x: Bit) -> Value {
+        Value::UInt(x.0)
+    }
+}
+
+/// Value of a `SET(...)` column, i.e. list of its members.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Set(pub Vec<String>);
+
+impl ConvIr<Set> for ParseIr<Set> {
+    fn new(v: Value) -> MyResult<ParseIr<Set>> {
+        let output = match v {
+            Value::Bytes(ref bytes) => match from_utf8(&*bytes) {
+                Ok("") => Some(Set(vec![])),
+                Ok(string) => Some(Set(string.split(',').map(|x| x.to_string()).collect())),
+                Err(_) => None,
+            },
+            _ => None,
+        };
+        match output {
+            Some(output) => Ok(ParseIr {
+                value: v,
+                output: output,
+            }),
+            None => Err(Error::FromValueError(v)),
+        }
+    }
+    fn commit(self) -> Set {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl From<Set> for Value {
+    fn from(x: Set) -> Value {
+        Value::Bytes(x.0.join(",").into_bytes())
+    }
+}
+
+/// Value of a `DECIMAL` column.
+///
+/// Server sends decimals as text (e.g. `-123.4500`), which is kept as is, so no precision is
+/// lost on the way to and from the server. Use `rust_decimal` or `bigdecimal` features to
+/// convert to and from types of those crates directly.
+///
+/// ```ignore
+/// let price: Decimal = try!(conn.exec_first("SELECT price FROM goods WHERE id = ?", (1,)))
+///     .unwrap();
+/// assert_eq!(price.as_str(), "19.99");
+/// assert_eq!(price.scale(), 2);
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Decimal(String);
+
+impl Decimal {
+    /// Returns textual representation of a decimal.
+    pub fn as_str(&self) -> &str {
+        &*self.0
+    }
+
+    /// Returns number of digits after decimal point.
+    pub fn scale(&self) -> usize {
+        self.0.find('.').map(|pos| self.0.len() - pos - 1).unwrap_or(0)
+    }
+
+    /// Returns `true` if decimal has minus sign.
+    pub fn is_negative(&self) -> bool {
+        self.0.starts_with('-')
+    }
+
+    /// Converts decimal to `f64` (precision may be lost).
+    pub fn to_f64(&self) -> f64 {
+        self.0.parse().unwrap_or(0.0)
+    }
+}
+
+/// Error returned by `Decimal::from_str` for a string which is not a decimal number.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct ParseDecimalError;
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid decimal literal")
+    }
+}
+
+impl error::Error for ParseDecimalError {
+    fn description(&self) -> &str {
+        "Invalid decimal literal"
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    /// Parses `[+-]digits[.digits]`.
+    fn from_str(s: &str) -> Result<Decimal, ParseDecimalError> {
+        let unsigned = if s.starts_with('-') || s.starts_with('+') { &s[1..] } else { s };
+        let mut parts = unsigned.splitn(2, '.');
+        let int_part = parts.next().unwrap_or("");
+        let frac_part = parts.next().unwrap_or("");
+        let is_digits = |x: &str| x.bytes().all(|b| b >= b'0' && b <= b'9');
+        if (int_part.is_empty() && frac_part.is_empty()) ||
+           !is_digits(int_part) || !is_digits(frac_part) {
+            return Err(ParseDecimalError);
+        }
+        if s.starts_with('+') {
+            Ok(Decimal(unsigned.to_string()))
+        } else {
+            Ok(Decimal(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&*self.0)
+    }
+}
+
+impl ConvIr<Decimal> for ParseIr<Decimal> {
+    fn new(v: Value) -> MyResult<ParseIr<Decimal>> {
+        let output = match v {
+            Value::Int(x) => Some(Decimal(x.to_string())),
+            Value::UInt(x) => Some(Decimal(x.to_string())),
+            Value::Bytes(ref bytes) => from_utf8(&*bytes).ok().and_then(|x| x.parse().ok()),
+            _ => None,
+        };
+        match output {
+            Some(output) => Ok(ParseIr {
+                value: v,
+                output: output,
+            }),
+            None => Err(Error::FromValueError(v)),
+        }
+    }
+    fn commit(self) -> Decimal {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(x: Decimal) -> Value {
+        Value::Bytes(x.0.into_bytes())
+    }
+}
+
+/// Implements conversions for a third-party decimal type which implements `FromStr`,
+/// `ToString` and `From<i64>`/`From<u64>`.
+macro_rules! impl_decimal_conversions {
+    ($t:ty, $msg:expr) => (
+        impl ConvIr<$t> for ParseIr<$t> {
+            fn new(v: Value) -> MyResult<ParseIr<$t>> {
+                let output = match v {
+                    Value::Int(x) => Some(<$t>::from(x)),
+                    Value::UInt(x) => Some(<$t>::from(x)),
+                    Value::Bytes(ref bytes) => {
+                        from_utf8(&*bytes).ok().and_then(|x| x.parse::<$t>().ok())
+                    },
+                    _ => None,
+                };
+                match output {
+                    Some(output) => Ok(ParseIr {
+                        value: v,
+                        output: output,
+                    }),
+                    None => Err(Error::FromValueError(v)),
+                }
+            }
+            fn commit(self) -> $t {
+                self.output
+            }
+            fn rollback(self) -> Value {
+                self.value
+            }
+        }
+
+        impl From<$t> for Value {
+            fn from(x: $t) -> Value {
+                Value::Bytes(x.to_string().into_bytes())
+            }
+        }
+
+        impl_from_value!($t, ParseIr<$t>, $msg);
+    );
+}
+
+#[cfg(feature = "rust_decimal")]
+impl_decimal_conversions!(::rust_decimal::Decimal,
+                          "Could not retrieve rust_decimal::Decimal from Value");
+
+#[cfg(feature = "bigdecimal")]
+impl_decimal_conversions!(::bigdecimal::BigDecimal,
+                          "Could not retrieve bigdecimal::BigDecimal from Value");
+
+/// Accepts 16-byte binary (`BINARY(16)` column) and textual (`CHAR(36)` or `CHAR(32)`
+/// column) representations.
+#[cfg(feature = "uuid")]
+impl ConvIr<::uuid::Uuid> for ParseIr<::uuid::Uuid> {
+    fn new(v: Value) -> MyResult<ParseIr<::uuid::Uuid>> {
+        let output = match v {
+            Value::Bytes(ref bytes) if bytes.len() == 16 => {
+                ::uuid::Uuid::from_bytes(&*bytes).ok()
+            },
+            Value::Bytes(ref bytes) => {
+                from_utf8(&*bytes).ok().and_then(|x| ::uuid::Uuid::parse_str(x).ok())
+            },
+            _ => None,
+        };
+        match output {
+            Some(output) => Ok(ParseIr {
+                value: v,
+                output: output,
+            }),
+            None => Err(Error::FromValueError(v)),
+        }
+    }
+    fn commit(self) -> ::uuid::Uuid {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+/// Converts `Uuid` to its 16-byte binary representation suitable for a `BINARY(16)` column.
+///
+/// Use `uuid.hyphenated().to_string()` to store it in a `CHAR(36)` column.
+#[cfg(feature = "uuid")]
+impl From<::uuid::Uuid> for Value {
+    fn from(x: ::uuid::Uuid) -> Value {
+        Value::Bytes(x.as_bytes().to_vec())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl_from_value!(::uuid::Uuid, ParseIr<::uuid::Uuid>, "Could not retrieve Uuid from Value");
+
 impl ConvIr<Timespec> for ParseIr<Timespec> {
     fn new (v: Value) -> MyResult<ParseIr<Timespec>> {
         match v {
             Value::Date(y, m, d, h, i, s, u) => Ok(ParseIr {
                 value: Value::Date(y, m, d, h, i, s, u),
-                output: Tm {
-                    tm_year: y as i32 - 1_900,
-                    tm_mon: m as i32 - 1,
-                    tm_mday: d as i32,
-                    tm_hour: h as i32,
-                    tm_min: i as i32,
-                    tm_sec: s as i32,
-                    tm_nsec: u as i32 * 1_000,
-                    tm_utcoff: *TM_UTCOFF,
-                    tm_wday: 0,
-                    tm_yday: 0,
-                    tm_isdst: *TM_ISDST,
-                }.to_timespec()
+                output: local_timespec(y as u32, m as u32, d as u32, h as u32, i as u32, s as u32,
+                                       u),
             }),
             Value::Bytes(bytes) => {
-                let val = from_utf8(&*bytes).ok().and_then(|s| {
-                    strptime(s, "%Y-%m-%d %H:%M:%S").or(strptime(s, "%Y-%m-%d")).ok()
-                }).map(|mut tm| {
-                    tm.tm_utcoff = *TM_UTCOFF;
-                    tm.tm_isdst = *TM_ISDST;
-                    tm.to_timespec()
-                });
-                match val {
-                    Some(timespec) => Ok(ParseIr {
+                match parse_mysql_datetime_string(&*bytes) {
+                    Some((y, m, d, h, i, s, u)) => Ok(ParseIr {
                         value: Value::Bytes(bytes),
-                        output: timespec,
+                        output: local_timespec(y, m, d, h, i, s, u),
                     }),
                     None => Err(Error::FromValueError(Value::Bytes(bytes))),
                 }
@@ -1745,7 +2719,37 @@ impl ConvIr<NaiveDate> for ParseIr<NaiveDate> {
     }
 }
 
+/// Converts local date and time to `Timespec`.
+fn local_timespec(y: u32, m: u32, d: u32, h: u32, i: u32, s: u32, u: u32) -> Timespec {
+    Tm {
+        tm_year: y as i32 - 1_900,
+        tm_mon: m as i32 - 1,
+        tm_mday: d as i32,
+        tm_hour: h as i32,
+        tm_min: i as i32,
+        tm_sec: s as i32,
+        tm_nsec: u as i32 * 1_000,
+        tm_utcoff: *TM_UTCOFF,
+        tm_wday: 0,
+        tm_yday: 0,
+        tm_isdst: *TM_ISDST,
+    }.to_timespec()
+}
+
 /// Returns (year, month, day, hour, minute, second, micros)
+/// Same as `parse_mysql_datetime_string` but also accepts ISO 8601 `T` separator and time
+/// without seconds (e.g. `2018-01-01T10:00`), which are accepted by server for `DATETIME`.
+fn parse_param_datetime_string(bytes: &[u8]) -> Option<(u32, u32, u32, u32, u32, u32, u32)> {
+    let mut bytes = bytes.to_vec();
+    if bytes.len() > 10 && bytes[10] == b'T' {
+        bytes[10] = b' ';
+    }
+    if bytes.len() == 16 && bytes[13] == b':' {
+        bytes.extend_from_slice(b":00");
+    }
+    parse_mysql_datetime_string(&*bytes)
+}
+
 fn parse_mysql_datetime_string(bytes: &[u8]) -> Option<(u32, u32, u32, u32, u32, u32, u32)> {
     if bytes.len() == 0 {
         return None;
@@ -1800,6 +2804,62 @@ fn parse_mysql_datetime_string(bytes: &[u8]) -> Option<(u32, u32, u32, u32, u32,
     })
 }
 
+/// Parses text protocol value the way binary protocol value of a column is read.
+fn coerce_text_bytes(bytes: &[u8], column: &Column) -> Option<Value> {
+    let text = match from_utf8(bytes) {
+        Ok(text) => text,
+        Err(_) => return None,
+    };
+    match column.column_type {
+        consts::ColumnType::MYSQL_TYPE_TINY |
+        consts::ColumnType::MYSQL_TYPE_SHORT |
+        consts::ColumnType::MYSQL_TYPE_YEAR |
+        consts::ColumnType::MYSQL_TYPE_LONG |
+        consts::ColumnType::MYSQL_TYPE_INT24 => text.parse().ok().map(Value::Int),
+        consts::ColumnType::MYSQL_TYPE_LONGLONG => {
+            if column.flags.contains(consts::UNSIGNED_FLAG) {
+                text.parse().ok().map(Value::UInt)
+            } else {
+                text.parse().ok().map(Value::Int)
+            }
+        },
+        consts::ColumnType::MYSQL_TYPE_FLOAT => {
+            text.parse::<f32>().ok().map(|x| Value::Float(x as f64))
+        },
+        consts::ColumnType::MYSQL_TYPE_DOUBLE => text.parse().ok().map(Value::Float),
+        consts::ColumnType::MYSQL_TYPE_TIMESTAMP |
+        consts::ColumnType::MYSQL_TYPE_DATE |
+        consts::ColumnType::MYSQL_TYPE_DATETIME => {
+            parse_mysql_datetime_string(bytes).map(|(y, m, d, h, i, s, u)| {
+                Value::Date(y as u16, m as u8, d as u8, h as u8, i as u8, s as u8, u)
+            })
+        },
+        consts::ColumnType::MYSQL_TYPE_TIME => {
+            parse_mysql_time_string(bytes).map(|(neg, h, i, s, u)| {
+                Value::Time(neg, h / 24, (h % 24) as u8, i as u8, s as u8, u)
+            })
+        },
+        _ => None,
+    }
+}
+
+/// Returns range of values of an integer parameter.
+fn int_param_range(param: &Column) -> (i128, i128) {
+    let bits = match param.column_type {
+        consts::ColumnType::MYSQL_TYPE_TINY => 8,
+        consts::ColumnType::MYSQL_TYPE_SHORT |
+        consts::ColumnType::MYSQL_TYPE_YEAR => 16,
+        consts::ColumnType::MYSQL_TYPE_INT24 => 24,
+        consts::ColumnType::MYSQL_TYPE_LONG => 32,
+        _ => 64,
+    };
+    if param.flags.contains(consts::UNSIGNED_FLAG) {
+        (0, (1i128 << bits) - 1)
+    } else {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    }
+}
+
 /// Returns (is_neg, hours, minutes, seconds, microseconds)
 fn parse_mysql_time_string(mut bytes: &[u8]) -> Option<(bool, u32, u32, u32, u32)> {
     if bytes.len() == 0 {
@@ -1882,9 +2942,14 @@ impl ConvIr<NaiveTime> for ParseIr<NaiveTime> {
     }
 }
 
+/// Negative time is not convertible to `Duration` (except of `-00:00:00`).
 impl ConvIr<Duration> for ParseIr<Duration> {
     fn new(v: Value) -> MyResult<ParseIr<Duration>> {
         match v {
+            Value::Time(true, 0, 0, 0, 0, 0) => Ok(ParseIr {
+                value: v,
+                output: Duration::new(0, 0),
+            }),
             Value::Time(false, days, hours, minutes, seconds, microseconds) => {
                 let nanos = (microseconds as u32) * 1000;
                 let secs = seconds as u64
@@ -1898,6 +2963,7 @@ impl ConvIr<Duration> for ParseIr<Duration> {
             },
             Value::Bytes(val_bytes) => {
                 let duration = match parse_mysql_time_string(&*val_bytes) {
+                    Some((true, 0, 0, 0, 0)) => Duration::new(0, 0),
                     Some((false, hours, minutes, seconds, microseconds)) => {
                         let nanos = microseconds * 1000;
                         let secs = seconds as u64
@@ -1923,6 +2989,29 @@ impl ConvIr<Duration> for ParseIr<Duration> {
     }
 }
 
+impl ConvIr<SystemTime> for ParseIr<SystemTime> {
+    fn new(v: Value) -> MyResult<ParseIr<SystemTime>> {
+        let ir: ParseIr<NaiveDateTime> = try!(ConvIr::new(v));
+        let secs = ir.output.timestamp();
+        let nanos = ir.output.nanosecond();
+        let output = if secs >= 0 {
+            UNIX_EPOCH + Duration::new(secs as u64, nanos)
+        } else {
+            UNIX_EPOCH - Duration::new(-secs as u64, 0) + Duration::new(0, nanos)
+        };
+        Ok(ParseIr {
+            value: ir.value,
+            output: output,
+        })
+    }
+    fn commit(self) -> SystemTime {
+        self.output
+    }
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
 impl ConvIr<time::Duration> for ParseIr<time::Duration> {
     fn new(v: Value) -> MyResult<ParseIr<time::Duration>> {
         match v {
@@ -1970,13 +3059,19 @@ impl_from_value!(NaiveDate, ParseIr<NaiveDate>, "Could not retrieve NaiveDate fr
 impl_from_value!(NaiveTime, ParseIr<NaiveTime>, "Could not retrieve NaiveTime from Value");
 impl_from_value!(Timespec, ParseIr<Timespec>, "Could not retrieve Timespec from Value");
 impl_from_value!(Duration, ParseIr<Duration>, "Could not retrieve Duration from Value");
+impl_from_value!(SystemTime, ParseIr<SystemTime>, "Could not retrieve SystemTime from Value");
 impl_from_value!(time::Duration, ParseIr<time::Duration>,
                  "Could not retrieve time::Duration from Value");
+impl_from_value!(Bit, ParseIr<Bit>, "Could not retrieve Bit from Value");
+impl_from_value!(Set, ParseIr<Set>, "Could not retrieve Set from Value");
+impl_from_value!(Decimal, ParseIr<Decimal>, "Could not retrieve Decimal from Value");
 impl_from_value!(String, StringIr, "Could not retrieve String from Value");
 impl_from_value!(Vec<u8>, BytesIr, "Could not retrieve Vec<u8> from Value");
 impl_from_value!(bool, ParseIr<bool>, "Could not retrieve bool from Value");
 impl_from_value!(i64, ParseIr<i64>, "Could not retrieve i64 from Value");
 impl_from_value!(u64, ParseIr<u64>, "Could not retrieve u64 from Value");
+impl_from_value!(i128, ParseIr<i128>, "Could not retrieve i128 from Value");
+impl_from_value!(u128, ParseIr<u128>, "Could not retrieve u128 from Value");
 impl_from_value!(f32, ParseIr<f32>, "Could not retrieve f32 from Value");
 impl_from_value!(f64, ParseIr<f64>, "Could not retrieve f64 from Value");
 impl_from_value_num_2!(i8, "Could not retrieve i8 from Value");
@@ -1987,6 +3082,18 @@ impl_from_value_num_2!(i32, "Could not retrieve i32 from Value");
 impl_from_value_num_2!(u32, "Could not retrieve u32 from Value");
 impl_from_value_num_2!(isize, "Could not retrieve isize from Value");
 impl_from_value_num_2!(usize, "Could not retrieve usize from Value");
+impl_from_value_non_zero!(NonZeroI8, i8, "Could not retrieve NonZeroI8 from Value");
+impl_from_value_non_zero!(NonZeroI16, i16, "Could not retrieve NonZeroI16 from Value");
+impl_from_value_non_zero!(NonZeroI32, i32, "Could not retrieve NonZeroI32 from Value");
+impl_from_value_non_zero!(NonZeroI64, i64, "Could not retrieve NonZeroI64 from Value");
+impl_from_value_non_zero!(NonZeroI128, i128, "Could not retrieve NonZeroI128 from Value");
+impl_from_value_non_zero!(NonZeroIsize, isize, "Could not retrieve NonZeroIsize from Value");
+impl_from_value_non_zero!(NonZeroU8, u8, "Could not retrieve NonZeroU8 from Value");
+impl_from_value_non_zero!(NonZeroU16, u16, "Could not retrieve NonZeroU16 from Value");
+impl_from_value_non_zero!(NonZeroU32, u32, "Could not retrieve NonZeroU32 from Value");
+impl_from_value_non_zero!(NonZeroU64, u64, "Could not retrieve NonZeroU64 from Value");
+impl_from_value_non_zero!(NonZeroU128, u128, "Could not retrieve NonZeroU128 from Value");
+impl_from_value_non_zero!(NonZeroUsize, usize, "Could not retrieve NonZeroUsize from Value");
 
 /// Will panic if could not convert `v` to `T`
 #[inline]
@@ -2003,6 +3110,7 @@ pub fn from_value_opt<T: FromValue>(v: Value) -> MyResult<T> {
 #[cfg(test)]
 #[allow(non_snake_case)]
 mod test {
+    #[allow(deprecated)]
     mod into_str {
         use super::super::Value::{Bytes, Int, UInt, Date, Time, Float, NULL};
         #[test]
@@ -2058,9 +3166,57 @@ mod test {
         }
     }
 
+    mod params {
+        use super::super::{Params, Value};
+
+        #[test]
+        fn should_convert_collections_to_params() {
+            let values = vec![Value::Int(1), Value::from("a")];
+            let expected = Params::Positional(values.clone());
+            assert_eq!(Params::from(values.clone()), expected);
+            assert_eq!(Params::from(&values[..]), expected);
+            assert_eq!(Params::from(&values), expected);
+            assert_eq!(Params::from([1u8, 2, 3]),
+                       Params::Positional(vec![Value::Int(1), Value::Int(2), Value::Int(3)]));
+            assert_eq!(Params::from(&["a", "b"]),
+                       Params::Positional(vec![Value::from("a"), Value::from("b")]));
+            assert_eq!((1..3).map(|x| x * 10).collect::<Params>(),
+                       Params::Positional(vec![Value::Int(10), Value::Int(20)]));
+            assert_eq!(Vec::<i32>::new().into_iter().collect::<Params>(), Params::Empty);
+            assert_eq!(Params::from(&Vec::<Value>::new()[..]), Params::Empty);
+        }
+    }
+
+    mod as_sql {
+        use super::super::Value::{Bytes, Int, UInt, Date, Time, Float, NULL};
+        use std::f64;
+        #[test]
+        fn should_render_values_as_sql_literals() {
+            assert_eq!(NULL.as_sql(false), "NULL");
+            assert_eq!(Int(-1).as_sql(false), "-1");
+            assert_eq!(UInt(18446744073709551615).as_sql(false), "18446744073709551615");
+            assert_eq!(Float(686.868).as_sql(false), "6.86868e2");
+            assert_eq!(Float(-0.5).as_sql(false), "-5e-1");
+            assert_eq!(Float(f64::NAN).as_sql(false), "NULL");
+            assert_eq!(Float(f64::INFINITY).as_sql(false), "NULL");
+            assert_eq!(Date(2014, 2, 20, 0, 0, 0, 0).as_sql(false), "'2014-02-20'");
+            assert_eq!(Date(2014, 2, 20, 22, 20, 10, 1).as_sql(false),
+                       "'2014-02-20 22:20:10.000001'");
+            assert_eq!(Time(false, 0, 0, 0, 0, 0).as_sql(false), "'00:00:00'");
+            assert_eq!(Time(false, 10, 100, 20, 30, 40).as_sql(false), "'340:20:30.000040'");
+        }
+        #[test]
+        fn should_escape_strings_and_use_hex_for_binary() {
+            assert_eq!(Bytes(b"a'b\\c\x00\n".to_vec()).as_sql(false), "'a\\'b\\\\c\\0\\n'");
+            assert_eq!(Bytes(b"a'b\\c\x00\n".to_vec()).as_sql(true), "'a''b\\c\x00\n'");
+            assert_eq!(Bytes(b"\x00\x01\xFF".to_vec()).as_sql(false), "X'0001FF'");
+            assert_eq!(Bytes(Vec::new()).as_sql(false), "''");
+        }
+    }
+
     mod from_value {
-        use super::super::from_value;
-        use super::super::Value::{Bytes, Date, Int, Time};
+        use super::super::{from_row, from_value, from_value_opt, Bit, Decimal, Set, Value};
+        use super::super::Value::{Bytes, Date, Float, Int, Time, UInt};
         use time::{Timespec, now, self};
         use super::super::super::conn::{Conn, Opts};
         use chrono::{
@@ -2086,7 +3242,7 @@ mod test {
                 ip_or_hostname: Some(ADDR.to_string()),
                 tcp_port: port,
                 init: vec!["SET GLOBAL sql_mode = 'TRADITIONAL'".to_owned()],
-                ssl_opts: Some((::std::convert::From::from("tests/ca-cert.pem"), None)),
+                ssl_opts: Some((::std::path::PathBuf::from("tests/ca-cert.pem"), None).into()),
                 ..Default::default()
             }
         }
@@ -2122,6 +3278,12 @@ mod test {
             assert_eq!(
                 Timespec { sec: 1414800000 - now().tm_utcoff as i64, nsec: 0 },
                 from_value::<Timespec>(Bytes(b"2014-11-01".to_vec())));
+            assert_eq!(
+                Timespec {
+                    sec: 1414866780 - now().tm_utcoff as i64,
+                    nsec: 123450000,
+                },
+                from_value::<Timespec>(Bytes(b"2014-11-01 18:33:00.12345".to_vec())));
         }
 
         #[test]
@@ -2252,11 +3414,264 @@ mod test {
                        from_value(Bytes(b"-120:30:30.005".to_vec())));
         }
 
+        #[test]
+        fn should_convert_std_Duration_and_SystemTime() {
+            use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+            let duration = Duration::new(2 * 86400 + 3723, 5_000);
+            assert_eq!(Value::from(duration), Time(false, 2, 1, 2, 3, 5));
+            assert_eq!(duration, from_value(Time(false, 2, 1, 2, 3, 5)));
+            assert_eq!(duration, from_value(Bytes(b"49:02:03.000005".to_vec())));
+            assert_eq!(Duration::new(0, 0), from_value(Time(true, 0, 0, 0, 0, 0)));
+            assert!(from_value_opt::<Duration>(Time(true, 0, 1, 2, 3, 0)).is_err());
+            assert!(from_value_opt::<Duration>(Bytes(b"-01:02:03".to_vec())).is_err());
+
+            let times = vec![UNIX_EPOCH + Duration::new(1414866780, 123_456_000),
+                             UNIX_EPOCH - Duration::new(86400 * 365, 0) + Duration::new(0, 1000)];
+            for moment in times {
+                let value = Value::from(moment);
+                match value {
+                    Date(..) => (),
+                    _ => panic!("Expected Date, got {:?}", value),
+                }
+                let text = value.as_sql(false).trim_matches('\'').as_bytes().to_vec();
+                assert_eq!(moment, from_value::<SystemTime>(value));
+                assert_eq!(moment, from_value::<SystemTime>(Bytes(text)));
+            }
+
+            // Conversion does not depend on the local time zone.
+            let moment = UNIX_EPOCH + Duration::new(1414866780, 0);
+            assert_eq!(Value::from(moment), Date(2014, 11, 1, 18, 33, 0, 0));
+            assert_eq!(moment, from_value(Bytes(b"2014-11-01 18:33:00".to_vec())));
+            let moment = UNIX_EPOCH - Duration::new(0, 1000);
+            assert_eq!(Value::from(moment), Date(1969, 12, 31, 23, 59, 59, 999_999));
+            assert_eq!(moment, from_value(Date(1969, 12, 31, 23, 59, 59, 999_999)));
+        }
+
         #[test]
         fn should_convert_signed_to_unsigned() {
             assert_eq!(1, from_value::<usize>(Int(1)));
         }
 
+        #[test]
+        fn should_convert_large_unsigned() {
+            assert_eq!(::std::u64::MAX, from_value::<u64>(UInt(::std::u64::MAX)));
+            assert_eq!(::std::u64::MAX,
+                       from_value::<u64>(Bytes(b"18446744073709551615".to_vec())));
+            assert!(from_value_opt::<i64>(UInt(::std::i64::MAX as u64 + 1)).is_err());
+            assert!(from_value::<bool>(UInt(1)));
+            assert_eq!(Value::from(::std::u64::MAX).to_bin().unwrap(), vec![0xff; 8]);
+        }
+
+        #[test]
+        fn should_convert_128_bit_and_non_zero_integers() {
+            use std::num::{NonZeroI32, NonZeroU64};
+            use super::super::super::error::Error;
+
+            assert_eq!(from_value::<i128>(Int(-1)), -1);
+            assert_eq!(from_value::<i128>(UInt(::std::u64::MAX)), ::std::u64::MAX as i128);
+            assert_eq!(from_value::<i128>(Bytes(b"-170141183460469231731687303715884105728"
+                                                    .to_vec())),
+                       ::std::i128::MIN);
+            assert_eq!(from_value::<u128>(Bytes(b"18446744073709551616".to_vec())),
+                       ::std::u64::MAX as u128 + 1);
+            assert!(from_value_opt::<u128>(Int(-1)).is_err());
+            assert!(from_value_opt::<i128>(Bytes(b"1.5".to_vec())).is_err());
+            assert_eq!(from_value::<NonZeroU64>(Bytes(b"42".to_vec())).get(), 42);
+            assert_eq!(from_value::<NonZeroI32>(Int(-7)).get(), -7);
+            assert_eq!(from_value::<Option<NonZeroI32>>(Value::NULL), None);
+            match from_value_opt::<NonZeroI32>(Int(0)) {
+                Err(Error::FromValueError(Int(0))) => (),
+                _ => panic!("Expected FromValueError(Int(0))"),
+            }
+            assert!(from_value_opt::<NonZeroU64>(Int(-1)).is_err());
+        }
+
+        #[test]
+        fn should_convert_invalid_utf8_according_to_policy() {
+            use super::super::{from_row_opt, Utf8Policy};
+            use super::super::super::conn::Row;
+            use super::super::super::error::Error;
+
+            let latin1 = b"caf\xe9".to_vec();
+            match from_value_opt::<String>(Bytes(latin1.clone())) {
+                Err(Error::Utf8Error(ref err)) => assert_eq!(err.as_bytes(), &*latin1),
+                _ => panic!("Expected Utf8Error"),
+            }
+            match from_value_opt::<Option<String>>(Bytes(latin1.clone())) {
+                Err(Error::Utf8Error(ref err)) => assert_eq!(err.as_bytes(), &*latin1),
+                _ => panic!("Expected Utf8Error"),
+            }
+            match from_row_opt::<(u8, String)>(Row::new(vec![Int(1), Bytes(latin1.clone())])) {
+                Err(Error::FromRowError(row)) => {
+                    assert_eq!(row, Row::new(vec![Int(1), Bytes(latin1.clone())]));
+                },
+                _ => panic!("Expected FromRowError"),
+            }
+            assert_eq!(from_value::<Option<String>>(Bytes(b"caf\xc3\xa9".to_vec())),
+                       Some("caf\u{e9}".to_string()));
+
+            assert!(Utf8Policy::Strict.decode(Bytes(latin1.clone())).is_err());
+            assert_eq!(Utf8Policy::Lossy.decode(Bytes(latin1.clone())).unwrap(), "caf\u{fffd}");
+            let text = Utf8Policy::Passthrough.decode(Bytes(latin1.clone())).unwrap();
+            assert_eq!(text, "caf\u{e9}");
+            assert_eq!(text.chars().map(|c| c as u8).collect::<Vec<u8>>(), latin1);
+            assert_eq!(Utf8Policy::Lossy.decode(Int(1)).unwrap(), "1");
+        }
+
+        #[test]
+        fn should_convert_bit_and_set() {
+            assert_eq!(from_value::<Bit>(Bytes(vec![0x01, 0x02])), Bit(0x0102));
+            assert_eq!(from_value::<Bit>(UInt(5)), Bit(5));
+            assert!(from_value_opt::<Bit>(Bytes(vec![0; 9])).is_err());
+            assert!(from_value::<bool>(Bytes(vec![0x01])));
+            assert!(!from_value::<bool>(Bytes(vec![0x00])));
+            assert_eq!(from_value::<Set>(Bytes(b"a,b".to_vec())),
+                       Set(vec!["a".to_string(), "b".to_string()]));
+            assert_eq!(from_value::<Set>(Bytes(vec![])), Set(vec![]));
+            assert_eq!(Value::from(Set(vec!["a".to_string(), "b".to_string()])),
+                       Bytes(b"a,b".to_vec()));
+        }
+
+        #[test]
+        fn should_convert_decimal() {
+            let x = from_value::<Decimal>(Bytes(b"-123.4500".to_vec()));
+            assert_eq!(x.as_str(), "-123.4500");
+            assert_eq!(x.scale(), 4);
+            assert!(x.is_negative());
+            assert_eq!(x.to_f64(), -123.45);
+            assert_eq!(from_value::<Decimal>(Int(-5)), "-5".parse().unwrap());
+            assert_eq!("+.5".parse::<Decimal>().unwrap().as_str(), ".5");
+            assert!("1.2.3".parse::<Decimal>().is_err());
+            assert!("-".parse::<Decimal>().is_err());
+            assert!("1e5".parse::<Decimal>().is_err());
+            assert!(from_value_opt::<Decimal>(Bytes(b"abc".to_vec())).is_err());
+            assert!(from_value_opt::<Decimal>(Float(1.5)).is_err());
+            assert_eq!(Value::from(Decimal("1.10".to_string())), Bytes(b"1.10".to_vec()));
+        }
+
+        #[test]
+        #[cfg(feature = "uuid")]
+        fn should_convert_uuid() {
+            use uuid::Uuid;
+
+            let text = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+            let uuid = Uuid::parse_str(text).unwrap();
+            assert_eq!(from_value::<Uuid>(Bytes(uuid.as_bytes().to_vec())), uuid);
+            assert_eq!(from_value::<Uuid>(Bytes(text.as_bytes().to_vec())), uuid);
+            assert_eq!(from_value::<Uuid>(Bytes(text.replace("-", "").into_bytes())), uuid);
+            assert_eq!(Value::from(uuid), Bytes(uuid.as_bytes().to_vec()));
+            assert!(from_value_opt::<Uuid>(Bytes(vec![0; 15])).is_err());
+            assert!(from_value_opt::<Uuid>(Int(1)).is_err());
+
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(a BINARY(16), b CHAR(36))").unwrap();
+            conn.prep_exec("INSERT INTO x.tbl(a, b) VALUES (?, ?)",
+                           (uuid, uuid.hyphenated().to_string())).unwrap();
+            let row: Option<(Uuid, Uuid)> = conn.exec_first("SELECT a, b FROM x.tbl", ())
+                .unwrap();
+            assert_eq!(row, Some((uuid, uuid)));
+            let row: Option<String> = conn.exec_first("SELECT b FROM x.tbl", ()).unwrap();
+            assert_eq!(row, Some(text.to_string()));
+        }
+
+        #[test]
+        fn should_roundtrip_decimal_column() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(a DECIMAL(65, 30))").unwrap();
+            let literal = "12345678901234567890123456789012345.123456789012345678901234567890";
+            let value: Decimal = literal.parse().unwrap();
+            conn.prep_exec("INSERT INTO x.tbl(a) VALUES (?)", (value.clone(),)).unwrap();
+            let text: Option<Decimal> = conn.query("SELECT a FROM x.tbl").unwrap()
+                .first_row().unwrap();
+            let bin: Option<Decimal> = conn.exec_first("SELECT a FROM x.tbl", ()).unwrap();
+            assert_eq!(text, Some(value.clone()));
+            assert_eq!(bin, Some(value));
+        }
+
+        #[test]
+        fn should_convert_bit_year_set_and_enum_columns() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(\
+                            a BIT(12),\
+                            b YEAR,\
+                            c SET('x', 'y', 'z'),\
+                            d ENUM('x', 'y')\
+                        )").unwrap();
+            conn.prep_exec("INSERT INTO x.tbl(a, b, c, d) VALUES (?, ?, ?, ?)",
+                           (Bit(0x0abc), 2016, Set(vec!["x".to_string(), "z".to_string()]), "y"))
+                .unwrap();
+            let query = "SELECT a, b, c, d FROM x.tbl";
+            let text_row = {
+                let mut result = conn.query(query).unwrap();
+                {
+                    let columns = result.columns_ref();
+                    assert!(columns[2].is_set());
+                    assert!(columns[3].is_enum());
+                }
+                result.next().unwrap().unwrap()
+            };
+            let bin_row = conn.prep_exec(query, ()).unwrap().next().unwrap().unwrap();
+            for row in vec![text_row, bin_row] {
+                let (a, b, c, d) = from_row::<(Bit, u16, Set, String)>(row);
+                assert_eq!(a, Bit(0x0abc));
+                assert_eq!(b, 2016);
+                assert_eq!(c, Set(vec!["x".to_string(), "z".to_string()]));
+                assert_eq!(d, "y");
+            }
+        }
+
+        #[test]
+        fn should_roundtrip_fractional_seconds() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query("CREATE TEMPORARY TABLE x.tbl(a DATETIME(6), b TIME(6), c TIMESTAMP(6))")
+                .unwrap();
+            let datetime = NaiveDate::from_ymd(2016, 2, 29).and_hms_micro(23, 59, 59, 123456);
+            let time = ::std::time::Duration::new(90061, 5000);
+            conn.prep_exec("INSERT INTO x.tbl(a, b, c) VALUES (?, ?, ?)",
+                           (datetime, time, datetime))
+                .unwrap();
+            let query = "SELECT a, b, c FROM x.tbl";
+            let text_row = conn.query(query).unwrap().next().unwrap().unwrap();
+            let bin_row = conn.prep_exec(query, ()).unwrap().next().unwrap().unwrap();
+            assert_eq!(bin_row[0], Date(2016, 2, 29, 23, 59, 59, 123456));
+            assert_eq!(bin_row[1], Time(false, 1, 1, 1, 1, 5));
+            for row in vec![text_row, bin_row] {
+                let (a, b, c) = from_row::<(NaiveDateTime, ::std::time::Duration, Timespec)>(row);
+                assert_eq!(a, datetime);
+                assert_eq!(b, time);
+                assert_eq!(c.nsec, 123456000);
+            }
+        }
+
+        #[test]
+        fn should_convert_NULL_only_to_Option() {
+            use std::num::NonZeroU32;
+            use super::super::super::error::Error;
+
+            macro_rules! check_null {
+                ($($t:ty),*) => ($(
+                    match from_value_opt::<$t>(Value::NULL) {
+                        Err(Error::FromValueError(Value::NULL)) => (),
+                        _ => panic!("Expected FromValueError(NULL) for {}", stringify!($t)),
+                    }
+                    assert!(from_value_opt::<Option<$t>>(Value::NULL).unwrap().is_none());
+                    assert!(from_value::<Option<$t>>(Value::NULL).is_none());
+                )*);
+            }
+
+            check_null!(bool, i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize,
+                        f32, f64, NonZeroU32, String, Vec<u8>, Bit, Set, Decimal, Timespec,
+                        ::std::time::Duration, time::Duration, NaiveDate, NaiveTime,
+                        NaiveDateTime);
+            #[cfg(feature = "uuid")]
+            check_null!(::uuid::Uuid);
+
+            assert_eq!(from_value::<Option<Option<i64>>>(Value::NULL), None);
+            assert_eq!(from_value::<Option<i64>>(Int(1)), Some(1));
+            assert!(from_value_opt::<Option<u64>>(Int(-1)).is_err());
+        }
+
         #[test]
         #[should_panic]
         fn should_not_convert_negative_to_unsigned() {
@@ -2265,6 +3680,7 @@ mod test {
     }
 
     mod to_value {
+        use std::time::Duration;
         use super::super::Value;
         use super::super::Value::{Date, Time};
         use time::{Timespec, now, self};
@@ -2284,6 +3700,25 @@ mod test {
             assert_eq!(Value::from(pos_dur), Time(false, 5, 0, 30, 30, 500000));
             assert_eq!(Value::from(neg_dur), Time(true, 5, 0, 30, 30, 500000));
         }
+        #[test]
+        fn should_convert_128_bit_and_non_zero_integers() {
+            use std::num::{NonZeroU8, NonZeroI64};
+
+            assert_eq!(Value::from(-1i128), Value::Int(-1));
+            assert_eq!(Value::from(::std::u64::MAX as i128), Value::UInt(::std::u64::MAX));
+            assert_eq!(Value::from(::std::i64::MIN as i128 - 1),
+                       Value::Bytes(b"-9223372036854775809".to_vec()));
+            assert_eq!(Value::from(5u128), Value::UInt(5));
+            assert_eq!(Value::from(::std::u128::MAX),
+                       Value::Bytes(b"340282366920938463463374607431768211455".to_vec()));
+            assert_eq!(Value::from(NonZeroU8::new(3).unwrap()), Value::Int(3));
+            assert_eq!(Value::from(NonZeroI64::new(-3).unwrap()), Value::Int(-3));
+        }
+        #[test]
+        fn should_not_round_micros_of_Duration_up() {
+            let dur = Duration::new(59, 999_999_999);
+            assert_eq!(Value::from(dur), Time(false, 0, 0, 0, 59, 999_999));
+        }
     }
 
     mod from_row {
@@ -2446,12 +3881,126 @@ mod test {
                 _ => unreachable!(),
             }
         }
+
+        #[test]
+        fn should_return_error_if_NULL_is_not_converted_to_Option() {
+            let row = Row::new(vec![Value::Int(1), Value::NULL]);
+            match from_row_opt::<(i64, String)>(row.clone()) {
+                Err(Error::FromRowError(e)) => assert_eq!(row, e),
+                _ => panic!("Expected FromRowError"),
+            }
+            assert_eq!(from_row_opt::<(i64, Option<String>)>(row).unwrap(), (1, None));
+        }
+    }
+
+    mod value_ref {
+        use super::super::{Value, ValueRef};
+
+        #[test]
+        fn should_parse_text_payload_without_copying() {
+            let pld = b"\x03foo\xfb\x00";
+            let values = ValueRef::from_payload(&pld[..], 3).unwrap();
+            assert_eq!(values, vec![ValueRef::Bytes(b"foo"), ValueRef::NULL, ValueRef::Bytes(b"")]);
+            assert_eq!(values[0].as_bytes().unwrap().as_ptr(), pld[1..].as_ptr());
+            assert_eq!(values[0].as_str(), Some("foo"));
+            assert_eq!(Value::from(values[0]), Value::Bytes(b"foo".to_vec()));
+        }
+
+        #[test]
+        fn should_return_error_on_truncated_payload() {
+            assert!(ValueRef::from_payload(b"\x05foo", 1).is_err());
+        }
+    }
+
+    mod bin_payload {
+        use super::super::Value;
+        use super::super::super::conn::Column;
+        use super::super::super::consts;
+
+        pub fn params(count: usize) -> Vec<Column> {
+            vec![Column {
+                schema: Vec::new(),
+                table: Vec::new(),
+                org_table: Vec::new(),
+                name: b"?".to_vec(),
+                org_name: Vec::new(),
+                default_values: Vec::new(),
+                column_length: 0,
+                character_set: 63,
+                flags: consts::ColumnFlags::empty(),
+                column_type: consts::ColumnType::MYSQL_TYPE_VAR_STRING,
+                decimals: 0,
+            }; count]
+        }
+
+        #[test]
+        fn should_write_bin_payload() {
+            let values = vec![Value::Int(-1), Value::NULL, Value::from("foo"), Value::UInt(2)];
+            let mut buf = vec![0x17];
+            let large_ids = Value::write_bin_payload(&params(4), &values, 1024, &mut buf).unwrap();
+            assert_eq!(large_ids, None);
+            assert_eq!(buf, vec![0x17,
+                                 0b0000_0010, 1,
+                                 8, 0, 253, 0, 253, 0, 8, 128,
+                                 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                                 3, b'f', b'o', b'o',
+                                 2, 0, 0, 0, 0, 0, 0, 0]);
+            let values = vec![Value::from(vec![b'x'; 1024]), Value::Int(1)];
+            buf.clear();
+            let large_ids = Value::write_bin_payload(&params(2), &values, 1024, &mut buf).unwrap();
+            assert_eq!(large_ids, Some(vec![0]));
+            assert_eq!(buf, vec![0, 1, 253, 0, 8, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        }
+
+        #[test]
+        fn should_coerce_params_using_param_types() {
+            let mut param = params(1).pop().unwrap();
+            assert_eq!(Value::Int(1).coerce_param(&param), Ok(Value::Int(1)));
+
+            param.column_type = consts::ColumnType::MYSQL_TYPE_TINY;
+            assert_eq!(Value::from(" -128").coerce_param(&param), Ok(Value::Int(-128)));
+            assert_eq!(Value::Int(128).coerce_param(&param), Err(Value::Int(128)));
+            assert_eq!(Value::NULL.coerce_param(&param), Ok(Value::NULL));
+            param.flags = consts::UNSIGNED_FLAG;
+            assert_eq!(Value::UInt(255).coerce_param(&param), Ok(Value::Int(255)));
+            assert_eq!(Value::Int(-1).coerce_param(&param), Err(Value::Int(-1)));
+            param.column_type = consts::ColumnType::MYSQL_TYPE_LONGLONG;
+            assert_eq!(Value::from("18446744073709551615").coerce_param(&param),
+                       Ok(Value::UInt(18446744073709551615)));
+            assert_eq!(Value::from("abc").coerce_param(&param), Err(Value::from("abc")));
+            param.flags = consts::ColumnFlags::empty();
+
+            param.column_type = consts::ColumnType::MYSQL_TYPE_NEWDECIMAL;
+            assert_eq!(Value::Int(10).coerce_param(&param), Ok(Value::Int(10)));
+            assert_eq!(Value::from("-1.50").coerce_param(&param), Ok(Value::from("-1.50")));
+            assert!(Value::from("1,5").coerce_param(&param).is_err());
+            param.column_type = consts::ColumnType::MYSQL_TYPE_DOUBLE;
+            assert_eq!(Value::from("1.5").coerce_param(&param), Ok(Value::Float(1.5)));
+            assert!(Value::Date(2018, 1, 1, 0, 0, 0, 0).coerce_param(&param).is_err());
+
+            param.column_type = consts::ColumnType::MYSQL_TYPE_DATETIME;
+            assert_eq!(Value::from("2018-01-02 10:00:00").coerce_param(&param),
+                       Ok(Value::Date(2018, 1, 2, 10, 0, 0, 0)));
+            // Example from `Opts::coerce_params` docs.
+            assert_eq!(Value::from("2018-01-01 10:00").coerce_param(&param),
+                       Ok(Value::Date(2018, 1, 1, 10, 0, 0, 0)));
+            assert_eq!(Value::from("2018-01-01T10:00:30.5").coerce_param(&param),
+                       Ok(Value::Date(2018, 1, 1, 10, 0, 30, 500000)));
+            assert!(Value::from("2018-01-01 10").coerce_param(&param).is_err());
+            assert!(Value::from("yesterday").coerce_param(&param).is_err());
+            assert!(Value::Int(1).coerce_param(&param).is_err());
+            param.column_type = consts::ColumnType::MYSQL_TYPE_TIME;
+            assert_eq!(Value::from("25:00:01").coerce_param(&param),
+                       Ok(Value::Time(false, 1, 1, 0, 1, 0)));
+            assert!(Value::Date(2018, 1, 1, 0, 0, 0, 0).coerce_param(&param).is_err());
+        }
     }
 
     #[cfg(feature = "nightly")]
     mod bench {
         use test;
         use time::Timespec;
+        use super::bin_payload::params;
         use super::super::{from_row, from_value, Value};
         use super::super::super::conn::Row;
         use chrono::NaiveDateTime;
@@ -2509,5 +4058,31 @@ mod test {
                 test::black_box(vs);
             });
         }
+
+        fn bin_payload_values() -> Vec<Value> {
+            vec![Value::Int(42), Value::from(b"123456".to_vec()), Value::Float(1.618), Value::NULL,
+                 Value::Date(2014, 11, 1, 18, 33, 00, 1)]
+        }
+
+        #[bench]
+        fn write_bin_payload_into_new_buffer(bencher: &mut test::Bencher) {
+            let (params, values) = (params(5), bin_payload_values());
+            bencher.iter(|| {
+                let mut buf = Vec::new();
+                let _ = Value::write_bin_payload(&params, &values, 1 << 24, &mut buf);
+                test::black_box(buf);
+            });
+        }
+
+        #[bench]
+        fn write_bin_payload_into_reused_buffer(bencher: &mut test::Bencher) {
+            let (params, values) = (params(5), bin_payload_values());
+            let mut buf = Vec::new();
+            bencher.iter(|| {
+                buf.clear();
+                let _ = Value::write_bin_payload(&params, &values, 1 << 24, &mut buf);
+                test::black_box(&buf);
+            });
+        }
     }
 }